@@ -0,0 +1,129 @@
+//! Writes a memo with a [TransactionProcessor], in any of its [Processing] modes.
+//! Shows the usual shape of a CLI built on this crate: Solana CLI style flags,
+//! signer resolution, mode selection and `--output` formatting.
+//!
+//! ```text
+//! cargo run --example memo-cli -- "hello" -u localnet -k ~/.config/solana/id.json
+//! cargo run --example memo-cli -- "hello" --mode simulate --output json
+//! cargo run --example memo-cli -- "hello" --mode offline-sign --blockhash <HASH>
+//! ```
+use std::io;
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anyhow::anyhow;
+use clap::{Parser, ValueEnum};
+use jungle_fi_cli_utils::cli::{get_solana_cli_config, resolve_commitment, resolve_keypair, resolve_url};
+use jungle_fi_cli_utils::clap_parsers::parse_hash;
+use jungle_fi_cli_utils::output::{CliOutput, OutputFormat};
+use serde_json::{Map, Value};
+use solana_client_tx_processor::{Processing, TransactionProcessor, TransactionProcessorError};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+/// Writes `message` to the memo program, signed by the fee payer.
+struct Memo {
+    message: String,
+}
+
+impl TransactionProcessor for Memo {
+    type OnlineArgs = ();
+    type RemainingArgs = ();
+
+    fn get_online_args(&self, _: &RpcClient) -> Result<Self::OnlineArgs, TransactionProcessorError> {
+        Ok(())
+    }
+
+    fn metadata(&self, primary_signer: &Pubkey, _: &Self::OnlineArgs, _: &Self::RemainingArgs) -> Map<String, Value> {
+        let mut map = Map::new();
+        map.insert("message".to_string(), Value::String(self.message.clone()));
+        map.insert("signer".to_string(), Value::String(primary_signer.to_string()));
+        map
+    }
+
+    fn name(&self, _: &Pubkey, _: &Self::OnlineArgs, _: &Self::RemainingArgs) -> String {
+        format!("memo: {}", self.message)
+    }
+
+    fn calc_remaining_args(&self, _: &Self::OnlineArgs, _: &Pubkey) -> Result<Self::RemainingArgs, TransactionProcessorError> {
+        Ok(())
+    }
+
+    fn named_instructions(
+        &self,
+        primary_signer: &Pubkey,
+        _: Self::OnlineArgs,
+        _: Self::RemainingArgs,
+    ) -> Result<Vec<(String, Instruction)>, TransactionProcessorError> {
+        Ok(vec![(
+            "memo".to_string(),
+            spl_memo::build_memo(self.message.as_bytes(), &[primary_signer]),
+        )])
+    }
+}
+
+/// One per [Processing] variant that's useful from the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    Execute,
+    Simulate,
+    Sign,
+    Serialize,
+    Instructions,
+    /// Sign with the blockhash from `--blockhash`, without contacting the cluster.
+    OfflineSign,
+}
+
+#[derive(Debug, Parser)]
+#[clap(name = "memo-cli")]
+struct Cli {
+    /// The memo to write.
+    message: String,
+    #[clap(long, value_enum, default_value = "execute")]
+    mode: Mode,
+    /// The cluster, as a moniker (devnet, localnet, ...) or RPC URL.
+    /// Defaults to the URL in the Solana CLI config.
+    #[clap(short, long)]
+    url: Option<String>,
+    /// The fee payer and memo signer. Defaults to the keypair in the Solana CLI config.
+    #[clap(short, long)]
+    keypair: Option<String>,
+    /// Processed, confirmed or finalized.
+    #[clap(long)]
+    commitment: Option<String>,
+    /// The recent blockhash to sign with, required by `--mode offline-sign`.
+    #[clap(long, value_parser = parse_hash)]
+    blockhash: Option<Hash>,
+    #[clap(long, value_enum, default_value_t)]
+    output: OutputFormat,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let config = get_solana_cli_config().ok();
+    let signer = resolve_keypair(&cli.keypair, config.as_ref())?;
+    let client = || -> anyhow::Result<RpcClient> {
+        Ok(RpcClient::new_with_commitment(
+            resolve_url(&cli.url, config.as_ref())?,
+            resolve_commitment(&cli.commitment, config.as_ref())?,
+        ))
+    };
+    let processing = match cli.mode {
+        Mode::Execute => Processing::Execute(client()?, signer, None),
+        Mode::Simulate => Processing::Simulate(client()?, signer, None),
+        Mode::Sign => Processing::Sign(client()?, signer, None),
+        Mode::Serialize => Processing::Serialize(client()?, signer.pubkey(), None),
+        Mode::Instructions => Processing::Instructions(client()?, signer.pubkey()),
+        Mode::OfflineSign => {
+            let blockhash = cli.blockhash
+                .ok_or_else(|| anyhow!("--mode offline-sign requires --blockhash"))?;
+            Processing::OfflineSign((), signer, None, blockhash)
+        }
+    };
+    let memo = Memo { message: cli.message };
+    let processed = memo
+        .process(processing, &mut vec![])
+        .map_err(|e| anyhow!("Failed to process the memo: {}", e))?;
+    processed.print(cli.output, &mut io::stdout())?;
+    Ok(())
+}
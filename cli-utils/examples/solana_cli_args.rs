@@ -0,0 +1,26 @@
+//! Prints the cluster, keypair and balance resolved from the standard Solana CLI flags.
+//!
+//! ```text
+//! cargo run --example solana_cli_args -- -u devnet -k ~/.config/solana/id.json
+//! ```
+use clap::Parser;
+use jungle_fi_cli_utils::clap::SolanaCliArgs;
+use solana_sdk::signature::Signer;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    #[clap(flatten)]
+    solana: SolanaCliArgs,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let context = cli.solana.resolve()?;
+    let pubkey = context.keypair.pubkey();
+    let balance = context.rpc_client.get_balance(&pubkey)?;
+    println!("Cluster: {}", context.cluster.url());
+    println!("Keypair: {}", pubkey);
+    println!("Commitment: {:?}", context.commitment.commitment);
+    println!("Balance: {} lamports", balance);
+    Ok(())
+}
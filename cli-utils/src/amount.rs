@@ -0,0 +1,299 @@
+/// Parsing and formatting of SOL and SPL token amounts, using exact decimal arithmetic.
+use std::collections::HashMap;
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anyhow::{anyhow, Result};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use spl_token::state::Mint;
+
+/// The number of decimal places in an amount of SOL.
+pub const SOL_DECIMALS: u8 = 9;
+
+/// An amount given on the command line, which may be everything available
+/// for commands that drain a balance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolAmount {
+    Lamports(u64),
+    All,
+}
+
+/// Parses an amount of SOL into lamports. Accepts:
+/// - `1.5` or `1.5sol`: SOL, with at most 9 decimal places.
+/// - `1500000000lamports`: a whole number of lamports.
+/// - `ALL` (any case): the whole balance, see [SolAmount::All].
+///
+/// Suffixes are case-insensitive, and may be separated from the number by whitespace.
+pub fn parse_amount(input: &str) -> Result<SolAmount> {
+    let trimmed = input.trim();
+    if trimmed.eq_ignore_ascii_case("all") {
+        return Ok(SolAmount::All);
+    }
+    let lowercase = trimmed.to_ascii_lowercase();
+    let lamports = if let Some(number) = lowercase.strip_suffix("lamports") {
+        parse_decimal(number.trim_end(), 0)
+    } else if let Some(number) = lowercase.strip_suffix("sol") {
+        parse_decimal(number.trim_end(), SOL_DECIMALS)
+    } else {
+        parse_decimal(&lowercase, SOL_DECIMALS)
+    };
+    lamports
+        .map(SolAmount::Lamports)
+        .map_err(|e| anyhow!("invalid amount {:?}: {}", input, e))
+}
+
+/// [parse_amount] for commands that need a specific amount, returning lamports.
+pub fn parse_sol_amount(input: &str) -> Result<u64> {
+    match parse_amount(input)? {
+        SolAmount::Lamports(lamports) => Ok(lamports),
+        SolAmount::All => Err(anyhow!("invalid amount {:?}: a specific amount is required", input)),
+    }
+}
+
+/// Formats lamports as SOL without trailing zeros, e.g. `1.5` or `0.000000001`.
+pub fn format_sol(lamports: u64) -> String {
+    format_decimal(lamports, SOL_DECIMALS)
+}
+
+/// Parses an amount of `mint` in UI units, e.g. `12.34`, into the raw token amount,
+/// fetching the mint's decimals. Pass a `cache` to avoid refetching mints when parsing
+/// several amounts.
+pub fn parse_token_amount(
+    client: &RpcClient,
+    mint: &Pubkey,
+    input: &str,
+    cache: Option<&mut HashMap<Pubkey, u8>>,
+) -> Result<u64> {
+    let decimals = match cache {
+        Some(cache) => match cache.get(mint) {
+            Some(decimals) => *decimals,
+            None => {
+                let decimals = mint_decimals(client, mint)?;
+                cache.insert(*mint, decimals);
+                decimals
+            }
+        },
+        None => mint_decimals(client, mint)?,
+    };
+    parse_token_amount_with_decimals(input, decimals)
+}
+
+/// [parse_token_amount] for a mint with known decimals.
+pub fn parse_token_amount_with_decimals(input: &str, decimals: u8) -> Result<u64> {
+    parse_decimal(input.trim(), decimals)
+        .map_err(|e| anyhow!("invalid token amount {:?}: {}", input, e))
+}
+
+/// Formats a raw token amount in UI units without trailing zeros.
+pub fn format_token_amount(raw: u64, decimals: u8) -> String {
+    format_decimal(raw, decimals)
+}
+
+fn mint_decimals(client: &RpcClient, mint: &Pubkey) -> Result<u8> {
+    let account = client.get_account(mint)
+        .map_err(|e| anyhow!("could not fetch mint {}: {}", mint, e))?;
+    if account.owner != spl_token::id() {
+        return Err(anyhow!("{} is not a mint, it is owned by {}", mint, account.owner));
+    }
+    Mint::unpack(&account.data)
+        .map(|mint| mint.decimals)
+        .map_err(|e| anyhow!("could not unpack mint {}: {}", mint, e))
+}
+
+/// Parses a non-negative decimal number into an integer of the smallest unit,
+/// e.g. `1.5` with 2 decimals is 150. Errors on more than `decimals` fractional digits
+/// rather than rounding.
+pub(crate) fn parse_decimal(number: &str, decimals: u8) -> Result<u64> {
+    let decimals = decimals as usize;
+    if number.starts_with('-') {
+        return Err(anyhow!("negative amounts are not allowed"));
+    }
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(anyhow!("no digits"));
+    }
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(anyhow!("only digits and a single decimal point are allowed"));
+    }
+    if fraction.len() > decimals {
+        return Err(if decimals == 0 {
+            anyhow!("expected a whole number")
+        } else {
+            anyhow!("found {} decimal places, at most {} are allowed", fraction.len(), decimals)
+        });
+    }
+    let too_large = || anyhow!("too large");
+    let whole = if whole.is_empty() { 0 } else {
+        whole.parse::<u64>().map_err(|_| too_large())?
+    };
+    let fraction = if decimals == 0 { 0 } else {
+        format!("{:0<width$}", fraction, width = decimals)
+            .parse::<u64>()
+            .map_err(|_| too_large())?
+    };
+    10u64
+        .checked_pow(decimals as u32)
+        .and_then(|scale| whole.checked_mul(scale))
+        .and_then(|scaled| scaled.checked_add(fraction))
+        .ok_or_else(too_large)
+}
+
+/// The inverse of [parse_decimal], trimming trailing zeros.
+pub(crate) fn format_decimal(amount: u64, decimals: u8) -> String {
+    if decimals == 0 {
+        return amount.to_string();
+    }
+    let digits = format!("{:0>width$}", amount, width = decimals as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_client::solana_client::mock_sender::Mocks;
+    use anchor_client::solana_client::rpc_request::RpcRequest;
+    use solana_account_decoder::{UiAccount, UiAccountEncoding};
+    use solana_sdk::account::Account;
+    use super::*;
+
+    #[test]
+    fn sol_amounts() {
+        for (input, lamports) in [
+            ("1", LAMPORTS_PER_SOL),
+            ("1.5", 1_500_000_000),
+            ("1.5sol", 1_500_000_000),
+            ("1.5 SOL", 1_500_000_000),
+            (" 2sol ", 2 * LAMPORTS_PER_SOL),
+            (".5", 500_000_000),
+            ("5.", 5 * LAMPORTS_PER_SOL),
+            ("0.000001", 1_000),
+            ("0.000000001", 1),
+            ("0", 0),
+            ("0lamports", 0),
+            ("1500000000lamports", 1_500_000_000),
+            ("42 Lamports", 42),
+            ("18446744073709551615lamports", u64::MAX),
+            ("18446744073.709551615", u64::MAX),
+        ] {
+            assert_eq!(parse_amount(input).unwrap(), SolAmount::Lamports(lamports), "{}", input);
+            assert_eq!(parse_sol_amount(input).unwrap(), lamports, "{}", input);
+        }
+    }
+
+    #[test]
+    fn all() {
+        for input in ["ALL", "all", " All "] {
+            assert_eq!(parse_amount(input).unwrap(), SolAmount::All);
+        }
+        assert!(parse_sol_amount("ALL").unwrap_err().to_string().contains("specific amount"));
+    }
+
+    #[test]
+    fn invalid_sol_amounts() {
+        for (input, reason) in [
+            ("", "no digits"),
+            (".", "no digits"),
+            ("sol", "no digits"),
+            ("-1", "negative"),
+            ("-0.5sol", "negative"),
+            ("0.0000000001", "found 10 decimal places, at most 9"),
+            ("1.5lamports", "whole number"),
+            ("1.2.3", "only digits"),
+            ("1e9", "only digits"),
+            ("1,000", "only digits"),
+            ("+1", "only digits"),
+            ("1 sol sol", "only digits"),
+            ("1lamport", "only digits"),
+            ("18446744073.709551616", "too large"),
+            ("18446744074", "too large"),
+            ("18446744073709551616lamports", "too large"),
+        ] {
+            let err = parse_amount(input).unwrap_err().to_string();
+            assert!(err.contains(reason), "{}: {}", input, err);
+        }
+    }
+
+    fn mock_mint(mint: &Pubkey, decimals: u8) -> RpcClient {
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(Mint {
+            decimals,
+            is_initialized: true,
+            ..Mint::default()
+        }, &mut data).unwrap();
+        let account = Account {
+            lamports: 1_000_000,
+            data,
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(RpcRequest::GetAccountInfo, serde_json::json!({
+            "context": { "slot": 1 },
+            "value": UiAccount::encode(mint, &account, UiAccountEncoding::Base64, None, None),
+        }));
+        RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+    }
+
+    #[test]
+    fn token_amounts() {
+        let mint = Pubkey::new_unique();
+        let client = mock_mint(&mint, 6);
+        assert_eq!(parse_token_amount(&client, &mint, "12.34", None).unwrap(), 12_340_000);
+
+        // Mocks are only returned once, so the second amount must use the cache.
+        let client = mock_mint(&mint, 2);
+        let mut cache = HashMap::new();
+        assert_eq!(parse_token_amount(&client, &mint, "1.5", Some(&mut cache)).unwrap(), 150);
+        assert_eq!(parse_token_amount(&client, &mint, "7", Some(&mut cache)).unwrap(), 700);
+        assert_eq!(cache.get(&mint), Some(&2));
+        assert!(parse_token_amount(&client, &mint, "7", None).is_err());
+
+        let client = mock_mint(&mint, 2);
+        let err = parse_token_amount(&client, &mint, "1.234", None).unwrap_err().to_string();
+        assert!(err.contains("found 3 decimal places, at most 2"), "{}", err);
+    }
+
+    #[test]
+    fn token_amounts_with_decimals() {
+        assert_eq!(parse_token_amount_with_decimals("12.34", 6).unwrap(), 12_340_000);
+        assert_eq!(parse_token_amount_with_decimals("5", 0).unwrap(), 5);
+        assert!(parse_token_amount_with_decimals("0.5", 0).is_err());
+        assert!(parse_token_amount_with_decimals("-1", 6).is_err());
+        assert_eq!(format_token_amount(12_340_000, 6), "12.34");
+        assert_eq!(format_token_amount(5, 0), "5");
+        assert_eq!(format_token_amount(1, 2), "0.01");
+    }
+
+    #[test]
+    fn not_a_mint() {
+        let mint = Pubkey::new_unique();
+        let mut mocks: Mocks = HashMap::new();
+        let account = Account::new(1, 0, &Pubkey::default());
+        mocks.insert(RpcRequest::GetAccountInfo, serde_json::json!({
+            "context": { "slot": 1 },
+            "value": UiAccount::encode(&mint, &account, UiAccountEncoding::Base64, None, None),
+        }));
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let err = parse_token_amount(&client, &mint, "1", None).unwrap_err().to_string();
+        assert!(err.contains("is not a mint"), "{}", err);
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_sol(0), "0");
+        assert_eq!(format_sol(1), "0.000000001");
+        assert_eq!(format_sol(1_000), "0.000001");
+        assert_eq!(format_sol(1_500_000_000), "1.5");
+        assert_eq!(format_sol(2 * LAMPORTS_PER_SOL), "2");
+        assert_eq!(format_sol(u64::MAX), "18446744073.709551615");
+        for lamports in [0, 1, 10, 999_999_999, 1_000_000_001, 123_456_789_000, u64::MAX] {
+            assert_eq!(parse_sol_amount(&format_sol(lamports)).unwrap(), lamports);
+        }
+    }
+}
@@ -1,13 +1,29 @@
 use anyhow::{anyhow, Result};
 use clap::parser::ArgMatches;
-use solana_clap_v3_utils::keypair::signer_from_path;
-use solana_sdk::pubkey::Pubkey;
+use clap::{Arg, Command};
+use solana_clap_v3_utils::keypair::{signer_from_path, SKIP_SEED_PHRASE_VALIDATION_ARG};
+use solana_clap_v3_utils::offline::{SIGNER_ARG, SIGN_ONLY_ARG};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::{Pubkey, MAX_SEEDS, MAX_SEED_LEN};
+use spl_associated_token_account::get_associated_token_address;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Arc;
+use solana_remote_wallet::remote_wallet::RemoteWalletManager;
+use solana_sdk::signature::{Signature, Signer};
+use solana_sdk::signer::SignerError;
+use clap::{Args, Parser};
 use anchor_client::Cluster;
-use solana_sdk::signature::Signer;
-use clap::Parser;
+use anchor_client::solana_client::rpc_client::RpcClient;
 use solana_cli_config::Config;
-use crate::cli::get_solana_cli_config;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Keypair;
+use crate::output::OutputFormat;
+use crate::cli::{
+    get_solana_cli_config, resolve_cluster, resolve_commitment, resolve_keypair, resolve_urls,
+};
 
 /// Put this (flattened) at the top level of a Clap CLI made with the Derive API to add the
 /// `-u/--url` CLI arg as it functions in the official Solana CLI.
@@ -24,7 +40,7 @@ pub struct UrlArg {
 impl UrlArg {
     pub fn resolve(&self, config: Option<&Config>) -> Result<String> {
         if let Some(url) = self.url.clone() {
-            return Ok(Cluster::from_str(&url)?.url().to_string());
+            return Ok(resolve_cluster(&url)?.url().to_string());
         }
         if let Some(config) = config {
             return Ok(config.json_rpc_url.clone());
@@ -70,32 +86,179 @@ impl KeypairArg {
     }
 }
 
-/// Parses [solana_sdk::pubkey::Pubkey] from a string.
+/// The flags shared by most Solana CLIs, as they function in the Solana CLI.
+/// Flatten this into a command made with the Derive API:
+///
+/// ```ignore
+/// #[derive(Parser)]
+/// struct Cli {
+///     #[clap(flatten)]
+///     solana: SolanaCliArgs,
+/// }
+/// ```
+///
+/// Anything not passed explicitly falls back to the Solana CLI config file.
+#[derive(Debug, Clone, Args)]
+pub struct SolanaCliArgs {
+    /// The signing keypair. Defaults to the keypair in the Solana CLI config.
+    #[clap(short, long)]
+    pub keypair: Option<String>,
+    /// The cluster to use, as a moniker (devnet, mainnet-beta, ...) or RPC URL.
+    /// Defaults to the URL in the Solana CLI config.
+    #[clap(short, long)]
+    pub url: Option<String>,
+    /// The commitment level for RPC requests: processed, confirmed or finalized.
+    /// Defaults to the commitment in the Solana CLI config.
+    #[clap(long)]
+    pub commitment: Option<String>,
+    /// Skip the preflight simulation when sending transactions.
+    #[clap(long)]
+    pub skip_preflight: bool,
+    /// The format of the command's output.
+    #[clap(long, value_name = "FORMAT", value_enum, default_value_t = OutputFormat::Human)]
+    pub output: OutputFormat,
+}
+
+/// Everything a command needs to talk to a cluster, resolved from [SolanaCliArgs]
+/// or [cli_context_from_config].
+pub struct CliContext {
+    pub cluster: Cluster,
+    pub keypair: Box<Keypair>,
+    pub commitment: CommitmentConfig,
+    pub rpc_url: String,
+    /// The WebSocket URL for subscriptions, see [crate::cli::resolve_urls].
+    pub ws_url: String,
+    /// A client for `rpc_url` with `commitment`. Creating it makes no requests.
+    pub rpc_client: RpcClient,
+    pub skip_preflight: bool,
+    pub output: OutputFormat,
+}
+
+impl CliContext {
+    /// A nonblocking client for `rpc_url` with `commitment`.
+    #[cfg(feature = "nonblocking")]
+    pub fn nonblocking_rpc_client(
+        &self,
+    ) -> anchor_client::solana_client::nonblocking::rpc_client::RpcClient {
+        anchor_client::solana_client::nonblocking::rpc_client::RpcClient::new_with_commitment(
+            self.rpc_url.clone(),
+            self.commitment,
+        )
+    }
+}
+
+/// Resolve a [CliContext] from optional `-k/--keypair`, `-u/--url` and `--commitment`
+/// values, falling back to the Solana CLI config file, and warning as
+/// [crate::cli::resolve_url] and friends do when it can't be loaded.
+/// For applications that don't use [SolanaCliArgs].
+pub fn cli_context_from_config(
+    keypair_path: &Option<String>,
+    url: &Option<String>,
+    commitment: &Option<String>,
+) -> Result<CliContext> {
+    SolanaCliArgs {
+        keypair: keypair_path.clone(),
+        url: url.clone(),
+        commitment: commitment.clone(),
+        skip_preflight: false,
+        output: OutputFormat::default(),
+    }.resolve()
+}
+
+impl SolanaCliArgs {
+    /// Resolve the flags, falling back to the Solana CLI config file.
+    pub fn resolve(&self) -> Result<CliContext> {
+        self.resolve_with_config(get_solana_cli_config().ok().as_ref())
+    }
+
+    /// Resolve the flags, falling back to `config`.
+    /// Passing [None] behaves like [crate::cli::resolve_url] and friends when no
+    /// config file is found.
+    pub fn resolve_with_config(&self, config: Option<&Config>) -> Result<CliContext> {
+        let urls = resolve_urls(&self.url, config)?;
+        let cluster = match &self.url {
+            Some(url) => resolve_cluster(url)?,
+            None => Cluster::Custom(urls.http.clone(), urls.ws.clone()),
+        };
+        let keypair = resolve_keypair(&self.keypair, config)?;
+        let commitment = resolve_commitment(&self.commitment, config)?;
+        let rpc_client = RpcClient::new_with_commitment(urls.http.clone(), commitment);
+        Ok(CliContext {
+            cluster,
+            keypair,
+            commitment,
+            rpc_url: urls.http,
+            ws_url: urls.ws,
+            rpc_client,
+            skip_preflight: self.skip_preflight,
+            output: self.output,
+        })
+    }
+}
+
+static WELL_KNOWN_PROGRAMS: [(&str, Pubkey); 8] = [
+    ("system", pubkey!("11111111111111111111111111111111")),
+    ("token", pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA")),
+    ("token22", pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PJnBQCXEjmXGAP5N")),
+    ("ata", pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL")),
+    ("memo", pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr")),
+    ("stake", pubkey!("Stake11111111111111111111111111111111111111")),
+    ("vote", pubkey!("Vote111111111111111111111111111111111111111")),
+    ("bpf-upgradeable", pubkey!("BPFLoaderUpgradeab1e11111111111111111111111")),
+];
+
+/// Shorthands accepted by [pubkey_arg] in place of well-known program ids,
+/// e.g. for generating help text.
+pub fn well_known_programs() -> &'static [(&'static str, Pubkey)] {
+    &WELL_KNOWN_PROGRAMS
+}
+
+/// The program id for a shorthand from [well_known_programs], ignoring case.
+pub fn well_known_program(shorthand: &str) -> Option<Pubkey> {
+    WELL_KNOWN_PROGRAMS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(shorthand))
+        .map(|(_, program)| *program)
+}
+
+/// Comma separated shorthands from [well_known_programs], for error messages.
+pub(crate) fn well_known_program_names() -> String {
+    WELL_KNOWN_PROGRAMS
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parses [solana_sdk::pubkey::Pubkey] from a string,
+/// or a well-known program shorthand like `token`, see [well_known_programs].
 pub fn pubkey_arg(pubkey: &str) -> Result<Pubkey> {
-    Pubkey::from_str(pubkey).map_err(
-        |e| anyhow!("invalid pubkey: {}", e.to_string())
-    )
+    if let Some(program) = well_known_program(pubkey) {
+        return Ok(program);
+    }
+    Pubkey::from_str(pubkey).map_err(|e| anyhow!(
+        "invalid pubkey: {}, expected base58 or one of: {}",
+        e.to_string(), well_known_program_names()
+    ))
 }
 
 /// Returns a pubkey using either its string representation,
 /// or reading it as a signer path and retaining only that signer's public key.
 /// Useful when you want a pubkey, but it might be more convenient to pass
 /// a signer path.
+///
+/// Two derived forms are also accepted, where each component is itself
+/// a pubkey or a signer path:
+/// - `ata:<owner>:<mint>` resolves to the associated token account of `owner` for `mint`,
+///   under the original SPL Token program. The mint follows the last `:`.
+/// - `pda:<program>:<seed1>,<seed2>,...` resolves to the program derived address
+///   of `program` for the given seeds. Seeds are used as utf8 bytes, unless prefixed with
+///   `hex:` (hex encoded bytes) or `pubkey:` (a pubkey or signer path). The program ends
+///   at the first `:` that isn't part of a signer URI scheme, like `usb://`.
+///
+/// Signer paths are resolved once per thread, see [SignerResolver].
 pub fn pubkey_or_signer_path(input: &str, matches: &ArgMatches) -> Result<Pubkey> {
-    if let Ok(pubkey) = Pubkey::from_str(input) {
-        Ok(pubkey)
-    } else {
-        let mut wallet_manager = None;
-        let signer = signer_from_path(
-            matches,
-            input,
-            "keypair",
-            &mut wallet_manager,
-        ).map_err(
-            |e| anyhow!("invalid pubkey or signer path {}: {}", input, e.to_string())
-        )?;
-        Ok(signer.pubkey())
-    }
+    RESOLVER.with(|resolver| resolver.borrow_mut().pubkey_or_signer_path(input, matches))
 }
 
 /// Branch over the possible ways that signers can be specified via user input.
@@ -103,13 +266,652 @@ pub fn pubkey_or_signer_path(input: &str, matches: &ArgMatches) -> Result<Pubkey
 /// with disregard to filesystem configuration. It is useful for situations
 /// where additional signers may be specified, e.g. grinding for an address and using
 /// it as a signer when creating a multisig account.
+///
+/// Signers are resolved once per thread, see [SignerResolver].
 pub fn parse_signer(matches: &ArgMatches, path: &str) -> Result<Box<dyn Signer>> {
-    let mut wallet_manager = None;
-    let signer = signer_from_path(
-        matches,
+    RESOLVER.with(|resolver| resolver.borrow_mut().parse_signer(matches, path))
+}
+
+/// Resolve several signers, e.g. from a repeated `--signer` flag. Entries may also
+/// be comma-separated lists. Besides the sources [parse_signer] accepts, entries may use
+/// any source of [crate::cli::keypair_from_path], such as base58 secret keys.
+///
+/// Signers with the same pubkey as an earlier entry are dropped, keeping input order.
+/// If any entry can't be resolved, the error lists every entry that failed.
+pub fn parse_signers(matches: &ArgMatches, inputs: &[String]) -> Result<Vec<Box<dyn Signer>>> {
+    RESOLVER.with(|resolver| resolver.borrow_mut().parse_signers(matches, inputs))
+}
+
+thread_local! {
+    static RESOLVER: RefCell<SignerResolver> = RefCell::new(SignerResolver::default());
+}
+
+/// Resolves signer paths with a single hardware wallet manager, remembering each
+/// resolved signer and pubkey by its source string. A command with several
+/// `usb://ledger` flags then enumerates devices and prompts once per key, rather than
+/// once per flag. Nothing is ever re-read, so a file that changes after it's been
+/// resolved keeps resolving to its original contents.
+///
+/// Signers are remembered along with the flags `signer_from_path` reads from the
+/// matches, e.g. `--skip-seed-phrase-validation` and `--confirm-key`, so the same
+/// source resolved with different flags is resolved again.
+///
+/// The free functions [parse_signer], [pubkey_or_signer_path] and [parse_signers]
+/// share a resolver per thread. Use one directly to control its lifetime.
+#[derive(Default)]
+pub struct SignerResolver {
+    wallet_manager: Option<Arc<RemoteWalletManager>>,
+    signers: HashMap<String, Rc<dyn Signer>>,
+    // Pubkeys of sources only resolved by `pubkey_or_signer_path`.
+    pubkeys: HashMap<String, Pubkey>,
+}
+
+impl SignerResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [parse_signer].
+    pub fn parse_signer(&mut self, matches: &ArgMatches, path: &str) -> Result<Box<dyn Signer>> {
+        let key = cache_key(path, matches);
+        if let Some(signer) = self.signers.get(&key) {
+            return Ok(Box::new(SharedSigner(signer.clone())));
+        }
+        let signer: Rc<dyn Signer> = signer_from_path(
+            matches,
+            path,
+            "keypair",
+            &mut self.wallet_manager,
+        ).map_err(|e| anyhow!("Could not resolve signer: {:?}", e))?.into();
+        self.signers.insert(key, signer.clone());
+        Ok(Box::new(SharedSigner(signer)))
+    }
+
+    /// See [pubkey_or_signer_path].
+    pub fn pubkey_or_signer_path(&mut self, input: &str, matches: &ArgMatches) -> Result<Pubkey> {
+        if let Some(components) = input.strip_prefix(ATA_PREFIX) {
+            self.associated_token_address(components, matches)
+        } else if let Some(components) = input.strip_prefix(PDA_PREFIX) {
+            self.program_derived_address(components, matches)
+        } else if let Ok(pubkey) = Pubkey::from_str(input) {
+            Ok(pubkey)
+        } else if let Some(pubkey) = self.pubkeys.get(&cache_key(input, matches)) {
+            Ok(*pubkey)
+        } else if let Some(signer) = self.signers.get(&cache_key(input, matches)) {
+            Ok(signer.pubkey())
+        } else {
+            let signer = signer_from_path(
+                matches,
+                input,
+                "keypair",
+                &mut self.wallet_manager,
+            ).map_err(
+                |e| anyhow!("invalid pubkey or signer path {}: {}", input, e.to_string())
+            )?;
+            self.pubkeys.insert(cache_key(input, matches), signer.pubkey());
+            Ok(signer.pubkey())
+        }
+    }
+
+    /// See [parse_signers].
+    pub fn parse_signers(
+        &mut self,
+        matches: &ArgMatches,
+        inputs: &[String],
+    ) -> Result<Vec<Box<dyn Signer>>> {
+        let entries: Vec<&str> = inputs
+            .iter()
+            .flat_map(|input| input.split(','))
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        let mut signers: Vec<Box<dyn Signer>> = vec![];
+        let mut failures = vec![];
+        for entry in &entries {
+            let signer = self.parse_signer(matches, entry).or_else(|e| {
+                crate::cli::keypair_from_path(entry)
+                    .map(|keypair| keypair as Box<dyn Signer>)
+                    .map_err(|_| e)
+            });
+            match signer {
+                Ok(signer) => {
+                    if signers.iter().any(|s| s.pubkey() == signer.pubkey()) {
+                        log::warn!("Ignoring duplicate signer {}: {}", entry, signer.pubkey());
+                    } else {
+                        signers.push(signer);
+                    }
+                }
+                Err(e) => failures.push(format!("  {}: {}", entry, e)),
+            }
+        }
+        if !failures.is_empty() {
+            return Err(anyhow!(
+                "Could not resolve {} of {} signers:\n{}",
+                failures.len(),
+                entries.len(),
+                failures.join("\n")
+            ));
+        }
+        Ok(signers)
+    }
+
+    fn associated_token_address(&mut self, components: &str, matches: &ArgMatches) -> Result<Pubkey> {
+        let (owner, mint) = ata_components(components)?;
+        let owner = self.pubkey_or_signer_path(owner, matches)
+            .map_err(|e| anyhow!("invalid associated token account owner: {}", e))?;
+        let mint = self.pubkey_or_signer_path(mint, matches)
+            .map_err(|e| anyhow!("invalid associated token account mint: {}", e))?;
+        Ok(get_associated_token_address(&owner, &mint))
+    }
+
+    fn program_derived_address(&mut self, components: &str, matches: &ArgMatches) -> Result<Pubkey> {
+        let (program, seeds) = pda_components(components)?;
+        let program_id = self.pubkey_or_signer_path(program, matches)
+            .map_err(|e| anyhow!("invalid program derived address program: {}", e))?;
+        let seeds = seeds
+            .split(',')
+            .enumerate()
+            .map(|(i, seed)| self.parse_seed(seed, matches)
+                .map_err(|e| anyhow!("invalid seed {} of program derived address: {}", i, e)))
+            .collect::<Result<Vec<Vec<u8>>>>()?;
+        if seeds.len() > MAX_SEEDS {
+            return Err(anyhow!(
+                "invalid program derived address: found {} seeds, the maximum is {}",
+                seeds.len(), MAX_SEEDS
+            ));
+        }
+        let seeds: Vec<&[u8]> = seeds.iter().map(Vec::as_slice).collect();
+        Pubkey::try_find_program_address(&seeds, &program_id)
+            .map(|(address, _)| address)
+            .ok_or_else(|| anyhow!("no viable bump seed for program derived address"))
+    }
+
+    fn parse_seed(&mut self, seed: &str, matches: &ArgMatches) -> Result<Vec<u8>> {
+        let bytes = if let Some(encoded) = seed.strip_prefix(HEX_SEED_PREFIX) {
+            hex::decode(encoded).map_err(|e| anyhow!("could not decode hex {}: {}", encoded, e))?
+        } else if let Some(pubkey) = seed.strip_prefix(PUBKEY_SEED_PREFIX) {
+            self.pubkey_or_signer_path(pubkey, matches)?.to_bytes().to_vec()
+        } else {
+            seed.as_bytes().to_vec()
+        };
+        if bytes.len() > MAX_SEED_LEN {
+            return Err(anyhow!(
+                "seed is {} bytes, the maximum is {}",
+                bytes.len(), MAX_SEED_LEN
+            ));
+        }
+        Ok(bytes)
+    }
+}
+
+// The owner and mint of `ata:` components. The owner may be a signer path with `:`
+// of its own, e.g. `usb://ledger`, so the mint follows the last `:`.
+fn ata_components(components: &str) -> Result<(&str, &str)> {
+    components.rsplit_once(':').ok_or_else(|| anyhow!(
+        "invalid associated token account: expected ata:<owner>:<mint>, found {}",
+        components
+    ))
+}
+
+// The program and seeds of `pda:` components. Seeds may contain `:` themselves, e.g. `hex:`
+// and `pubkey:` seeds, so the program ends at the first `:` that doesn't open the `//`
+// of a signer URI like `usb://ledger`.
+fn pda_components(components: &str) -> Result<(&str, &str)> {
+    components
+        .match_indices(':')
+        .map(|(i, _)| i)
+        .find(|&i| !components[i + 1..].starts_with("//"))
+        .map(|i| (&components[..i], &components[i + 1..]))
+        .ok_or_else(|| anyhow!(
+            "invalid program derived address: expected pda:<program>:<seed1>,<seed2>,..., found {}",
+            components
+        ))
+}
+
+// The source string along with every flag `signer_from_path` reads alongside it.
+// Flags that `matches` doesn't declare count as absent.
+fn cache_key(path: &str, matches: &ArgMatches) -> String {
+    let present = |name: &str| matches.try_contains_id(name).unwrap_or(false);
+    let signer_args: Vec<String> = matches
+        .try_get_raw(SIGNER_ARG.name)
+        .ok()
+        .flatten()
+        .map(|values| values.map(|value| value.to_string_lossy().into_owned()).collect())
+        .unwrap_or_default();
+    format!(
+        "{}\0{}{}{}\0{}",
         path,
-        "keypair",
-        &mut wallet_manager,
-    ).map_err(|e| anyhow!("Could not resolve signer: {:?}", e))?;
-    Ok(signer)
+        present(SKIP_SEED_PHRASE_VALIDATION_ARG.name) as u8,
+        present("confirm_key") as u8,
+        present(SIGN_ONLY_ARG.name) as u8,
+        signer_args.join(","),
+    )
+}
+
+const ATA_PREFIX: &str = "ata:";
+const PDA_PREFIX: &str = "pda:";
+const HEX_SEED_PREFIX: &str = "hex:";
+const PUBKEY_SEED_PREFIX: &str = "pubkey:";
+
+// A signer remembered by a [SignerResolver], handed out as a `Box<dyn Signer>`.
+struct SharedSigner(Rc<dyn Signer>);
+
+impl Signer for SharedSigner {
+    fn try_pubkey(&self) -> std::result::Result<Pubkey, SignerError> {
+        self.0.try_pubkey()
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> std::result::Result<Signature, SignerError> {
+        self.0.try_sign_message(message)
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.0.is_interactive()
+    }
+}
+
+/// [parse_signers], also returning the pubkeys of the signers in input order,
+/// as instruction builders usually want.
+pub fn signers_and_pubkeys(
+    matches: &ArgMatches,
+    inputs: &[String],
+) -> Result<(Vec<Box<dyn Signer>>, Vec<Pubkey>)> {
+    let signers = parse_signers(matches, inputs)?;
+    let pubkeys = signers.iter().map(|signer| signer.pubkey()).collect();
+    Ok((signers, pubkeys))
+}
+
+/// [parse_signer] for applications without an [ArgMatches] at hand,
+/// e.g. in the value parsers of a clap-derive CLI.
+///
+/// File, stdin, `prompt:`/`ASK` and `usb:` sources work as they do with [parse_signer].
+/// Since there are no real matches, the flags the Solana CLI reads alongside a signer
+/// are always considered absent:
+/// - seed phrases are always validated (no `--skip-seed-phrase-validation`),
+/// - hardware wallet keys are never confirmed (no `--confirm-key`),
+/// - a bare pubkey can't be used as a signer, since there are no `--signer`
+///   pubkey/signature pairs and no `--sign-only`.
+pub fn parse_signer_standalone(path: &str) -> Result<Box<dyn Signer>> {
+    parse_signer(&standalone_matches(), path)
+}
+
+/// [pubkey_or_signer_path] for applications without an [ArgMatches] at hand.
+/// See [parse_signer_standalone] for how signer paths are handled.
+pub fn pubkey_or_signer_path_standalone(input: &str) -> Result<Pubkey> {
+    pubkey_or_signer_path(input, &standalone_matches())
+}
+
+// Matches that declare every argument `signer_from_path` may look up,
+// which clap requires to avoid panicking in debug builds, with none of them present.
+pub(crate) fn standalone_matches() -> ArgMatches {
+    Command::new("standalone")
+        .arg(Arg::new(SKIP_SEED_PHRASE_VALIDATION_ARG.name)
+            .long(SKIP_SEED_PHRASE_VALIDATION_ARG.long))
+        .arg(Arg::new("confirm_key").long("confirm-key"))
+        .arg(Arg::new(SIGN_ONLY_ARG.name).long(SIGN_ONLY_ARG.long))
+        .arg(Arg::new(SIGNER_ARG.name)
+            .long(SIGNER_ARG.long)
+            .takes_value(true)
+            .multiple_occurrences(true))
+        .get_matches_from(vec!["standalone"])
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signature::{write_keypair_file, Keypair};
+    use super::*;
+
+    const MEMO_PROGRAM: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+    const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn matches() -> ArgMatches {
+        Command::new("test").get_matches_from(vec!["test"])
+    }
+
+    #[test]
+    fn well_known_program_shorthands() {
+        let expected = [
+            ("system", solana_sdk::system_program::id()),
+            ("token", spl_token::id()),
+            ("token22", Pubkey::from_str("TokenzQdBNbLqP5VEhdkAS6EPFLC1PJnBQCXEjmXGAP5N").unwrap()),
+            ("ata", spl_associated_token_account::id()),
+            ("memo", Pubkey::from_str(MEMO_PROGRAM).unwrap()),
+            ("stake", solana_sdk::stake::program::id()),
+            ("vote", Pubkey::from_str("Vote111111111111111111111111111111111111111").unwrap()),
+            ("bpf-upgradeable", solana_sdk::bpf_loader_upgradeable::id()),
+        ];
+        assert_eq!(well_known_programs(), &expected);
+        for (shorthand, program) in expected {
+            assert_eq!(pubkey_arg(shorthand).unwrap(), program);
+            assert_eq!(pubkey_arg(&shorthand.to_uppercase()).unwrap(), program);
+        }
+        assert_eq!(pubkey_arg(USDC_MINT).unwrap().to_string(), USDC_MINT);
+        let err = pubkey_arg("tokens").unwrap_err().to_string();
+        assert!(err.contains("system, token, token22, ata, memo, stake, vote, bpf-upgradeable"), "{}", err);
+    }
+
+    #[test]
+    fn ata_address() {
+        let owner = Keypair::new();
+        let path = std::env::temp_dir()
+            .join(format!("ata-owner-{}.json", std::process::id()));
+        write_keypair_file(&owner, &path).unwrap();
+        let mint = Pubkey::from_str(USDC_MINT).unwrap();
+
+        let ata = pubkey_or_signer_path(
+            &format!("ata:{}:{}", path.display(), USDC_MINT),
+            &matches(),
+        ).unwrap();
+        assert_eq!(ata, get_associated_token_address(&owner.pubkey(), &mint));
+        std::fs::remove_file(&path).unwrap();
+
+        let ata = pubkey_or_signer_path(
+            &format!("ata:HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk:{}", USDC_MINT),
+            &matches(),
+        ).unwrap();
+        assert_eq!(ata.to_string(), "5N3f1tj9v1vc5TUZ8S7mCAnVmjVKrfnzXWhxLaxyZAgt");
+    }
+
+    #[test]
+    fn ata_errors() {
+        let err = pubkey_or_signer_path(&format!("ata:{}", USDC_MINT), &matches())
+            .unwrap_err().to_string();
+        assert!(err.contains("expected ata:<owner>:<mint>"), "{}", err);
+        let err = pubkey_or_signer_path(
+            &format!("ata:{}:{}:{}", USDC_MINT, USDC_MINT, USDC_MINT),
+            &matches(),
+        ).unwrap_err().to_string();
+        assert!(err.contains("invalid associated token account owner"), "{}", err);
+    }
+
+    #[test]
+    fn signer_uri_components() {
+        // Only the splitting is checked, these signers can't be resolved in a test.
+        assert_eq!(
+            ata_components(&format!("prompt://:{}", USDC_MINT)).unwrap(),
+            ("prompt://", USDC_MINT),
+        );
+        assert_eq!(
+            ata_components(&format!("usb://ledger?key=0:{}", USDC_MINT)).unwrap(),
+            ("usb://ledger?key=0", USDC_MINT),
+        );
+        assert_eq!(
+            ata_components(&format!("file:///k.json:{}", USDC_MINT)).unwrap(),
+            ("file:///k.json", USDC_MINT),
+        );
+        assert_eq!(
+            pda_components("usb://ledger:hello,hex:0102").unwrap(),
+            ("usb://ledger", "hello,hex:0102"),
+        );
+        assert_eq!(
+            pda_components(&format!("{}:pubkey:prompt://", MEMO_PROGRAM)).unwrap(),
+            (MEMO_PROGRAM, "pubkey:prompt://"),
+        );
+    }
+
+    #[test]
+    fn pda_address() {
+        let pda = pubkey_or_signer_path(
+            &format!("pda:{}:hello,hex:0102,pubkey:11111111111111111111111111111111", MEMO_PROGRAM),
+            &matches(),
+        ).unwrap();
+        assert_eq!(pda.to_string(), "G94JAEKbCcT9Vu29NCWQjbScCzsqe54kWtUMRigh4hnN");
+    }
+
+    #[test]
+    fn pda_errors() {
+        let err = pubkey_or_signer_path(&format!("pda:{}", MEMO_PROGRAM), &matches())
+            .unwrap_err().to_string();
+        assert!(err.contains("expected pda:<program>"), "{}", err);
+        let err = pubkey_or_signer_path(&format!("pda:{}:a,hex:zz", MEMO_PROGRAM), &matches())
+            .unwrap_err().to_string();
+        assert!(err.contains("invalid seed 1"), "{}", err);
+        let err = pubkey_or_signer_path(
+            &format!("pda:{}:{}", MEMO_PROGRAM, "a".repeat(33)),
+            &matches(),
+        ).unwrap_err().to_string();
+        assert!(err.contains("seed is 33 bytes"), "{}", err);
+    }
+
+    #[test]
+    fn standalone_file_signer() {
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir()
+            .join(format!("standalone-signer-{}.json", std::process::id()));
+        write_keypair_file(&keypair, &path).unwrap();
+        let path = path.display().to_string();
+
+        assert_eq!(parse_signer_standalone(&path).unwrap().pubkey(), keypair.pubkey());
+        assert_eq!(pubkey_or_signer_path_standalone(&path).unwrap(), keypair.pubkey());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn signer_resolver_memoizes() {
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir()
+            .join(format!("resolver-signer-{}.json", std::process::id()));
+        write_keypair_file(&keypair, &path).unwrap();
+        let path = path.display().to_string();
+        let other = Keypair::new();
+        let other_path = std::env::temp_dir()
+            .join(format!("resolver-pubkey-{}.json", std::process::id()));
+        write_keypair_file(&other, &other_path).unwrap();
+        let other_path = other_path.display().to_string();
+
+        let mut resolver = SignerResolver::new();
+        let matches = standalone_matches();
+        let signer = resolver.parse_signer(&matches, &path).unwrap();
+        assert_eq!(resolver.pubkey_or_signer_path(&other_path, &matches).unwrap(), other.pubkey());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&other_path).unwrap();
+
+        // Neither file is read again once resolved.
+        let again = resolver.parse_signer(&matches, &path).unwrap();
+        assert_eq!(again.pubkey(), keypair.pubkey());
+        assert_eq!(again.sign_message(b"memo"), signer.sign_message(b"memo"));
+        assert_eq!(again.sign_message(b"memo"), keypair.sign_message(b"memo"));
+        assert_eq!(resolver.pubkey_or_signer_path(&path, &matches).unwrap(), keypair.pubkey());
+        assert_eq!(resolver.pubkey_or_signer_path(&other_path, &matches).unwrap(), other.pubkey());
+        let ata = resolver.pubkey_or_signer_path(
+            &format!("ata:{}:{}", other_path, USDC_MINT),
+            &matches,
+        ).unwrap();
+        assert_eq!(ata, get_associated_token_address(&other.pubkey(), &Pubkey::from_str(USDC_MINT).unwrap()));
+        let signers = resolver.parse_signers(&matches, &[path.clone()]).unwrap();
+        assert_eq!(signers[0].pubkey(), keypair.pubkey());
+
+        // Only a full signer can be used as one, not a remembered pubkey.
+        assert!(resolver.parse_signer(&matches, &other_path).is_err());
+        // A new resolver remembers nothing.
+        assert!(SignerResolver::new().parse_signer(&matches, &path).is_err());
+    }
+
+    #[test]
+    fn signer_resolver_keys_on_flags() {
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir()
+            .join(format!("resolver-flags-{}.json", std::process::id()));
+        write_keypair_file(&keypair, &path).unwrap();
+        let path = path.display().to_string();
+
+        let mut resolver = SignerResolver::new();
+        let standalone = standalone_matches();
+        resolver.parse_signer(&standalone, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(resolver.parse_signer(&standalone_matches(), &path).is_ok());
+
+        // The same source with different flags isn't the remembered signer.
+        let skip_validation = Command::new("standalone")
+            .arg(Arg::new(SKIP_SEED_PHRASE_VALIDATION_ARG.name)
+                .long(SKIP_SEED_PHRASE_VALIDATION_ARG.long))
+            .get_matches_from(vec!["standalone", "--skip-seed-phrase-validation"]);
+        assert_ne!(cache_key(&path, &skip_validation), cache_key(&path, &standalone));
+        assert!(resolver.parse_signer(&skip_validation, &path).is_err());
+        assert!(resolver.pubkey_or_signer_path(&path, &skip_validation).is_err());
+        // Undeclared flags are absent.
+        assert_eq!(cache_key(&path, &matches()), cache_key(&path, &standalone));
+    }
+
+    #[test]
+    fn free_functions_share_a_resolver() {
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir()
+            .join(format!("resolver-thread-{}.json", std::process::id()));
+        write_keypair_file(&keypair, &path).unwrap();
+        let path = path.display().to_string();
+        assert_eq!(parse_signer_standalone(&path).unwrap().pubkey(), keypair.pubkey());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(parse_signer_standalone(&path).unwrap().pubkey(), keypair.pubkey());
+        assert_eq!(pubkey_or_signer_path_standalone(&path).unwrap(), keypair.pubkey());
+
+        // Other threads have their own.
+        let on_other_thread = std::thread::spawn(move || parse_signer_standalone(&path).is_err());
+        assert!(on_other_thread.join().unwrap());
+    }
+
+    #[test]
+    fn standalone_pubkey() {
+        assert_eq!(
+            pubkey_or_signer_path_standalone(USDC_MINT).unwrap(),
+            Pubkey::from_str(USDC_MINT).unwrap(),
+        );
+        assert!(parse_signer_standalone(USDC_MINT).is_err());
+    }
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[clap(flatten)]
+        solana: SolanaCliArgs,
+    }
+
+    fn resolve_args(args: &[&str], config: &Config) -> Result<CliContext> {
+        let cli = TestCli::try_parse_from(std::iter::once("test").chain(args.iter().copied()))?;
+        cli.solana.resolve_with_config(Some(config))
+    }
+
+    #[test]
+    fn solana_cli_args_from_flags() {
+        let context = resolve_args(&[
+            "-k", "test/test-keypair.json",
+            "--url", "devnet",
+            "--commitment", "finalized",
+            "--skip-preflight",
+            "--output", "json",
+        ], &Config::default()).unwrap();
+        let keypair = crate::cli::keypair_from_path("test/test-keypair.json").unwrap();
+        assert_eq!(context.keypair.pubkey(), keypair.pubkey());
+        assert_eq!(context.cluster, Cluster::Devnet);
+        assert_eq!(context.commitment, CommitmentConfig::finalized());
+        assert_eq!(context.rpc_url, "https://api.devnet.solana.com");
+        assert_eq!(context.ws_url, "wss://api.devnet.solana.com");
+        assert_eq!(context.rpc_client.url(), "https://api.devnet.solana.com");
+        assert_eq!(context.rpc_client.commitment(), CommitmentConfig::finalized());
+        assert!(context.skip_preflight);
+        assert_eq!(context.output, OutputFormat::Json);
+    }
+
+    #[test]
+    fn solana_cli_args_from_config() {
+        let config = Config {
+            json_rpc_url: "http://localhost:18899".to_string(),
+            websocket_url: String::new(),
+            keypair_path: "test/test-keypair2.json".to_string(),
+            commitment: "processed".to_string(),
+            ..Config::default()
+        };
+        let context = resolve_args(&[], &config).unwrap();
+        let keypair = crate::cli::keypair_from_path("test/test-keypair2.json").unwrap();
+        assert_eq!(context.keypair.pubkey(), keypair.pubkey());
+        assert_eq!(context.cluster.url(), "http://localhost:18899");
+        assert_eq!(context.cluster.ws_url(), "ws://localhost:18900/");
+        assert_eq!(context.rpc_url, "http://localhost:18899");
+        assert_eq!(context.ws_url, "ws://localhost:18900/");
+        assert_eq!(context.rpc_client.url(), "http://localhost:18899");
+        assert_eq!(context.commitment, CommitmentConfig::processed());
+        assert!(!context.skip_preflight);
+        assert_eq!(context.output, OutputFormat::Human);
+
+        // Flags take precedence over the config.
+        let context = resolve_args(&["-u", "m", "--commitment", "confirmed"], &config).unwrap();
+        assert_eq!(context.cluster, Cluster::Mainnet);
+        assert_eq!(context.commitment, CommitmentConfig::confirmed());
+        let context = resolve_args(&["-u", "mainnet-beta"], &config).unwrap();
+        assert_eq!(context.cluster, Cluster::Mainnet);
+        assert_eq!(context.rpc_client.url(), "https://api.mainnet-beta.solana.com");
+    }
+
+    #[test]
+    fn cli_context_defaults() {
+        let _lock = crate::cli::SOLANA_CONFIG_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let missing = std::env::temp_dir().join(format!("missing-config-{}.yml", std::process::id()));
+        std::env::set_var(crate::cli::SOLANA_CONFIG_ENV_VAR, &missing);
+        let context = cli_context_from_config(&Some("test/test-keypair.json".to_string()), &None, &None);
+        let from_flags = cli_context_from_config(
+            &Some("test/test-keypair2.json".to_string()),
+            &Some("testnet".to_string()),
+            &Some("processed".to_string()),
+        );
+        std::env::remove_var(crate::cli::SOLANA_CONFIG_ENV_VAR);
+
+        let context = context.unwrap();
+        let keypair = crate::cli::keypair_from_path("test/test-keypair.json").unwrap();
+        assert_eq!(context.keypair.pubkey(), keypair.pubkey());
+        assert_eq!(context.cluster.url(), "http://localhost:8899");
+        assert_eq!(context.rpc_url, "http://localhost:8899");
+        assert_eq!(context.ws_url, "ws://localhost:8900/");
+        assert_eq!(context.commitment, CommitmentConfig::confirmed());
+        assert_eq!(context.rpc_client.commitment(), CommitmentConfig::confirmed());
+        assert!(!context.skip_preflight);
+        assert_eq!(context.output, OutputFormat::Human);
+
+        let context = from_flags.unwrap();
+        let keypair = crate::cli::keypair_from_path("test/test-keypair2.json").unwrap();
+        assert_eq!(context.keypair.pubkey(), keypair.pubkey());
+        assert_eq!(context.cluster, Cluster::Testnet);
+        assert_eq!(context.rpc_url, "https://api.testnet.solana.com");
+        assert_eq!(context.ws_url, "wss://api.testnet.solana.com");
+        assert_eq!(context.commitment, CommitmentConfig::processed());
+    }
+
+    #[test]
+    fn solana_cli_args_invalid() {
+        assert!(TestCli::try_parse_from(["test", "--output", "yaml"]).is_err());
+        let err = resolve_args(
+            &["-k", "test/test-keypair.json", "--commitment", "max"],
+            &Config::default(),
+        )
+            .err().unwrap().to_string();
+        assert!(err.contains("max"), "{}", err);
+    }
+
+    #[test]
+    fn multiple_signers() {
+        let keypair = crate::cli::keypair_from_path("test/test-keypair.json").unwrap();
+        let keypair2 = crate::cli::keypair_from_path("test/test-keypair2.json").unwrap();
+        let base58 = Keypair::new();
+        let inputs = vec![
+            "test/test-keypair.json,test/test-keypair2.json".to_string(),
+            base58.to_base58_string(),
+            // Duplicates are dropped.
+            "test/test-keypair.json".to_string(),
+        ];
+        let (signers, pubkeys) = signers_and_pubkeys(&standalone_matches(), &inputs).unwrap();
+        assert_eq!(signers.len(), 3);
+        assert_eq!(pubkeys, vec![keypair.pubkey(), keypair2.pubkey(), base58.pubkey()]);
+    }
+
+    #[test]
+    fn multiple_signers_aggregated_error() {
+        let inputs = vec![
+            "test/test-keypair.json".to_string(),
+            "test/missing-keypair.json".to_string(),
+            "test/test-keypair2.json".to_string(),
+        ];
+        let err = parse_signers(&standalone_matches(), &inputs).err().unwrap().to_string();
+        assert!(err.contains("1 of 3 signers"), "{}", err);
+        assert!(err.contains("test/missing-keypair.json"), "{}", err);
+        assert!(!err.contains("test/test-keypair.json"), "{}", err);
+        assert!(!err.contains("test/test-keypair2.json"), "{}", err);
+    }
 }
@@ -0,0 +1,175 @@
+/// Value parsers for common Solana types, for use with clap's `value_parser`:
+///
+/// ```ignore
+/// #[derive(Parser)]
+/// struct Transfer {
+///     #[clap(value_parser = parse_pubkey_or_signer_path)]
+///     to: Pubkey,
+///     #[clap(value_parser = parse_sol_amount)]
+///     amount: u64,
+/// }
+/// ```
+///
+/// Errors include the offending value and an example of a valid one.
+use std::str::FromStr;
+use anchor_client::Cluster;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use crate::amount;
+use crate::clap::{pubkey_or_signer_path_standalone, well_known_program, well_known_program_names};
+use crate::cli::{parse_commitment, resolve_cluster};
+
+const EXAMPLE_PUBKEY: &str = "11111111111111111111111111111111";
+const EXAMPLE_HASH: &str = "EkSnNWid2cvwEVnVx9aBqawnmiCNiDgp3gUdkDPTKN1N";
+const EXAMPLE_SIGNATURE: &str =
+    "2Ana1pUpv2ZbMVkwF5FXapYeBEjdxDatLn7nvJkhgTSXbs59SyZSx866bXirPgj8QQVB57uxHJBG1YFvkRbFj4T";
+
+/// Parses a base58 [Pubkey], or a well-known program shorthand like `token`,
+/// see [crate::clap::well_known_programs].
+pub fn parse_pubkey(input: &str) -> Result<Pubkey, String> {
+    if let Some(program) = well_known_program(input) {
+        return Ok(program);
+    }
+    Pubkey::from_str(input).map_err(|e| format!(
+        "invalid pubkey {:?}: {}, expected a base58 address like {} or one of: {}",
+        input, e, EXAMPLE_PUBKEY, well_known_program_names()
+    ))
+}
+
+/// Parses a cluster moniker, URL or alias, see [resolve_cluster].
+pub fn parse_cluster(input: &str) -> Result<Cluster, String> {
+    resolve_cluster(input).map_err(|e| format!(
+        "invalid cluster {:?}: {}, expected a moniker like devnet or a URL like https://api.devnet.solana.com",
+        input, e
+    ))
+}
+
+/// Parses a base58 [Hash], e.g. a blockhash.
+pub fn parse_hash(input: &str) -> Result<Hash, String> {
+    Hash::from_str(input).map_err(|e| format!(
+        "invalid hash {:?}: {}, expected a base58 hash like {}",
+        input, e, EXAMPLE_HASH
+    ))
+}
+
+/// Parses a base58 transaction [Signature].
+pub fn parse_signature(input: &str) -> Result<Signature, String> {
+    Signature::from_str(input).map_err(|e| format!(
+        "invalid signature {:?}: {}, expected a base58 signature like {}",
+        input, e, EXAMPLE_SIGNATURE
+    ))
+}
+
+/// Parses `processed`, `confirmed` or `finalized`, ignoring case.
+pub fn parse_commitment_config(input: &str) -> Result<CommitmentConfig, String> {
+    parse_commitment(input).map_err(|e| format!("{}, e.g. confirmed", e))
+}
+
+/// Parses a pubkey, or the pubkey of a signer path,
+/// see [crate::clap::pubkey_or_signer_path_standalone].
+pub fn parse_pubkey_or_signer_path(input: &str) -> Result<Pubkey, String> {
+    pubkey_or_signer_path_standalone(input).map_err(|e| format!(
+        "{}, expected a pubkey like {} or a keypair file like ~/.config/solana/id.json",
+        e, EXAMPLE_PUBKEY
+    ))
+}
+
+/// Parses an amount of SOL into lamports, e.g. `1.5`, `1.5sol` or `1500000000lamports`.
+/// See [crate::amount::parse_sol_amount].
+pub fn parse_sol_amount(input: &str) -> Result<u64, String> {
+    amount::parse_sol_amount(input)
+        .map_err(|e| format!("{}, expected an amount like 1.5 or 1500000000lamports", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::commitment_config::CommitmentLevel;
+    use solana_sdk::native_token::LAMPORTS_PER_SOL;
+    use solana_sdk::signature::{write_keypair_file, Keypair, Signer};
+    use super::*;
+
+    #[test]
+    fn pubkey() {
+        assert_eq!(parse_pubkey(EXAMPLE_PUBKEY).unwrap(), Pubkey::default());
+        let err = parse_pubkey("not-a-pubkey").unwrap_err();
+        assert!(err.contains("\"not-a-pubkey\""), "{}", err);
+        assert!(err.contains(EXAMPLE_PUBKEY), "{}", err);
+        assert!(parse_pubkey("").is_err());
+        assert!(err.contains("token22, ata, memo"), "{}", err);
+        assert_eq!(parse_pubkey("Token").unwrap(), spl_token::id());
+    }
+
+    #[test]
+    fn cluster() {
+        assert_eq!(parse_cluster("devnet").unwrap(), Cluster::Devnet);
+        assert_eq!(parse_cluster("mainnet-beta").unwrap(), Cluster::Mainnet);
+        let custom = parse_cluster("https://rpc.example.com").unwrap();
+        assert_eq!(custom.url(), "https://rpc.example.com");
+        let err = parse_cluster("nonsense").unwrap_err();
+        assert!(err.contains("\"nonsense\""), "{}", err);
+        assert!(err.contains("https://api.devnet.solana.com"), "{}", err);
+    }
+
+    #[test]
+    fn hash() {
+        assert_eq!(parse_hash(EXAMPLE_HASH).unwrap().to_string(), EXAMPLE_HASH);
+        let err = parse_hash("abc").unwrap_err();
+        assert!(err.contains("\"abc\""), "{}", err);
+        assert!(err.contains(EXAMPLE_HASH), "{}", err);
+    }
+
+    #[test]
+    fn signature() {
+        assert_eq!(parse_signature(EXAMPLE_SIGNATURE).unwrap().to_string(), EXAMPLE_SIGNATURE);
+        let err = parse_signature(EXAMPLE_HASH).unwrap_err();
+        assert!(err.contains(EXAMPLE_HASH), "{}", err);
+        assert!(err.contains(EXAMPLE_SIGNATURE), "{}", err);
+    }
+
+    #[test]
+    fn commitment() {
+        assert_eq!(
+            parse_commitment_config("Finalized").unwrap().commitment,
+            CommitmentLevel::Finalized,
+        );
+        let err = parse_commitment_config("max").unwrap_err();
+        assert!(err.contains("\"max\""), "{}", err);
+        assert!(err.contains("e.g. confirmed"), "{}", err);
+    }
+
+    #[test]
+    fn pubkey_or_signer_path() {
+        assert_eq!(parse_pubkey_or_signer_path(EXAMPLE_PUBKEY).unwrap(), Pubkey::default());
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir()
+            .join(format!("clap-parsers-{}.json", std::process::id()));
+        write_keypair_file(&keypair, &path).unwrap();
+        assert_eq!(
+            parse_pubkey_or_signer_path(&path.display().to_string()).unwrap(),
+            keypair.pubkey(),
+        );
+        std::fs::remove_file(&path).unwrap();
+        let err = parse_pubkey_or_signer_path("/does/not/exist.json").unwrap_err();
+        assert!(err.contains("/does/not/exist.json"), "{}", err);
+    }
+
+    #[test]
+    fn sol_amount() {
+        assert_eq!(parse_sol_amount("1").unwrap(), LAMPORTS_PER_SOL);
+        assert_eq!(parse_sol_amount("1.5").unwrap(), 1_500_000_000);
+        assert_eq!(parse_sol_amount(".5").unwrap(), 500_000_000);
+        assert_eq!(parse_sol_amount("2.").unwrap(), 2 * LAMPORTS_PER_SOL);
+        assert_eq!(parse_sol_amount("0.000000001").unwrap(), 1);
+        assert_eq!(parse_sol_amount("0").unwrap(), 0);
+        assert_eq!(parse_sol_amount("5lamports").unwrap(), 5);
+
+        let err = parse_sol_amount("0.0000000001").unwrap_err();
+        assert!(err.contains("at most 9 are allowed"), "{}", err);
+        assert!(err.contains("1.5"), "{}", err);
+        for invalid in ["", ".", "-1", "1.2.3", "1e9", "18446744074", "ALL"] {
+            assert!(parse_sol_amount(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anchor_client::Cluster;
+use anyhow::anyhow;
+use serde::Deserialize;
+
+/// Overrides [default_cluster_aliases_path].
+pub const CLUSTER_ALIASES_ENV_VAR: &str = "JUNGLE_FI_CLUSTERS";
+
+/// A named cluster in the aliases file, e.g.
+///
+/// ```toml
+/// [jungle-mainnet]
+/// url = "https://rpc.example.com"
+/// ws_url = "wss://rpc.example.com"
+/// auth_token_env = "EXAMPLE_RPC_TOKEN"
+///
+/// [jungle-mainnet.headers]
+/// x-client = "jungle-cli"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ClusterAlias {
+    pub url: String,
+    /// Derived from the [ClusterAlias::url] if not given.
+    pub ws_url: Option<String>,
+    /// Extra HTTP headers to send with every RPC request.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// An environment variable holding a token, sent as `Authorization: Bearer <token>`.
+    pub auth_token_env: Option<String>,
+}
+
+impl ClusterAlias {
+    pub fn cluster(&self) -> anyhow::Result<Cluster> {
+        let ws_url = match &self.ws_url {
+            Some(ws_url) => ws_url.clone(),
+            None => Cluster::from_str(&self.url)?.ws_url().to_string(),
+        };
+        Ok(Cluster::Custom(self.url.clone(), ws_url))
+    }
+
+    /// The configured headers, plus the `Authorization` header if the alias
+    /// has an `auth_token_env`. Errors if that variable isn't set.
+    pub fn headers(&self) -> anyhow::Result<BTreeMap<String, String>> {
+        let mut headers = self.headers.clone();
+        if let Some(var) = &self.auth_token_env {
+            let token = std::env::var(var)
+                .map_err(|_| anyhow!("environment variable {} is not set", var))?;
+            headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+        }
+        Ok(headers)
+    }
+}
+
+/// `~/.config/jungle-fi/clusters.toml`, unless [CLUSTER_ALIASES_ENV_VAR] is set.
+pub fn default_cluster_aliases_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var(CLUSTER_ALIASES_ENV_VAR) {
+        return Some(PathBuf::from(path));
+    }
+    dirs_next::home_dir().map(|home| home.join(".config/jungle-fi/clusters.toml"))
+}
+
+/// Load every alias in `path`. A missing file has no aliases.
+pub fn load_cluster_aliases(path: &Path) -> anyhow::Result<BTreeMap<String, ClusterAlias>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(anyhow!("unable to read {}: {}", path.display(), e)),
+    };
+    toml::from_str(&contents)
+        .map_err(|e| anyhow!("invalid cluster aliases file {}: {}", path.display(), e))
+}
+
+/// Look up an alias in the file at `path`.
+pub fn find_cluster_alias(path: &Path, name: &str) -> anyhow::Result<Option<ClusterAlias>> {
+    Ok(load_cluster_aliases(path)?.remove(name))
+}
+
+/// Resolve a cluster moniker, URL or alias. Aliases in the file at `aliases_path`
+/// are checked first, and anything else is parsed by [Cluster::from_str], which also
+/// accepts the Solana CLI's `mainnet-beta` and `localhost` monikers.
+pub fn resolve_cluster_with_aliases(
+    url: &str,
+    aliases_path: Option<&Path>,
+) -> anyhow::Result<Cluster> {
+    // URLs can't be aliases, so don't bother reading the file.
+    if !url.starts_with("http") {
+        if let Some(path) = aliases_path {
+            if let Some(alias) = find_cluster_alias(path, url)? {
+                return alias.cluster();
+            }
+        }
+    }
+    Cluster::from_str(normalize_moniker(url))
+}
+
+// The Solana CLI's spelling of a moniker, in the spelling anchor's [Cluster] parses.
+fn normalize_moniker(url: &str) -> &str {
+    if url.eq_ignore_ascii_case("mainnet-beta") {
+        "mainnet"
+    } else if url.eq_ignore_ascii_case("localhost") {
+        "localnet"
+    } else {
+        url
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("clusters-{}-{}.toml", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    const ALIASES: &str = r#"
+[jungle-mainnet]
+url = "https://rpc.example.com"
+auth_token_env = "CLUSTER_ALIASES_TEST_TOKEN"
+
+[jungle-mainnet.headers]
+x-client = "jungle-cli"
+
+[jungle-devnet]
+url = "https://devnet.example.com:8899"
+ws_url = "wss://devnet-ws.example.com"
+"#;
+
+    #[test]
+    fn alias_hit() {
+        let path = aliases_file("hit", ALIASES);
+        let cluster = resolve_cluster_with_aliases("jungle-devnet", Some(&path)).unwrap();
+        assert_eq!(cluster.url(), "https://devnet.example.com:8899");
+        assert_eq!(cluster.ws_url(), "wss://devnet-ws.example.com");
+        let cluster = resolve_cluster_with_aliases("jungle-mainnet", Some(&path)).unwrap();
+        assert_eq!(cluster.ws_url(), "wss://rpc.example.com/");
+
+        let alias = find_cluster_alias(&path, "jungle-mainnet").unwrap().unwrap();
+        std::env::set_var("CLUSTER_ALIASES_TEST_TOKEN", "secret");
+        let headers = alias.headers().unwrap();
+        assert_eq!(headers["Authorization"], "Bearer secret");
+        assert_eq!(headers["x-client"], "jungle-cli");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn alias_miss_falls_through() {
+        let path = aliases_file("miss", ALIASES);
+        assert_eq!(resolve_cluster_with_aliases("devnet", Some(&path)).unwrap(), Cluster::Devnet);
+        assert_eq!(
+            resolve_cluster_with_aliases("devnet", Some(&path.with_extension("missing"))).unwrap(),
+            Cluster::Devnet,
+        );
+        assert!(resolve_cluster_with_aliases("not-an-alias", Some(&path)).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn solana_cli_monikers() {
+        assert_eq!(resolve_cluster_with_aliases("mainnet-beta", None).unwrap(), Cluster::Mainnet);
+        assert_eq!(resolve_cluster_with_aliases("Mainnet-Beta", None).unwrap(), Cluster::Mainnet);
+        assert_eq!(resolve_cluster_with_aliases("localhost", None).unwrap(), Cluster::Localnet);
+    }
+
+    #[test]
+    fn malformed_file_errors() {
+        let path = aliases_file("malformed", "[jungle-mainnet]\nws_url = 5\n");
+        let err = resolve_cluster_with_aliases("jungle-mainnet", Some(&path)).unwrap_err();
+        assert!(err.to_string().contains("invalid cluster aliases file"));
+        fs::remove_file(&path).unwrap();
+    }
+}
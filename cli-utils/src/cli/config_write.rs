@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::Path;
+use anchor_client::solana_sdk::signature::read_keypair_file;
+use anyhow::anyhow;
+use solana_cli_config::Config;
+use crate::cli::{get_solana_cli_config_from, resolve_cluster, solana_cli_config_path};
+
+/// Set the RPC URL in the Solana CLI config file, like `solana config set --url`.
+/// `url` may be anything [resolve_cluster] accepts, and is saved as a URL.
+/// The WebSocket URL is reset, so that it's computed from the new RPC URL.
+///
+/// The config file is chosen like [get_solana_cli_config_from], and created if it
+/// doesn't exist. Returns the updated config.
+pub fn set_config_url(url: &str, path: Option<&Path>) -> anyhow::Result<Config> {
+    let cluster = resolve_cluster(url)
+        .map_err(|e| anyhow!("invalid url {}: {}", url, e))?;
+    update_config(path, |config| {
+        config.json_rpc_url = cluster.url().to_string();
+        config.websocket_url = String::new();
+    })
+}
+
+/// Set the keypair path in the Solana CLI config file, like `solana config set --keypair`.
+/// The keypair file must exist and contain a valid keypair.
+///
+/// The config file is chosen like [get_solana_cli_config_from], and created if it
+/// doesn't exist. Returns the updated config.
+pub fn set_config_keypair(keypair_path: &str, path: Option<&Path>) -> anyhow::Result<Config> {
+    read_keypair_file(keypair_path)
+        .map_err(|e| anyhow!("invalid keypair file {}: {}", keypair_path, e))?;
+    update_config(path, |config| {
+        config.keypair_path = keypair_path.to_string();
+    })
+}
+
+// Load the config (or the default if there's none yet), update it, and save it atomically
+// by writing a temporary file next to it and renaming it over the original.
+fn update_config(path: Option<&Path>, update: impl FnOnce(&mut Config)) -> anyhow::Result<Config> {
+    let (config_file, _) = solana_cli_config_path(path)?;
+    let mut config = if config_file.exists() {
+        get_solana_cli_config_from(Some(&config_file))?
+    } else {
+        Config::default()
+    };
+    update(&mut config);
+    let mut temp_file = config_file.clone().into_os_string();
+    temp_file.push(".tmp");
+    let temp_file = temp_file.into_string()
+        .map_err(|_| anyhow!("config file path {} is not valid utf8", config_file.display()))?;
+    config.save(&temp_file)
+        .map_err(|e| anyhow!("unable to write config file {}: {}", temp_file, e))?;
+    fs::rename(&temp_file, &config_file)
+        .map_err(|e| anyhow!("unable to replace config file {}: {}", config_file.display(), e))?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_and_update_config() {
+        let dir = std::env::temp_dir().join(format!("config-write-{}", std::process::id()));
+        let config_file = dir.join("nested").join("config.yml");
+        let _ = fs::remove_dir_all(&dir);
+
+        // Creates a new config, with any missing directories.
+        let config = set_config_url("devnet", Some(&config_file)).unwrap();
+        assert_eq!(config.json_rpc_url, "https://api.devnet.solana.com");
+        assert_eq!(config.websocket_url, "");
+        assert_eq!(config.keypair_path, Config::default().keypair_path);
+        let loaded = get_solana_cli_config_from(Some(&config_file)).unwrap();
+        assert_eq!(loaded.json_rpc_url, "https://api.devnet.solana.com");
+
+        // Updates the existing config, keeping other fields.
+        let mut config = loaded;
+        config.websocket_url = "ws://custom:8900".to_string();
+        config.save(config_file.to_str().unwrap()).unwrap();
+        let config = set_config_keypair("test/test-keypair.json", Some(&config_file)).unwrap();
+        assert_eq!(config.keypair_path, "test/test-keypair.json");
+        assert_eq!(config.websocket_url, "ws://custom:8900");
+        let config = set_config_url("http://localhost:18899", Some(&config_file)).unwrap();
+        assert_eq!(config.keypair_path, "test/test-keypair.json");
+        assert_eq!(config.websocket_url, "");
+        let loaded = get_solana_cli_config_from(Some(&config_file)).unwrap();
+        assert_eq!(loaded.json_rpc_url, "http://localhost:18899");
+        assert_eq!(loaded.keypair_path, "test/test-keypair.json");
+        let config = set_config_url("mainnet-beta", Some(&config_file)).unwrap();
+        assert_eq!(config.json_rpc_url, "https://api.mainnet-beta.solana.com");
+        assert!(!dir.join("nested").join("config.yml.tmp").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_keypair_is_not_saved() {
+        let dir = std::env::temp_dir().join(format!("config-write-invalid-{}", std::process::id()));
+        let config_file = dir.join("config.yml");
+        let err = set_config_keypair("test/missing-keypair.json", Some(&config_file))
+            .unwrap_err().to_string();
+        assert!(err.contains("invalid keypair file test/missing-keypair.json"), "{}", err);
+        assert!(!config_file.exists());
+    }
+}
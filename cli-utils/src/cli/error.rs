@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+use anchor_client::solana_sdk::derivation_path::DerivationPathError;
+use thiserror::Error;
+use crate::cli::CLUSTER_MONIKERS;
+
+/// Why a cluster URL, keypair or config file couldn't be resolved.
+/// Converts into [anyhow::Error] with `?` like any other error.
+///
+/// Keypair errors never include secret keys or seed phrases: `location` is a file path
+/// or environment variable name, and is [None] for sources like `base58:` and `prompt:`.
+#[derive(Debug, Error)]
+pub enum CliConfigError {
+    #[error(
+        "invalid url {url:?}: this is a WebSocket URL, pass the cluster's http(s) RPC URL instead. \
+        The WebSocket URL is derived from it by resolve_urls."
+    )]
+    WebSocketUrl { url: String },
+    /// Not an http(s) URL, moniker or alias. `suggestion` is the moniker the user
+    /// most likely meant, if any.
+    #[error(
+        "invalid url {url:?}: expected an http(s) URL or one of: {}.{}",
+        CLUSTER_MONIKERS.join(", "),
+        .suggestion.map(|moniker| format!(" Did you mean {:?}?", moniker)).unwrap_or_default()
+    )]
+    InvalidUrl {
+        url: String,
+        suggestion: Option<&'static str>,
+    },
+    /// The keypair path has an unknown `scheme:`, or isn't a URI at all.
+    /// The rest of the path isn't kept, in case it's a mistyped secret.
+    #[error(
+        "unrecognized keypair source{}",
+        .scheme.as_ref().map(|scheme| format!(" scheme {:?}", scheme)).unwrap_or_default()
+    )]
+    UnrecognizedKeypairSource { scheme: Option<String> },
+    #[error("invalid derivation path in keypair source {path:?}: {error}")]
+    InvalidDerivationPath {
+        path: String,
+        error: DerivationPathError,
+    },
+    /// The keypair path isn't a file, nor a base58 secret key.
+    #[error("Failed to read keypair from {path}: {error}")]
+    KeypairFileNotFound {
+        path: String,
+        error: std::io::Error,
+    },
+    /// The source was found, but didn't hold a usable keypair.
+    #[error("Failed to read keypair from {kind}: {message}")]
+    KeypairUnreadable {
+        /// The source kind, e.g. `file`, `env` or `seed-file`.
+        kind: &'static str,
+        location: Option<String>,
+        message: String,
+    },
+    #[error("unable to determine a config file path on this OS or user")]
+    ConfigPathUnknown,
+    #[error("config file {} from {origin} does not exist", .path.display())]
+    ConfigMissing {
+        path: PathBuf,
+        /// Where the path came from, e.g. the SOLANA_CONFIG environment variable.
+        origin: &'static str,
+    },
+    #[error("config file path {} is not valid utf8", .path.display())]
+    ConfigPathNotUtf8 { path: PathBuf },
+    #[error("unable to parse config file {} from {origin}: {message}", .path.display())]
+    ConfigInvalid {
+        path: PathBuf,
+        origin: &'static str,
+        message: String,
+    },
+}
@@ -1,42 +1,195 @@
 mod parse_keypair_from_path;
+mod cluster_aliases;
+mod rpc_client;
+mod config_write;
+mod error;
 
-use std::str::FromStr;
 use anchor_client::Cluster;
 use solana_cli_config::Config;
-use solana_sdk::signature::Keypair;
+use clap::ArgMatches;
+use solana_sdk::signature::{Keypair, Signer};
 use log::warn;
 use anyhow::anyhow;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use thiserror::Error;
+use std::path::{Path, PathBuf};
 
+pub use crate::cli::error::CliConfigError;
 pub use crate::cli::parse_keypair_from_path::keypair_from_path;
+pub use crate::cli::rpc_client::{DEFAULT_RPC_TIMEOUT, rpc_client_from_cli_config};
+#[cfg(feature = "nonblocking")]
+pub use crate::cli::rpc_client::nonblocking_rpc_client_from_cli_config;
+pub use crate::cli::config_write::{set_config_keypair, set_config_url};
+pub use crate::cli::cluster_aliases::{
+    ClusterAlias, CLUSTER_ALIASES_ENV_VAR, default_cluster_aliases_path, find_cluster_alias,
+    load_cluster_aliases, resolve_cluster_with_aliases,
+};
 
 const LOCALNET_URL: &str = "http://localhost:8899";
 
+/// Parse a cluster moniker, URL, or an alias from the [default_cluster_aliases_path] file.
+pub fn resolve_cluster(url: &str) -> anyhow::Result<Cluster> {
+    resolve_cluster_with_aliases(url, default_cluster_aliases_path().as_deref())
+}
+
+/// The cluster monikers accepted by [resolve_cluster] and [resolve_url], besides URLs and aliases.
+pub const CLUSTER_MONIKERS: [&str; 4] = ["mainnet-beta", "devnet", "testnet", "localnet"];
+
 /// Return a url [String] based on an optional url or [solana_cli_config::Config] object.
 /// Passing [None] to both arguments will fetch the config file and resolve from there.
+///
+/// An explicit `url` must be a cluster moniker (see [CLUSTER_MONIKERS]), an alias,
+/// or an http(s) URL, which is returned without a trailing slash.
 pub fn resolve_url(
     url: &Option<String>,
     config: Option<&Config>,
+) -> Result<String, CliConfigError> {
+    match url {
+        Some(url) => validate_cluster_url(url),
+        None => Ok(config_url(config)),
+    }
+}
+
+/// [resolve_url] without validating an explicit `url`. Monikers, aliases and URLs are
+/// resolved by [resolve_cluster], and anything else is returned unchanged.
+pub fn resolve_url_lenient(
+    url: &Option<String>,
+    config: Option<&Config>,
 ) -> anyhow::Result<String> {
     // Prioritize the URL, if passed in.
+    if let Some(url) = url {
+        return Ok(match resolve_cluster(url) {
+            Ok(cluster) => cluster.url().to_string(),
+            Err(_) => url.clone(),
+        });
+    }
+    Ok(config_url(config))
+}
+
+// The RPC URL from `config`, or else from the config file, defaulting to localnet.
+fn config_url(config: Option<&Config>) -> String {
+    if let Some(config) = config {
+        return config.json_rpc_url.clone();
+    }
+    let config = get_solana_cli_config().unwrap_or_else(
+        |_| {
+            warn!("No config file found or url provided, defaulting to localnet");
+            let mut config = Config::default();
+            config.json_rpc_url = LOCALNET_URL.to_string();
+            config
+        }
+    );
+    config.json_rpc_url
+}
+
+fn validate_cluster_url(input: &str) -> Result<String, CliConfigError> {
+    if input.starts_with("ws://") || input.starts_with("wss://") {
+        return Err(CliConfigError::WebSocketUrl { url: input.to_string() });
+    }
+    let invalid = || CliConfigError::InvalidUrl {
+        url: input.to_string(),
+        suggestion: nearest_moniker(input),
+    };
+    let cluster = resolve_cluster(input).map_err(|_| invalid())?;
+    let url = cluster.url();
+    let is_http = match uriparse::URI::try_from(url) {
+        Ok(uri) => matches!(uri.scheme().as_str(), "http" | "https")
+            && uri.host().map_or(false, |host| !host.to_string().is_empty()),
+        Err(_) => false,
+    };
+    if !is_http {
+        return Err(invalid());
+    }
+    // Normalize a trailing slash, unless it's part of a query.
+    if url.contains('?') {
+        Ok(url.to_string())
+    } else {
+        Ok(url.trim_end_matches('/').to_string())
+    }
+}
+
+// The moniker within one edit (or transposition) of `input`, if any.
+fn nearest_moniker(input: &str) -> Option<&'static str> {
+    let input = input.to_ascii_lowercase();
+    ["mainnet", "mainnet-beta", "devnet", "testnet", "localnet"]
+        .into_iter()
+        .find(|moniker| edit_distance(&input, moniker) <= 1)
+        .map(|moniker| if moniker == "mainnet" { "mainnet-beta" } else { moniker })
+}
+
+// Optimal string alignment distance.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for j in 0..=b.len() {
+        d[0][j] = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// The HTTP and WebSocket endpoints of a cluster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedUrls {
+    pub http: String,
+    pub ws: String,
+}
+
+/// Like [resolve_url], but also returns the WebSocket URL for subscriptions.
+/// Cluster monikers use the WebSocket URLs known to [Cluster], while localnet and
+/// explicit URLs follow the Solana CLI's derivation (`ws` scheme, port + 1).
+/// A non-empty `websocket_url` in the config is used as-is.
+pub fn resolve_urls(
+    url: &Option<String>,
+    config: Option<&Config>,
+) -> anyhow::Result<ResolvedUrls> {
     if let Some(url) = url.clone() {
-        return Ok(Cluster::from_str(&url)?.url().to_string());
+        let cluster = resolve_cluster(&url)?;
+        let http = cluster.url().to_string();
+        let ws = match cluster {
+            Cluster::Localnet | Cluster::Debug => Config::compute_websocket_url(&http),
+            _ => cluster.ws_url().to_string(),
+        };
+        return Ok(ResolvedUrls { http, ws });
     }
-    // Otherwise, call back to the config file.
-    // Find the config file (or create a default one), and use the supplied
-    // RPC URL to create the [anchor_client::cluster::Cluster].
+    let urls_from_config = |config: &Config| {
+        let ws = if config.websocket_url.is_empty() {
+            Config::compute_websocket_url(&config.json_rpc_url)
+        } else {
+            config.websocket_url.clone()
+        };
+        ResolvedUrls {
+            http: config.json_rpc_url.clone(),
+            ws,
+        }
+    };
     if let Some(config) = config {
-        return Ok(config.json_rpc_url.clone());
+        return Ok(urls_from_config(config));
     }
-    let config = get_solana_cli_config().unwrap_or(
-        {
+    let config = get_solana_cli_config().unwrap_or_else(
+        |_| {
             warn!("No config file found or url provided, defaulting to localnet");
-            println!("No config file found or url provided, defaulting to localnet");
             let mut config = Config::default();
             config.json_rpc_url = LOCALNET_URL.to_string();
+            config.websocket_url = String::new();
             config
         }
     );
-    Ok(config.json_rpc_url)
+    Ok(urls_from_config(&config))
 }
 
 /// Return a [solana_sdk::signer::Keypair] based on an optional keypair path
@@ -45,37 +198,192 @@ pub fn resolve_url(
 pub fn resolve_keypair(
     keypair_path: &Option<String>,
     config: Option<&Config>,
-) -> anyhow::Result<Box<Keypair>> {
+) -> Result<Box<Keypair>, CliConfigError> {
     if let Some(keypair_path) = keypair_path {
         return keypair_from_path(keypair_path);
     }
     if let Some(config) = config {
         return keypair_from_path(&config.keypair_path);
     }
-    let config = get_solana_cli_config().unwrap_or(
-        {
+    let config = get_solana_cli_config().unwrap_or_else(
+        |_| {
             warn!("No config file found or -k/--keypair provided, defaulting to ~/.config/solana/id.json");
-            println!("No config file found or -k/--keypair provided, defaulting to ~/.config/solana/id.json");
-            let config = Config::default();
-            config
+            Config::default()
         }
     );
     keypair_from_path(&config.keypair_path)
 }
 
+/// Like [resolve_keypair], but tries each of `fallbacks` in order between the
+/// `-k/--keypair` value and the config file, for keys kept at role-specific paths.
+/// Returns the keypair along with a description of the source it was read from.
+///
+/// Only files that don't exist are skipped. A source that exists but can't be read
+/// or isn't a keypair, e.g. due to permissions, is an error rather than falling through.
+pub fn resolve_keypair_with_fallbacks(
+    primary: &Option<String>,
+    fallbacks: &[String],
+    config: Option<&Config>,
+) -> anyhow::Result<(Box<Keypair>, String)> {
+    let mut candidates = vec![];
+    if let Some(primary) = primary {
+        candidates.push((primary.clone(), "-k/--keypair"));
+    }
+    candidates.extend(fallbacks.iter().map(|fallback| (fallback.clone(), "fallback")));
+    let config_path = match config {
+        Some(config) => config.keypair_path.clone(),
+        None => get_solana_cli_config()
+            .unwrap_or_else(|_| {
+                warn!("No config file found, defaulting to ~/.config/solana/id.json");
+                Config::default()
+            })
+            .keypair_path,
+    };
+    candidates.push((config_path, "the config file"));
+
+    let mut missing = vec![];
+    for (path, source) in candidates {
+        if parse_keypair_from_path::is_missing_file(&path) {
+            missing.push(path);
+            continue;
+        }
+        let keypair = keypair_from_path(&path)
+            .map_err(|e| anyhow!("Could not read keypair {} from {}: {}", path, source, e))?;
+        return Ok((keypair, format!("{} ({})", source, path)));
+    }
+    Err(anyhow!("No keypair found, tried: {}", missing.join(", ")))
+}
+
+/// Like [resolve_keypair], but resolves any signer that `-k/--keypair` accepts in
+/// the Solana CLI, including hardware wallets (`usb://ledger`).
+/// `matches` supplies `--confirm-key` and `--skip-seed-phrase-validation`, see [crate::clap::KeypairArg].
+///
+/// The signer is resolved by the thread's [crate::clap::SignerResolver], so hardware wallets
+/// share one wallet manager with [crate::clap::parse_signer] and [crate::clap::parse_signers].
+pub fn resolve_signer(
+    signer_path: &Option<String>,
+    config: Option<&Config>,
+    matches: &ArgMatches,
+) -> anyhow::Result<Box<dyn Signer>> {
+    let (path, source) = match (signer_path, config) {
+        (Some(signer_path), _) => (signer_path.clone(), "-k/--keypair"),
+        (None, Some(config)) => (config.keypair_path.clone(), "the config file"),
+        (None, None) => {
+            let config = get_solana_cli_config().unwrap_or_else(
+                |_| {
+                    warn!("No config file found or -k/--keypair provided, defaulting to ~/.config/solana/id.json");
+                    Config::default()
+                }
+            );
+            (config.keypair_path, "the config file default")
+        }
+    };
+    crate::clap::parse_signer(matches, &path)
+        .map_err(|e| anyhow!("Could not resolve {} from {}: {}", path, source, e))
+}
+
+/// [resolve_signer] without any `ArgMatches`, for when hardware wallet
+/// options like `--confirm-key` aren't needed.
+/// See [crate::clap::parse_signer_standalone] for the sources this supports.
+pub fn resolve_signer_simple(
+    signer_path: &Option<String>,
+    config: Option<&Config>,
+) -> anyhow::Result<Box<dyn Signer>> {
+    resolve_signer(signer_path, config, &crate::clap::standalone_matches())
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("invalid commitment level {0:?}, expected one of: processed, confirmed, finalized")]
+pub struct InvalidCommitment(pub String);
 
-/// Load configuration from the standard Solana CLI config path.
+/// Parses `processed`, `confirmed` or `finalized`, ignoring case.
+pub fn parse_commitment(commitment: &str) -> Result<CommitmentConfig, InvalidCommitment> {
+    let commitment = match commitment.to_ascii_lowercase().as_str() {
+        "processed" => CommitmentLevel::Processed,
+        "confirmed" => CommitmentLevel::Confirmed,
+        "finalized" => CommitmentLevel::Finalized,
+        _ => return Err(InvalidCommitment(commitment.to_string())),
+    };
+    Ok(CommitmentConfig { commitment })
+}
+
+/// Return a [CommitmentConfig] based on an optional flag value or [solana_cli_config::Config] object.
+/// Passing [None] to both arguments will fetch the config file and resolve from there,
+/// defaulting to `confirmed` if it can't be loaded or has no commitment set.
+pub fn resolve_commitment(
+    flag: &Option<String>,
+    config: Option<&Config>,
+) -> anyhow::Result<CommitmentConfig> {
+    if let Some(flag) = flag {
+        return Ok(parse_commitment(flag)?);
+    }
+    let from_config = |config: &Config| -> anyhow::Result<CommitmentConfig> {
+        if config.commitment.is_empty() {
+            return Ok(CommitmentConfig::confirmed());
+        }
+        Ok(parse_commitment(&config.commitment)?)
+    };
+    if let Some(config) = config {
+        return from_config(config);
+    }
+    match get_solana_cli_config() {
+        Ok(config) => from_config(&config),
+        Err(_) => Ok(CommitmentConfig::confirmed()),
+    }
+}
+
+/// The environment variable that overrides the location of the Solana CLI config file.
+pub const SOLANA_CONFIG_ENV_VAR: &str = "SOLANA_CONFIG";
+
+// Held by tests that set SOLANA_CONFIG_ENV_VAR.
+#[cfg(test)]
+pub(crate) static SOLANA_CONFIG_ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Load configuration from the standard Solana CLI config path,
+/// or the path in the [SOLANA_CONFIG_ENV_VAR] environment variable if it's set.
 /// Those config values are used as defaults at runtime whenever
 /// keypair and/or url are not explicitly passed in.
 /// This can possibly fail if there is no Solana CLI installed, nor a config file
 /// at the expected location.
-pub fn get_solana_cli_config() -> anyhow::Result<Config> {
-    let config_file = solana_cli_config::CONFIG_FILE.as_ref()
-        .ok_or_else(|| anyhow!("unable to determine a config file path on this OS or user"))?;
-    Config::load(&config_file)
-        .map_err(|e| anyhow!("unable to load config file: {}", e.to_string()))
+pub fn get_solana_cli_config() -> Result<Config, CliConfigError> {
+    get_solana_cli_config_from(None)
 }
 
+// The config file to use, and a description of where it came from for error messages.
+fn solana_cli_config_path(path: Option<&Path>) -> Result<(PathBuf, &'static str), CliConfigError> {
+    Ok(match path {
+        Some(path) => (path.to_path_buf(), "the given config path"),
+        None => match std::env::var_os(SOLANA_CONFIG_ENV_VAR) {
+            Some(path) if !path.is_empty() => (
+                PathBuf::from(path),
+                "the SOLANA_CONFIG environment variable",
+            ),
+            _ => {
+                let path = solana_cli_config::CONFIG_FILE.as_ref()
+                    .ok_or(CliConfigError::ConfigPathUnknown)?;
+                (PathBuf::from(path), "the default config location")
+            }
+        },
+    })
+}
+
+/// Load configuration from, in order of preference: `path`, the path in the
+/// [SOLANA_CONFIG_ENV_VAR] environment variable, or the standard Solana CLI config path.
+/// Errors name the source that was used, and whether the file is missing or invalid.
+pub fn get_solana_cli_config_from(path: Option<&Path>) -> Result<Config, CliConfigError> {
+    let (config_file, origin) = solana_cli_config_path(path)?;
+    if !config_file.exists() {
+        return Err(CliConfigError::ConfigMissing { path: config_file, origin });
+    }
+    let config_file_str = config_file.to_str()
+        .ok_or_else(|| CliConfigError::ConfigPathNotUtf8 { path: config_file.clone() })?;
+    Config::load(config_file_str)
+        .map_err(|e| CliConfigError::ConfigInvalid {
+            path: config_file.clone(),
+            origin,
+            message: e.to_string(),
+        })
+}
 
 #[cfg(test)]
 mod tests {
@@ -85,9 +393,9 @@ mod tests {
     #[test]
     fn test_resolve_url() {
         // Always use the passed URL.
-        let url = resolve_url(&Some("foo".to_string()), None)
+        let url = resolve_url(&Some("https://foo.com".to_string()), None)
             .unwrap();
-        assert_eq!(url, "foo".to_string());
+        assert_eq!(url, "https://foo.com".to_string());
         let mut config = Config::default();
         // Or use the config file
         config.json_rpc_url = "bar".to_string();
@@ -95,9 +403,136 @@ mod tests {
             .unwrap();
         assert_eq!(url, "bar".to_string());
         // Even if the config file was passed in, we use the url passed in.
-        let url = resolve_url(&Some("foo".to_string()), Some(&config))
+        let url = resolve_url(&Some("https://foo.com".to_string()), Some(&config))
+            .unwrap();
+        assert_eq!(url, "https://foo.com".to_string());
+        // Anything that isn't a URL or moniker is rejected, unless resolving leniently.
+        let err = resolve_url(&Some("foo".to_string()), None).unwrap_err().to_string();
+        assert!(err.contains("\"foo\""), "{}", err);
+        assert!(err.contains("mainnet-beta, devnet, testnet, localnet"), "{}", err);
+        let url = resolve_url_lenient(&Some("foo".to_string()), None)
             .unwrap();
         assert_eq!(url, "foo".to_string());
+        let url = resolve_url_lenient(&Some("devnet".to_string()), None)
+            .unwrap();
+        assert_eq!(url, "https://api.devnet.solana.com".to_string());
+    }
+
+    #[test]
+    fn test_resolve_url_validation() {
+        let resolve = |url: &str| resolve_url(&Some(url.to_string()), None);
+        // Monikers
+        assert_eq!(resolve("mainnet-beta").unwrap(), "https://api.mainnet-beta.solana.com");
+        assert_eq!(resolve("devnet").unwrap(), "https://api.devnet.solana.com");
+        assert_eq!(resolve("l").unwrap(), "http://127.0.0.1:8899");
+        // Trailing slashes are normalized.
+        assert_eq!(resolve("https://rpc.example.com/").unwrap(), "https://rpc.example.com");
+        assert_eq!(resolve("http://localhost:8899/").unwrap(), "http://localhost:8899");
+        assert_eq!(resolve("https://rpc.example.com/?key=a/").unwrap(), "https://rpc.example.com/?key=a/");
+        // Typos get a suggestion.
+        let err = resolve("mainet").unwrap_err().to_string();
+        assert!(err.contains("Did you mean \"mainnet-beta\"?"), "{}", err);
+        let err = resolve("devent").unwrap_err().to_string();
+        assert!(err.contains("Did you mean \"devnet\"?"), "{}", err);
+        let err = resolve("nonsense").unwrap_err().to_string();
+        assert!(!err.contains("Did you mean"), "{}", err);
+        // WebSocket URLs point to resolve_urls.
+        let err = resolve("wss://api.devnet.solana.com").unwrap_err().to_string();
+        assert!(err.contains("WebSocket URL"), "{}", err);
+        assert!(err.contains("resolve_urls"), "{}", err);
+        assert!(resolve("http://").is_err());
+    }
+
+    #[test]
+    fn test_resolve_urls() {
+        let resolve = |url: &str| resolve_urls(&Some(url.to_string()), None).unwrap();
+        // Cluster monikers
+        assert_eq!(resolve("devnet"), ResolvedUrls {
+            http: "https://api.devnet.solana.com".to_string(),
+            ws: "wss://api.devnet.solana.com".to_string(),
+        });
+        assert_eq!(resolve("m").ws, "wss://api.mainnet-beta.solana.com");
+        assert_eq!(resolve("mainnet-beta"), ResolvedUrls {
+            http: "https://api.mainnet-beta.solana.com".to_string(),
+            ws: "wss://api.mainnet-beta.solana.com".to_string(),
+        });
+        assert_eq!(resolve("localnet").ws, "ws://127.0.0.1:8900/");
+        // Explicit URLs
+        assert_eq!(resolve("https://rpc.example.com"), ResolvedUrls {
+            http: "https://rpc.example.com".to_string(),
+            ws: "wss://rpc.example.com/".to_string(),
+        });
+        assert_eq!(resolve("http://localhost:18899"), ResolvedUrls {
+            http: "http://localhost:18899".to_string(),
+            ws: "ws://localhost:18900/".to_string(),
+        });
+        // Config files, with and without a websocket_url
+        let mut config = Config::default();
+        config.json_rpc_url = "http://localhost:8899".to_string();
+        config.websocket_url = String::new();
+        let urls = resolve_urls(&None, Some(&config)).unwrap();
+        assert_eq!(urls.ws, "ws://localhost:8900/");
+        config.websocket_url = "wss://ws.example.com".to_string();
+        let urls = resolve_urls(&None, Some(&config)).unwrap();
+        assert_eq!(urls.http, "http://localhost:8899");
+        assert_eq!(urls.ws, "wss://ws.example.com");
+    }
+
+    #[test]
+    fn test_resolve_signer() {
+        let path1 = "test/test-keypair.json";
+        let path2 = "test/test-keypair2.json";
+        let keypair1 = solana_sdk::signer::keypair::read_keypair_file(path1).unwrap();
+        let keypair2 = solana_sdk::signer::keypair::read_keypair_file(path2).unwrap();
+        let signer = resolve_signer_simple(&Some(path1.to_string()), None).unwrap();
+        assert_eq!(signer.pubkey(), keypair1.pubkey());
+        let mut config = Config::default();
+        config.keypair_path = path2.to_string();
+        let signer = resolve_signer_simple(&None, Some(&config)).unwrap();
+        assert_eq!(signer.pubkey(), keypair2.pubkey());
+        let signer = resolve_signer_simple(&Some(path1.to_string()), Some(&config)).unwrap();
+        assert_eq!(signer.pubkey(), keypair1.pubkey());
+        // Errors name the source that was attempted.
+        config.keypair_path = "test/missing.json".to_string();
+        let err = resolve_signer_simple(&None, Some(&config)).err().unwrap();
+        assert!(err.to_string().contains("from the config file"));
+        let err = resolve_signer_simple(&Some("test/missing.json".to_string()), None).err().unwrap();
+        assert!(err.to_string().contains("from -k/--keypair"));
+    }
+
+    #[test]
+    fn test_usb_signer_source() {
+        use solana_clap_v3_utils::keypair::{parse_signer_source, SignerSourceKind};
+        let source = parse_signer_source("usb://ledger").unwrap();
+        assert!(matches!(source.kind, SignerSourceKind::Usb(_)));
+    }
+
+    #[test]
+    fn test_resolve_commitment() {
+        let mut config = Config::default();
+        config.commitment = "finalized".to_string();
+        // The flag wins, with or without a config.
+        let flag = Some("Processed".to_string());
+        assert_eq!(resolve_commitment(&flag, None).unwrap(), CommitmentConfig::processed());
+        assert_eq!(resolve_commitment(&flag, Some(&config)).unwrap(), CommitmentConfig::processed());
+        // Then the config.
+        assert_eq!(resolve_commitment(&None, Some(&config)).unwrap(), CommitmentConfig::finalized());
+        // A config without a commitment defaults to confirmed.
+        config.commitment = String::new();
+        assert_eq!(resolve_commitment(&None, Some(&config)).unwrap(), CommitmentConfig::confirmed());
+    }
+
+    #[test]
+    fn test_invalid_commitment() {
+        let err = resolve_commitment(&Some("max".to_string()), None).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<InvalidCommitment>(),
+            Some(&InvalidCommitment("max".to_string())),
+        );
+        assert!(err.to_string().contains("processed, confirmed, finalized"));
+        let mut config = Config::default();
+        config.commitment = "single".to_string();
+        assert!(resolve_commitment(&None, Some(&config)).is_err());
     }
 
     #[test]
@@ -125,4 +560,148 @@ mod tests {
             .unwrap();
         assert_eq!(*keypair, keypair1);
     }
+
+    #[test]
+    fn test_resolve_keypair_with_fallbacks() {
+        let path1 = "test/test-keypair.json";
+        let path2 = "test/test-keypair2.json";
+        let keypair1 = keypair_from_path(path1).unwrap();
+        let keypair2 = keypair_from_path(path2).unwrap();
+        let missing = "test/missing-keypair.json".to_string();
+        let config = Config {
+            keypair_path: path2.to_string(),
+            ..Config::default()
+        };
+        let resolve = |primary: Option<&str>, fallbacks: &[&str]| {
+            let fallbacks: Vec<String> = fallbacks.iter().map(|f| f.to_string()).collect();
+            resolve_keypair_with_fallbacks(&primary.map(str::to_string), &fallbacks, Some(&config))
+        };
+
+        // The flag
+        let (keypair, source) = resolve(Some(path1), &[path2]).unwrap();
+        assert_eq!(keypair, keypair1);
+        assert_eq!(source, "-k/--keypair (test/test-keypair.json)");
+        // The first fallback that exists
+        let (keypair, source) = resolve(Some(&missing), &[&missing, path1, path2]).unwrap();
+        assert_eq!(keypair, keypair1);
+        assert_eq!(source, "fallback (test/test-keypair.json)");
+        // The config file
+        let (keypair, source) = resolve(None, &[&missing]).unwrap();
+        assert_eq!(keypair, keypair2);
+        assert_eq!(source, "the config file (test/test-keypair2.json)");
+
+        // Nothing exists
+        let config = Config {
+            keypair_path: missing.clone(),
+            ..Config::default()
+        };
+        let err = resolve_keypair_with_fallbacks(&None, &[missing.clone()], Some(&config))
+            .unwrap_err().to_string();
+        assert!(err.contains("No keypair found, tried: test/missing-keypair.json, test/missing-keypair.json"), "{}", err);
+    }
+
+    #[test]
+    fn test_resolve_keypair_with_fallbacks_aborts_on_unreadable() {
+        let invalid = std::env::temp_dir()
+            .join(format!("invalid-keypair-{}.json", std::process::id()));
+        std::fs::write(&invalid, "not a keypair").unwrap();
+        let config = Config {
+            keypair_path: "test/test-keypair.json".to_string(),
+            ..Config::default()
+        };
+        let result = resolve_keypair_with_fallbacks(
+            &None,
+            &[invalid.display().to_string(), "test/test-keypair2.json".to_string()],
+            Some(&config),
+        );
+        std::fs::remove_file(&invalid).unwrap();
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("Could not read keypair"), "{}", err);
+        assert!(err.contains("from fallback"), "{}", err);
+    }
+
+    #[test]
+    fn test_get_solana_cli_config_from() {
+        let dir = std::env::temp_dir().join(format!("solana-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let explicit = dir.join("explicit.yml");
+        let from_env = dir.join("env.yml");
+        let mut config = Config::default();
+        config.json_rpc_url = "http://explicit:8899".to_string();
+        config.save(explicit.to_str().unwrap()).unwrap();
+        config.json_rpc_url = "http://env:8899".to_string();
+        config.save(from_env.to_str().unwrap()).unwrap();
+
+        // An explicit path
+        let loaded = get_solana_cli_config_from(Some(&explicit)).unwrap();
+        assert_eq!(loaded.json_rpc_url, "http://explicit:8899");
+        let err = get_solana_cli_config_from(Some(&dir.join("missing.yml")))
+            .unwrap_err().to_string();
+        assert!(err.contains("missing.yml from the given config path does not exist"), "{}", err);
+        let invalid = dir.join("invalid.yml");
+        std::fs::write(&invalid, "json_rpc_url: [").unwrap();
+        let err = get_solana_cli_config_from(Some(&invalid)).unwrap_err().to_string();
+        assert!(err.contains("unable to parse config file"), "{}", err);
+
+        // The environment variable, which the explicit path still takes precedence over
+        let _lock = SOLANA_CONFIG_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::env::set_var(SOLANA_CONFIG_ENV_VAR, &from_env);
+        let loaded = get_solana_cli_config_from(None).map(|c| c.json_rpc_url);
+        let explicit_loaded = get_solana_cli_config_from(Some(&explicit)).map(|c| c.json_rpc_url);
+        std::env::set_var(SOLANA_CONFIG_ENV_VAR, dir.join("missing.yml"));
+        let missing = get_solana_cli_config_from(None).map(|c| c.json_rpc_url);
+        std::env::remove_var(SOLANA_CONFIG_ENV_VAR);
+        assert_eq!(loaded.unwrap(), "http://env:8899");
+        assert_eq!(explicit_loaded.unwrap(), "http://explicit:8899");
+        let err = missing.unwrap_err().to_string();
+        assert!(err.contains("from the SOLANA_CONFIG environment variable does not exist"), "{}", err);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn typed_errors() {
+        let url = |url: &str| resolve_url(&Some(url.to_string()), None).unwrap_err();
+        assert!(matches!(
+            url("ws://localhost:8900"),
+            CliConfigError::WebSocketUrl { url } if url == "ws://localhost:8900"
+        ));
+        assert!(matches!(
+            url("devnte"),
+            CliConfigError::InvalidUrl { url, suggestion: Some("devnet") } if url == "devnte"
+        ));
+        assert!(matches!(url("ftp://foo"), CliConfigError::InvalidUrl { suggestion: None, .. }));
+
+        let missing = std::env::temp_dir().join(format!("typed-errors-{}.yml", std::process::id()));
+        assert!(matches!(
+            get_solana_cli_config_from(Some(&missing)).unwrap_err(),
+            CliConfigError::ConfigMissing { path, origin: "the given config path" } if path == missing
+        ));
+
+        assert!(matches!(
+            resolve_keypair(&Some("nope:abc".to_string()), None).unwrap_err(),
+            CliConfigError::UnrecognizedKeypairSource { scheme: Some(scheme) } if scheme == "nope"
+        ));
+        let path = missing.display().to_string();
+        assert!(matches!(
+            keypair_from_path(&path).unwrap_err(),
+            CliConfigError::KeypairFileNotFound { path: p, error } if p == path
+                && error.kind() == std::io::ErrorKind::NotFound
+        ));
+        let secret = solana_sdk::pubkey::Pubkey::new_unique().to_string();
+        let err = keypair_from_path(&format!("base58:{}", secret)).unwrap_err();
+        assert!(matches!(
+            &err,
+            CliConfigError::KeypairUnreadable { kind: "base58", location: None, .. }
+        ));
+        assert!(!err.to_string().contains(&secret), "{}", err);
+        assert!(matches!(
+            keypair_from_path("env:TYPED_ERRORS_UNSET_VAR").unwrap_err(),
+            CliConfigError::KeypairUnreadable { kind: "env", location: Some(var), .. }
+                if var == "TYPED_ERRORS_UNSET_VAR"
+        ));
+
+        // Still usable as anyhow errors.
+        let err: anyhow::Error = url("foo").into();
+        assert!(err.downcast_ref::<CliConfigError>().is_some());
+    }
 }
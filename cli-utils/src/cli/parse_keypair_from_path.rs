@@ -3,11 +3,14 @@
 /// While this necessitates the removal of the `usb` and `pubkey` input prefixes,
 /// It returns a concrete type, and also allows for flexibility
 /// when designing a system to safely pass a keypair to an automated signing server at startup.
-use anyhow::anyhow;
 use solana_clap_v3_utils::keypair::keypair_from_seed_phrase;
 use anchor_client::solana_sdk::derivation_path::{DerivationPath, DerivationPathError};
 use anchor_client::solana_sdk::signature::{read_keypair, read_keypair_file, Keypair};
+use anchor_client::solana_sdk::signer::keypair::{
+    generate_seed_from_seed_phrase_and_passphrase, keypair_from_seed_and_derivation_path,
+};
 use thiserror::Error;
+use crate::cli::CliConfigError;
 
 const STDOUT_OUTFILE_TOKEN: &str = "-";
 
@@ -30,23 +33,44 @@ impl SignerSource {
 const SIGNER_SOURCE_PROMPT: &str = "prompt";
 const SIGNER_SOURCE_FILEPATH: &str = "file";
 const SIGNER_SOURCE_STDIN: &str = "stdin";
+const SIGNER_SOURCE_BASE58: &str = "base58";
+const SIGNER_SOURCE_ENV: &str = "env";
+const SIGNER_SOURCE_SEED_FILE: &str = "seed-file";
+
+/// BIP39 mnemonics have 12, 15, 18, 21 or 24 words.
+const SEED_PHRASE_WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
 
 enum SignerSourceKind {
     Prompt,
     Filepath(String),
     Stdin,
+    /// A base58 encoded 64 byte secret key.
+    Base58(String),
+    /// The name of an environment variable holding a JSON byte array or base58 secret key.
+    Env(String),
+    /// A file containing a seed phrase, and optionally a passphrase on the second line.
+    SeedFile(String),
 }
 
-impl AsRef<str> for SignerSourceKind {
-    fn as_ref(&self) -> &str {
+impl SignerSourceKind {
+    fn name(&self) -> &'static str {
         match self {
             Self::Prompt => SIGNER_SOURCE_PROMPT,
             Self::Filepath(_) => SIGNER_SOURCE_FILEPATH,
             Self::Stdin => SIGNER_SOURCE_STDIN,
+            Self::Base58(_) => SIGNER_SOURCE_BASE58,
+            Self::Env(_) => SIGNER_SOURCE_ENV,
+            Self::SeedFile(_) => SIGNER_SOURCE_SEED_FILE,
         }
     }
 }
 
+impl AsRef<str> for SignerSourceKind {
+    fn as_ref(&self) -> &str {
+        self.name()
+    }
+}
+
 impl std::fmt::Debug for SignerSourceKind {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let s: &str = self.as_ref();
@@ -81,6 +105,17 @@ fn parse_signer_source<S: AsRef<str>>(source: S) -> Result<SignerSource, SignerS
                         uri.path().to_string(),
                     ))),
                     SIGNER_SOURCE_STDIN => Ok(SignerSource::new(SignerSourceKind::Stdin)),
+                    SIGNER_SOURCE_BASE58 => Ok(SignerSource::new(SignerSourceKind::Base58(
+                        uri.path().to_string(),
+                    ))),
+                    SIGNER_SOURCE_ENV => Ok(SignerSource::new(SignerSourceKind::Env(
+                        uri.path().to_string(),
+                    ))),
+                    SIGNER_SOURCE_SEED_FILE => Ok(SignerSource {
+                        kind: SignerSourceKind::SeedFile(uri.path().to_string()),
+                        derivation_path: DerivationPath::from_uri_key_query(&uri)?,
+                        legacy: false,
+                    }),
                     _ => {
                         Err(SignerSourceError::UnrecognizedSource)
                     }
@@ -88,37 +123,280 @@ fn parse_signer_source<S: AsRef<str>>(source: S) -> Result<SignerSource, SignerS
             } else {
                 match source.as_str() {
                     STDOUT_OUTFILE_TOKEN => Ok(SignerSource::new(SignerSourceKind::Stdin)),
-                    _ => std::fs::metadata(source.as_str())
-                        .map(|_| SignerSource::new(SignerSourceKind::Filepath(source)))
-                        .map_err(|err| err.into()),
+                    _ => match std::fs::metadata(source.as_str()) {
+                        Ok(_) => Ok(SignerSource::new(SignerSourceKind::Filepath(source))),
+                        // Only treat the input as a secret key if it isn't a file.
+                        Err(_) if is_base58_secret_key(&source) => {
+                            Ok(SignerSource::new(SignerSourceKind::Base58(source)))
+                        }
+                        Err(err) => Err(err.into()),
+                    },
                 }
             }
         }
     }
 }
 
+fn is_base58_secret_key(source: &str) -> bool {
+    matches!(bs58::decode(source).into_vec(), Ok(bytes) if bytes.len() == 64)
+}
+
+// The source as it may be shown in an error. Something that decodes as more base58
+// than a pubkey, ignoring any mistyped characters, may be a secret key, so only its
+// length is shown.
+fn displayable_source(source: &str) -> String {
+    let base58: String = source
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() && !matches!(c, '0' | 'O' | 'I' | 'l'))
+        .collect();
+    let may_be_secret = !source.contains(&['/', '\\', '.'][..])
+        && matches!(bs58::decode(base58).into_vec(), Ok(bytes) if bytes.len() > 32);
+    if may_be_secret {
+        format!("<{} characters, hidden as it may be a secret key>", source.len())
+    } else {
+        source.to_string()
+    }
+}
+
 /// Switches over only the allowed variants if what we need is a keypair,
-/// including: `file`, `prompt`, `stdin`.
-pub fn keypair_from_path(keypair_path: &str) -> anyhow::Result<Box<Keypair>> {
+/// including: `file`, `prompt`, `stdin`, `env`, `seed-file`, and `base58`
+/// (or a bare base58 secret key).
+pub fn keypair_from_path(keypair_path: &str) -> Result<Box<Keypair>, CliConfigError> {
     let SignerSource {
         kind,
         derivation_path,
         legacy,
-    } = parse_signer_source(keypair_path)?;
-    match kind {
-        SignerSourceKind::Prompt => Ok(Box::new(
-            keypair_from_seed_phrase("keypair", false, false, derivation_path, legacy)
-                .map_err(|e| anyhow!("Failed to read keypair from prompt: {:?}", e))?,
-        )),
-        SignerSourceKind::Filepath(path) => match read_keypair_file(path) {
-            Err(e) => Err(anyhow!("Failed to read keypair from filepath: {:?}", e)),
-            Ok(file) => Ok(Box::new(file)),
+    } = parse_signer_source(keypair_path).map_err(|e| match e {
+        SignerSourceError::UnrecognizedSource => CliConfigError::UnrecognizedKeypairSource {
+            scheme: uriparse::URIReference::try_from(keypair_path)
+                .ok()
+                .and_then(|uri| uri.scheme().map(|scheme| scheme.as_str().to_string())),
         },
+        SignerSourceError::DerivationPathError(error) => CliConfigError::InvalidDerivationPath {
+            path: keypair_path.to_string(),
+            error,
+        },
+        SignerSourceError::IoError(error) => CliConfigError::KeypairFileNotFound {
+            path: displayable_source(keypair_path),
+            error,
+        },
+    })?;
+    let name = kind.name();
+    let unreadable = |location: Option<&str>, message: String| CliConfigError::KeypairUnreadable {
+        kind: name,
+        location: location.map(str::to_string),
+        message,
+    };
+    let keypair = match kind {
+        SignerSourceKind::Prompt => {
+            keypair_from_seed_phrase("keypair", false, false, derivation_path, legacy)
+                .map_err(|e| unreadable(None, e.to_string()))?
+        }
+        SignerSourceKind::Filepath(path) => read_keypair_file(&path)
+            .map_err(|e| unreadable(Some(&path), format!("{}: {}", path, e)))?,
         SignerSourceKind::Stdin => {
             let mut stdin = std::io::stdin();
-            Ok(Box::new(read_keypair(&mut stdin).map_err(|e| {
-                anyhow!("Failed to read keypair from stdin: {:?}", e)
-            })?))
+            read_keypair(&mut stdin).map_err(|e| unreadable(None, e.to_string()))?
         }
+        SignerSourceKind::Base58(secret) => {
+            keypair_from_base58(&secret).map_err(|e| unreadable(None, e))?
+        }
+        SignerSourceKind::Env(var) => keypair_from_env(&var).map_err(|e| unreadable(Some(&var), e))?,
+        SignerSourceKind::SeedFile(path) => keypair_from_seed_file(&path, derivation_path)
+            .map_err(|e| unreadable(Some(&path), e))?,
+    };
+    Ok(Box::new(keypair))
+}
+
+/// Whether `keypair_path` names a keypair or seed phrase file that doesn't exist,
+/// as opposed to a file that exists or a source that isn't a file.
+pub(crate) fn is_missing_file(keypair_path: &str) -> bool {
+    match parse_signer_source(keypair_path) {
+        Err(SignerSourceError::IoError(e)) => e.kind() == std::io::ErrorKind::NotFound,
+        Ok(SignerSource { kind: SignerSourceKind::Filepath(path), .. })
+        | Ok(SignerSource { kind: SignerSourceKind::SeedFile(path), .. }) => {
+            !std::path::Path::new(&path).exists()
+        }
+        _ => false,
+    }
+}
+
+// Derives a keypair like the `prompt:` source, reading the seed phrase and optional
+// passphrase from a file instead. Errors never include the words themselves.
+fn keypair_from_seed_file(
+    path: &str,
+    derivation_path: Option<DerivationPath>,
+) -> Result<Keypair, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read seed phrase from {}: {}", path, e))?;
+    let mut lines = contents
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .skip_while(|line| line.trim().is_empty());
+    let words: Vec<String> = lines
+        .next()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect();
+    if !SEED_PHRASE_WORD_COUNTS.contains(&words.len()) {
+        return Err(format!(
+            "could not read seed phrase from {}: found {} words, expected 12, 15, 18, 21 or 24",
+            path,
+            words.len()
+        ));
+    }
+    let passphrase = lines.next().unwrap_or_default();
+    let seed = generate_seed_from_seed_phrase_and_passphrase(&words.join(" "), passphrase);
+    keypair_from_seed_and_derivation_path(&seed, derivation_path)
+        .map_err(|e| format!("could not derive keypair from seed phrase in {}: {}", path, e))
+}
+
+// Errors never include the secret itself.
+fn keypair_from_base58(secret: &str) -> Result<Keypair, String> {
+    let bytes = bs58::decode(secret)
+        .into_vec()
+        .map_err(|_| "invalid base58".to_string())?;
+    if bytes.len() != 64 {
+        return Err(format!("expected 64 bytes, found {}", bytes.len()));
+    }
+    Keypair::from_bytes(&bytes).map_err(|_| "invalid secret key".to_string())
+}
+
+// Reads either the JSON byte array format of keypair files, or a base58 secret key.
+// Errors name the variable, but never include its value.
+fn keypair_from_env(var: &str) -> Result<Keypair, String> {
+    let value = std::env::var(var).map_err(|e| match e {
+        std::env::VarError::NotPresent => format!("{} is not set", var),
+        std::env::VarError::NotUnicode(_) => format!("{} is not valid unicode", var),
+    })?;
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("{} is empty", var));
+    }
+    let keypair = if value.starts_with('[') {
+        read_keypair(&mut value.as_bytes()).map_err(|_| "invalid JSON keypair".to_string())
+    } else {
+        keypair_from_base58(value)
+    };
+    keypair.map_err(|e| format!("{} could not be decoded: {}", var, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+    use anchor_client::solana_sdk::signature::{write_keypair_file, Signer};
+    use super::*;
+
+    // Tests that modify the environment hold this, so they don't race each other.
+    static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn env_secret_key() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let keypair = Keypair::new();
+        let var = "KEYPAIR_FROM_PATH_TEST_KEYPAIR";
+        std::env::set_var(var, format!("{:?}\n", keypair.to_bytes().to_vec()));
+        assert_eq!(*keypair_from_path(&format!("env:{}", var)).unwrap(), keypair);
+        std::env::set_var(var, keypair.to_base58_string());
+        assert_eq!(*keypair_from_path(&format!("env:{}", var)).unwrap(), keypair);
+        std::env::remove_var(var);
+    }
+
+    #[test]
+    fn env_errors_name_the_variable() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let var = "KEYPAIR_FROM_PATH_TEST_ERRORS";
+        let path = format!("env:{}", var);
+        std::env::remove_var(var);
+        let err = keypair_from_path(&path).unwrap_err().to_string();
+        assert!(err.contains(&format!("{} is not set", var)));
+        std::env::set_var(var, "  ");
+        let err = keypair_from_path(&path).unwrap_err().to_string();
+        assert!(err.contains(&format!("{} is empty", var)));
+        let secret = "[1, 2, 3, \"not a byte\"]";
+        std::env::set_var(var, secret);
+        let err = keypair_from_path(&path).unwrap_err().to_string();
+        assert!(err.contains(&format!("{} could not be decoded", var)));
+        assert!(!err.contains("not a byte"));
+        std::env::set_var(var, "0OIl");
+        let err = keypair_from_path(&path).unwrap_err().to_string();
+        assert!(err.contains(&format!("{} could not be decoded", var)));
+        assert!(!err.contains("0OIl"));
+        std::env::remove_var(var);
+    }
+
+    const TEST_MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn seed_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir()
+            .join(format!("seed-file-{}-{}", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn seed_file_reference_vectors() {
+        // Whitespace is normalized, and a blank line before the phrase is skipped.
+        let path = seed_file("vectors", &format!("\n  {}  \r\n", TEST_MNEMONIC.replace(' ', "   ")));
+        let pubkey = |uri: String| keypair_from_path(&uri).unwrap().pubkey().to_string();
+        assert_eq!(pubkey(format!("seed-file:{}", path)), "D2PPQSYFe83nDzk96FqGumVU8JA7J8vj2Rhjc2oXzEi5");
+        assert_eq!(pubkey(format!("seed-file:{}?key=0/0", path)), "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk");
+        assert_eq!(pubkey(format!("seed-file:{}?key=0/1", path)), "GKreMsHvt8A79VApjboYDq3J4ZCXSJRYYQk9BscMbi1H");
+        std::fs::remove_file(&path).unwrap();
+
+        let path = seed_file("passphrase", &format!("{}\nTREZOR\n", TEST_MNEMONIC));
+        assert_eq!(pubkey(format!("seed-file:{}?key=0/0", path)), "7zSmbu6gKkb6HB7UDPtHYjwCWuBHU1D4TpNZFm4sndQe");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn seed_file_word_count() {
+        let path = seed_file("short", "abandon abandon secret\n");
+        let err = keypair_from_path(&format!("seed-file:{}", path)).unwrap_err().to_string();
+        assert!(err.contains("found 3 words"));
+        assert!(!err.contains("secret"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn base58_secret_key() {
+        let keypair = Keypair::new();
+        let secret = keypair.to_base58_string();
+        assert_eq!(*keypair_from_path(&secret).unwrap(), keypair);
+        let explicit = format!("base58:{}", secret);
+        assert_eq!(*keypair_from_path(&explicit).unwrap(), keypair);
+    }
+
+    #[test]
+    fn base58_wrong_length() {
+        // 32 bytes is a pubkey, not a secret key, so it falls through to the file error.
+        assert!(keypair_from_path(&Pubkey::new_unique().to_string()).is_err());
+        let err = keypair_from_path(&format!("base58:{}", Pubkey::new_unique())).unwrap_err();
+        assert!(err.to_string().contains("expected 64 bytes, found 32"));
+    }
+
+    #[test]
+    fn mistyped_secret_key_is_not_echoed() {
+        let secret = Keypair::new().to_base58_string();
+        let bad_character = format!("{}0{}", &secret[..40], &secret[41..]);
+        let truncated = secret[..secret.len() - 1].to_string();
+        for input in [bad_character, truncated] {
+            let err = keypair_from_path(&input).unwrap_err();
+            assert!(matches!(err, CliConfigError::KeypairFileNotFound { .. }), "{}", err);
+            assert!(!err.to_string().contains(&input), "{}", err);
+            assert!(!err.to_string().contains(&input[..40]), "{}", err);
+        }
+    }
+
+    #[test]
+    fn base58_file_name_is_a_file() {
+        // A file named like a secret key is still read as a file.
+        let file_keypair = Keypair::new();
+        let file_name = Keypair::new().to_base58_string();
+        write_keypair_file(&file_keypair, &file_name).unwrap();
+        let keypair = keypair_from_path(&file_name);
+        std::fs::remove_file(&file_name).unwrap();
+        assert_eq!(*keypair.unwrap(), file_keypair);
     }
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use std::time::Duration;
+use anchor_client::solana_client::client_error::reqwest::header::HeaderMap;
+use anchor_client::solana_client::rpc_client::{RpcClient, RpcClientConfig};
+use solana_cli_config::Config;
+use solana_rpc_client_headers::HttpSenderWithHeaders;
+use crate::cli::{resolve_commitment, resolve_url};
+
+/// The RPC timeout used when none is given, the same as [RpcClient::new].
+pub const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Build an [RpcClient] from an optional url and commitment, falling back to the
+/// [solana_cli_config::Config] as [resolve_url] and [resolve_commitment] do.
+/// If `headers` are given, they are sent with every request via [HttpSenderWithHeaders].
+pub fn rpc_client_from_cli_config(
+    url: &Option<String>,
+    commitment: &Option<String>,
+    config: Option<&Config>,
+    headers: Option<HeaderMap>,
+    timeout: Option<Duration>,
+) -> anyhow::Result<RpcClient> {
+    let url = resolve_url(url, config)?;
+    let commitment = resolve_commitment(commitment, config)?;
+    let timeout = timeout.unwrap_or(DEFAULT_RPC_TIMEOUT);
+    Ok(match headers {
+        Some(headers) => RpcClient::new_sender(
+            HttpSenderWithHeaders::new_with_timeout(url, timeout, Some(headers)),
+            RpcClientConfig::with_commitment(commitment),
+        ),
+        None => RpcClient::new_with_timeout_and_commitment(url, timeout, commitment),
+    })
+}
+
+/// The nonblocking equivalent of [rpc_client_from_cli_config].
+#[cfg(feature = "nonblocking")]
+pub fn nonblocking_rpc_client_from_cli_config(
+    url: &Option<String>,
+    commitment: &Option<String>,
+    config: Option<&Config>,
+    headers: Option<HeaderMap>,
+    timeout: Option<Duration>,
+) -> anyhow::Result<anchor_client::solana_client::nonblocking::rpc_client::RpcClient> {
+    use anchor_client::solana_client::nonblocking::rpc_client::RpcClient;
+    let url = resolve_url(url, config)?;
+    let commitment = resolve_commitment(commitment, config)?;
+    let timeout = timeout.unwrap_or(DEFAULT_RPC_TIMEOUT);
+    Ok(match headers {
+        Some(headers) => RpcClient::new_sender(
+            HttpSenderWithHeaders::new_with_timeout(url, timeout, Some(headers)),
+            RpcClientConfig::with_commitment(commitment),
+        ),
+        None => RpcClient::new_with_timeout_and_commitment(url, timeout, commitment),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_client::solana_client::client_error::reqwest::header::{AUTHORIZATION, HeaderValue};
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use super::*;
+
+    #[test]
+    fn test_rpc_client_from_cli_config() {
+        let mut config = Config::default();
+        config.json_rpc_url = "http://localhost:8899".to_string();
+        config.commitment = "finalized".to_string();
+        // Explicit values win.
+        let client = rpc_client_from_cli_config(
+            &Some("https://api.devnet.solana.com".to_string()),
+            &Some("processed".to_string()),
+            Some(&config),
+            None,
+            None,
+        ).unwrap();
+        assert_eq!(client.url(), "https://api.devnet.solana.com");
+        assert_eq!(client.commitment(), CommitmentConfig::processed());
+        // Otherwise the config is used.
+        let client = rpc_client_from_cli_config(&None, &None, Some(&config), None, None).unwrap();
+        assert_eq!(client.url(), "http://localhost:8899");
+        assert_eq!(client.commitment(), CommitmentConfig::finalized());
+        // With headers.
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer token"));
+        let client = rpc_client_from_cli_config(
+            &Some("http://localhost:18899".to_string()),
+            &None,
+            Some(&config),
+            Some(headers),
+            Some(Duration::from_secs(5)),
+        ).unwrap();
+        assert_eq!(client.url(), "http://localhost:18899");
+        assert_eq!(client.commitment(), CommitmentConfig::finalized());
+    }
+}
@@ -0,0 +1,258 @@
+/// A confirmation gate before sending transactions, for commands that operators run by hand.
+use std::io::{self, BufRead, Write};
+use anyhow::{anyhow, Result};
+use solana_client_tx_processor::ProcessedTransaction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use crate::amount::format_sol;
+
+/// What's shown to the operator before a transaction is sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxSummary {
+    pub name: String,
+    pub payer: Pubkey,
+    /// [None] if the number of instructions isn't known, e.g. for a serialized transaction.
+    pub instruction_count: Option<usize>,
+    /// The estimated fee in lamports, if known.
+    pub estimated_fee: Option<u64>,
+}
+
+impl TxSummary {
+    /// Summarize a transaction that's about to be built from `instructions`.
+    pub fn new(
+        name: impl Into<String>,
+        payer: Pubkey,
+        instructions: &[Instruction],
+        estimated_fee: Option<u64>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            payer,
+            instruction_count: Some(instructions.len()),
+            estimated_fee,
+        }
+    }
+
+    /// Summarize a previewed transaction, e.g. from [solana_client_tx_processor::Processing::Instructions].
+    pub fn from_processed(
+        processed: &ProcessedTransaction,
+        payer: Pubkey,
+        estimated_fee: Option<u64>,
+    ) -> Self {
+        let (name, instruction_count) = match processed {
+            ProcessedTransaction::Execution { name, .. } => (name, None),
+            ProcessedTransaction::Simulation { name, .. } => (name, None),
+            ProcessedTransaction::SignedSerialized { name, .. } => (name, None),
+            ProcessedTransaction::UnsignedSerialized { name, .. } => (name, None),
+            ProcessedTransaction::InstructionSet { name, instructions, .. } => {
+                (name, Some(instructions.len()))
+            }
+        };
+        Self {
+            name: name.clone(),
+            payer,
+            instruction_count,
+            estimated_fee,
+        }
+    }
+}
+
+impl std::fmt::Display for TxSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, payer {}", self.name, self.payer)?;
+        if let Some(count) = self.instruction_count {
+            write!(f, ", {} instruction{}", count, if count == 1 { "" } else { "s" })?;
+        }
+        if let Some(fee) = self.estimated_fee {
+            write!(f, ", est. fee {} SOL", format_sol(fee))?;
+        }
+        Ok(())
+    }
+}
+
+/// What to do when there's no terminal to prompt on, e.g. in CI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonTtyBehavior {
+    /// Return an error asking for `-y`.
+    #[default]
+    Fail,
+    /// Decline the transaction.
+    AutoNo,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConfirmOptions {
+    /// Skip the prompt and proceed, e.g. from `-y/--yes`.
+    pub assume_yes: bool,
+    /// Require typing the transaction name rather than `y`, for destructive operations.
+    pub require_typed_phrase: bool,
+    pub non_tty: NonTtyBehavior,
+}
+
+/// Ask the operator whether to send the transaction. The prompt is written to stderr,
+/// so stdout stays machine-parseable. Returns whether to proceed.
+pub fn confirm_transaction_prompt(summary: &TxSummary, opts: &ConfirmOptions) -> Result<bool> {
+    let is_tty = atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stderr);
+    confirm_transaction_prompt_with(
+        summary,
+        opts,
+        &mut io::stdin().lock(),
+        &mut io::stderr(),
+        is_tty,
+    )
+}
+
+/// [confirm_transaction_prompt] with the input, output and terminal detection supplied.
+pub fn confirm_transaction_prompt_with(
+    summary: &TxSummary,
+    opts: &ConfirmOptions,
+    reader: &mut dyn BufRead,
+    writer: &mut dyn Write,
+    is_tty: bool,
+) -> Result<bool> {
+    if opts.assume_yes {
+        return Ok(true);
+    }
+    if !is_tty {
+        return match opts.non_tty {
+            NonTtyBehavior::Fail => Err(anyhow!(
+                "Refusing to send {} without confirmation: not running in a terminal. Pass -y to proceed.",
+                summary.name
+            )),
+            NonTtyBehavior::AutoNo => {
+                writeln!(writer, "Not running in a terminal, declining to send: {}", summary)?;
+                Ok(false)
+            }
+        };
+    }
+    writeln!(writer, "About to send: {}", summary)?;
+    if opts.require_typed_phrase {
+        write!(writer, "Type \"{}\" to proceed: ", summary.name)?;
+    } else {
+        write!(writer, "Proceed? [y/N] ")?;
+    }
+    writer.flush()?;
+    let mut answer = String::new();
+    if reader.read_line(&mut answer)? == 0 {
+        writeln!(writer)?;
+        return Ok(false);
+    }
+    let answer = answer.trim();
+    let proceed = if opts.require_typed_phrase {
+        answer == summary.name
+    } else {
+        answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes")
+    };
+    if !proceed {
+        writeln!(writer, "Aborted.")?;
+    }
+    Ok(proceed)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Map;
+    use super::*;
+
+    fn summary() -> TxSummary {
+        TxSummary {
+            name: "Close vault".to_string(),
+            payer: Pubkey::default(),
+            instruction_count: Some(2),
+            estimated_fee: Some(5000),
+        }
+    }
+
+    fn prompt(input: &str, opts: &ConfirmOptions, is_tty: bool) -> (Result<bool>, String) {
+        let mut output = vec![];
+        let result = confirm_transaction_prompt_with(
+            &summary(), opts, &mut input.as_bytes(), &mut output, is_tty,
+        );
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn yes_and_no() {
+        let opts = ConfirmOptions::default();
+        for input in ["y\n", "Y\n", "yes\n", " YES \r\n"] {
+            let (result, output) = prompt(input, &opts, true);
+            assert!(result.unwrap(), "{:?}", input);
+            assert_eq!(output, "\
+About to send: Close vault, payer 11111111111111111111111111111111, 2 instructions, est. fee 0.000005 SOL
+Proceed? [y/N] ");
+        }
+        for input in ["\n", "n\n", "no\n", "yep\n"] {
+            let (result, output) = prompt(input, &opts, true);
+            assert!(!result.unwrap(), "{:?}", input);
+            assert!(output.ends_with("Aborted.\n"));
+        }
+    }
+
+    #[test]
+    fn eof_declines() {
+        let (result, _) = prompt("", &ConfirmOptions::default(), true);
+        assert!(!result.unwrap());
+    }
+
+    #[test]
+    fn typed_phrase() {
+        let opts = ConfirmOptions {
+            require_typed_phrase: true,
+            ..Default::default()
+        };
+        let (result, output) = prompt("Close vault\n", &opts, true);
+        assert!(result.unwrap());
+        assert!(output.ends_with("Type \"Close vault\" to proceed: "), "{}", output);
+        for input in ["y\n", "close vault\n", "Close\n", ""] {
+            let (result, _) = prompt(input, &opts, true);
+            assert!(!result.unwrap(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn assume_yes_and_non_tty() {
+        let opts = ConfirmOptions {
+            assume_yes: true,
+            require_typed_phrase: true,
+            ..Default::default()
+        };
+        let (result, output) = prompt("", &opts, false);
+        assert!(result.unwrap());
+        assert!(output.is_empty());
+
+        let (result, _) = prompt("y\n", &ConfirmOptions::default(), false);
+        assert!(result.unwrap_err().to_string().contains("Pass -y"));
+
+        let opts = ConfirmOptions {
+            non_tty: NonTtyBehavior::AutoNo,
+            ..Default::default()
+        };
+        let (result, output) = prompt("y\n", &opts, false);
+        assert!(!result.unwrap());
+        assert!(output.starts_with("Not running in a terminal"), "{}", output);
+    }
+
+    #[test]
+    fn summaries() {
+        let summary = TxSummary::new("Memo", Pubkey::default(), &[], None);
+        assert_eq!(summary.to_string(), "Memo, payer 11111111111111111111111111111111, 0 instructions");
+        let processed = ProcessedTransaction::InstructionSet {
+            instructions: vec!["a".to_string()],
+            instruction_names: vec!["memo".to_string()],
+            name: "Memo".to_string(),
+            metadata: Map::new(),
+        };
+        let summary = TxSummary::from_processed(&processed, Pubkey::default(), Some(10_000));
+        assert_eq!(
+            summary.to_string(),
+            "Memo, payer 11111111111111111111111111111111, 1 instruction, est. fee 0.00001 SOL",
+        );
+        let processed = ProcessedTransaction::UnsignedSerialized {
+            transaction: "abc".to_string(),
+            name: "Memo".to_string(),
+            metadata: Map::new(),
+        };
+        let summary = TxSummary::from_processed(&processed, Pubkey::default(), None);
+        assert_eq!(summary.instruction_count, None);
+    }
+}
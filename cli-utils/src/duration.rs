@@ -0,0 +1,186 @@
+/// Parsing of durations and slot counts for flags like lockups and timeouts.
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use solana_sdk::clock::DEFAULT_MS_PER_SLOT;
+
+const SECONDS_PER_UNIT: [(char, u64); 5] = [
+    ('s', 1),
+    ('m', 60),
+    ('h', 60 * 60),
+    ('d', 24 * 60 * 60),
+    ('w', 7 * 24 * 60 * 60),
+];
+
+/// Parses a whole number of seconds (`90`), or a whole number with a unit suffix:
+/// `s`, `m`, `h`, `d` or `w`, e.g. `90m` or `2d`.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let trimmed = input.trim();
+    let invalid = |reason: &str| anyhow!(
+        "invalid duration {:?}: {}, expected seconds or a number with s, m, h, d or w, e.g. 90m",
+        input, reason
+    );
+    let (number, seconds_per_unit) = match trimmed.chars().last() {
+        Some(unit) if unit.is_ascii_alphabetic() => {
+            let seconds_per_unit = SECONDS_PER_UNIT
+                .iter()
+                .find(|(suffix, _)| *suffix == unit.to_ascii_lowercase())
+                .map(|(_, seconds)| *seconds)
+                .ok_or_else(|| invalid("unknown unit"))?;
+            (trimmed[..trimmed.len() - 1].trim_end(), seconds_per_unit)
+        }
+        _ => (trimmed, 1),
+    };
+    let number = parse_whole_number(number).map_err(invalid)?;
+    number
+        .checked_mul(seconds_per_unit)
+        .map(Duration::from_secs)
+        .ok_or_else(|| invalid("too large"))
+}
+
+/// Parses a whole number of slots (`450000` or `450000 slots`), or epochs (`3 epochs`)
+/// which requires `slots_per_epoch`.
+pub fn parse_slots(input: &str, slots_per_epoch: Option<u64>) -> Result<u64> {
+    let trimmed = input.trim();
+    let lowercase = trimmed.to_ascii_lowercase();
+    let invalid = |reason: &str| anyhow!(
+        "invalid slot count {:?}: {}, expected slots or epochs, e.g. 450000 slots or 3 epochs",
+        input, reason
+    );
+    let (number, slots_per_unit) = if let Some(number) = strip_unit(&lowercase, "epoch") {
+        let slots_per_epoch = slots_per_epoch
+            .ok_or_else(|| invalid("epochs can't be used without knowing the slots per epoch"))?;
+        (number, slots_per_epoch)
+    } else if let Some(number) = strip_unit(&lowercase, "slot") {
+        (number, 1)
+    } else {
+        (lowercase.as_str(), 1)
+    };
+    let number = parse_whole_number(number).map_err(invalid)?;
+    number
+        .checked_mul(slots_per_unit)
+        .ok_or_else(|| invalid("too large"))
+}
+
+/// An estimate of the number of slots in `duration`, assuming the 400ms target slot time.
+/// Actual slot times vary with cluster conditions and are usually somewhat longer,
+/// so don't rely on this for anything that must happen by a deadline.
+pub fn estimate_slots_from_duration(duration: Duration) -> u64 {
+    let slots = duration.as_millis() / DEFAULT_MS_PER_SLOT as u128;
+    u64::try_from(slots).unwrap_or(u64::MAX)
+}
+
+// Strips a singular or plural unit from the end of `input`.
+fn strip_unit<'a>(input: &'a str, unit: &str) -> Option<&'a str> {
+    input
+        .strip_suffix('s')
+        .and_then(|input| input.strip_suffix(unit))
+        .or_else(|| input.strip_suffix(unit))
+        .map(str::trim_end)
+}
+
+fn parse_whole_number(number: &str) -> std::result::Result<u64, &'static str> {
+    if number.is_empty() {
+        return Err("no digits");
+    }
+    if !number.chars().all(|c| c.is_ascii_digit()) {
+        return Err("expected a whole number");
+    }
+    number.parse().map_err(|_| "too large")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        for (input, seconds) in [
+            ("0", 0),
+            ("90", 90),
+            ("90s", 90),
+            ("90m", 90 * 60),
+            ("2h", 2 * 60 * 60),
+            ("2d", 2 * 24 * 60 * 60),
+            ("1w", 7 * 24 * 60 * 60),
+            (" 3 H ", 3 * 60 * 60),
+            ("18446744073709551615", u64::MAX),
+            ("18446744073709551615s", u64::MAX),
+            ("307445734561825860m", 307445734561825860 * 60),
+            ("30500568904943w", 30500568904943 * 7 * 24 * 60 * 60),
+        ] {
+            assert_eq!(parse_duration(input).unwrap(), Duration::from_secs(seconds), "{}", input);
+        }
+        // Every unit and amount round-trips through seconds.
+        for (unit, seconds_per_unit) in SECONDS_PER_UNIT {
+            for n in [0, 1, 7, 59, 1_000_000] {
+                assert_eq!(
+                    parse_duration(&format!("{}{}", n, unit)).unwrap().as_secs(),
+                    n * seconds_per_unit,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for (input, reason) in [
+            ("", "no digits"),
+            ("m", "no digits"),
+            ("1.5h", "whole number"),
+            ("-1", "whole number"),
+            ("1 2", "whole number"),
+            ("2y", "unknown unit"),
+            ("2min", "unknown unit"),
+            ("18446744073709551616", "too large"),
+            ("307445734561825861m", "too large"),
+            ("30500568904944w", "too large"),
+        ] {
+            let err = parse_duration(input).unwrap_err().to_string();
+            assert!(err.contains(reason), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn slots() {
+        for (input, slots) in [
+            ("0", 0),
+            ("450000", 450_000),
+            ("450000 slots", 450_000),
+            ("1 slot", 1),
+            ("10Slots", 10),
+            ("3 epochs", 3 * 432_000),
+            ("1 epoch", 432_000),
+            ("18446744073709551615 slots", u64::MAX),
+        ] {
+            assert_eq!(parse_slots(input, Some(432_000)).unwrap(), slots, "{}", input);
+        }
+        assert_eq!(parse_slots("2 epochs", Some(32)).unwrap(), 64);
+        assert_eq!(parse_slots("450000", None).unwrap(), 450_000);
+    }
+
+    #[test]
+    fn invalid_slots() {
+        for (input, slots_per_epoch, reason) in [
+            ("3 epochs", None, "slots per epoch"),
+            ("", Some(32), "no digits"),
+            ("slots", Some(32), "no digits"),
+            ("1.5 epochs", Some(32), "whole number"),
+            ("90m", Some(32), "whole number"),
+            ("-1 slots", Some(32), "whole number"),
+            ("18446744073709551616", Some(32), "too large"),
+            ("576460752303423488 epochs", Some(32), "too large"),
+        ] {
+            let err = parse_slots(input, slots_per_epoch).unwrap_err().to_string();
+            assert!(err.contains(reason), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn slots_from_duration() {
+        assert_eq!(estimate_slots_from_duration(Duration::from_secs(0)), 0);
+        assert_eq!(estimate_slots_from_duration(Duration::from_millis(399)), 0);
+        assert_eq!(estimate_slots_from_duration(Duration::from_secs(2)), 5);
+        assert_eq!(estimate_slots_from_duration(Duration::from_secs(48 * 60 * 60)), 432_000);
+        assert_eq!(estimate_slots_from_duration(Duration::MAX), u64::MAX);
+    }
+}
@@ -0,0 +1,293 @@
+/// Consistent `--mode`, `--dry-run` and `--simulate-only` handling for mutating commands,
+/// mapped onto [Processing] modes of the transaction processor.
+use anchor_client::solana_client::rpc_config::RpcSendTransactionConfig;
+use anyhow::{anyhow, Result};
+use clap::{Args, ValueEnum};
+use solana_client_tx_processor::Processing;
+use solana_sdk::signature::Signer;
+use crate::clap::CliContext;
+use crate::output::OutputFormat;
+
+/// What a mutating command should do with its transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ExecutionMode {
+    /// Sign and send the transaction.
+    #[default]
+    Execute,
+    /// Sign and simulate the transaction, without sending it.
+    Simulate,
+    /// Sign and print the serialized transaction, e.g. to collect more signatures.
+    SignOnly,
+    /// Print the serialized unsigned message.
+    Serialize,
+    /// Print the serialized instructions, e.g. for a multisig proposal.
+    Instructions,
+    /// Print the serialized unsigned message, never signing anything.
+    DryRun,
+}
+
+impl ExecutionMode {
+    /// The [Processing] mode for `self`, with the client of `ctx`, so its headers and timeouts
+    /// are kept. Execute skips the preflight simulation if `ctx` says so.
+    ///
+    /// `online_args` are what the transaction would otherwise fetch from the cluster.
+    /// Execute and simulate always fetch their own, so passing `online_args` with them is an
+    /// error rather than silently ignored. The other modes are offline, and passing no
+    /// `online_args` with them is an error rather than silently going online: sign-only
+    /// still fetches a recent blockhash from `ctx`, while serialize, instructions and dry-run
+    /// make no requests.
+    ///
+    /// `signer` defaults to the keypair of `ctx`. It's only used by modes that sign. The
+    /// others, including dry-run, only use its pubkey.
+    pub fn to_processing<T>(
+        &self,
+        ctx: CliContext,
+        online_args: Option<T>,
+        signer: Option<Box<dyn Signer>>,
+    ) -> Result<Processing<T>> {
+        let CliContext { rpc_client, keypair, skip_preflight, .. } = ctx;
+        let signer = signer.unwrap_or(keypair);
+        Ok(match (self, online_args) {
+            (Self::Execute, None) if skip_preflight => {
+                let config = RpcSendTransactionConfig {
+                    skip_preflight: true,
+                    preflight_commitment: Some(rpc_client.commitment().commitment),
+                    ..Default::default()
+                };
+                Processing::ExecuteWithConfig(rpc_client, signer, None, config)
+            }
+            (Self::Execute, None) => Processing::Execute(rpc_client, signer, None),
+            (Self::Simulate, None) => Processing::Simulate(rpc_client, signer, None),
+            (Self::Execute | Self::Simulate, Some(_)) => return Err(anyhow!(
+                "cannot {} with offline arguments, the transaction fetches its own from the cluster",
+                self.name()
+            )),
+            (Self::SignOnly, Some(args)) => {
+                let blockhash = rpc_client.get_latest_blockhash()
+                    .map_err(|e| anyhow!("Could not fetch a recent blockhash to sign with: {}", e))?;
+                Processing::OfflineSign(args, signer, None, blockhash)
+            }
+            (Self::Serialize | Self::DryRun, Some(args)) => {
+                Processing::OfflineSerialize(args, signer.pubkey(), None)
+            }
+            (Self::Instructions, Some(args)) => Processing::OfflineInstructions(args, signer.pubkey()),
+            (Self::SignOnly | Self::Serialize | Self::Instructions | Self::DryRun, None) => {
+                return Err(anyhow!(
+                    "cannot {} without offline arguments, they must be given instead of fetched from the cluster",
+                    self.name()
+                ))
+            }
+        })
+    }
+
+    /// Whether the transaction is sent to the cluster for execution.
+    pub fn is_mutating(&self) -> bool {
+        *self == Self::Execute
+    }
+
+    /// The output format to print the [solana_client_tx_processor::ProcessedTransaction]
+    /// in when the user didn't ask for one. Modes whose output is usually piped to
+    /// another command print compact JSON, which [crate::tx_input::read_transaction_input]
+    /// accepts. The rest are for people to read.
+    pub fn default_output_format(&self) -> OutputFormat {
+        match self {
+            Self::Execute | Self::Simulate | Self::DryRun => OutputFormat::Human,
+            Self::SignOnly | Self::Serialize | Self::Instructions => OutputFormat::JsonCompact,
+        }
+    }
+
+    fn name(&self) -> String {
+        self.to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Put this (flattened) in mutating commands made with the Derive API to add
+/// `--mode`, and the `--dry-run` and `--simulate-only` shorthands for it.
+#[derive(Debug, Clone, Default, Args)]
+pub struct ExecutionModeArgs {
+    /// What to do with the transaction.
+    #[clap(long, name = "mode", value_enum, value_name = "MODE", default_value_t)]
+    pub mode: ExecutionMode,
+    /// Print the unsigned transaction instead of sending it. Shorthand for --mode dry-run.
+    #[clap(long, name = "dry_run", conflicts_with_all = &["mode", "simulate_only"])]
+    pub dry_run: bool,
+    /// Simulate the transaction instead of sending it. Shorthand for --mode simulate.
+    #[clap(long, name = "simulate_only", conflicts_with = "mode")]
+    pub simulate_only: bool,
+}
+
+impl ExecutionModeArgs {
+    pub fn mode(&self) -> ExecutionMode {
+        if self.dry_run {
+            ExecutionMode::DryRun
+        } else if self.simulate_only {
+            ExecutionMode::Simulate
+        } else {
+            self.mode
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use anchor_client::Cluster;
+    use anchor_client::solana_client::mock_sender::Mocks;
+    use anchor_client::solana_client::rpc_client::RpcClient;
+    use anchor_client::solana_client::rpc_request::RpcRequest;
+    use clap::Parser;
+    use solana_sdk::commitment_config::CommitmentConfig;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::signature::Keypair;
+    use super::*;
+
+    fn context(blockhash: Hash) -> CliContext {
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(RpcRequest::GetLatestBlockhash, serde_json::json!({
+            "context": { "slot": 1 },
+            "value": { "blockhash": blockhash.to_string(), "lastValidBlockHeight": 100 },
+        }));
+        CliContext {
+            cluster: Cluster::Localnet,
+            keypair: Box::new(Keypair::new()),
+            commitment: CommitmentConfig::confirmed(),
+            rpc_url: "succeeds".to_string(),
+            ws_url: "ws://localhost:8900/".to_string(),
+            rpc_client: RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks),
+            skip_preflight: false,
+            output: OutputFormat::Human,
+        }
+    }
+
+    #[test]
+    fn online_mappings() {
+        let signer = Keypair::new();
+        let pubkey = signer.pubkey();
+        let processing = |mode: ExecutionMode, ctx: CliContext| {
+            mode.to_processing::<()>(ctx, None, Some(Box::new(Keypair::from_bytes(&signer.to_bytes()).unwrap())))
+        };
+        assert!(matches!(
+            processing(ExecutionMode::Execute, context(Hash::default())).unwrap(),
+            Processing::Execute(_, s, None) if s.pubkey() == pubkey
+        ));
+        assert!(matches!(
+            processing(ExecutionMode::Simulate, context(Hash::default())).unwrap(),
+            Processing::Simulate(_, s, None) if s.pubkey() == pubkey
+        ));
+
+        // The context's client is kept, along with --skip-preflight.
+        let mut ctx = context(Hash::default());
+        ctx.skip_preflight = true;
+        let url = ctx.rpc_client.url();
+        match processing(ExecutionMode::Execute, ctx).unwrap() {
+            Processing::ExecuteWithConfig(client, s, None, config) => {
+                assert_eq!(client.url(), url);
+                assert_eq!(s.pubkey(), pubkey);
+                assert!(config.skip_preflight);
+                assert_eq!(config.preflight_commitment, Some(CommitmentConfig::confirmed().commitment));
+            }
+            _ => panic!("wrong processing"),
+        }
+
+        // The offline modes never go online instead.
+        for mode in [ExecutionMode::SignOnly, ExecutionMode::Serialize, ExecutionMode::Instructions, ExecutionMode::DryRun] {
+            let err = processing(mode, context(Hash::default())).err().unwrap().to_string();
+            assert!(err.contains("without offline arguments"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn signs_with_the_context_keypair() {
+        let ctx = context(Hash::default());
+        let pubkey = ctx.keypair.pubkey();
+        assert!(matches!(
+            ExecutionMode::Simulate.to_processing::<()>(ctx, None, None).unwrap(),
+            Processing::Simulate(_, s, None) if s.pubkey() == pubkey
+        ));
+    }
+
+    #[test]
+    fn offline_mappings() {
+        let blockhash = Hash::new_unique();
+        let signer = Keypair::new();
+        let pubkey = signer.pubkey();
+        let processing = |mode: ExecutionMode| {
+            mode.to_processing(
+                context(blockhash),
+                Some(42u64),
+                Some(Box::new(Keypair::from_bytes(&signer.to_bytes()).unwrap())),
+            )
+        };
+        assert!(matches!(
+            processing(ExecutionMode::SignOnly).unwrap(),
+            Processing::OfflineSign(42, s, None, hash) if s.pubkey() == pubkey && hash == blockhash
+        ));
+        assert!(matches!(
+            processing(ExecutionMode::Serialize).unwrap(),
+            Processing::OfflineSerialize(42, p, None) if p == pubkey
+        ));
+        assert!(matches!(
+            processing(ExecutionMode::Instructions).unwrap(),
+            Processing::OfflineInstructions(42, p) if p == pubkey
+        ));
+        assert!(matches!(
+            processing(ExecutionMode::DryRun).unwrap(),
+            Processing::OfflineSerialize(42, p, None) if p == pubkey
+        ));
+        for mode in [ExecutionMode::Execute, ExecutionMode::Simulate] {
+            let err = processing(mode).err().unwrap().to_string();
+            assert!(err.contains("with offline arguments"), "{}", err);
+        }
+        let err = processing(ExecutionMode::Simulate).err().unwrap().to_string();
+        assert!(err.starts_with("cannot simulate"), "{}", err);
+    }
+
+    #[test]
+    fn sign_only_needs_a_blockhash() {
+        let failing = || {
+            let mut ctx = context(Hash::default());
+            ctx.rpc_client = RpcClient::new_mock("fails".to_string());
+            ctx
+        };
+        let err = ExecutionMode::SignOnly
+            .to_processing(failing(), Some(()), Some(Box::new(Keypair::new())))
+            .err().unwrap().to_string();
+        assert!(err.contains("Could not fetch a recent blockhash"), "{}", err);
+        // Modes that don't sign never need one.
+        assert!(ExecutionMode::DryRun.to_processing(failing(), Some(()), Some(Box::new(Keypair::new()))).is_ok());
+    }
+
+    #[test]
+    fn output_defaults() {
+        assert_eq!(ExecutionMode::Execute.default_output_format(), OutputFormat::Human);
+        assert_eq!(ExecutionMode::DryRun.default_output_format(), OutputFormat::Human);
+        assert_eq!(ExecutionMode::SignOnly.default_output_format(), OutputFormat::JsonCompact);
+        assert_eq!(ExecutionMode::Serialize.default_output_format(), OutputFormat::JsonCompact);
+        assert!(ExecutionMode::Execute.is_mutating());
+        assert!(!ExecutionMode::DryRun.is_mutating());
+    }
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[clap(flatten)]
+        mode: ExecutionModeArgs,
+    }
+
+    #[test]
+    fn flags() {
+        let mode = |args: &[&str]| {
+            TestCli::try_parse_from(std::iter::once("test").chain(args.iter().copied()))
+                .map(|cli| cli.mode.mode())
+        };
+        assert_eq!(mode(&[]).unwrap(), ExecutionMode::Execute);
+        assert_eq!(mode(&["--mode", "sign-only"]).unwrap(), ExecutionMode::SignOnly);
+        assert_eq!(mode(&["--mode", "dry-run"]).unwrap(), ExecutionMode::DryRun);
+        assert_eq!(mode(&["--dry-run"]).unwrap(), ExecutionMode::DryRun);
+        assert_eq!(mode(&["--simulate-only"]).unwrap(), ExecutionMode::Simulate);
+        assert!(mode(&["--dry-run", "--simulate-only"]).is_err());
+        assert!(mode(&["--dry-run", "--mode", "execute"]).is_err());
+        assert!(mode(&["--mode", "broadcast"]).is_err());
+    }
+}
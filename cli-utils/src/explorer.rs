@@ -0,0 +1,152 @@
+/// Links to block explorers for signatures, addresses and blocks on any cluster.
+use anchor_client::Cluster;
+use solana_sdk::pubkey::Pubkey;
+
+/// What to link to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExplorerTarget<'a> {
+    /// A transaction signature.
+    Tx(&'a str),
+    Address(&'a Pubkey),
+    /// A block, by slot.
+    Block(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Explorer {
+    /// <https://explorer.solana.com>
+    #[default]
+    Solana,
+    /// <https://solscan.io>
+    Solscan,
+    /// <https://solana.fm>
+    SolanaFm,
+}
+
+/// A link to `target` on `explorer`, for the given cluster.
+/// Clusters other than mainnet, devnet and testnet, including localnet, are linked
+/// as custom clusters with their RPC URL, which the explorers query from the browser.
+pub fn explorer_url(kind: ExplorerTarget, cluster: &Cluster, explorer: Explorer) -> String {
+    let base = match explorer {
+        Explorer::Solana => "https://explorer.solana.com",
+        Explorer::Solscan => "https://solscan.io",
+        Explorer::SolanaFm => "https://solana.fm",
+    };
+    let path = match (kind, explorer) {
+        (ExplorerTarget::Tx(signature), _) => format!("tx/{}", signature),
+        (ExplorerTarget::Address(address), Explorer::Solscan) => format!("account/{}", address),
+        (ExplorerTarget::Address(address), _) => format!("address/{}", address),
+        (ExplorerTarget::Block(slot), _) => format!("block/{}", slot),
+    };
+    let query = match (cluster, explorer) {
+        (Cluster::Mainnet, _) => None,
+        (Cluster::Devnet, Explorer::SolanaFm) => Some("cluster=devnet-solana".to_string()),
+        (Cluster::Testnet, Explorer::SolanaFm) => Some("cluster=testnet-solana".to_string()),
+        (Cluster::Devnet, _) => Some("cluster=devnet".to_string()),
+        (Cluster::Testnet, _) => Some("cluster=testnet".to_string()),
+        (cluster, _) => Some(format!("cluster=custom&customUrl={}", percent_encode(cluster.url()))),
+    };
+    match query {
+        Some(query) => format!("{}/{}?{}", base, path, query),
+        None => format!("{}/{}", base, path),
+    }
+}
+
+// Percent-encode everything except RFC 3986 unreserved characters.
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::*;
+
+    const SIGNATURE: &str = "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW";
+    const ADDRESS: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    #[test]
+    fn solana_explorer() {
+        let address = Pubkey::from_str(ADDRESS).unwrap();
+        let url = |target, cluster| explorer_url(target, &cluster, Explorer::Solana);
+        assert_eq!(
+            url(ExplorerTarget::Tx(SIGNATURE), Cluster::Mainnet),
+            format!("https://explorer.solana.com/tx/{}", SIGNATURE),
+        );
+        assert_eq!(
+            url(ExplorerTarget::Address(&address), Cluster::Devnet),
+            format!("https://explorer.solana.com/address/{}?cluster=devnet", ADDRESS),
+        );
+        assert_eq!(
+            url(ExplorerTarget::Block(42), Cluster::Testnet),
+            "https://explorer.solana.com/block/42?cluster=testnet",
+        );
+        assert_eq!(
+            url(ExplorerTarget::Tx(SIGNATURE), Cluster::Localnet),
+            format!(
+                "https://explorer.solana.com/tx/{}?cluster=custom&customUrl=http%3A%2F%2F127.0.0.1%3A8899",
+                SIGNATURE,
+            ),
+        );
+    }
+
+    #[test]
+    fn solscan() {
+        let address = Pubkey::from_str(ADDRESS).unwrap();
+        let url = |target, cluster| explorer_url(target, &cluster, Explorer::Solscan);
+        assert_eq!(
+            url(ExplorerTarget::Tx(SIGNATURE), Cluster::Devnet),
+            format!("https://solscan.io/tx/{}?cluster=devnet", SIGNATURE),
+        );
+        assert_eq!(
+            url(ExplorerTarget::Address(&address), Cluster::Mainnet),
+            format!("https://solscan.io/account/{}", ADDRESS),
+        );
+        assert_eq!(
+            url(ExplorerTarget::Block(42), Cluster::Testnet),
+            "https://solscan.io/block/42?cluster=testnet",
+        );
+        assert_eq!(
+            url(ExplorerTarget::Block(42), Cluster::Custom(
+                "http://localhost:8899".to_string(),
+                "ws://localhost:8900".to_string(),
+            )),
+            "https://solscan.io/block/42?cluster=custom&customUrl=http%3A%2F%2Flocalhost%3A8899",
+        );
+    }
+
+    #[test]
+    fn solana_fm() {
+        let address = Pubkey::from_str(ADDRESS).unwrap();
+        let url = |target, cluster| explorer_url(target, &cluster, Explorer::SolanaFm);
+        assert_eq!(
+            url(ExplorerTarget::Tx(SIGNATURE), Cluster::Mainnet),
+            format!("https://solana.fm/tx/{}", SIGNATURE),
+        );
+        assert_eq!(
+            url(ExplorerTarget::Address(&address), Cluster::Devnet),
+            format!("https://solana.fm/address/{}?cluster=devnet-solana", ADDRESS),
+        );
+        assert_eq!(
+            url(ExplorerTarget::Block(42), Cluster::Testnet),
+            "https://solana.fm/block/42?cluster=testnet-solana",
+        );
+        assert_eq!(
+            url(ExplorerTarget::Address(&address), Cluster::Custom(
+                "https://rpc.example.com/?api-key=a b".to_string(),
+                "wss://rpc.example.com/".to_string(),
+            )),
+            format!(
+                "https://solana.fm/address/{}?cluster=custom&customUrl=https%3A%2F%2Frpc.example.com%2F%3Fapi-key%3Da%20b",
+                ADDRESS,
+            ),
+        );
+    }
+}
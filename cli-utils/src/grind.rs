@@ -0,0 +1,253 @@
+/// Grinding for keypairs with recognizable addresses, like `solana-keygen grind`,
+/// e.g. for multisigs and program ids.
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use anyhow::anyhow;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{write_keypair_file, Keypair, Signer};
+use thiserror::Error;
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+/// The longest base58 encoding of a pubkey.
+const MAX_PUBKEY_LEN: usize = 44;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+// Attempts are counted in batches, so threads don't contend on the counter.
+const ATTEMPTS_PER_BATCH: u64 = 256;
+
+/// What the base58 address of a ground keypair must look like.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GrindCriteria {
+    pub prefix: Option<String>,
+    pub suffix: Option<String>,
+    /// Ignore case when matching. Each character matched this way roughly halves the
+    /// expected number of attempts.
+    pub ignore_case: bool,
+}
+
+impl GrindCriteria {
+    pub fn prefix(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: Some(prefix.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn suffix(suffix: impl Into<String>) -> Self {
+        Self {
+            suffix: Some(suffix.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn ignore_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+
+    /// Whether `pubkey` satisfies the criteria.
+    pub fn matches(&self, pubkey: &Pubkey) -> bool {
+        let address = pubkey.to_string();
+        let (address, prefix, suffix) = if self.ignore_case {
+            (
+                address.to_ascii_lowercase(),
+                self.prefix.as_ref().map(|p| p.to_ascii_lowercase()),
+                self.suffix.as_ref().map(|s| s.to_ascii_lowercase()),
+            )
+        } else {
+            (address, self.prefix.clone(), self.suffix.clone())
+        };
+        prefix.map_or(true, |prefix| address.starts_with(&prefix))
+            && suffix.map_or(true, |suffix| address.ends_with(&suffix))
+    }
+
+    fn validate(&self) -> Result<(), GrindError> {
+        let prefix = self.prefix.as_deref().unwrap_or_default();
+        let suffix = self.suffix.as_deref().unwrap_or_default();
+        if prefix.is_empty() && suffix.is_empty() {
+            return Err(GrindError::InvalidCriteria("a prefix or suffix is required".to_string()));
+        }
+        if prefix.len() + suffix.len() > MAX_PUBKEY_LEN {
+            return Err(GrindError::InvalidCriteria(format!(
+                "the prefix and suffix are {} characters, addresses have at most {}",
+                prefix.len() + suffix.len(), MAX_PUBKEY_LEN
+            )));
+        }
+        let matchable = |c: char| {
+            BASE58_ALPHABET.contains(c)
+                || (self.ignore_case
+                    && (BASE58_ALPHABET.contains(c.to_ascii_lowercase())
+                        || BASE58_ALPHABET.contains(c.to_ascii_uppercase())))
+        };
+        if let Some(c) = prefix.chars().chain(suffix.chars()).find(|c| !matchable(*c)) {
+            return Err(GrindError::InvalidCriteria(format!(
+                "{:?} never appears in base58 addresses, which exclude 0, O, I and l",
+                c
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Reported about once a second while grinding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrindProgress {
+    pub attempts: u64,
+    pub attempts_per_second: f64,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Error)]
+pub enum GrindError {
+    #[error("invalid grind criteria: {0}")]
+    InvalidCriteria(String),
+    #[error("no matching keypair found after {attempts} attempts in {elapsed:?}")]
+    Timeout { attempts: u64, elapsed: Duration },
+}
+
+/// Grind for a keypair matching `criteria` on `threads` threads, giving up with
+/// [GrindError::Timeout] once `timeout` has elapsed.
+pub fn grind_keypair(
+    criteria: GrindCriteria,
+    threads: usize,
+    timeout: Option<Duration>,
+) -> Result<Keypair, GrindError> {
+    grind_keypair_with_progress(criteria, threads, timeout, |_| {})
+}
+
+/// [grind_keypair], calling `progress` about once a second with the attempts so far.
+pub fn grind_keypair_with_progress(
+    criteria: GrindCriteria,
+    threads: usize,
+    timeout: Option<Duration>,
+    progress: impl Fn(GrindProgress),
+) -> Result<Keypair, GrindError> {
+    criteria.validate()?;
+    let start = Instant::now();
+    let attempts = AtomicU64::new(0);
+    let done = AtomicBool::new(false);
+    let found = Mutex::new(None);
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    for _ in 0..ATTEMPTS_PER_BATCH {
+                        let keypair = Keypair::new();
+                        if criteria.matches(&keypair.pubkey()) {
+                            found.lock().unwrap().get_or_insert(keypair);
+                            done.store(true, Ordering::Relaxed);
+                            break;
+                        }
+                    }
+                    attempts.fetch_add(ATTEMPTS_PER_BATCH, Ordering::Relaxed);
+                }
+            });
+        }
+        let mut last_progress = start;
+        while !done.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(10));
+            let elapsed = start.elapsed();
+            if timeout.map_or(false, |timeout| elapsed >= timeout) {
+                done.store(true, Ordering::Relaxed);
+            } else if last_progress.elapsed() >= PROGRESS_INTERVAL {
+                last_progress = Instant::now();
+                let attempts = attempts.load(Ordering::Relaxed);
+                progress(GrindProgress {
+                    attempts,
+                    attempts_per_second: attempts as f64 / elapsed.as_secs_f64(),
+                    elapsed,
+                });
+            }
+        }
+    });
+    let found = found.into_inner().unwrap();
+    found.ok_or_else(|| GrindError::Timeout {
+        attempts: attempts.into_inner(),
+        elapsed: start.elapsed(),
+    })
+}
+
+/// Grind for a keypair matching `criteria` on every available core, and write it
+/// to `path` in the JSON format of Solana CLI keypair files.
+pub fn grind_and_save(criteria: GrindCriteria, path: impl AsRef<Path>) -> anyhow::Result<Keypair> {
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let keypair = grind_keypair(criteria, threads, None)?;
+    let path = path.as_ref();
+    write_keypair_file(&keypair, path)
+        .map_err(|e| anyhow!("Could not write keypair to {}: {}", path.display(), e))?;
+    Ok(keypair)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::keypair_from_path;
+    use super::*;
+
+    #[test]
+    fn grind_prefix() {
+        let criteria = GrindCriteria::prefix("a").ignore_case();
+        let keypair = grind_keypair(criteria.clone(), 2, Some(Duration::from_secs(60))).unwrap();
+        assert!(criteria.matches(&keypair.pubkey()));
+        assert!(keypair.pubkey().to_string().to_ascii_lowercase().starts_with('a'));
+
+        let criteria = GrindCriteria::suffix("Z");
+        let keypair = grind_keypair(criteria, 2, Some(Duration::from_secs(60))).unwrap();
+        assert!(keypair.pubkey().to_string().ends_with('Z'));
+    }
+
+    #[test]
+    fn grind_and_save_round_trips() {
+        let path = std::env::temp_dir().join(format!("grind-{}.json", std::process::id()));
+        let criteria = GrindCriteria::prefix("b").ignore_case();
+        let keypair = grind_and_save(criteria.clone(), &path).unwrap();
+        let read = keypair_from_path(&path.display().to_string());
+        std::fs::remove_file(&path).unwrap();
+        let read = read.unwrap();
+        assert_eq!(*read, keypair);
+        assert!(criteria.matches(&read.pubkey()));
+    }
+
+    #[test]
+    fn timeout() {
+        // 10 case-sensitive characters take around 58^10 attempts.
+        let criteria = GrindCriteria::prefix("abcdefghij");
+        let err = grind_keypair(criteria, 1, Some(Duration::from_millis(200))).unwrap_err();
+        match err {
+            GrindError::Timeout { attempts, elapsed } => {
+                assert!(attempts > 0);
+                assert!(elapsed >= Duration::from_millis(200));
+            }
+            err => panic!("unexpected error {}", err),
+        }
+    }
+
+    #[test]
+    fn matching() {
+        let pubkey = Pubkey::new_from_array([1; 32]);
+        let address = pubkey.to_string();
+        assert!(GrindCriteria::prefix(&address[..3]).matches(&pubkey));
+        assert!(GrindCriteria::suffix(&address[address.len() - 3..]).matches(&pubkey));
+        let swapped: String = address[..3]
+            .chars()
+            .map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() })
+            .collect();
+        assert!(!GrindCriteria::prefix(&swapped).matches(&pubkey));
+        assert!(GrindCriteria::prefix(&swapped).ignore_case().matches(&pubkey));
+    }
+
+    #[test]
+    fn invalid_criteria() {
+        for (criteria, reason) in [
+            (GrindCriteria::default(), "prefix or suffix is required"),
+            (GrindCriteria::prefix("0"), "'0' never appears"),
+            (GrindCriteria::suffix("Il"), "'I' never appears"),
+            (GrindCriteria::prefix("x".repeat(45)), "at most 44"),
+        ] {
+            let err = grind_keypair(criteria, 1, None).unwrap_err().to_string();
+            assert!(err.contains(reason), "{}", err);
+        }
+        // Case-insensitively, `O` can match `o`.
+        assert!(GrindCriteria::prefix("O").ignore_case().validate().is_ok());
+    }
+}
@@ -1,3 +1,15 @@
+pub mod amount;
 pub mod serde_pubkey_str;
 pub mod clap;
-pub mod cli;
\ No newline at end of file
+pub mod clap_parsers;
+pub mod confirm;
+pub mod cli;
+pub mod duration;
+pub mod execution_mode;
+pub mod explorer;
+pub mod grind;
+pub mod logging;
+pub mod output;
+pub mod prompts;
+pub mod transaction_processing;
+pub mod tx_input;
\ No newline at end of file
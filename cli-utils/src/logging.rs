@@ -0,0 +1,131 @@
+/// Logging setup shared by CLIs, so `-v/-q` and `RUST_LOG` work the same everywhere.
+use clap::{ArgAction, Args};
+use env_logger::{Builder, Target};
+use log::LevelFilter;
+
+/// Put this (flattened) in a Clap CLI made with the Derive API to add `-v/--verbose`
+/// and `-q/--quiet`, then call [Verbosity::init_logging].
+#[derive(Debug, Clone, Default, Args)]
+pub struct Verbosity {
+    /// Log more, repeat for even more (-v, -vv, -vvv). Ignored if RUST_LOG is set.
+    #[clap(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Only log errors. Ignored if RUST_LOG is set.
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+}
+
+impl Verbosity {
+    pub fn init_logging(&self) {
+        init_logging(self.verbose, self.quiet);
+    }
+}
+
+/// Initialize logging to stderr. `RUST_LOG` takes precedence if it's set, otherwise
+/// warnings are logged by default, and each `verbosity` level adds info, debug and trace.
+/// `quiet` limits logging to errors. Does nothing if a logger is already set.
+pub fn init_logging(verbosity: u8, quiet: bool) {
+    let _ = logging_builder(verbosity, quiet, std::env::var("RUST_LOG").ok(), Target::Stderr)
+        .try_init();
+}
+
+/// The level logged for `-v` flags given `verbosity` times, or `-q`.
+pub fn level_filter(verbosity: u8, quiet: bool) -> LevelFilter {
+    if quiet {
+        return LevelFilter::Error;
+    }
+    match verbosity {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+fn logging_builder(verbosity: u8, quiet: bool, rust_log: Option<String>, target: Target) -> Builder {
+    let mut builder = Builder::new();
+    builder
+        .filter_level(level_filter(verbosity, quiet))
+        .format_timestamp(None)
+        .target(target);
+    if let Some(filters) = rust_log.filter(|filters| !filters.is_empty()) {
+        builder.parse_filters(&filters);
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use clap::Parser;
+    use log::Log;
+    use crate::cli::{resolve_url_lenient, SOLANA_CONFIG_ENV_LOCK, SOLANA_CONFIG_ENV_VAR};
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[clap(flatten)]
+        verbosity: Verbosity,
+    }
+
+    #[test]
+    fn verbosity_flags() {
+        let parse = |args: &[&str]| {
+            let verbosity = TestCli::try_parse_from(std::iter::once("test").chain(args.iter().copied()))
+                .unwrap()
+                .verbosity;
+            level_filter(verbosity.verbose, verbosity.quiet)
+        };
+        assert_eq!(parse(&[]), LevelFilter::Warn);
+        assert_eq!(parse(&["-v"]), LevelFilter::Info);
+        assert_eq!(parse(&["-vv"]), LevelFilter::Debug);
+        assert_eq!(parse(&["-vvv"]), LevelFilter::Trace);
+        assert_eq!(parse(&["-v", "-v", "-v", "-v"]), LevelFilter::Trace);
+        assert_eq!(parse(&["--quiet"]), LevelFilter::Error);
+        assert!(TestCli::try_parse_from(["test", "-v", "-q"]).is_err());
+    }
+
+    #[test]
+    fn rust_log_takes_precedence() {
+        let logger = logging_builder(3, false, Some("error".to_string()), Target::Stderr).build();
+        assert_eq!(logger.filter(), LevelFilter::Error);
+        let logger = logging_builder(0, true, Some("debug".to_string()), Target::Stderr).build();
+        assert_eq!(logger.filter(), LevelFilter::Debug);
+        let logger = logging_builder(1, false, Some(String::new()), Target::Stderr).build();
+        assert_eq!(logger.filter(), LevelFilter::Info);
+    }
+
+    // This is the only test that installs a global logger.
+    #[test]
+    fn config_fallback_warns_once() {
+        let captured = Captured::default();
+        logging_builder(0, false, None, Target::Pipe(Box::new(captured.clone())))
+            .try_init()
+            .unwrap();
+
+        let _lock = SOLANA_CONFIG_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let missing = std::env::temp_dir().join(format!("missing-config-{}.yml", std::process::id()));
+        std::env::set_var(SOLANA_CONFIG_ENV_VAR, &missing);
+        let url = resolve_url_lenient(&None, None);
+        std::env::remove_var(SOLANA_CONFIG_ENV_VAR);
+        assert_eq!(url.unwrap(), "http://localhost:8899");
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let warning = "No config file found or url provided, defaulting to localnet";
+        assert_eq!(output.matches(warning).count(), 1, "{}", output);
+    }
+}
@@ -0,0 +1,563 @@
+/// Consistent `--output` handling for CLI results: aligned key/value pairs for humans,
+/// and stable-keyed JSON for scripts.
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::str::FromStr;
+use anchor_client::Cluster;
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use solana_cli_config::Config;
+use solana_client_tx_processor::ProcessedTransaction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use crate::explorer::{explorer_url, Explorer, ExplorerTarget};
+
+/// The format of a command's output, e.g. from `--output json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned key/value pairs.
+    #[default]
+    Human,
+    /// Pretty-printed JSON.
+    Json,
+    /// JSON on a single line.
+    JsonCompact,
+}
+
+/// A command result that can be printed in any [OutputFormat].
+pub trait CliOutput {
+    /// Key/value pairs for [OutputFormat::Human], in display order.
+    fn human_fields(&self) -> Vec<(String, String)>;
+
+    /// The JSON representation. Object keys are sorted when printed.
+    fn to_json(&self) -> Value;
+
+    fn print(&self, format: OutputFormat, w: &mut dyn Write) -> io::Result<()> {
+        match format {
+            OutputFormat::Human => write_fields(&self.human_fields(), w),
+            OutputFormat::Json => {
+                serde_json::to_writer_pretty(&mut *w, &sort_keys(self.to_json()))?;
+                writeln!(w)
+            }
+            OutputFormat::JsonCompact => {
+                serde_json::to_writer(&mut *w, &sort_keys(self.to_json()))?;
+                writeln!(w)
+            }
+        }
+    }
+}
+
+// Align values after the longest key. Continuation lines of multi-line values
+// are indented to the same column.
+fn write_fields(fields: &[(String, String)], w: &mut dyn Write) -> io::Result<()> {
+    let width = fields.iter().map(|(key, _)| key.len() + 1).max().unwrap_or(0);
+    for (key, value) in fields {
+        let mut lines = value.lines();
+        let label = format!("{}:", key);
+        writeln!(w, "{:width$} {}", label, lines.next().unwrap_or_default(), width = width)?;
+        for line in lines {
+            writeln!(w, "{:width$} {}", "", line, width = width)?;
+        }
+    }
+    Ok(())
+}
+
+// Rebuild objects with their keys inserted in sorted order, so output is stable
+// whether or not serde_json preserves insertion order.
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(entries.into_iter().map(|(k, v)| (k, sort_keys(v))).collect())
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+// Metadata values are shown without JSON quoting when they're plain strings.
+fn human_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn metadata_fields(metadata: &Map<String, Value>) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = metadata
+        .iter()
+        .map(|(key, value)| (key.clone(), human_value(value)))
+        .collect();
+    fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+    fields
+}
+
+impl CliOutput for ProcessedTransaction {
+    fn human_fields(&self) -> Vec<(String, String)> {
+        let field = |key: &str, value: &str| (key.to_string(), value.to_string());
+        let (mut fields, metadata) = match self {
+            ProcessedTransaction::Execution {
+                signature, name, metadata, confirmation_slot, transaction_error,
+            } => {
+                let mut fields = vec![field("Transaction", name), field("Signature", signature)];
+                if let Some(slot) = confirmation_slot {
+                    fields.push(field("Confirmed slot", &slot.to_string()));
+                    fields.push(field("Result", &match transaction_error {
+                        Some(err) => format!("Failed: {}", err),
+                        None => "Success".to_string(),
+                    }));
+                }
+                (fields, metadata)
+            }
+            ProcessedTransaction::Simulation {
+                name, metadata, simulation_context, simulation_report, ..
+            } => {
+                let mut fields = vec![
+                    field("Simulated", name),
+                    field("Slot", &simulation_context.slot.to_string()),
+                    field("Result", &match &simulation_report.error {
+                        Some(err) => format!("Failed: {}", err),
+                        None => "Success".to_string(),
+                    }),
+                ];
+                if let Some(units) = simulation_report.units_consumed {
+                    fields.push(field("Units consumed", &units.to_string()));
+                }
+                if !simulation_report.logs.is_empty() {
+                    fields.push(field("Logs", &simulation_report.logs.join("\n")));
+                }
+                (fields, metadata)
+            }
+            ProcessedTransaction::SignedSerialized { transaction, name, metadata } => (
+                vec![field("Signed transaction", name), field("Transaction", transaction)],
+                metadata,
+            ),
+            ProcessedTransaction::UnsignedSerialized { transaction, name, metadata } => (
+                vec![field("Unsigned transaction", name), field("Message", transaction)],
+                metadata,
+            ),
+            ProcessedTransaction::InstructionSet {
+                instructions, instruction_names, name, metadata,
+            } => {
+                let mut fields = vec![field("Instructions", name)];
+                for (ix_name, ix) in instruction_names.iter().zip(instructions) {
+                    fields.push(field(ix_name, ix));
+                }
+                (fields, metadata)
+            }
+        };
+        fields.extend(metadata_fields(metadata));
+        fields
+    }
+
+    /// The serde representation of [ProcessedTransaction], tagged with its `kind`, so that
+    /// the output parses back into a [ProcessedTransaction].
+    fn to_json(&self) -> Value {
+        serde_json::to_value(self).expect("processed transactions always serialize")
+    }
+}
+
+/// A [ProcessedTransaction] printed with a link to `explorer` for executed transactions.
+/// JSON output is unchanged.
+pub struct WithExplorerLink<'a> {
+    pub processed: &'a ProcessedTransaction,
+    pub cluster: &'a Cluster,
+    pub explorer: Explorer,
+}
+
+impl CliOutput for WithExplorerLink<'_> {
+    fn human_fields(&self) -> Vec<(String, String)> {
+        let mut fields = self.processed.human_fields();
+        if let ProcessedTransaction::Execution { signature, .. } = self.processed {
+            let url = explorer_url(ExplorerTarget::Tx(signature), self.cluster, self.explorer);
+            // After the name and signature
+            fields.insert(2, ("Explorer".to_string(), url));
+        }
+        fields
+    }
+
+    fn to_json(&self) -> Value {
+        self.processed.to_json()
+    }
+}
+
+fn serialize_display<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn deserialize_from_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    T::from_str(&s).map_err(serde::de::Error::custom)
+}
+
+/// A transaction signature, e.g. the result of a command that sends a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureOutput {
+    #[serde(serialize_with = "serialize_display", deserialize_with = "deserialize_from_str")]
+    pub signature: Signature,
+}
+
+impl CliOutput for SignatureOutput {
+    fn human_fields(&self) -> Vec<(String, String)> {
+        vec![("Signature".to_string(), self.signature.to_string())]
+    }
+
+    fn to_json(&self) -> Value {
+        serde_json::to_value(self).expect("signature output always serializes")
+    }
+}
+
+/// An address, e.g. the result of a command that creates an account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PubkeyOutput {
+    #[serde(with = "crate::serde_pubkey_str")]
+    pub pubkey: Pubkey,
+}
+
+impl CliOutput for PubkeyOutput {
+    fn human_fields(&self) -> Vec<(String, String)> {
+        vec![("Pubkey".to_string(), self.pubkey.to_string())]
+    }
+
+    fn to_json(&self) -> Value {
+        serde_json::to_value(self).expect("pubkey output always serializes")
+    }
+}
+
+/// The resolved Solana CLI config, like `solana config get`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigSummary {
+    pub config_file: Option<String>,
+    pub rpc_url: String,
+    pub websocket_url: String,
+    pub keypair_path: String,
+    pub commitment: String,
+}
+
+impl ConfigSummary {
+    /// Summarize `config`, computing the WebSocket URL if it isn't set.
+    pub fn new(config: &Config, config_file: Option<String>) -> Self {
+        let websocket_url = if config.websocket_url.is_empty() {
+            Config::compute_websocket_url(&config.json_rpc_url)
+        } else {
+            config.websocket_url.clone()
+        };
+        Self {
+            config_file,
+            rpc_url: config.json_rpc_url.clone(),
+            websocket_url,
+            keypair_path: config.keypair_path.clone(),
+            commitment: config.commitment.clone(),
+        }
+    }
+}
+
+impl CliOutput for ConfigSummary {
+    fn human_fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![];
+        if let Some(config_file) = &self.config_file {
+            fields.push(("Config File".to_string(), config_file.clone()));
+        }
+        fields.extend([
+            ("RPC URL".to_string(), self.rpc_url.clone()),
+            ("WebSocket URL".to_string(), self.websocket_url.clone()),
+            ("Keypair Path".to_string(), self.keypair_path.clone()),
+            ("Commitment".to_string(), self.commitment.clone()),
+        ]);
+        fields
+    }
+
+    fn to_json(&self) -> Value {
+        serde_json::to_value(self).expect("config summary always serializes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_client::solana_client::rpc_response::{RpcResponseContext, RpcSimulateTransactionResult};
+    use serde_json::json;
+    use solana_client_tx_processor::SimulationReport;
+    use super::*;
+
+    fn print(output: &dyn CliOutput, format: OutputFormat) -> String {
+        let mut buf = vec![];
+        output.print(format, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn metadata() -> Map<String, Value> {
+        let mut metadata = Map::new();
+        metadata.insert("new_account".to_string(), json!("11111111111111111111111111111111"));
+        metadata.insert("amount".to_string(), json!(5));
+        metadata
+    }
+
+    #[test]
+    fn execution() {
+        let execution = ProcessedTransaction::Execution {
+            signature: "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW".to_string(),
+            name: "Create account".to_string(),
+            metadata: metadata(),
+            confirmation_slot: None,
+            transaction_error: None,
+        };
+        assert_eq!(print(&execution, OutputFormat::Human), "\
+Transaction: Create account
+Signature:   5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW
+amount:      5
+new_account: 11111111111111111111111111111111
+");
+        assert_eq!(print(&execution, OutputFormat::JsonCompact), "\
+{\"confirmation_slot\":null,\"kind\":\"execution\",\
+\"metadata\":{\"amount\":5,\"new_account\":\"11111111111111111111111111111111\"},\
+\"name\":\"Create account\",\
+\"signature\":\"5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW\",\
+\"transaction_error\":null}
+");
+        assert_eq!(print(&execution, OutputFormat::Json), r#"{
+  "confirmation_slot": null,
+  "kind": "execution",
+  "metadata": {
+    "amount": 5,
+    "new_account": "11111111111111111111111111111111"
+  },
+  "name": "Create account",
+  "signature": "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW",
+  "transaction_error": null
+}
+"#);
+    }
+
+    #[test]
+    fn confirmed_execution() {
+        let execution = ProcessedTransaction::Execution {
+            signature: "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW".to_string(),
+            name: "Memo".to_string(),
+            metadata: Map::new(),
+            confirmation_slot: Some(42),
+            transaction_error: Some(solana_sdk::transaction::TransactionError::AccountInUse),
+        };
+        assert_eq!(print(&execution, OutputFormat::Human), "\
+Transaction:    Memo
+Signature:      5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW
+Confirmed slot: 42
+Result:         Failed: Account in use
+");
+        assert_eq!(print(&execution, OutputFormat::JsonCompact), "\
+{\"confirmation_slot\":42,\"kind\":\"execution\",\"metadata\":{},\"name\":\"Memo\",\
+\"signature\":\"5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW\",\
+\"transaction_error\":\"AccountInUse\"}
+");
+    }
+
+    #[test]
+    fn execution_with_explorer_link() {
+        let execution = ProcessedTransaction::Execution {
+            signature: "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW".to_string(),
+            name: "Memo".to_string(),
+            metadata: Map::new(),
+            confirmation_slot: None,
+            transaction_error: None,
+        };
+        let linked = WithExplorerLink {
+            processed: &execution,
+            cluster: &Cluster::Devnet,
+            explorer: Explorer::Solana,
+        };
+        assert_eq!(print(&linked, OutputFormat::Human), "\
+Transaction: Memo
+Signature:   5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW
+Explorer:    https://explorer.solana.com/tx/5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW?cluster=devnet
+");
+        assert_eq!(
+            print(&linked, OutputFormat::JsonCompact),
+            print(&execution, OutputFormat::JsonCompact),
+        );
+    }
+
+    #[test]
+    fn simulation() {
+        let simulation_result = RpcSimulateTransactionResult {
+            err: None,
+            logs: Some(vec!["Program log: hello".to_string(), "Program success".to_string()]),
+            accounts: None,
+            units_consumed: Some(1200),
+            return_data: None,
+        };
+        let simulation = ProcessedTransaction::Simulation {
+            name: "Memo".to_string(),
+            metadata: Map::new(),
+            simulation_report: SimulationReport::from(&simulation_result),
+            simulation_result,
+            simulation_context: RpcResponseContext { slot: 7, api_version: None },
+        };
+        assert_eq!(print(&simulation, OutputFormat::Human), "\
+Simulated:      Memo
+Slot:           7
+Result:         Success
+Units consumed: 1200
+Logs:           Program log: hello
+                Program success
+");
+        assert_eq!(print(&simulation, OutputFormat::JsonCompact), "\
+{\"kind\":\"simulation\",\"metadata\":{},\"name\":\"Memo\",\
+\"simulation_context\":{\"slot\":7},\
+\"simulation_report\":{\"error\":null,\"logs\":[\"Program log: hello\",\"Program success\"],\
+\"return_data\":null,\"succeeded\":true,\"units_consumed\":1200},\
+\"simulation_result\":{\"accounts\":null,\"err\":null,\"logs\":[\"Program log: hello\",\"Program success\"],\
+\"returnData\":null,\"unitsConsumed\":1200}}
+");
+    }
+
+    #[test]
+    fn serialized_and_instructions() {
+        let signed = ProcessedTransaction::SignedSerialized {
+            transaction: "abc".to_string(),
+            name: "Memo".to_string(),
+            metadata: Map::new(),
+        };
+        assert_eq!(print(&signed, OutputFormat::Human), "\
+Signed transaction: Memo
+Transaction:        abc
+");
+        assert_eq!(
+            print(&signed, OutputFormat::JsonCompact),
+            "{\"kind\":\"signed_serialized\",\"metadata\":{},\"name\":\"Memo\",\"transaction\":\"abc\"}\n",
+        );
+        let unsigned = ProcessedTransaction::UnsignedSerialized {
+            transaction: "def".to_string(),
+            name: "Memo".to_string(),
+            metadata: Map::new(),
+        };
+        assert_eq!(print(&unsigned, OutputFormat::Human), "\
+Unsigned transaction: Memo
+Message:              def
+");
+        assert_eq!(
+            print(&unsigned, OutputFormat::JsonCompact),
+            "{\"kind\":\"unsigned_serialized\",\"metadata\":{},\"name\":\"Memo\",\"transaction\":\"def\"}\n",
+        );
+        let instructions = ProcessedTransaction::InstructionSet {
+            instructions: vec!["ix1".to_string(), "ix2".to_string()],
+            instruction_names: vec!["create".to_string(), "initialize".to_string()],
+            name: "Setup".to_string(),
+            metadata: Map::new(),
+        };
+        assert_eq!(print(&instructions, OutputFormat::Human), "\
+Instructions: Setup
+create:       ix1
+initialize:   ix2
+");
+        assert_eq!(print(&instructions, OutputFormat::JsonCompact), "\
+{\"instruction_names\":[\"create\",\"initialize\"],\"instructions\":[\"ix1\",\"ix2\"],\
+\"kind\":\"instruction_set\",\"metadata\":{},\"name\":\"Setup\"}
+");
+    }
+
+    #[test]
+    fn processed_transaction_round_trip() {
+        let simulation_result = RpcSimulateTransactionResult {
+            err: Some(solana_sdk::transaction::TransactionError::AccountInUse),
+            logs: Some(vec!["Program log: hello".to_string()]),
+            accounts: None,
+            units_consumed: Some(1200),
+            return_data: None,
+        };
+        let processed = [
+            ProcessedTransaction::Execution {
+                signature: "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW".to_string(),
+                name: "Memo".to_string(),
+                metadata: metadata(),
+                confirmation_slot: Some(42),
+                transaction_error: Some(solana_sdk::transaction::TransactionError::AccountInUse),
+            },
+            ProcessedTransaction::Simulation {
+                name: "Memo".to_string(),
+                metadata: Map::new(),
+                simulation_report: SimulationReport::from(&simulation_result),
+                simulation_result,
+                simulation_context: RpcResponseContext { slot: 7, api_version: None },
+            },
+            ProcessedTransaction::SignedSerialized {
+                transaction: "abc".to_string(),
+                name: "Memo".to_string(),
+                metadata: Map::new(),
+            },
+            ProcessedTransaction::UnsignedSerialized {
+                transaction: "def".to_string(),
+                name: "Memo".to_string(),
+                metadata: Map::new(),
+            },
+            ProcessedTransaction::InstructionSet {
+                instructions: vec!["ix1".to_string()],
+                instruction_names: vec!["create".to_string()],
+                name: "Setup".to_string(),
+                metadata: Map::new(),
+            },
+        ];
+        for processed in processed {
+            let json = print(&processed, OutputFormat::Json);
+            let parsed: ProcessedTransaction = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.to_json(), processed.to_json());
+        }
+    }
+
+    #[test]
+    fn signature_and_pubkey() {
+        let signature = SignatureOutput { signature: Signature::new(&[1; 64]) };
+        let expected = signature.signature.to_string();
+        assert_eq!(print(&signature, OutputFormat::Human), format!("Signature: {}\n", expected));
+        let json = print(&signature, OutputFormat::JsonCompact);
+        assert_eq!(json, format!("{{\"signature\":\"{}\"}}\n", expected));
+        assert_eq!(serde_json::from_str::<SignatureOutput>(&json).unwrap(), signature);
+
+        let pubkey = PubkeyOutput { pubkey: Pubkey::default() };
+        assert_eq!(print(&pubkey, OutputFormat::Human), "Pubkey: 11111111111111111111111111111111\n");
+        let json = print(&pubkey, OutputFormat::Json);
+        assert_eq!(json, "{\n  \"pubkey\": \"11111111111111111111111111111111\"\n}\n");
+        assert_eq!(serde_json::from_str::<PubkeyOutput>(&json).unwrap(), pubkey);
+    }
+
+    #[test]
+    fn config_summary() {
+        let config = Config {
+            json_rpc_url: "http://localhost:8899".to_string(),
+            websocket_url: String::new(),
+            keypair_path: "/home/user/.config/solana/id.json".to_string(),
+            commitment: "confirmed".to_string(),
+            ..Config::default()
+        };
+        let summary = ConfigSummary::new(&config, Some("/home/user/.config/solana/cli/config.yml".to_string()));
+        assert_eq!(print(&summary, OutputFormat::Human), "\
+Config File:   /home/user/.config/solana/cli/config.yml
+RPC URL:       http://localhost:8899
+WebSocket URL: ws://localhost:8900/
+Keypair Path:  /home/user/.config/solana/id.json
+Commitment:    confirmed
+");
+        let json = print(&summary, OutputFormat::JsonCompact);
+        assert_eq!(json, "\
+{\"commitment\":\"confirmed\",\"configFile\":\"/home/user/.config/solana/cli/config.yml\",\
+\"keypairPath\":\"/home/user/.config/solana/id.json\",\"rpcUrl\":\"http://localhost:8899\",\
+\"websocketUrl\":\"ws://localhost:8900/\"}
+");
+        assert_eq!(serde_json::from_str::<ConfigSummary>(&json).unwrap(), summary);
+    }
+
+    #[test]
+    fn output_format_values() {
+        let names: Vec<String> = OutputFormat::value_variants()
+            .iter()
+            .map(|format| format.to_possible_value().unwrap().get_name().to_string())
+            .collect();
+        assert_eq!(names, vec!["human", "json", "json-compact"]);
+    }
+}
@@ -0,0 +1,279 @@
+/// Interactive prompts for values that weren't passed as flags, e.g. in onboarding flows.
+/// Answers are validated with the parsers in [crate::clap_parsers], and invalid answers
+/// are asked for again, up to a limit.
+///
+/// Prompts are written to stderr, so stdout stays machine-parseable. When stdin isn't
+/// a terminal, or the user passed `--no-input`, prompting is an error instead,
+/// so scripts fail rather than hang.
+use std::io::{self, BufRead, Write};
+use anyhow::{anyhow, Result};
+use solana_sdk::pubkey::Pubkey;
+use crate::clap_parsers::{parse_pubkey, parse_sol_amount};
+
+/// The default number of answers to accept before giving up.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct PromptOptions {
+    /// Never prompt, e.g. from `--no-input`.
+    pub no_input: bool,
+    /// How many answers to accept before giving up.
+    pub max_attempts: usize,
+}
+
+impl Default for PromptOptions {
+    fn default() -> Self {
+        Self {
+            no_input: false,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+}
+
+/// Prompt for a pubkey, or a well-known program shorthand, see [parse_pubkey].
+pub fn prompt_pubkey(label: &str, opts: &PromptOptions) -> Result<Pubkey> {
+    Prompter::stdio(opts.clone()).pubkey(label)
+}
+
+/// Prompt for an amount of SOL, returning lamports, see [parse_sol_amount].
+pub fn prompt_amount_sol(label: &str, opts: &PromptOptions) -> Result<u64> {
+    Prompter::stdio(opts.clone()).amount_sol(label)
+}
+
+/// Prompt for one of `options`, returning its index.
+pub fn prompt_select(label: &str, options: &[&str], opts: &PromptOptions) -> Result<usize> {
+    Prompter::stdio(opts.clone()).select(label, options)
+}
+
+/// Prompt for a yes or no answer, where no answer means no.
+pub fn prompt_confirm(label: &str, opts: &PromptOptions) -> Result<bool> {
+    Prompter::stdio(opts.clone()).confirm(label)
+}
+
+/// The prompts, reading answers from `reader` and writing prompts to `writer`.
+pub struct Prompter<'a> {
+    reader: Box<dyn BufRead + 'a>,
+    writer: Box<dyn Write + 'a>,
+    is_tty: bool,
+    opts: PromptOptions,
+}
+
+impl<'a> Prompter<'a> {
+    pub fn new(
+        reader: impl BufRead + 'a,
+        writer: impl Write + 'a,
+        is_tty: bool,
+        opts: PromptOptions,
+    ) -> Self {
+        Self {
+            reader: Box::new(reader),
+            writer: Box::new(writer),
+            is_tty,
+            opts,
+        }
+    }
+
+    /// Prompts on stderr, reading answers from stdin.
+    pub fn stdio(opts: PromptOptions) -> Prompter<'static> {
+        let is_tty = atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stderr);
+        Prompter::new(io::BufReader::new(io::stdin()), io::stderr(), is_tty, opts)
+    }
+
+    /// See [prompt_pubkey].
+    pub fn pubkey(&mut self, label: &str) -> Result<Pubkey> {
+        self.ask(label, &format!("{}: ", label), |answer| parse_pubkey(answer))
+    }
+
+    /// See [prompt_amount_sol].
+    pub fn amount_sol(&mut self, label: &str) -> Result<u64> {
+        self.ask(label, &format!("{} (SOL): ", label), |answer| parse_sol_amount(answer))
+    }
+
+    /// See [prompt_select]. Options can be chosen by number or by name, ignoring case.
+    pub fn select(&mut self, label: &str, options: &[&str]) -> Result<usize> {
+        if options.is_empty() {
+            return Err(anyhow!("Nothing to choose for {}", label));
+        }
+        self.check_interactive(label)?;
+        writeln!(self.writer, "{}:", label)?;
+        for (i, option) in options.iter().enumerate() {
+            writeln!(self.writer, "  {}) {}", i + 1, option)?;
+        }
+        let prompt = format!("Choose 1-{}: ", options.len());
+        self.ask(label, &prompt, |answer| {
+            if let Ok(number) = answer.parse::<usize>() {
+                if (1..=options.len()).contains(&number) {
+                    return Ok(number - 1);
+                }
+            }
+            options
+                .iter()
+                .position(|option| option.eq_ignore_ascii_case(answer))
+                .ok_or_else(|| format!(
+                    "{:?} isn't one of the options, expected a number from 1 to {}",
+                    answer, options.len()
+                ))
+        })
+    }
+
+    /// See [prompt_confirm].
+    pub fn confirm(&mut self, label: &str) -> Result<bool> {
+        self.ask(label, &format!("{} [y/N] ", label), |answer| {
+            match answer.to_ascii_lowercase().as_str() {
+                "y" | "yes" => Ok(true),
+                "" | "n" | "no" => Ok(false),
+                _ => Err(format!("{:?} isn't an answer, expected y or n", answer)),
+            }
+        })
+    }
+
+    fn check_interactive(&self, label: &str) -> Result<()> {
+        if self.opts.no_input {
+            return Err(anyhow!("{} is required, and prompting is disabled by --no-input", label));
+        }
+        if !self.is_tty {
+            return Err(anyhow!("{} is required, and can't be prompted for outside a terminal", label));
+        }
+        Ok(())
+    }
+
+    // Prompts until `parse` accepts an answer, at most `max_attempts` times.
+    fn ask<T>(
+        &mut self,
+        label: &str,
+        prompt: &str,
+        parse: impl Fn(&str) -> std::result::Result<T, String>,
+    ) -> Result<T> {
+        self.check_interactive(label)?;
+        for _ in 0..self.opts.max_attempts {
+            write!(self.writer, "{}", prompt)?;
+            self.writer.flush()?;
+            let mut answer = String::new();
+            if self.reader.read_line(&mut answer)? == 0 {
+                writeln!(self.writer)?;
+                return Err(anyhow!("No answer given for {}", label));
+            }
+            match parse(answer.trim()) {
+                Ok(value) => return Ok(value),
+                Err(e) => writeln!(self.writer, "{}", e)?,
+            }
+        }
+        Err(anyhow!(
+            "No valid answer for {} after {} attempts",
+            label, self.opts.max_attempts
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use solana_sdk::native_token::LAMPORTS_PER_SOL;
+    use super::*;
+
+    const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn prompter<'a>(input: &'a str, output: &'a mut Vec<u8>) -> Prompter<'a> {
+        Prompter::new(input.as_bytes(), output, true, PromptOptions::default())
+    }
+
+    #[test]
+    fn pubkey_retries() {
+        let mut output = vec![];
+        let pubkey = prompter(&format!("nope\n\n{}\n", USDC_MINT), &mut output)
+            .pubkey("Mint")
+            .unwrap();
+        assert_eq!(pubkey, Pubkey::from_str(USDC_MINT).unwrap());
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("Mint: ").count(), 3);
+        assert!(output.contains("invalid pubkey \"nope\""), "{}", output);
+        assert!(output.contains("invalid pubkey \"\""), "{}", output);
+
+        let mut output = vec![];
+        let pubkey = prompter(" memo \n", &mut output).pubkey("Program").unwrap();
+        assert_eq!(pubkey.to_string(), "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+    }
+
+    #[test]
+    fn gives_up() {
+        let mut output = vec![];
+        let err = prompter("a\nb\nc\nd\n", &mut output).pubkey("Mint").unwrap_err().to_string();
+        assert_eq!(err, "No valid answer for Mint after 3 attempts");
+
+        let mut output = vec![];
+        let mut once = Prompter::new(
+            "a\n".as_bytes(),
+            &mut output,
+            true,
+            PromptOptions { max_attempts: 1, ..Default::default() },
+        );
+        let err = once.amount_sol("Amount").unwrap_err().to_string();
+        assert_eq!(err, "No valid answer for Amount after 1 attempts");
+
+        // The input ends before a valid answer.
+        let mut output = vec![];
+        let err = prompter("1.5x\n", &mut output).amount_sol("Amount").unwrap_err().to_string();
+        assert_eq!(err, "No answer given for Amount");
+    }
+
+    #[test]
+    fn amount() {
+        let mut output = vec![];
+        let lamports = prompter("ALL\n-1\n1.5\n", &mut output).amount_sol("Amount").unwrap();
+        assert_eq!(lamports, 3 * LAMPORTS_PER_SOL / 2);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("Amount (SOL): "), "{}", output);
+        assert!(output.contains("a specific amount is required"), "{}", output);
+        assert!(output.contains("negative amounts are not allowed"), "{}", output);
+    }
+
+    #[test]
+    fn select() {
+        let options = ["devnet", "mainnet-beta", "testnet"];
+        let mut output = vec![];
+        let choice = prompter("0\n4\nMainnet-Beta\n", &mut output)
+            .select("Cluster", &options)
+            .unwrap();
+        assert_eq!(choice, 1);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("Cluster:\n  1) devnet\n  2) mainnet-beta\n  3) testnet\nChoose 1-3: "), "{}", output);
+        assert!(output.contains("\"4\" isn't one of the options"), "{}", output);
+
+        let mut output = vec![];
+        assert_eq!(prompter("3\n", &mut output).select("Cluster", &options).unwrap(), 2);
+        let mut output = vec![];
+        assert!(prompter("1\n", &mut output).select("Cluster", &[]).is_err());
+    }
+
+    #[test]
+    fn confirm() {
+        for (input, expected) in [("y\n", true), ("YES\n", true), ("\n", false), ("n\n", false), ("maybe\nyes\n", true)] {
+            let mut output = vec![];
+            assert_eq!(prompter(input, &mut output).confirm("Continue?").unwrap(), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn non_interactive() {
+        let mut output = vec![];
+        let mut prompter = Prompter::new(
+            "y\n".as_bytes(), &mut output, false, PromptOptions::default(),
+        );
+        let err = prompter.confirm("Continue?").unwrap_err().to_string();
+        assert!(err.contains("outside a terminal"), "{}", err);
+        let err = prompter.select("Cluster", &["devnet"]).unwrap_err().to_string();
+        assert!(err.contains("outside a terminal"), "{}", err);
+
+        let mut output = vec![];
+        let mut prompter = Prompter::new(
+            "y\n".as_bytes(),
+            &mut output,
+            true,
+            PromptOptions { no_input: true, ..Default::default() },
+        );
+        let err = prompter.pubkey("Mint").unwrap_err().to_string();
+        assert_eq!(err, "Mint is required, and prompting is disabled by --no-input");
+        drop(prompter);
+        assert!(output.is_empty());
+    }
+}
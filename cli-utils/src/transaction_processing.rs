@@ -0,0 +1,899 @@
+/// Building, sending and confirming transactions from instructions, for commands that
+/// don't go through a [solana_client_tx_processor::TransactionProcessor].
+use std::time::{Duration, Instant};
+use anchor_client::solana_client::client_error::{ClientError, ClientErrorKind};
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig};
+use anchor_client::solana_client::rpc_request::RpcError;
+use anyhow::anyhow;
+use bincode::Options;
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::{v0, Message, VersionedMessage};
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::{Signer, SignerError};
+use solana_sdk::transaction::{Transaction, TransactionError, VersionedTransaction};
+use solana_sdk::transaction_context::TransactionReturnData;
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+use thiserror::Error;
+use crate::amount::format_sol;
+use crate::output::CliOutput;
+
+// The compute budget program's defaults, for transactions that don't set a limit.
+const DEFAULT_UNITS_PER_INSTRUCTION: u64 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+// JSON-RPC's "method not found" error code.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+#[derive(Debug, Error)]
+pub enum TxError {
+    #[error("rpc client error: {0}")]
+    ClientError(#[from] ClientError),
+    #[error("could not sign the transaction: {0}")]
+    Signing(#[from] SignerError),
+    #[error("transaction {signature} failed: {error}")]
+    Failed { signature: Signature, error: TransactionError },
+    #[error(
+        "transaction {signature} expired before it was confirmed, its blockhash {blockhash} is \
+        no longer valid. Rebuild it with a new blockhash and try again"
+    )]
+    BlockhashExpired { signature: Signature, blockhash: Hash },
+    #[error("transaction {signature} was not confirmed after {elapsed:?}")]
+    Timeout { signature: Signature, elapsed: Duration },
+    #[error("the RPC node doesn't support fee estimates (getFeeForMessage), it may be running an old version")]
+    FeeEstimateUnsupported,
+}
+
+/// How [send_and_confirm] waits for confirmation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendAndConfirmOptions {
+    /// The commitment to wait for.
+    pub commitment: CommitmentConfig,
+    /// How long to wait before giving up with [TxError::Timeout].
+    pub timeout: Duration,
+    /// How long to wait between checks of the signature status.
+    pub poll_interval: Duration,
+    /// Send the same signed transaction again this often while waiting,
+    /// in case the first one was dropped.
+    pub rebroadcast_interval: Option<Duration>,
+}
+
+impl Default for SendAndConfirmOptions {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            timeout: Duration::from_secs(90),
+            poll_interval: Duration::from_millis(500),
+            rebroadcast_interval: Some(Duration::from_secs(2)),
+        }
+    }
+}
+
+/// A transaction that reached the commitment it was sent with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmedSignature {
+    pub signature: Signature,
+    pub slot: u64,
+    pub confirmation_status: Option<TransactionConfirmationStatus>,
+    pub elapsed: Duration,
+}
+
+/// Sign and send a transaction of `ixs`, then wait for it to reach `opts.commitment`.
+///
+/// If the transaction's blockhash expires before it lands, it never will, so this
+/// returns [TxError::BlockhashExpired] instead of waiting out the timeout.
+pub fn send_and_confirm(
+    ixs: &[Instruction],
+    fee_payer: &Pubkey,
+    signers: &[Box<dyn Signer>],
+    client: &RpcClient,
+    opts: &SendAndConfirmOptions,
+) -> Result<ConfirmedSignature, TxError> {
+    send_and_confirm_with_progress(ixs, fee_payer, signers, client, opts, |_, _| {})
+}
+
+/// [send_and_confirm], calling `progress` with the time since sending and the latest
+/// status after every check, e.g. to drive a spinner.
+/// The status is [None] until the cluster has seen the transaction.
+pub fn send_and_confirm_with_progress(
+    ixs: &[Instruction],
+    fee_payer: &Pubkey,
+    signers: &[Box<dyn Signer>],
+    client: &RpcClient,
+    opts: &SendAndConfirmOptions,
+    progress: impl Fn(Duration, Option<&TransactionStatus>),
+) -> Result<ConfirmedSignature, TxError> {
+    let blockhash = client.get_latest_blockhash()?;
+    let mut tx = Transaction::new_unsigned(Message::new(ixs, Some(fee_payer)));
+    tx.try_sign(signers, blockhash)?;
+    let signature = client.send_transaction(&tx)?;
+    let start = Instant::now();
+    let mut last_broadcast = start;
+    loop {
+        // Check the blockhash first, so a transaction that lands in between
+        // is still seen below rather than reported as expired.
+        let blockhash_valid = client.is_blockhash_valid(&blockhash, CommitmentConfig::processed())?;
+        let status = client.get_signature_statuses(&[signature])?.value.pop().flatten();
+        let elapsed = start.elapsed();
+        progress(elapsed, status.as_ref());
+        match status {
+            Some(TransactionStatus { err: Some(error), .. }) => {
+                return Err(TxError::Failed { signature, error });
+            }
+            Some(status) if status.satisfies_commitment(opts.commitment) => {
+                return Ok(ConfirmedSignature {
+                    signature,
+                    slot: status.slot,
+                    confirmation_status: status.confirmation_status,
+                    elapsed,
+                });
+            }
+            Some(_) => {}
+            None if !blockhash_valid => {
+                return Err(TxError::BlockhashExpired { signature, blockhash });
+            }
+            None => {}
+        }
+        if elapsed >= opts.timeout {
+            return Err(TxError::Timeout { signature, elapsed });
+        }
+        if let Some(interval) = opts.rebroadcast_interval {
+            if last_broadcast.elapsed() >= interval {
+                last_broadcast = Instant::now();
+                // The transaction was already preflighted, and failing to rebroadcast
+                // isn't fatal while the original may still land.
+                let _ = client.send_transaction_with_config(&tx, RpcSendTransactionConfig {
+                    skip_preflight: true,
+                    ..RpcSendTransactionConfig::default()
+                });
+            }
+        }
+        std::thread::sleep(opts.poll_interval);
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SignError {
+    #[error("could not sign the transaction: {0}")]
+    Signer(#[from] SignerError),
+    #[error("the transaction is missing signatures from {}", format_pubkeys(.0))]
+    MissingSignatures(Vec<Pubkey>),
+    /// The signed transaction couldn't be encoded, e.g. it's too large to send.
+    #[error("could not serialize the transaction: {0}")]
+    Serialization(#[from] bincode::Error),
+}
+
+fn format_pubkeys(pubkeys: &[Pubkey]) -> String {
+    pubkeys.iter().map(|pubkey| pubkey.to_string()).collect::<Vec<String>>().join(", ")
+}
+
+/// A transaction from [sign_transaction].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedTransaction {
+    /// The base-58 encoded transaction.
+    pub transaction: String,
+    /// The required signers that haven't signed yet. Always empty unless partial
+    /// signing was allowed.
+    pub missing: Vec<Pubkey>,
+}
+
+/// Sign a transaction of `ixs` with `blockhash`, and encode it for sending elsewhere.
+///
+/// Unless `allow_partial`, every required signature must be covered by `signers`, and
+/// [SignError::MissingSignatures] lists the ones that aren't, e.g. a co-signer that
+/// wasn't passed in, or a PDA mistakenly marked as a signer. With `allow_partial`,
+/// e.g. to collect the other signatures offline, they're returned with the transaction.
+/// Transactions too large to send are a [SignError::Serialization].
+pub fn sign_transaction(
+    ixs: &[Instruction],
+    fee_payer: &Pubkey,
+    signers: &[Box<dyn Signer>],
+    blockhash: Hash,
+    allow_partial: bool,
+) -> Result<SignedTransaction, SignError> {
+    let mut tx = Transaction::new_unsigned(Message::new(ixs, Some(fee_payer)));
+    tx.try_partial_sign(signers, blockhash)?;
+    let required = tx.message.header.num_required_signatures as usize;
+    let missing: Vec<Pubkey> = tx.message.account_keys[..required]
+        .iter()
+        .zip(&tx.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(pubkey, _)| *pubkey)
+        .collect();
+    if !missing.is_empty() && !allow_partial {
+        return Err(SignError::MissingSignatures(missing));
+    }
+    let serialized = bincode::options()
+        .with_limit(PACKET_DATA_SIZE as u64)
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .serialize(&tx)?;
+    Ok(SignedTransaction {
+        transaction: bs58::encode(serialized).into_string(),
+        missing,
+    })
+}
+
+/// How serialized transactions and messages are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum TxEncoding {
+    #[default]
+    Base58,
+    /// What wallet adapters and most web tooling expect.
+    Base64,
+}
+
+impl TxEncoding {
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Base58 => bs58::encode(bytes).into_string(),
+            Self::Base64 => base64::encode(bytes),
+        }
+    }
+}
+
+/// Which message format to build.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MessageVersion {
+    #[default]
+    Legacy,
+    /// A v0 message, loading accounts from these lookup tables where possible.
+    V0(Vec<AddressLookupTableAccount>),
+}
+
+/// Serialize the unsigned message of a transaction of `ixs`, base-58 encoded,
+/// e.g. to be signed elsewhere. See [serialize_transaction_with].
+pub fn serialize_transaction(ixs: &[Instruction], fee_payer: &Pubkey) -> anyhow::Result<String> {
+    serialize_transaction_with(ixs, fee_payer, None, TxEncoding::Base58, MessageVersion::Legacy)
+}
+
+/// Serialize the unsigned message of a transaction of `ixs`, in the given `version`.
+/// The message has `blockhash` if given, e.g. for a wallet to sign as is.
+///
+/// Fails if the transaction wouldn't fit in a packet once signed, e.g. a v0 message
+/// with too many accounts that aren't in any of its lookup tables.
+pub fn serialize_transaction_with(
+    ixs: &[Instruction],
+    fee_payer: &Pubkey,
+    blockhash: Option<Hash>,
+    encoding: TxEncoding,
+    version: MessageVersion,
+) -> anyhow::Result<String> {
+    let blockhash = blockhash.unwrap_or_default();
+    let (message, lookup_tables) = match version {
+        MessageVersion::Legacy => (
+            VersionedMessage::Legacy(Message::new_with_blockhash(ixs, Some(fee_payer), &blockhash)),
+            0,
+        ),
+        MessageVersion::V0(lookup_tables) => (
+            VersionedMessage::V0(
+                v0::Message::try_compile(fee_payer, ixs, &lookup_tables, blockhash)
+                    .map_err(|e| anyhow!("Could not compile the v0 message: {}", e))?,
+            ),
+            lookup_tables.len(),
+        ),
+    };
+    let unsigned = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header().num_required_signatures as usize],
+        message,
+    };
+    let size = bincode::serialized_size(&unsigned)? as usize;
+    if size > PACKET_DATA_SIZE {
+        let hint = match (&unsigned.message, lookup_tables) {
+            (VersionedMessage::V0(_), 0) => ", provide address lookup tables for its accounts",
+            (VersionedMessage::V0(_), _) => ", its lookup tables don't cover enough of its accounts",
+            (VersionedMessage::Legacy(_), _) => ", try a v0 message with address lookup tables",
+        };
+        return Err(anyhow!(
+            "The signed transaction would be {} bytes, over the {} byte limit{}",
+            size, PACKET_DATA_SIZE, hint
+        ));
+    }
+    Ok(encoding.encode(&unsigned.message.serialize()))
+}
+
+/// The outcome of [simulate].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulationReport {
+    pub slot: u64,
+    pub err: Option<TransactionError>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    pub return_data: Option<TransactionReturnData>,
+    /// The failing program and its error, read from the logs when the simulation failed.
+    pub decoded_error: Option<DecodedProgramError>,
+}
+
+/// A program failure, as reported in the transaction logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedProgramError {
+    pub program_id: Pubkey,
+    /// The code of a custom program error, e.g. an Anchor error code.
+    pub custom_code: Option<u32>,
+    /// The error after `failed: ` in the log line.
+    pub message: String,
+}
+
+/// Simulate a transaction of `ixs`.
+///
+/// With `signers`, the transaction is signed with a recent blockhash and its signatures
+/// are verified. Without them, it's simulated unsigned, with `sig_verify` off and the
+/// blockhash replaced by the cluster, overriding those fields of `config`.
+pub fn simulate(
+    ixs: &[Instruction],
+    fee_payer: &Pubkey,
+    signers: Option<&[Box<dyn Signer>]>,
+    client: &RpcClient,
+    config: RpcSimulateTransactionConfig,
+) -> Result<SimulationReport, TxError> {
+    let mut tx = Transaction::new_unsigned(Message::new(ixs, Some(fee_payer)));
+    let config = match signers {
+        Some(signers) => {
+            tx.try_sign(signers, client.get_latest_blockhash()?)?;
+            RpcSimulateTransactionConfig {
+                sig_verify: true,
+                replace_recent_blockhash: false,
+                ..config
+            }
+        }
+        None => RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            ..config
+        },
+    };
+    let response = client.simulate_transaction_with_config(&tx, config)?;
+    let result = response.value;
+    let logs = result.logs.unwrap_or_default();
+    let decoded_error = result.err.as_ref().and_then(|_| decode_program_error(&logs));
+    let return_data = result.return_data.and_then(|return_data| {
+        Some(TransactionReturnData {
+            program_id: return_data.program_id.parse().ok()?,
+            data: base64::decode(return_data.data.0).ok()?,
+        })
+    });
+    Ok(SimulationReport {
+        slot: response.context.slot,
+        err: result.err,
+        logs,
+        units_consumed: result.units_consumed,
+        return_data,
+        decoded_error,
+    })
+}
+
+/// Find the program failure in transaction logs, i.e. the last
+/// `Program <id> failed: <error>` line.
+pub fn decode_program_error(logs: &[String]) -> Option<DecodedProgramError> {
+    logs.iter().rev().find_map(|line| {
+        let (program_id, message) = line.strip_prefix("Program ")?.split_once(" failed: ")?;
+        let custom_code = message
+            .strip_prefix("custom program error: 0x")
+            .and_then(|code| u32::from_str_radix(code, 16).ok());
+        Some(DecodedProgramError {
+            program_id: program_id.parse().ok()?,
+            custom_code,
+            message: message.to_string(),
+        })
+    })
+}
+
+impl CliOutput for SimulationReport {
+    fn human_fields(&self) -> Vec<(String, String)> {
+        let field = |key: &str, value: String| (key.to_string(), value);
+        let mut fields = vec![
+            field("Slot", self.slot.to_string()),
+            field("Result", match &self.err {
+                Some(err) => format!("Failed: {}", err),
+                None => "Success".to_string(),
+            }),
+        ];
+        if let Some(decoded) = &self.decoded_error {
+            fields.push(field("Failed program", decoded.program_id.to_string()));
+            if let Some(code) = decoded.custom_code {
+                fields.push(field("Error code", format!("{} ({:#x})", code, code)));
+            }
+        }
+        if let Some(units) = self.units_consumed {
+            fields.push(field("Units consumed", units.to_string()));
+        }
+        if let Some(return_data) = &self.return_data {
+            fields.push(field("Return data", format!(
+                "{} from {}", base64::encode(&return_data.data), return_data.program_id
+            )));
+        }
+        if !self.logs.is_empty() {
+            fields.push(field("Logs", self.logs.join("\n")));
+        }
+        fields
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "slot": self.slot,
+            "err": self.err,
+            "logs": self.logs,
+            "unitsConsumed": self.units_consumed,
+            "returnData": self.return_data.as_ref().map(|return_data| json!({
+                "programId": return_data.program_id.to_string(),
+                "data": base64::encode(&return_data.data),
+            })),
+            "decodedError": self.decoded_error.as_ref().map(|decoded| json!({
+                "programId": decoded.program_id.to_string(),
+                "customCode": decoded.custom_code,
+                "message": decoded.message,
+            })),
+        })
+    }
+}
+
+/// What a transaction is expected to cost, from [estimate_fee].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimate {
+    /// The signature fees.
+    pub base_lamports: u64,
+    /// The prioritization fee, from the compute unit price and limit.
+    pub priority_lamports: u64,
+    pub total_lamports: u64,
+    /// The number of required signatures.
+    pub signatures: u8,
+}
+
+impl FeeEstimate {
+    /// The total, e.g. `0.000012 SOL`.
+    pub fn format_sol(&self) -> String {
+        format!("{} SOL", format_sol(self.total_lamports))
+    }
+}
+
+/// Estimate the fee for a transaction of `ixs` paid by `fee_payer`, before signing it.
+///
+/// The base fee comes from the cluster. When `ixs` set a compute unit price, the
+/// priority fee is added: the price times the compute unit limit, either the one set
+/// in `ixs` or the default for the number of instructions.
+pub fn estimate_fee(
+    ixs: &[Instruction],
+    fee_payer: &Pubkey,
+    client: &RpcClient,
+) -> Result<FeeEstimate, TxError> {
+    let mut unit_limit = None;
+    let mut unit_price = None;
+    let mut other_instructions = 0;
+    for ix in ixs {
+        match parse_compute_budget(ix) {
+            Some(ComputeBudget::UnitLimit(limit)) => unit_limit = Some(limit as u64),
+            Some(ComputeBudget::UnitPrice(price)) => unit_price = Some(price),
+            Some(ComputeBudget::Other) => {}
+            None => other_instructions += 1,
+        }
+    }
+    // The price is left out of the message sent for the base fee, so clusters that
+    // include the priority fee in their answer don't count it twice.
+    let base_ixs: Vec<Instruction> = ixs
+        .iter()
+        .filter(|ix| !matches!(parse_compute_budget(ix), Some(ComputeBudget::UnitPrice(_))))
+        .cloned()
+        .collect();
+    let blockhash = client.get_latest_blockhash()?;
+    let message = Message::new_with_blockhash(&base_ixs, Some(fee_payer), &blockhash);
+    let base_lamports = client.get_fee_for_message(&message).map_err(|e| match &e.kind {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. })
+            if *code == METHOD_NOT_FOUND => TxError::FeeEstimateUnsupported,
+        _ => TxError::ClientError(e),
+    })?;
+    let priority_lamports = unit_price.map_or(0, |price| {
+        let limit = unit_limit.unwrap_or_else(|| {
+            (other_instructions * DEFAULT_UNITS_PER_INSTRUCTION).min(MAX_COMPUTE_UNIT_LIMIT)
+        });
+        let micro_lamports = price as u128 * limit as u128;
+        // Rounded up, like the runtime does.
+        ((micro_lamports + MICRO_LAMPORTS_PER_LAMPORT - 1) / MICRO_LAMPORTS_PER_LAMPORT) as u64
+    });
+    Ok(FeeEstimate {
+        base_lamports,
+        priority_lamports,
+        total_lamports: base_lamports + priority_lamports,
+        signatures: message.header.num_required_signatures,
+    })
+}
+
+enum ComputeBudget {
+    UnitLimit(u32),
+    UnitPrice(u64),
+    Other,
+}
+
+// Compute budget instructions are a borsh enum tag followed by little endian fields:
+// 2 is SetComputeUnitLimit(u32) and 3 is SetComputeUnitPrice(u64).
+fn parse_compute_budget(ix: &Instruction) -> Option<ComputeBudget> {
+    if ix.program_id != compute_budget::id() {
+        return None;
+    }
+    Some(match ix.data.split_first() {
+        Some((2, limit)) => limit.try_into().map_or(ComputeBudget::Other, |limit| {
+            ComputeBudget::UnitLimit(u32::from_le_bytes(limit))
+        }),
+        Some((3, price)) => price.try_into().map_or(ComputeBudget::Other, |price| {
+            ComputeBudget::UnitPrice(u64::from_le_bytes(price))
+        }),
+        _ => ComputeBudget::Other,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use anchor_client::solana_client::mock_sender::Mocks;
+    use anchor_client::solana_client::rpc_request::RpcRequest;
+    use serde_json::json;
+    use solana_sdk::signature::Keypair;
+    use crate::output::OutputFormat;
+    use super::*;
+
+    fn memo(payer: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            Pubkey::from_str("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr").unwrap(),
+            b"hello",
+            vec![solana_sdk::instruction::AccountMeta::new(*payer, true)],
+        )
+    }
+
+    fn client(url: &str, blockhash_valid: bool) -> RpcClient {
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(RpcRequest::IsBlockhashValid, json!({
+            "context": { "slot": 1 },
+            "value": blockhash_valid,
+        }));
+        RpcClient::new_mock_with_mocks(url.to_string(), mocks)
+    }
+
+    fn opts() -> SendAndConfirmOptions {
+        SendAndConfirmOptions {
+            poll_interval: Duration::from_millis(1),
+            ..SendAndConfirmOptions::default()
+        }
+    }
+
+    #[test]
+    fn confirms() {
+        let payer: Box<dyn Signer> = Box::new(Keypair::new());
+        let pubkey = payer.pubkey();
+        let calls = RefCell::new(vec![]);
+        let confirmed = send_and_confirm_with_progress(
+            &[memo(&pubkey)],
+            &pubkey,
+            &[payer],
+            &client("succeeds", true),
+            &opts(),
+            |_, status| calls.borrow_mut().push(status.cloned()),
+        ).unwrap();
+        assert_eq!(confirmed.slot, 1);
+        assert_eq!(confirmed.confirmation_status, Some(TransactionConfirmationStatus::Finalized));
+        assert_ne!(confirmed.signature, Signature::default());
+        assert_eq!(calls.borrow().len(), 1);
+        assert!(calls.borrow()[0].is_some());
+    }
+
+    #[test]
+    fn failed() {
+        let payer: Box<dyn Signer> = Box::new(Keypair::new());
+        let pubkey = payer.pubkey();
+        let err = send_and_confirm(
+            &[memo(&pubkey)], &pubkey, &[payer], &client("instruction_error", true), &opts(),
+        ).unwrap_err();
+        assert!(matches!(err, TxError::Failed { .. }), "{}", err);
+    }
+
+    #[test]
+    fn expired() {
+        let payer: Box<dyn Signer> = Box::new(Keypair::new());
+        let pubkey = payer.pubkey();
+        let err = send_and_confirm(
+            &[memo(&pubkey)], &pubkey, &[payer], &client("sig_not_found", false), &opts(),
+        ).unwrap_err();
+        assert!(matches!(err, TxError::BlockhashExpired { .. }), "{}", err);
+        assert!(err.to_string().contains("Rebuild it with a new blockhash"), "{}", err);
+    }
+
+    #[test]
+    fn missing_signer() {
+        let payer = Keypair::new();
+        let other: Box<dyn Signer> = Box::new(Keypair::new());
+        let err = send_and_confirm(
+            &[memo(&payer.pubkey())], &payer.pubkey(), &[other], &client("succeeds", true), &opts(),
+        ).unwrap_err();
+        assert!(matches!(err, TxError::Signing(_)), "{}", err);
+    }
+
+    const FAILED_LOGS: &[&str] = &[
+        "Program ComputeBudget111111111111111111111111111111 invoke [1]",
+        "Program ComputeBudget111111111111111111111111111111 success",
+        "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc invoke [1]",
+        "Program log: Instruction: Swap",
+        "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [2]",
+        "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
+        "Program log: AnchorError occurred. Error Code: AmountOutBelowMinimum. Error Number: 6036.",
+        "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc consumed 45123 of 200000 compute units",
+        "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc failed: custom program error: 0x1794",
+    ];
+
+    fn logs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn decodes_custom_errors() {
+        let decoded = decode_program_error(&logs(FAILED_LOGS)).unwrap();
+        assert_eq!(decoded.program_id.to_string(), "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+        assert_eq!(decoded.custom_code, Some(6036));
+        assert_eq!(decoded.message, "custom program error: 0x1794");
+    }
+
+    #[test]
+    fn decodes_other_errors() {
+        let decoded = decode_program_error(&logs(&[
+            "Program 11111111111111111111111111111111 invoke [1]",
+            "Transfer: insufficient lamports 10, need 100",
+            "Program 11111111111111111111111111111111 failed: custom program error: 0x1",
+        ])).unwrap();
+        assert_eq!(decoded.custom_code, Some(1));
+
+        let decoded = decode_program_error(&logs(&[
+            "Program MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr invoke [1]",
+            "Program MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr failed: invalid instruction data",
+        ])).unwrap();
+        assert_eq!(decoded.custom_code, None);
+        assert_eq!(decoded.message, "invalid instruction data");
+
+        assert_eq!(decode_program_error(&logs(&FAILED_LOGS[..2])), None);
+        assert_eq!(decode_program_error(&logs(&["Program log: failed: nope"])), None);
+    }
+
+    #[test]
+    fn simulates_unsigned() {
+        let payer = Keypair::new();
+        let report = simulate(
+            &[memo(&payer.pubkey())],
+            &payer.pubkey(),
+            None,
+            &RpcClient::new_mock("succeeds".to_string()),
+            RpcSimulateTransactionConfig::default(),
+        ).unwrap();
+        assert_eq!(report.err, None);
+        assert_eq!(report.decoded_error, None);
+        let mut output = vec![];
+        report.print(OutputFormat::Human, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Result: Success"), "{}", output);
+    }
+
+    #[test]
+    fn report_output() {
+        let report = SimulationReport {
+            slot: 7,
+            err: Some(TransactionError::InstructionError(
+                1, solana_sdk::instruction::InstructionError::Custom(6036),
+            )),
+            logs: logs(FAILED_LOGS),
+            units_consumed: Some(45273),
+            return_data: None,
+            decoded_error: decode_program_error(&logs(FAILED_LOGS)),
+        };
+        let fields = report.human_fields();
+        assert!(fields.contains(&("Error code".to_string(), "6036 (0x1794)".to_string())), "{:?}", fields);
+        let value = report.to_json();
+        assert_eq!(value["decodedError"]["customCode"], 6036);
+        assert_eq!(value["unitsConsumed"], 45273);
+        assert_eq!(value["logs"].as_array().unwrap().len(), FAILED_LOGS.len());
+    }
+
+    fn fee_client(lamports_per_signature: u64) -> RpcClient {
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(RpcRequest::GetFeeForMessage, json!({
+            "context": { "slot": 1 },
+            "value": lamports_per_signature,
+        }));
+        RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+    }
+
+    #[test]
+    fn estimates_base_fee() {
+        let payer = Pubkey::new_unique();
+        let estimate = estimate_fee(&[memo(&payer)], &payer, &fee_client(5000)).unwrap();
+        assert_eq!(estimate, FeeEstimate {
+            base_lamports: 5000,
+            priority_lamports: 0,
+            total_lamports: 5000,
+            signatures: 1,
+        });
+        assert_eq!(estimate.format_sol(), "0.000005 SOL");
+
+        let cosigned = Instruction {
+            accounts: vec![
+                solana_sdk::instruction::AccountMeta::new(payer, true),
+                solana_sdk::instruction::AccountMeta::new_readonly(Pubkey::new_unique(), true),
+            ],
+            ..memo(&payer)
+        };
+        let estimate = estimate_fee(&[cosigned], &payer, &fee_client(10000)).unwrap();
+        assert_eq!(estimate.signatures, 2);
+    }
+
+    #[test]
+    fn estimates_priority_fee() {
+        use solana_sdk::compute_budget::ComputeBudgetInstruction;
+        let payer = Pubkey::new_unique();
+        let ixs = [
+            ComputeBudgetInstruction::set_compute_unit_limit(50_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+            memo(&payer),
+        ];
+        let estimate = estimate_fee(&ixs, &payer, &fee_client(5000)).unwrap();
+        assert_eq!(estimate.priority_lamports, 50_000);
+        assert_eq!(estimate.total_lamports, 55_000);
+        assert_eq!(estimate.format_sol(), "0.000055 SOL");
+
+        // Without a limit, each other instruction gets the default, and fractions
+        // of a lamport round up.
+        let ixs = [ComputeBudgetInstruction::set_compute_unit_price(3), memo(&payer), memo(&payer)];
+        let estimate = estimate_fee(&ixs, &payer, &fee_client(5000)).unwrap();
+        assert_eq!(estimate.priority_lamports, 2);
+        assert_eq!(estimate.total_lamports, 5002);
+    }
+
+    fn cosigned(payer: &Pubkey, cosigner: &Pubkey) -> Instruction {
+        Instruction {
+            accounts: vec![
+                solana_sdk::instruction::AccountMeta::new(*payer, true),
+                solana_sdk::instruction::AccountMeta::new_readonly(*cosigner, true),
+            ],
+            ..memo(payer)
+        }
+    }
+
+    fn decode(signed: &SignedTransaction) -> Transaction {
+        bincode::deserialize(&bs58::decode(&signed.transaction).into_vec().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn signs_fully() {
+        let payer = Keypair::new();
+        let cosigner = Keypair::new();
+        let ix = cosigned(&payer.pubkey(), &cosigner.pubkey());
+        let blockhash = Hash::new_unique();
+        let signers: Vec<Box<dyn Signer>> = vec![Box::new(cosigner), Box::new(payer)];
+        let signed = sign_transaction(&[ix], &signers[1].pubkey(), &signers, blockhash, false).unwrap();
+        assert!(signed.missing.is_empty());
+        let tx = decode(&signed);
+        assert_eq!(tx.message.recent_blockhash, blockhash);
+        assert!(tx.verify().is_ok());
+    }
+
+    #[test]
+    fn missing_cosigner() {
+        let payer = Keypair::new();
+        let cosigner = Pubkey::new_unique();
+        let ix = cosigned(&payer.pubkey(), &cosigner);
+        let pubkey = payer.pubkey();
+        let signers: Vec<Box<dyn Signer>> = vec![Box::new(payer)];
+        let err = sign_transaction(&[ix], &pubkey, &signers, Hash::new_unique(), false).unwrap_err();
+        match &err {
+            SignError::MissingSignatures(missing) => assert_eq!(*missing, vec![cosigner]),
+            err => panic!("unexpected error {}", err),
+        }
+        assert!(err.to_string().contains(&cosigner.to_string()), "{}", err);
+    }
+
+    #[test]
+    fn oversize_transactions_fail_to_serialize() {
+        let payer = Keypair::new();
+        let ix = Instruction {
+            data: vec![0; PACKET_DATA_SIZE],
+            ..memo(&payer.pubkey())
+        };
+        let pubkey = payer.pubkey();
+        let signers: Vec<Box<dyn Signer>> = vec![Box::new(payer)];
+        let err = sign_transaction(&[ix], &pubkey, &signers, Hash::new_unique(), false).unwrap_err();
+        match &err {
+            SignError::Serialization(e) => assert!(matches!(**e, bincode::ErrorKind::SizeLimit), "{}", e),
+            err => panic!("unexpected error {}", err),
+        }
+    }
+
+    #[test]
+    fn partial_signing() {
+        let payer = Keypair::new();
+        let cosigner = Keypair::new();
+        let ix = cosigned(&payer.pubkey(), &cosigner.pubkey());
+        let pubkey = payer.pubkey();
+        let signers: Vec<Box<dyn Signer>> = vec![Box::new(cosigner)];
+        let signed = sign_transaction(&[ix], &pubkey, &signers, Hash::new_unique(), true).unwrap();
+        assert_eq!(signed.missing, vec![pubkey]);
+        let tx = decode(&signed);
+        assert_eq!(tx.signatures[0], Signature::default());
+        assert_ne!(tx.signatures[1], Signature::default());
+    }
+
+    fn decode_message(encoded: &str, encoding: TxEncoding) -> VersionedMessage {
+        let bytes = match encoding {
+            TxEncoding::Base58 => bs58::decode(encoded).into_vec().unwrap(),
+            TxEncoding::Base64 => base64::decode(encoded).unwrap(),
+        };
+        bincode::deserialize(&bytes).unwrap()
+    }
+
+    #[test]
+    fn serializes_legacy() {
+        let payer = Pubkey::new_unique();
+        let encoded = serialize_transaction(&[memo(&payer)], &payer).unwrap();
+        let message = decode_message(&encoded, TxEncoding::Base58);
+        assert!(matches!(message, VersionedMessage::Legacy(_)));
+        assert_eq!(message.static_account_keys()[0], payer);
+        assert_eq!(*message.recent_blockhash(), Hash::default());
+        // The same bytes as serializing the legacy message directly.
+        assert_eq!(encoded, bs58::encode(Message::new(&[memo(&payer)], Some(&payer)).serialize()).into_string());
+
+        let blockhash = Hash::new_unique();
+        let encoded = serialize_transaction_with(
+            &[memo(&payer)], &payer, Some(blockhash), TxEncoding::Base64, MessageVersion::Legacy,
+        ).unwrap();
+        let message = decode_message(&encoded, TxEncoding::Base64);
+        assert!(matches!(message, VersionedMessage::Legacy(_)));
+        assert_eq!(*message.recent_blockhash(), blockhash);
+    }
+
+    #[test]
+    fn serializes_v0() {
+        let payer = Pubkey::new_unique();
+        let blockhash = Hash::new_unique();
+        let encoded = serialize_transaction_with(
+            &[memo(&payer)], &payer, Some(blockhash), TxEncoding::Base64, MessageVersion::V0(vec![]),
+        ).unwrap();
+        let message = decode_message(&encoded, TxEncoding::Base64);
+        assert!(matches!(message, VersionedMessage::V0(_)));
+        assert_eq!(message.static_account_keys()[0], payer);
+        assert_eq!(*message.recent_blockhash(), blockhash);
+    }
+
+    #[test]
+    fn v0_needs_lookup_tables_for_many_accounts() {
+        let payer = Pubkey::new_unique();
+        let addresses: Vec<Pubkey> = (0..40).map(|_| Pubkey::new_unique()).collect();
+        let ix = Instruction {
+            accounts: std::iter::once(solana_sdk::instruction::AccountMeta::new(payer, true))
+                .chain(addresses.iter().map(|address| solana_sdk::instruction::AccountMeta::new(*address, false)))
+                .collect(),
+            ..memo(&payer)
+        };
+        let err = serialize_transaction_with(
+            &[ix.clone()], &payer, None, TxEncoding::Base64, MessageVersion::V0(vec![]),
+        ).unwrap_err().to_string();
+        assert!(err.contains("provide address lookup tables"), "{}", err);
+        let err = serialize_transaction_with(
+            &[ix.clone()], &payer, None, TxEncoding::Base64, MessageVersion::Legacy,
+        ).unwrap_err().to_string();
+        assert!(err.contains("over the 1232 byte limit"), "{}", err);
+
+        let table = AddressLookupTableAccount { key: Pubkey::new_unique(), addresses };
+        let encoded = serialize_transaction_with(
+            &[ix], &payer, None, TxEncoding::Base64, MessageVersion::V0(vec![table.clone()]),
+        ).unwrap();
+        match decode_message(&encoded, TxEncoding::Base64) {
+            VersionedMessage::V0(message) => {
+                assert_eq!(message.address_table_lookups.len(), 1);
+                assert_eq!(message.address_table_lookups[0].account_key, table.key);
+            }
+            message => panic!("unexpected message {:?}", message),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+/// Reading serialized transactions, messages and instructions handed between CLIs,
+/// e.g. one that builds and serializes a transaction, one that signs it, and one that sends it.
+use std::io::Read;
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::transaction::VersionedTransaction;
+
+/// Where to read the input from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxInputSource {
+    Stdin,
+    File(PathBuf),
+    /// The encoded input itself, e.g. from a positional argument.
+    Arg(String),
+}
+
+impl TxInputSource {
+    /// Interprets a CLI argument: `-` is stdin, an existing file is read,
+    /// and anything else is the encoded input itself.
+    pub fn from_arg(arg: &str) -> Self {
+        if arg == "-" {
+            Self::Stdin
+        } else if std::path::Path::new(arg).is_file() {
+            Self::File(PathBuf::from(arg))
+        } else {
+            Self::Arg(arg.to_string())
+        }
+    }
+}
+
+/// What the input decoded to. Legacy transactions and messages are returned as
+/// [VersionedTransaction] and [VersionedMessage::Legacy], which encode identically.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedTxInput {
+    /// A transaction, which may be partially signed.
+    Transaction(VersionedTransaction),
+    /// A message without signatures, e.g. from [solana_client_tx_processor::Processing::Serialize].
+    Message(VersionedMessage),
+    /// A single instruction, e.g. from [solana_client_tx_processor::Processing::Instructions].
+    Instruction(Instruction),
+}
+
+/// Read a serialized transaction, message or instruction from `source`.
+///
+/// Accepts base58 or base64, with surrounding whitespace, raw bytes from stdin or a file,
+/// and the JSON output of [crate::output::CliOutput] for a
+/// [solana_client_tx_processor::ProcessedTransaction], or a JSON string.
+pub fn read_transaction_input(source: TxInputSource) -> Result<ParsedTxInput> {
+    let bytes = match &source {
+        TxInputSource::Stdin => {
+            let mut bytes = vec![];
+            std::io::stdin().read_to_end(&mut bytes)
+                .map_err(|e| anyhow!("Could not read transaction from stdin: {}", e))?;
+            bytes
+        }
+        TxInputSource::File(path) => std::fs::read(path)
+            .map_err(|e| anyhow!("Could not read transaction from {}: {}", path.display(), e))?,
+        TxInputSource::Arg(arg) => arg.as_bytes().to_vec(),
+    };
+    match String::from_utf8(bytes) {
+        Ok(text) => parse_transaction_input(&text),
+        // Not text, so it can only be the serialized bytes themselves.
+        Err(e) => deserialize_input(e.as_bytes()).ok_or_else(|| anyhow!(
+            "Could not read transaction: the input is binary, \
+            but isn't a serialized transaction, message or instruction"
+        )),
+    }
+}
+
+/// [read_transaction_input] for input that's already been read.
+pub fn parse_transaction_input(input: &str) -> Result<ParsedTxInput> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(anyhow!("Could not read transaction: the input is empty"));
+    }
+    if input.starts_with('{') || input.starts_with('"') {
+        let json: Value = serde_json::from_str(input)
+            .map_err(|e| anyhow!("Could not read transaction: invalid JSON: {}", e))?;
+        return parse_transaction_input(&encoded_from_json(&json)?);
+    }
+    let mut attempts = vec![];
+    match bs58::decode(input).into_vec() {
+        Ok(bytes) => match deserialize_input(&bytes) {
+            Some(parsed) => return Ok(parsed),
+            None => attempts.push("decodes as base58, but not to a transaction, message or instruction"),
+        },
+        Err(_) => attempts.push("isn't base58"),
+    }
+    match base64::decode(input) {
+        Ok(bytes) => match deserialize_input(&bytes) {
+            Some(parsed) => return Ok(parsed),
+            None => attempts.push("decodes as base64, but not to a transaction, message or instruction"),
+        },
+        Err(_) => attempts.push("isn't base64"),
+    }
+    Err(anyhow!("Could not read transaction: the input {}", attempts.join(", and ")))
+}
+
+// The encoded transaction, message or instruction in `json`.
+fn encoded_from_json(json: &Value) -> Result<String> {
+    if let Value::String(encoded) = json {
+        return Ok(encoded.clone());
+    }
+    for key in ["transaction", "message"] {
+        if let Some(Value::String(encoded)) = json.get(key) {
+            return Ok(encoded.clone());
+        }
+    }
+    if let Some(Value::Array(instructions)) = json.get("instructions") {
+        return match instructions.as_slice() {
+            [instruction] => instruction
+                .as_str()
+                .or_else(|| instruction.get("data").and_then(Value::as_str))
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("Could not read transaction: the instruction has no \"data\"")),
+            _ => Err(anyhow!(
+                "Could not read transaction: found {} instructions, expected exactly 1",
+                instructions.len()
+            )),
+        };
+    }
+    Err(anyhow!(
+        "Could not read transaction: the JSON has no \"transaction\", \"message\" or \"instructions\""
+    ))
+}
+
+// Tries each kind of input, most specific first.
+fn deserialize_input(bytes: &[u8]) -> Option<ParsedTxInput> {
+    if let Some(transaction) = deserialize_exact::<VersionedTransaction>(bytes) {
+        let header = transaction.message.header();
+        if transaction.signatures.len() == header.num_required_signatures as usize
+            && transaction.message.static_account_keys().len() >= transaction.signatures.len()
+        {
+            return Some(ParsedTxInput::Transaction(transaction));
+        }
+    }
+    if let Some(message) = deserialize_exact::<VersionedMessage>(bytes) {
+        if message.static_account_keys().len() >= message.header().num_required_signatures as usize {
+            return Some(ParsedTxInput::Message(message));
+        }
+    }
+    deserialize_exact::<Instruction>(bytes).map(ParsedTxInput::Instruction)
+}
+
+// Deserializes like `bincode::deserialize`, but all of `bytes` must be used.
+fn deserialize_exact<T: DeserializeOwned>(bytes: &[u8]) -> Option<T> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .with_limit(bytes.len() as u64)
+        .deserialize(bytes)
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_client::solana_client::rpc_client::RpcClient;
+    use serde_json::Map;
+    use solana_client_tx_processor::{
+        ProcessedTransaction, Processing, TransactionProcessor, TransactionProcessorError,
+    };
+    use solana_sdk::hash::Hash;
+    use solana_sdk::instruction::AccountMeta;
+    use solana_sdk::message::{v0, Message};
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::{Keypair, Signer};
+    use solana_sdk::transaction::Transaction;
+    use crate::clap::well_known_program;
+    use crate::output::CliOutput;
+    use super::*;
+
+    struct Memo;
+
+    impl TransactionProcessor for Memo {
+        type OnlineArgs = ();
+        type RemainingArgs = ();
+
+        fn get_online_args(&self, _: &RpcClient) -> Result<(), TransactionProcessorError> {
+            Ok(())
+        }
+
+        fn name(&self, _: &Pubkey, _: &(), _: &()) -> String {
+            "memo".to_string()
+        }
+
+        fn calc_remaining_args(&self, _: &(), _: &Pubkey) -> Result<(), TransactionProcessorError> {
+            Ok(())
+        }
+
+        fn named_instructions(
+            &self,
+            primary_signer: &Pubkey,
+            _: (),
+            _: (),
+        ) -> Result<Vec<(String, Instruction)>, TransactionProcessorError> {
+            Ok(vec![("memo".to_string(), memo(primary_signer))])
+        }
+    }
+
+    fn memo(signer: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: well_known_program("memo").unwrap(),
+            accounts: vec![AccountMeta::new_readonly(*signer, true)],
+            data: b"hello".to_vec(),
+        }
+    }
+
+    fn process(mode: Processing<()>) -> ProcessedTransaction {
+        Memo.process(mode, &mut vec![]).unwrap()
+    }
+
+    #[test]
+    fn signed_transaction() {
+        let signer = Keypair::new();
+        let pubkey = signer.pubkey();
+        let processed = process(Processing::OfflineSign((), Box::new(signer), None, Hash::new_unique()));
+        let encoded = match &processed {
+            ProcessedTransaction::SignedSerialized { transaction, .. } => transaction.clone(),
+            _ => panic!("wrong processing"),
+        };
+        for input in [
+            encoded.clone(),
+            format!("\n  {}\r\n", encoded),
+            processed.to_json().to_string(),
+            serde_json::to_string(&encoded).unwrap(),
+        ] {
+            match parse_transaction_input(&input).unwrap() {
+                ParsedTxInput::Transaction(transaction) => {
+                    assert_eq!(transaction.message.static_account_keys()[0], pubkey);
+                    assert_eq!(transaction.signatures.len(), 1);
+                    assert!(transaction.verify_with_results().iter().all(|verified| *verified));
+                }
+                parsed => panic!("{:?}", parsed),
+            }
+        }
+    }
+
+    #[test]
+    fn unsigned_message() {
+        let pubkey = Pubkey::new_unique();
+        let processed = process(Processing::OfflineSerialize((), pubkey, None));
+        let encoded = match &processed {
+            ProcessedTransaction::UnsignedSerialized { transaction, .. } => transaction.clone(),
+            _ => panic!("wrong processing"),
+        };
+        let expected = Message::new(&[memo(&pubkey)], Some(&pubkey));
+        for input in [encoded, processed.to_json().to_string()] {
+            match parse_transaction_input(&input).unwrap() {
+                ParsedTxInput::Message(VersionedMessage::Legacy(message)) => {
+                    assert_eq!(message.account_keys, expected.account_keys);
+                    assert_eq!(message.instructions, expected.instructions);
+                }
+                parsed => panic!("{:?}", parsed),
+            }
+        }
+    }
+
+    #[test]
+    fn instruction() {
+        let pubkey = Pubkey::new_unique();
+        let processed = process(Processing::OfflineInstructions((), pubkey));
+        let encoded = match &processed {
+            ProcessedTransaction::InstructionSet { instructions, .. } => instructions[0].clone(),
+            _ => panic!("wrong processing"),
+        };
+        for input in [encoded, processed.to_json().to_string()] {
+            assert_eq!(
+                parse_transaction_input(&input).unwrap(),
+                ParsedTxInput::Instruction(memo(&pubkey)),
+            );
+        }
+        let two = ProcessedTransaction::InstructionSet {
+            instructions: vec!["a".to_string(), "b".to_string()],
+            instruction_names: vec!["a".to_string(), "b".to_string()],
+            name: "two".to_string(),
+            metadata: Map::new(),
+        };
+        let err = parse_transaction_input(&two.to_json().to_string()).unwrap_err().to_string();
+        assert!(err.contains("found 2 instructions"), "{}", err);
+    }
+
+    #[test]
+    fn base64_and_versioned() {
+        let signer = Keypair::new();
+        let legacy = Message::new(&[memo(&signer.pubkey())], Some(&signer.pubkey()));
+        let message = VersionedMessage::V0(v0::Message {
+            header: legacy.header,
+            account_keys: legacy.account_keys.clone(),
+            recent_blockhash: Hash::new_unique(),
+            instructions: legacy.instructions.clone(),
+            address_table_lookups: vec![],
+        });
+        let encoded = base64::encode(bincode::serialize(&message).unwrap());
+        assert_eq!(
+            parse_transaction_input(&encoded).unwrap(),
+            ParsedTxInput::Message(message.clone()),
+        );
+        let transaction = VersionedTransaction::try_new(message, &[&signer]).unwrap();
+        let encoded = base64::encode(bincode::serialize(&transaction).unwrap());
+        assert_eq!(
+            parse_transaction_input(&encoded).unwrap(),
+            ParsedTxInput::Transaction(transaction),
+        );
+
+        let legacy = Transaction::new_signed_with_payer(
+            &[memo(&signer.pubkey())], Some(&signer.pubkey()), &[&signer], Hash::new_unique(),
+        );
+        let bytes = bincode::serialize(&legacy).unwrap();
+        assert_eq!(
+            parse_transaction_input(&base64::encode(&bytes)).unwrap(),
+            ParsedTxInput::Transaction(VersionedTransaction::from(legacy)),
+        );
+    }
+
+    #[test]
+    fn sources() {
+        let instruction = memo(&Pubkey::new_unique());
+        let bytes = bincode::serialize(&instruction).unwrap();
+        let path = std::env::temp_dir().join(format!("tx-input-{}", std::process::id()));
+
+        // Raw bytes
+        std::fs::write(&path, &bytes).unwrap();
+        let raw = read_transaction_input(TxInputSource::File(path.clone()));
+        // Text, with a trailing newline as written by `echo`
+        std::fs::write(&path, format!("{}\n", bs58::encode(&bytes).into_string())).unwrap();
+        let text = read_transaction_input(TxInputSource::from_arg(&path.display().to_string()));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(raw.unwrap(), ParsedTxInput::Instruction(instruction.clone()));
+        assert_eq!(text.unwrap(), ParsedTxInput::Instruction(instruction.clone()));
+
+        let arg = bs58::encode(&bytes).into_string();
+        assert_eq!(TxInputSource::from_arg(&arg), TxInputSource::Arg(arg.clone()));
+        assert_eq!(TxInputSource::from_arg("-"), TxInputSource::Stdin);
+        assert_eq!(
+            read_transaction_input(TxInputSource::Arg(arg)).unwrap(),
+            ParsedTxInput::Instruction(instruction),
+        );
+        let err = read_transaction_input(TxInputSource::File(path)).unwrap_err().to_string();
+        assert!(err.contains("Could not read transaction from"), "{}", err);
+    }
+
+    #[test]
+    fn invalid() {
+        for (input, reason) in [
+            ("", "empty"),
+            ("  \n", "empty"),
+            ("0OIl", "isn't base58, and decodes as base64, but not"),
+            ("abc!", "isn't base58, and isn't base64"),
+            ("3yZe7d", "decodes as base58, but not to a transaction"),
+            ("{\"name\": \"memo\"}", "no \"transaction\""),
+            ("{", "invalid JSON"),
+        ] {
+            let err = parse_transaction_input(input).unwrap_err().to_string();
+            assert!(err.contains(reason), "{:?}: {}", input, err);
+        }
+    }
+}
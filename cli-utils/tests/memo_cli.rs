@@ -0,0 +1,93 @@
+//! Runs `examples/memo-cli.rs` against a local test validator.
+use std::path::PathBuf;
+use std::process::Command;
+use anchor_cli::config::{Config, TestValidator, Validator};
+use jungle_fi_localnet_tools::test_validator::{start_test_validator, TestValidatorHandle, TestValidatorOptions};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{write_keypair_file, Keypair, Signer};
+
+fn start_validator(name: &str) -> TestValidatorHandle {
+    let test_validator = Some(TestValidator {
+        validator: Some(Validator {
+            ledger: format!(".anchor/memo-cli-{}-ledger", name),
+            bind_address: "0.0.0.0".to_string(),
+            rpc_port: 8899,
+            ..Default::default()
+        }),
+        startup_wait: 30_000,
+        ..Default::default()
+    });
+    let mut config = Config::default();
+    config.test_validator = test_validator.clone();
+    let options = TestValidatorOptions {
+        auto_pick_ports: true,
+        mint_pubkey: Some(Pubkey::new_unique()),
+        ..Default::default()
+    };
+    start_test_validator(&config, &test_validator, None, true, &options).unwrap()
+}
+
+// A funded keypair, written where `-k` can find it.
+fn funded_keypair(handle: &TestValidatorHandle, name: &str) -> (Keypair, PathBuf) {
+    let keypair = Keypair::new();
+    let path = std::env::temp_dir().join(format!("memo-cli-{}-{}.json", name, std::process::id()));
+    write_keypair_file(&keypair, &path).unwrap();
+    handle.airdrop(&keypair.pubkey(), 1_000_000_000).unwrap();
+    (keypair, path)
+}
+
+fn memo_cli(args: &[&str]) -> Value {
+    let output = Command::new(env!("CARGO"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["run", "--quiet", "--example", "memo-cli", "--"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "memo-cli failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+#[ignore = "requires solana-test-validator on the PATH"]
+fn execute() {
+    let handle = start_validator("execute");
+    let (keypair, path) = funded_keypair(&handle, "execute");
+    let output = memo_cli(&[
+        "hello",
+        "--mode", "execute",
+        "--url", &handle.rpc_url,
+        "-k", path.to_str().unwrap(),
+        "--commitment", "confirmed",
+        "--output", "json",
+    ]);
+    assert_eq!(output["kind"], "execution");
+    assert_eq!(output["name"], "memo: hello");
+    assert_eq!(output["metadata"]["signer"], keypair.pubkey().to_string());
+    assert!(output["signature"].as_str().map_or(false, |s| !s.is_empty()), "{}", output);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+#[ignore = "requires solana-test-validator on the PATH"]
+fn simulate() {
+    let handle = start_validator("simulate");
+    let (_, path) = funded_keypair(&handle, "simulate");
+    let output = memo_cli(&[
+        "hello",
+        "--mode", "simulate",
+        "--url", &handle.rpc_url,
+        "-k", path.to_str().unwrap(),
+        "--output", "json",
+    ]);
+    assert_eq!(output["kind"], "simulation");
+    assert_eq!(output["name"], "memo: hello");
+    assert!(output["simulation_result"]["err"].is_null(), "{}", output);
+    let logs = output["simulation_result"]["logs"].as_array().unwrap();
+    assert!(logs.iter().any(|log| log.as_str().unwrap().contains("Memo")), "{}", output);
+    std::fs::remove_file(path).unwrap();
+}
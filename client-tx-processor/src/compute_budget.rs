@@ -0,0 +1,204 @@
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_request::RpcRequest;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use solana_sdk::borsh::try_from_slice_unchecked;
+use solana_sdk::compute_budget::{self, ComputeBudgetInstruction};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use crate::error::TransactionProcessorError;
+
+/// `getRecentPrioritizationFees` takes at most this many accounts.
+const MAX_FEE_ACCOUNTS: usize = 128;
+
+/// Compute budget instructions for [crate::TransactionProcessor::compute_budget] to add
+/// to the start of the transaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComputeBudget {
+    /// The compute unit limit of the transaction.
+    pub unit_limit: Option<u32>,
+    /// The priority fee per compute unit.
+    pub unit_price: Option<UnitPrice>,
+}
+
+/// The priority fee of a [ComputeBudget].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitPrice {
+    /// In micro-lamports per compute unit.
+    Fixed(u64),
+    /// The given percentile of the cluster's recent prioritization fees for the transaction's
+    /// writable accounts, see [estimate_priority_fee]. Only for the online modes.
+    Auto { percentile: u8 },
+}
+
+impl ComputeBudget {
+    /// Prepend the budget's instructions to `ixs`, and their names to `names`, with the
+    /// already resolved `unit_price`. Instructions the processor already sets itself are
+    /// left out. An added price is recorded in `metadata` as `compute_unit_price`.
+    pub(crate) fn prepend(
+        &self,
+        unit_price: Option<u64>,
+        names: Vec<String>,
+        ixs: Vec<Instruction>,
+        metadata: &mut Map<String, Value>,
+    ) -> (Vec<String>, Vec<Instruction>) {
+        let existing: Vec<ComputeBudgetInstruction> = ixs
+            .iter()
+            .filter(|ix| ix.program_id == compute_budget::id())
+            .filter_map(|ix| try_from_slice_unchecked(&ix.data).ok())
+            .collect();
+        let sets_limit = existing.iter()
+            .any(|ix| matches!(ix, ComputeBudgetInstruction::SetComputeUnitLimit(_)));
+        let sets_price = existing.iter()
+            .any(|ix| matches!(ix, ComputeBudgetInstruction::SetComputeUnitPrice(_)));
+
+        let mut budget_names = vec![];
+        let mut budget_ixs = vec![];
+        if let (Some(units), false) = (self.unit_limit, sets_limit) {
+            budget_names.push("set_compute_unit_limit".to_string());
+            budget_ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(units));
+        }
+        if let (Some(micro_lamports), false) = (unit_price, sets_price) {
+            budget_names.push("set_compute_unit_price".to_string());
+            budget_ixs.push(ComputeBudgetInstruction::set_compute_unit_price(micro_lamports));
+            metadata.insert("compute_unit_price".to_string(), json!(micro_lamports));
+        }
+        budget_names.extend(names);
+        budget_ixs.extend(ixs);
+        (budget_names, budget_ixs)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RecentPrioritizationFee {
+    pub prioritization_fee: u64,
+}
+
+/// Suggest a compute unit price for `ixs`, in micro-lamports: the `percentile` (0 to 100)
+/// of the prioritization fees paid recently by transactions writing to the same accounts.
+/// Usable in [crate::TransactionProcessor::get_online_args], or automatically with [UnitPrice::Auto].
+pub fn estimate_priority_fee(
+    client: &RpcClient,
+    ixs: &[Instruction],
+    percentile: u8,
+) -> Result<u64, TransactionProcessorError> {
+    let fees: Vec<RecentPrioritizationFee> = client.send(
+        RpcRequest::Custom { method: "getRecentPrioritizationFees" },
+        json!([fee_accounts(ixs)]),
+    ).map_err(TransactionProcessorError::ClientError)?;
+    Ok(fee_percentile(fees, percentile))
+}
+
+/// The writable accounts of `ixs`, which the recent fees are looked up for.
+pub(crate) fn fee_accounts(ixs: &[Instruction]) -> Vec<String> {
+    let mut accounts: Vec<Pubkey> = vec![];
+    for meta in ixs.iter().flat_map(|ix| &ix.accounts) {
+        if meta.is_writable && !accounts.contains(&meta.pubkey) {
+            accounts.push(meta.pubkey);
+        }
+    }
+    accounts.truncate(MAX_FEE_ACCOUNTS);
+    accounts.iter().map(|account| account.to_string()).collect()
+}
+
+/// The nearest-rank percentile of the fees, or zero without fees.
+pub(crate) fn fee_percentile(fees: Vec<RecentPrioritizationFee>, percentile: u8) -> u64 {
+    let mut fees: Vec<u64> = fees.into_iter().map(|fee| fee.prioritization_fee).collect();
+    if fees.is_empty() {
+        return 0;
+    }
+    fees.sort_unstable();
+    let percentile = percentile.min(100) as usize;
+    let rank = (percentile * fees.len() + 99) / 100;
+    fees[rank.saturating_sub(1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use anchor_client::solana_client::mock_sender::Mocks;
+    use solana_sdk::instruction::AccountMeta;
+    use super::*;
+
+    #[test]
+    fn prepends_missing_instructions() {
+        let memo = spl_memo::build_memo(b"memo", &[]);
+        let budget = ComputeBudget { unit_limit: Some(100_000), unit_price: Some(UnitPrice::Fixed(5)) };
+        let mut metadata = Map::new();
+        let (names, ixs) = budget.prepend(Some(5), vec!["memo".to_string()], vec![memo.clone()], &mut metadata);
+        assert_eq!(names, vec!["set_compute_unit_limit", "set_compute_unit_price", "memo"]);
+        assert_eq!(ixs, vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(100_000),
+            ComputeBudgetInstruction::set_compute_unit_price(5),
+            memo.clone(),
+        ]);
+        assert_eq!(metadata["compute_unit_price"], json!(5));
+
+        // The processor's own limit wins.
+        let own_limit = ComputeBudgetInstruction::set_compute_unit_limit(1_000);
+        let (names, ixs) = budget.prepend(
+            Some(5),
+            vec!["limit".to_string(), "memo".to_string()],
+            vec![own_limit.clone(), memo.clone()],
+            &mut Map::new(),
+        );
+        assert_eq!(names, vec!["set_compute_unit_price", "limit", "memo"]);
+        assert_eq!(ixs, vec![
+            ComputeBudgetInstruction::set_compute_unit_price(5),
+            own_limit,
+            memo.clone(),
+        ]);
+
+        let mut metadata = Map::new();
+        let (names, ixs) = ComputeBudget::default().prepend(None, vec!["memo".to_string()], vec![memo.clone()], &mut metadata);
+        assert_eq!(names, vec!["memo"]);
+        assert_eq!(ixs, vec![memo]);
+        assert!(metadata.is_empty());
+
+        // Other programs' data isn't mistaken for compute budget instructions.
+        let other = Instruction::new_with_bytes(Pubkey::new_unique(), &[2, 0, 0, 0, 0], vec![]);
+        let (names, _) = budget.prepend(Some(5), vec!["other".to_string()], vec![other], &mut Map::new());
+        assert_eq!(names.len(), 3);
+    }
+
+    fn fees(fees: &[u64]) -> Vec<RecentPrioritizationFee> {
+        fees.iter().map(|&prioritization_fee| RecentPrioritizationFee { prioritization_fee }).collect()
+    }
+
+    #[test]
+    fn percentiles() {
+        let recent = [0, 500, 100, 0, 300, 200, 10_000, 0, 0, 400];
+        assert_eq!(fee_percentile(fees(&recent), 0), 0);
+        assert_eq!(fee_percentile(fees(&recent), 50), 100);
+        assert_eq!(fee_percentile(fees(&recent), 75), 400);
+        assert_eq!(fee_percentile(fees(&recent), 90), 500);
+        assert_eq!(fee_percentile(fees(&recent), 100), 10_000);
+        assert_eq!(fee_percentile(fees(&recent), 255), 10_000);
+        assert_eq!(fee_percentile(fees(&[7]), 50), 7);
+        assert_eq!(fee_percentile(vec![], 50), 0);
+    }
+
+    #[test]
+    fn estimates_from_recent_fees() {
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::Custom { method: "getRecentPrioritizationFees" },
+            json!([
+                { "slot": 1, "prioritizationFee": 0 },
+                { "slot": 2, "prioritizationFee": 1_000 },
+                { "slot": 3, "prioritizationFee": 5_000 },
+                { "slot": 4, "prioritizationFee": 2_000 },
+            ]),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let writable = Pubkey::new_unique();
+        let ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![
+            AccountMeta::new(writable, true),
+            AccountMeta::new_readonly(Pubkey::new_unique(), false),
+            AccountMeta::new(writable, false),
+        ]);
+        assert_eq!(fee_accounts(&[ix.clone()]), vec![writable.to_string()]);
+        assert_eq!(estimate_priority_fee(&client, &[ix], 75).unwrap(), 2_000);
+    }
+}
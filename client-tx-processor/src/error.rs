@@ -1,14 +1,43 @@
+use std::time::Duration;
 use anchor_client::solana_client;
 use anchor_client::solana_client::client_error::ClientErrorKind;
 use anchor_client::solana_client::rpc_request::{RpcError, RpcResponseErrorData};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::SignerError;
+use solana_sdk::transaction::VersionedTransaction;
 use thiserror::Error;
+use crate::reporter::{StdoutReporter, TxReporter};
 
 #[derive(Debug, Error)]
 pub enum TransactionProcessorError {
     #[error("rpc client error: {0}")]
     ClientError(solana_client::client_error::ClientError),
+    /// The signers don't match the signatures the message requires.
+    #[error("signing error: {0}")]
+    SignerError(SignerError),
+    /// The transaction didn't reach the requested commitment in time. It may still land.
+    #[error("transaction {signature} was not confirmed within {timeout:?}")]
+    ConfirmationTimeout { signature: Signature, timeout: Duration },
+    /// Every attempt's blockhash expired before the transaction landed.
+    #[error("transaction {signature} was not confirmed after {attempts} attempts, its blockhash expired")]
+    BlockhashExpired { signature: Signature, attempts: usize },
+    #[error("nonce account {address} does not exist")]
+    NonceAccountNotFound { address: Pubkey },
+    #[error("nonce account {address} is not initialized")]
+    NonceAccountNotInitialized { address: Pubkey },
+    #[error("invalid nonce account {address}: {message}")]
+    InvalidNonceAccount { address: Pubkey, message: String },
+    /// [crate::TransactionProcessor::create_instructions] returned a different number of
+    /// names than instructions.
+    #[error("{names} instruction names for {instructions} instructions")]
+    InstructionNameMismatch { names: usize, instructions: usize },
+    /// A transaction or instruction couldn't be encoded, e.g. a signed transaction
+    /// too large to send.
+    #[error("serialization error: {0}")]
+    SerializationError(bincode::Error),
     #[error("{0}")]
-    Other(Box<dyn std::error::Error>),
+    Other(Box<dyn std::error::Error + Send + Sync>),
 }
 
 /// Prints the transaction logs for failed preflight simulations.
@@ -17,15 +46,35 @@ pub enum TransactionProcessorError {
 #[allow(dead_code)]
 pub fn maybe_print_preflight_simulation_logs(
     err: solana_client::client_error::ClientError
+) -> solana_client::client_error::ClientError {
+    report_preflight_simulation_logs(err, &StdoutReporter)
+}
+
+/// Report a request with `tx` that failed, along with the logs of a failed preflight
+/// simulation, as every mode that sends or simulates does.
+pub(crate) fn report_failure(
+    err: solana_client::client_error::ClientError,
+    tx: &VersionedTransaction,
+    reporter: &dyn TxReporter,
+) -> TransactionProcessorError {
+    let err = report_preflight_simulation_logs(err, reporter);
+    reporter.on_failed(&tx.signatures[0], &err);
+    TransactionProcessorError::ClientError(err)
+}
+
+/// [maybe_print_preflight_simulation_logs], sending the logs to `reporter`.
+pub fn report_preflight_simulation_logs(
+    err: solana_client::client_error::ClientError,
+    reporter: &dyn TxReporter,
 ) -> solana_client::client_error::ClientError {
     if let ClientErrorKind::RpcError(err) = &err.kind {
         if let RpcError::RpcResponseError { data, .. } = err {
-            // print the transaction logs for a failed pre-flight simulation
+            // report the transaction logs for a failed pre-flight simulation
             if let RpcResponseErrorData::SendTransactionPreflightFailure(
                 result
             ) = data {
                 if let Some(logs) = &result.logs {
-                    logs.iter().for_each(|e| println!("{}", e))
+                    reporter.on_preflight_logs(logs)
                 }
             }
         }
@@ -1,42 +1,101 @@
+use std::time::Duration;
 use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig};
 use solana_sdk::signature::Signer;
 use anchor_client::anchor_lang::prelude::Pubkey;
 use anchor_client::anchor_lang::solana_program::hash::Hash;
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use anchor_client::solana_client::rpc_response::{RpcResponseContext, RpcSimulateTransactionResult};
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::transaction::TransactionError;
+use crate::SimulationReport;
 
 /// Offline variants require passing in some [T] which would
 /// normally come from querying the cluster.
 /// [Offline*] variants do not require network traffic, but [online_args: T] must be created
 /// by other means.
+///
+/// The signer is the primary signer passed to [crate::TransactionProcessor::named_instructions],
+/// and pays the fees, unless there's a separate fee payer. A fee payer is recorded in the
+/// metadata as `fee_payer`, along with the `primary_signer`.
 pub enum Processing<T> {
     /// Sign, serialize, and send the transaction for execution on the cluster.
-    Execute(RpcClient, Box<dyn Signer>),
+    Execute(RpcClient, Box<dyn Signer>, Option<Box<dyn Signer>>), // client, signer, fee payer
+    /// Like [Processing<T>::Execute], sending the transaction with the given config,
+    /// e.g. to skip preflight or cap the RPC node's retries.
+    ExecuteWithConfig(RpcClient, Box<dyn Signer>, Option<Box<dyn Signer>>, RpcSendTransactionConfig),
+    /// Like [Processing<T>::Execute], then wait for the transaction to be confirmed.
+    ExecuteAndConfirm(RpcClient, Box<dyn Signer>, Option<Box<dyn Signer>>, ConfirmOptions),
     /// Sign, serialize, and simulate the transaction.
-    Simulate(RpcClient, Box<dyn Signer>),
+    Simulate(RpcClient, Box<dyn Signer>, Option<Box<dyn Signer>>),
+    /// Like [Processing<T>::Simulate], simulating the transaction with the given config.
+    SimulateWithConfig(RpcClient, Box<dyn Signer>, Option<Box<dyn Signer>>, RpcSimulateTransactionConfig),
     /// Sign and serialize the transaction. Useful to hand to third parties
     /// for additional requires signatures before publishing the transaction on-chain.
-    Sign(RpcClient, Box<dyn Signer>),
+    Sign(RpcClient, Box<dyn Signer>, Option<Box<dyn Signer>>),
+    /// Like [Processing<T>::Execute], using the durable nonce stored in the nonce account
+    /// instead of a recent blockhash, and advancing it in the first instruction.
+    /// The nonce authority must be the signer, or one of the extra signers.
+    ExecuteWithNonce(RpcClient, Box<dyn Signer>, Option<Box<dyn Signer>>, Pubkey, Pubkey), // client, signer, fee payer, nonce account, nonce authority
+    /// Like [Processing<T>::Sign], with a durable nonce as in [Processing<T>::ExecuteWithNonce].
+    /// The transaction doesn't expire until the nonce is advanced, so it can be signed by
+    /// third parties at their leisure.
+    SignWithNonce(RpcClient, Box<dyn Signer>, Option<Box<dyn Signer>>, Pubkey, Pubkey), // client, signer, fee payer, nonce account, nonce authority
     /// No signatures applied, simply the Transaction Message serialized.
-    Serialize(RpcClient, Pubkey), // client, signer
+    Serialize(RpcClient, Pubkey, Option<Pubkey>), // client, signer, fee payer
     /// Output the transaction instructions in Base58 encoding. This allows one to compose
     /// multisig proposals.
     Instructions(RpcClient, Pubkey), // client, multisig_signer
     /// Similar to [Processing<T>::Sign], except prerequisite data must be created offline.
-    OfflineSign(T, Box<dyn Signer>, Hash),
+    OfflineSign(T, Box<dyn Signer>, Option<Box<dyn Signer>>, Hash),
+    /// Similar to [Processing<T>::SignWithNonce], except prerequisite data must be created offline,
+    /// and the nonce is passed in, e.g. from `solana nonce <nonce account>`.
+    OfflineSignWithNonce(T, Box<dyn Signer>, Option<Box<dyn Signer>>, Pubkey, Pubkey, Hash), // args, signer, fee payer, nonce account, nonce authority, nonce
     /// Similar to [Processing<T>::Serialize], except prerequisite data must be created offline.
-    OfflineSerialize(T, Pubkey),
+    OfflineSerialize(T, Pubkey, Option<Pubkey>),
     /// Similar to [Processing<T>::Instructions], except prerequisite data must be created offline.
     OfflineInstructions(T, Pubkey),
 }
 
+/// How long to wait for [Processing::ExecuteAndConfirm] transactions to be confirmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmOptions {
+    pub commitment: CommitmentConfig,
+    /// How long to wait in total, over all attempts.
+    pub timeout: Duration,
+    /// How many times to send the transaction, signing it again with a new blockhash
+    /// each time the last one expires before the transaction lands.
+    pub max_attempts: usize,
+}
+
+impl Default for ConfirmOptions {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            timeout: Duration::from_secs(90),
+            max_attempts: 3,
+        }
+    }
+}
+
 /// The return type for [TransactionProcessor::process].
+///
+/// Serializes as an object tagged with its `kind`, e.g. `{"kind": "execution", "signature": ...}`,
+/// so it can be handed to and parsed back by other tools.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum ProcessedTransaction {
     /// Pertinent information after a transaction has been successfully executed.
     Execution {
         signature: String,
         name: String,
         metadata: Map<String, Value>,
+        /// The slot the transaction was confirmed in, with [Processing::ExecuteAndConfirm].
+        confirmation_slot: Option<Slot>,
+        /// Why the transaction failed on-chain, if it was confirmed and failed.
+        transaction_error: Option<TransactionError>,
     },
     /// Pertinent information after a transaction has been successfully simulated.
     Simulation {
@@ -44,6 +103,8 @@ pub enum ProcessedTransaction {
         metadata: Map<String, Value>,
         simulation_result: RpcSimulateTransactionResult,
         simulation_context: RpcResponseContext,
+        /// The outcome of the simulation, extracted from the `simulation_result`.
+        simulation_report: SimulationReport,
     },
     /// The signed/serialized transaction, plus related pertinent information.
     SignedSerialized {
@@ -65,3 +126,119 @@ pub enum ProcessedTransaction {
         metadata: Map<String, Value>,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use solana_sdk::instruction::InstructionError;
+    use super::*;
+
+    /// Round trip `processed` through JSON, returning the JSON.
+    fn round_trip(processed: ProcessedTransaction) -> Value {
+        let json = serde_json::to_value(&processed).unwrap();
+        let parsed: ProcessedTransaction = serde_json::from_str(&serde_json::to_string(&processed).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+        json
+    }
+
+    fn metadata() -> Map<String, Value> {
+        let mut metadata = Map::new();
+        metadata.insert("account".to_string(), json!("11111111111111111111111111111111"));
+        metadata.insert("amount".to_string(), json!(5));
+        metadata
+    }
+
+    #[test]
+    fn execution() {
+        let json = round_trip(ProcessedTransaction::Execution {
+            signature: "sig".to_string(),
+            name: "memo".to_string(),
+            metadata: metadata(),
+            confirmation_slot: Some(12),
+            transaction_error: Some(TransactionError::InstructionError(0, InstructionError::Custom(6000))),
+        });
+        assert_eq!(json["kind"], json!("execution"));
+        assert_eq!(json["metadata"], Value::Object(metadata()));
+        assert_eq!(json["confirmation_slot"], json!(12));
+
+        let json = round_trip(ProcessedTransaction::Execution {
+            signature: "sig".to_string(),
+            name: "memo".to_string(),
+            metadata: Map::new(),
+            confirmation_slot: None,
+            transaction_error: None,
+        });
+        assert_eq!(json["transaction_error"], Value::Null);
+    }
+
+    #[test]
+    fn simulation() {
+        let logs = vec![
+            "Program MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr invoke [1]".to_string(),
+            "Program log: Memo (len 6): \"Foobar\"".to_string(),
+            "Program MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr success".to_string(),
+        ];
+        let simulation_result = RpcSimulateTransactionResult {
+            err: Some(TransactionError::AccountInUse),
+            logs: Some(logs.clone()),
+            accounts: None,
+            units_consumed: Some(12_345),
+            return_data: None,
+        };
+        let json = round_trip(ProcessedTransaction::Simulation {
+            name: "memo".to_string(),
+            metadata: metadata(),
+            simulation_report: SimulationReport::from(&simulation_result),
+            simulation_result,
+            simulation_context: RpcResponseContext { slot: 7, api_version: None },
+        });
+        assert_eq!(json["kind"], json!("simulation"));
+        assert_eq!(json["simulation_result"]["logs"], json!(logs));
+        assert_eq!(json["simulation_result"]["unitsConsumed"], json!(12_345));
+        assert_eq!(json["simulation_context"]["slot"], json!(7));
+        assert_eq!(json["simulation_report"]["units_consumed"], json!(12_345));
+        assert_eq!(json["simulation_report"]["succeeded"], json!(false));
+        assert_eq!(json["simulation_report"]["logs"], json!(logs));
+    }
+
+    #[test]
+    fn serialized_and_instructions() {
+        let json = round_trip(ProcessedTransaction::SignedSerialized {
+            transaction: "signed".to_string(),
+            name: "memo".to_string(),
+            metadata: metadata(),
+        });
+        assert_eq!(json["kind"], json!("signed_serialized"));
+
+        let json = round_trip(ProcessedTransaction::UnsignedSerialized {
+            transaction: "unsigned".to_string(),
+            name: "memo".to_string(),
+            metadata: metadata(),
+        });
+        assert_eq!(json["kind"], json!("unsigned_serialized"));
+
+        let json = round_trip(ProcessedTransaction::InstructionSet {
+            instructions: vec!["ix1".to_string(), "ix2".to_string()],
+            instruction_names: vec!["first".to_string(), "second".to_string()],
+            name: "memo".to_string(),
+            metadata: metadata(),
+        });
+        assert_eq!(json["kind"], json!("instruction_set"));
+        assert_eq!(json["instruction_names"], json!(["first", "second"]));
+    }
+
+    #[test]
+    fn parses_other_tools_output() {
+        let processed: ProcessedTransaction = serde_json::from_value(json!({
+            "kind": "execution",
+            "signature": "sig",
+            "name": "memo",
+            "metadata": {},
+        })).unwrap();
+        assert!(matches!(
+            processed,
+            ProcessedTransaction::Execution { confirmation_slot: None, transaction_error: None, .. }
+        ));
+        assert!(serde_json::from_value::<ProcessedTransaction>(json!({ "kind": "unknown" })).is_err());
+    }
+}
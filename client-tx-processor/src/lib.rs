@@ -1,5 +1,16 @@
+mod compute_budget;
 mod error;
 mod interface_types;
+mod lookup_table;
+#[cfg(feature = "nonblocking")]
+mod nonblocking;
+mod nonce;
+mod prepared;
+mod reporter;
+mod simulation;
+mod summary;
+#[cfg(test)]
+mod test_processor;
 /// Define a struct representing a transaction schema.
 /// Implementing [TransactionProcessor] allows for a number of
 /// approaches to processing the transaction, from the most common
@@ -9,18 +20,40 @@ mod interface_types;
 /// This can be used in both CLI or servers.
 /// This is only an advisable approach when you have some standardized transaction schemas,
 /// and you need multiple forms of transaction processing. Otherwise, this is all overkill.
+use std::time::{Duration, Instant};
+use bincode::Options;
 use anchor_client::solana_client::rpc_client::RpcClient;
 use serde_json::{Map, Value};
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
 use solana_sdk::bs58;
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::Hash;
 use solana_sdk::instruction::Instruction;
+use solana_sdk::message::{v0, VersionedMessage};
+use solana_sdk::packet::PACKET_DATA_SIZE;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
 use solana_sdk::signer::Signer;
-use solana_sdk::transaction::Transaction;
+use solana_sdk::signer::signers::Signers;
+use solana_sdk::transaction::{Transaction, TransactionError, VersionedTransaction};
 
+pub use compute_budget::{estimate_priority_fee, ComputeBudget, UnitPrice};
 pub use error::TransactionProcessorError;
-pub use interface_types::{ProcessedTransaction, Processing};
-use crate::error::maybe_print_preflight_simulation_logs;
+pub use interface_types::{ConfirmOptions, ProcessedTransaction, Processing};
+pub use lookup_table::fetch_lookup_tables;
+#[cfg(feature = "nonblocking")]
+pub use nonblocking::{
+    estimate_priority_fee_async, fetch_nonce_async, AsyncProcessing, AsyncTransactionProcessor,
+};
+pub use nonce::fetch_nonce;
+pub use reporter::{LogReporter, SilentReporter, StdoutReporter, TxReporter};
+pub use simulation::SimulationReport;
+pub use summary::SummaryOptions;
+use crate::error::report_failure;
+use crate::prepared::{online, Args, Mode, Output, PreparedTransaction};
 
+/// How often [Processing::ExecuteAndConfirm] checks the transaction's status.
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 /// If you can calculate values instead of require the user pass them in,
 /// then do so in the constructor. If you need to pull cluster data first,
@@ -71,18 +104,65 @@ pub trait TransactionProcessor {
         primary_signer: &Pubkey,
     ) -> Result<Self::RemainingArgs, TransactionProcessorError>;
 
+    /// Address lookup tables for the transaction. With [Some], the transaction is built
+    /// with a v0 message, which fits many more accounts than a legacy one, even if no
+    /// table has any of its accounts. Fetch the tables in [TransactionProcessor::get_online_args],
+    /// e.g. with [fetch_lookup_tables], so the offline modes can use them as well.
+    fn lookup_tables(&self, _online_args: &Self::OnlineArgs) -> Option<Vec<AddressLookupTableAccount>> {
+        None
+    }
+
+    /// Compute budget instructions to add to the start of the transaction, in every mode.
+    /// Any the processor already creates itself in [TransactionProcessor::named_instructions]
+    /// are left out.
+    fn compute_budget(&self) -> Option<ComputeBudget> {
+        None
+    }
+
     /// Create a vec of instructions paired with names.
     /// Creates a tuple of two vectors:
     /// - [Vec<Instruction>] represents an ordered list of instructions
     /// to add to the transaction.
     /// - [Vec<&str>] represents the names for each instruction, where the corresponding
     /// indices match across both this vec and the [Vec<Instruction>].
+    ///
+    /// Vectors of different lengths are a [TransactionProcessorError::InstructionNameMismatch].
+    ///
+    /// Both this and [TransactionProcessor::named_instructions] have defaults, so a processor
+    /// implementing neither still compiles, but fails at runtime with
+    /// `Other("implement TransactionProcessor::named_instructions")`.
+    #[deprecated(note = "implement `named_instructions` instead, whose names can't be misaligned")]
+    #[allow(unused)]
     fn create_instructions(
         &self,
         primary_signer: &Pubkey,
         online_args: Self::OnlineArgs,
         remaining: Self::RemainingArgs,
-    ) -> Result<(Vec<&str>, Vec<Instruction>), TransactionProcessorError>;
+    ) -> Result<(Vec<&str>, Vec<Instruction>), TransactionProcessorError> {
+        Err(TransactionProcessorError::Other(
+            "implement TransactionProcessor::named_instructions".into()
+        ))
+    }
+
+    /// The ordered list of instructions to add to the transaction, each paired with its name.
+    /// Processors still implementing [TransactionProcessor::create_instructions] instead get
+    /// its instructions and names paired up here.
+    fn named_instructions(
+        &self,
+        primary_signer: &Pubkey,
+        online_args: Self::OnlineArgs,
+        remaining: Self::RemainingArgs,
+    ) -> Result<Vec<(String, Instruction)>, TransactionProcessorError> {
+        #[allow(deprecated)]
+        let (names, ixs) = self.create_instructions(primary_signer, online_args, remaining)?;
+        if names.len() != ixs.len() {
+            return Err(TransactionProcessorError::InstructionNameMismatch {
+                names: names.len(),
+                instructions: ixs.len(),
+            });
+        }
+        Ok(names.into_iter().map(String::from).zip(ixs).collect())
+    }
 
     /// Runs the transaction processing, according to the given mode of processing.
     /// This
@@ -91,358 +171,232 @@ pub trait TransactionProcessor {
         mode: Processing<Self::OnlineArgs>,
         extra_signers: &mut Vec<Box<dyn Signer>>,
     ) -> Result<ProcessedTransaction, TransactionProcessorError> {
-        match mode {
-            Processing::Execute(client, signer) => {
-                let primary_signer = signer.pubkey();
+        self.process_with_reporter(mode, extra_signers, None)
+    }
+
+    /// [TransactionProcessor::process], reporting sent transactions and failed
+    /// preflight simulations to `reporter`. With [None], preflight logs are printed
+    /// to stdout, see [StdoutReporter].
+    fn process_with_reporter(
+        &self,
+        mode: Processing<Self::OnlineArgs>,
+        extra_signers: &mut Vec<Box<dyn Signer>>,
+        reporter: Option<&dyn TxReporter>,
+    ) -> Result<ProcessedTransaction, TransactionProcessorError> {
+        let reporter = reporter.unwrap_or(&StdoutReporter);
+        let Mode { args, primary_signer, fee_payer, signers, output } = Mode::from(mode);
+        let (client, online_args) = match args {
+            Args::Online(client) => {
                 let online_args = self.get_online_args(&client)?;
-                let remaining_args = self.calc_remaining_args(
-                    &online_args,
-                    &primary_signer,
-                )?;
-                extra_signers.push(signer);
-                let name = self.name(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let metadata = self.metadata(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let (_, ixs) = self.create_instructions(
-                    &primary_signer,
-                    online_args,
-                    remaining_args,
-                )?;
-                let recent_blockhash = client.get_latest_blockhash()
-                    .map_err(|e| TransactionProcessorError::ClientError(e))?;
-                let tx = Transaction::new_signed_with_payer(
-                    &ixs,
-                    Some(&primary_signer), // payer
-                    extra_signers,
-                    recent_blockhash,
-                );
-                let signature = client.send_transaction(&tx)
-                    .map_err(|e| {
-                        let e = maybe_print_preflight_simulation_logs(e);
-                        TransactionProcessorError::ClientError(e)
-                    })?;
+                (Some(client), online_args)
+            }
+            Args::Offline(online_args) => (None, online_args),
+        };
+        let client = client.as_ref();
+        extra_signers.extend(signers);
+        let signers = &*extra_signers;
+        let mut prepared = PreparedTransaction::new(self, online_args, &primary_signer)?;
+        prepared.add_compute_budget(self, client)?;
+        let payer = prepared.payer(&primary_signer, fee_payer);
+        match output {
+            Output::Send(blockhash, config) => {
+                let recent_blockhash = prepared.blockhash(blockhash, client)?;
+                let tx = prepared.sign(&payer, signers, recent_blockhash)?;
+                let signature = online(client).send_transaction_with_config(&tx, config)
+                    .map_err(|e| report_failure(e, &tx, reporter))?;
+                reporter.on_sent(&signature);
                 Ok(ProcessedTransaction::Execution {
-                    name,
+                    name: prepared.name,
                     signature: signature.to_string(),
-                    metadata,
+                    metadata: prepared.metadata,
+                    confirmation_slot: None,
+                    transaction_error: None,
                 })
             }
-            Processing::Simulate(client, signer) => {
-                let primary_signer = signer.pubkey();
-                let online_args = self.get_online_args(&client)?;
-                let remaining_args = self.calc_remaining_args(
-                    &online_args,
-                    &primary_signer,
-                )?;
-                extra_signers.push(signer);
-                let name = self.name(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let metadata = self.metadata(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let (_, ixs) = self.create_instructions(
-                    &primary_signer,
-                    online_args,
-                    remaining_args,
+            Output::Confirm(options) => {
+                let (signature, slot, transaction_error) = send_and_confirm(
+                    online(client),
+                    |recent_blockhash| prepared.sign(&payer, signers, recent_blockhash),
+                    &options,
+                    reporter,
                 )?;
-                let recent_blockhash = client.get_latest_blockhash()
-                    .map_err(|e| TransactionProcessorError::ClientError(e))?;
-                let tx = Transaction::new_signed_with_payer(
-                    &ixs,
-                    Some(&primary_signer), // payer
-                    extra_signers,
-                    recent_blockhash,
-                );
-                let response = client.simulate_transaction(&tx)
-                    .map_err(|e| {
-                        let e = maybe_print_preflight_simulation_logs(e);
-                        TransactionProcessorError::ClientError(e)
-                    })?;
-                let result = response.value;
-                let context = response.context;
-                Ok(ProcessedTransaction::Simulation {
-                    name,
-                    metadata,
-                    simulation_result: result,
-                    simulation_context: context,
-
+                Ok(ProcessedTransaction::Execution {
+                    name: prepared.name,
+                    signature: signature.to_string(),
+                    metadata: prepared.metadata,
+                    confirmation_slot: Some(slot),
+                    transaction_error,
                 })
             }
-            Processing::Sign(client, signer) => {
-                let primary_signer = signer.pubkey();
-                let online_args = self.get_online_args(&client)?;
-                let remaining_args = self.calc_remaining_args(
-                    &online_args,
-                    &primary_signer,
-                )?;
-                extra_signers.push(signer);
-                let name = self.name(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let metadata = self.metadata(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let (_, ixs) = self.create_instructions(
-                    &primary_signer,
-                    online_args,
-                    remaining_args,
-                )?;
-                let recent_blockhash = client.get_latest_blockhash()
-                    .map_err(|e| TransactionProcessorError::ClientError(e))?;
-                let tx = Transaction::new_signed_with_payer(
-                    &ixs,
-                    Some(&primary_signer), // payer
-                    extra_signers,
-                    recent_blockhash,
-                );
-                let serialized = bincode::serialize(&tx)
-                    .expect("transaction failed to serialize");
-                Ok(ProcessedTransaction::SignedSerialized {
-                    transaction: bs58::encode(&serialized).into_string(),
-                    name,
-                    metadata,
-                })
+            Output::Simulate(blockhash, config) => {
+                let recent_blockhash = prepared.blockhash(blockhash, client)?;
+                let tx = prepared.sign(&payer, signers, recent_blockhash)?;
+                let response = online(client).simulate_transaction_with_config(&tx, config)
+                    .map_err(|e| report_failure(e, &tx, reporter))?;
+                Ok(prepared.simulation(response))
             }
-            Processing::Serialize(client, primary_signer) => {
-                let online_args = self.get_online_args(&client)?;
-                let remaining_args = self.calc_remaining_args(
-                    &online_args,
-                    &primary_signer,
-                )?;
-                let name = self.name(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let metadata = self.metadata(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let (_, ixs) = self.create_instructions(
-                    &primary_signer,
-                    online_args,
-                    remaining_args,
-                )?;
-                let tx = Transaction::new_with_payer(
-                    &ixs,
-                    Some(&primary_signer), // payer
-                );
-                Ok(ProcessedTransaction::UnsignedSerialized {
-                    transaction: bs58::encode(tx.message.serialize()).into_string(),
-                    name,
-                    metadata,
-                })
+            Output::Sign(blockhash) => {
+                let recent_blockhash = prepared.blockhash(blockhash, client)?;
+                let tx = prepared.sign(&payer, signers, recent_blockhash)?;
+                prepared.signed_serialized(&tx)
             }
-            Processing::Instructions(client, primary_signer) => {
-                let online_args = self.get_online_args(&client)?;
-                let remaining_args = self.calc_remaining_args(
-                    &online_args,
-                    &primary_signer,
-                )?;
-                let name = self.name(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let metadata = self.metadata(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let (names, ixs) = self.create_instructions(
-                    &primary_signer,
-                    online_args,
-                    remaining_args,
-                )?;
-                let ixs = ixs.iter().map(
-                    serialize_ix
-                ).collect();
-                Ok(ProcessedTransaction::InstructionSet {
-                    instructions: ixs,
-                    instruction_names: names.iter().map(|s| s.to_string()).collect(),
-                    name,
-                    metadata,
-                })
-            }
-            Processing::OfflineSign(online_args, signer, recent_blockhash) => {
-                let primary_signer = signer.pubkey();
-                let remaining_args = self.calc_remaining_args(
-                    &online_args,
-                    &primary_signer,
-                )?;
-                extra_signers.push(signer);
-                let name = self.name(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let metadata = self.metadata(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let (_, ixs) = self.create_instructions(
-                    &primary_signer,
-                    online_args,
-                    remaining_args,
-                )?;
-                let tx = Transaction::new_signed_with_payer(
-                    &ixs,
-                    Some(&primary_signer), // payer
-                    extra_signers,
-                    recent_blockhash,
-                );
-                let serialized = bincode::serialize(&tx)
-                    .expect("transaction failed to serialize");
-                Ok(ProcessedTransaction::SignedSerialized {
-                    transaction: bs58::encode(serialized).into_string(),
-                    name,
-                    metadata
-                })
+            Output::Serialize => prepared.unsigned_serialized(&payer),
+            Output::Instructions => prepared.instruction_set(),
+        }
+    }
+}
+
+/// Sign a transaction paid for by `payer`: a legacy transaction, or a v0 transaction
+/// if there are address lookup tables.
+pub(crate) fn signed_transaction<T: Signers>(
+    ixs: &[Instruction],
+    payer: &Pubkey,
+    signers: &T,
+    recent_blockhash: Hash,
+    lookup_tables: &Option<Vec<AddressLookupTableAccount>>,
+) -> Result<VersionedTransaction, TransactionProcessorError> {
+    match lookup_tables {
+        None => Ok(Transaction::new_signed_with_payer(
+            ixs,
+            Some(payer),
+            signers,
+            recent_blockhash,
+        ).into()),
+        Some(lookup_tables) => {
+            let message = v0::Message::try_compile(payer, ixs, lookup_tables, recent_blockhash)
+                .map_err(|e| TransactionProcessorError::Other(Box::new(e)))?;
+            VersionedTransaction::try_new(VersionedMessage::V0(message), signers)
+                .map_err(TransactionProcessorError::SignerError)
+        }
+    }
+}
+
+/// Send the transaction signed by `sign` with the latest blockhash, and wait for it to be
+/// confirmed. Each time the blockhash expires first, it's signed again with a new blockhash
+/// and resent, up to [ConfirmOptions::max_attempts] times.
+/// Returns the signature, the slot it was confirmed in, and its on-chain error, if it failed.
+fn send_and_confirm(
+    client: &RpcClient,
+    sign: impl Fn(Hash) -> Result<VersionedTransaction, TransactionProcessorError>,
+    options: &ConfirmOptions,
+    reporter: &dyn TxReporter,
+) -> Result<(Signature, Slot, Option<TransactionError>), TransactionProcessorError> {
+    let start = Instant::now();
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let (recent_blockhash, last_valid_block_height) = client
+            .get_latest_blockhash_with_commitment(client.commitment())
+            .map_err(TransactionProcessorError::ClientError)?;
+        let tx = sign(recent_blockhash)?;
+        let signature = client.send_transaction(&tx)
+            .map_err(|e| report_failure(e, &tx, reporter))?;
+        reporter.on_sent(&signature);
+        loop {
+            if let Some(confirmed) = confirmed_status(client, &signature, options)? {
+                return Ok(confirmed);
             }
-            Processing::OfflineSerialize(online_args, primary_signer) => {
-                let remaining_args = self.calc_remaining_args(
-                    &online_args,
-                    &primary_signer,
-                )?;
-                let name = self.name(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let metadata = self.metadata(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let (_, ixs) = self.create_instructions(
-                    &primary_signer,
-                    online_args,
-                    remaining_args,
-                )?;
-                let tx = Transaction::new_with_payer(
-                    &ixs,
-                    Some(&primary_signer), // payer
-                );
-                Ok(ProcessedTransaction::UnsignedSerialized {
-                    transaction: bs58::encode(tx.message.serialize()).into_string(),
-                    name,
-                    metadata,
-                })
+            let block_height = client.get_block_height()
+                .map_err(TransactionProcessorError::ClientError)?;
+            if block_height > last_valid_block_height {
+                // The transaction may have landed since its status was checked. If it
+                // still hasn't, it can't land anymore.
+                match confirmed_status(client, &signature, options)? {
+                    Some(confirmed) => return Ok(confirmed),
+                    None => break,
+                }
             }
-            Processing::OfflineInstructions(online_args, primary_signer) => {
-                let remaining_args = self.calc_remaining_args(
-                    &online_args,
-                    &primary_signer,
-                )?;
-                let name = self.name(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let metadata = self.metadata(
-                    &primary_signer,
-                    &online_args,
-                    &remaining_args,
-                );
-                let (names, ixs) = self.create_instructions(
-                    &primary_signer,
-                    online_args,
-                    remaining_args,
-                )?;
-                let ixs = ixs.iter().map(
-                    serialize_ix
-                ).collect();
-                Ok(ProcessedTransaction::InstructionSet {
-                    instructions: ixs,
-                    instruction_names: names.iter().map(|s| s.to_string()).collect(),
-                    name,
-                    metadata,
-                })
+            if start.elapsed() >= options.timeout {
+                return Err(TransactionProcessorError::ConfirmationTimeout {
+                    signature,
+                    timeout: options.timeout,
+                });
             }
+            std::thread::sleep(CONFIRM_POLL_INTERVAL);
+        }
+        if attempts >= options.max_attempts {
+            return Err(TransactionProcessorError::BlockhashExpired { signature, attempts });
         }
     }
 }
 
+/// The slot and result of the transaction, once it satisfies the commitment of `options`.
+fn confirmed_status(
+    client: &RpcClient,
+    signature: &Signature,
+    options: &ConfirmOptions,
+) -> Result<Option<(Signature, Slot, Option<TransactionError>)>, TransactionProcessorError> {
+    let status = client.get_signature_statuses(&[*signature])
+        .map_err(TransactionProcessorError::ClientError)?
+        .value
+        .pop()
+        .flatten();
+    Ok(status
+        .filter(|status| status.satisfies_commitment(options.commitment))
+        .map(|status| (*signature, status.slot, status.err)))
+}
+
+/// The message of [signed_transaction], without a blockhash or signatures.
+pub(crate) fn unsigned_message(
+    ixs: &[Instruction],
+    payer: &Pubkey,
+    lookup_tables: &Option<Vec<AddressLookupTableAccount>>,
+) -> Result<VersionedMessage, TransactionProcessorError> {
+    match lookup_tables {
+        None => Ok(VersionedMessage::Legacy(Transaction::new_with_payer(ixs, Some(payer)).message)),
+        Some(lookup_tables) => v0::Message::try_compile(payer, ixs, lookup_tables, Hash::default())
+            .map(VersionedMessage::V0)
+            .map_err(|e| TransactionProcessorError::Other(Box::new(e))),
+    }
+}
+
 /// Base-58 encode an [Instruction] from the Solana SDK.
-fn serialize_ix(ix: &Instruction) -> String {
-    bs58::encode(
-        bincode::serialize(ix).expect("instruction failed to serialize")
-    ).into_string()
+pub(crate) fn serialize_ix(ix: &Instruction) -> Result<String, TransactionProcessorError> {
+    let serialized = bincode::serialize(ix)
+        .map_err(TransactionProcessorError::SerializationError)?;
+    Ok(bs58::encode(serialized).into_string())
+}
+
+/// Base-58 encode a signed transaction, in the same encoding it's sent in. Transactions
+/// too large to send are an error.
+pub(crate) fn serialize_transaction(tx: &VersionedTransaction) -> Result<String, TransactionProcessorError> {
+    let serialized = bincode::options()
+        .with_limit(PACKET_DATA_SIZE as u64)
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .serialize(tx)
+        .map_err(TransactionProcessorError::SerializationError)?;
+    Ok(bs58::encode(serialized).into_string())
 }
 
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use anchor_client::solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+    use anchor_client::solana_client::mock_sender::{MockSender, Mocks};
+    use anchor_client::solana_client::rpc_client::RpcClientConfig;
+    use anchor_client::solana_client::rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig};
+    use anchor_client::solana_client::rpc_request::{RpcError, RpcRequest, RpcResponseErrorData};
+    use anchor_client::solana_client::rpc_response::RpcSimulateTransactionResult;
+    use anchor_client::solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+    use serde_json::json;
+    use solana_sdk::commitment_config::CommitmentConfig;
     use solana_sdk::hash::Hash;
     use solana_sdk::signature::Keypair;
+    use crate::error::report_preflight_simulation_logs;
+    use crate::test_processor::{memo, transfers, NamedOnly, TestProcessor};
     use super::*;
 
-    /// Simple memo transaction
-    pub struct Memo {
-        message: String,
-    }
-
-    impl TransactionProcessor for Memo {
-        type OnlineArgs = ();
-        type RemainingArgs = ();
-
-        fn get_online_args(&self, _: &RpcClient) -> Result<Self::OnlineArgs, TransactionProcessorError> {
-            Ok(())
-        }
-
-        fn metadata(&self, primary_signer: &Pubkey, _: &Self::OnlineArgs, _: &Self::RemainingArgs) -> Map<String, Value> {
-            let mut map = Map::new();
-            map.insert("message".to_string(), Value::String(self.message.to_string()));
-            map.insert("signer".to_string(), Value::String(primary_signer.to_string()));
-            map
-        }
-
-        fn name(&self, _: &Pubkey, _: &Self::OnlineArgs, _: &Self::RemainingArgs) -> String {
-            format!("memo: {}", self.message)
-        }
-
-        fn calc_remaining_args(&self, _: &Self::OnlineArgs, _: &Pubkey) -> Result<Self::RemainingArgs, TransactionProcessorError> {
-            Ok(())
-        }
-
-        fn create_instructions(&self, primary_signer: &Pubkey, _: Self::OnlineArgs, _: Self::RemainingArgs) -> Result<(Vec<&str>, Vec<Instruction>), TransactionProcessorError> {
-            Ok(
-                (
-                    vec!["memo"],
-                    vec![spl_memo::build_memo(self.message.as_bytes(), &[primary_signer])]
-                )
-            )
-        }
-    }
-
     #[test]
     fn execution() {
-        let memo_tx = Memo {
-            message: "Foobar".to_string()
-        };
+        let memo_tx = memo("Foobar");
 
         let signer = Keypair::new();
         let client = RpcClient::new_mock("succeeds");
         let response = memo_tx.process(
-            Processing::Execute(client, Box::new(signer)),
+            Processing::Execute(client, Box::new(signer), None),
             &mut vec![],
         ).unwrap();
         if let ProcessedTransaction::Execution {
@@ -455,16 +409,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn execute_and_confirm() {
+        let memo_tx = memo("Foobar");
+        let process = |url: &str, options: ConfirmOptions| memo_tx.process(
+            Processing::ExecuteAndConfirm(RpcClient::new_mock(url), Box::new(Keypair::new()), None, options),
+            &mut vec![],
+        );
+
+        let response = process("succeeds", ConfirmOptions::default()).unwrap();
+        if let ProcessedTransaction::Execution { confirmation_slot, transaction_error, .. } = response {
+            assert!(confirmation_slot.is_some());
+            assert_eq!(transaction_error, None);
+        } else {
+            panic!("wrong processing");
+        }
+
+        // Landed, but failed.
+        let response = process("account_in_use", ConfirmOptions::default()).unwrap();
+        if let ProcessedTransaction::Execution { transaction_error, .. } = response {
+            assert_eq!(transaction_error, Some(TransactionError::AccountInUse));
+        } else {
+            panic!("wrong processing");
+        }
+
+        let options = ConfirmOptions { timeout: Duration::ZERO, ..Default::default() };
+        let err = process("sig_not_found", options).err().unwrap();
+        assert!(matches!(err, TransactionProcessorError::ConfirmationTimeout { timeout, .. } if timeout == Duration::ZERO));
+    }
+
+    #[test]
+    fn confirm_blockhash_expired() {
+        let memo_tx = memo("Foobar");
+        // Past the mock blockhash's last valid block height of 1234.
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(RpcRequest::GetBlockHeight, json!(1_235));
+        let client = RpcClient::new_mock_with_mocks("sig_not_found".to_string(), mocks);
+        let options = ConfirmOptions { max_attempts: 1, ..Default::default() };
+        let err = memo_tx.process(
+            Processing::ExecuteAndConfirm(client, Box::new(Keypair::new()), None, options),
+            &mut vec![],
+        ).err().unwrap();
+        assert!(matches!(err, TransactionProcessorError::BlockhashExpired { attempts: 1, .. }), "{}", err);
+    }
+
     #[test]
     fn simulation() {
-        let memo_tx = Memo {
-            message: "Foobar".to_string()
-        };
+        let memo_tx = memo("Foobar");
 
         let signer = Keypair::new();
         let client = RpcClient::new_mock("succeeds");
         let response = memo_tx.process(
-            Processing::Simulate(client, Box::new(signer)),
+            Processing::Simulate(client, Box::new(signer), None),
             &mut vec![],
         ).unwrap();
         if let ProcessedTransaction::Simulation {
@@ -477,16 +473,128 @@ mod tests {
         }
     }
 
+    #[test]
+    fn simulation_report() {
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(RpcRequest::SimulateTransaction, json!({
+            "context": { "slot": 1 },
+            "value": {
+                "err": null,
+                "logs": ["Program log: Memo (len 6): \"Foobar\""],
+                "accounts": null,
+                "unitsConsumed": 7_500,
+                "returnData": null,
+            },
+        }));
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let memo = memo("Foobar");
+        let response = memo.process(
+            Processing::Simulate(client, Box::new(Keypair::new()), None),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::Simulation { metadata, simulation_report, .. } = response {
+            assert!(simulation_report.succeeded);
+            assert_eq!(simulation_report.units_consumed, Some(7_500));
+            assert_eq!(simulation_report.logs, vec!["Program log: Memo (len 6): \"Foobar\"".to_string()]);
+            assert_eq!(simulation_report.return_data, None);
+            assert_eq!(metadata["units_consumed"], json!(7_500));
+        } else {
+            panic!("wrong processing");
+        }
+
+        // Without units consumed, there's nothing to record.
+        let response = memo.process(
+            Processing::Simulate(RpcClient::new_mock("succeeds"), Box::new(Keypair::new()), None),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::Simulation { metadata, .. } = response {
+            assert!(!metadata.contains_key("units_consumed"));
+        } else {
+            panic!("wrong processing");
+        }
+    }
+
+    /// Delegates to the mock sender, keeping the params of every request.
+    struct RecordingSender {
+        mock: MockSender,
+        requests: Arc<Mutex<Vec<(RpcRequest, Value)>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl RpcSender for RecordingSender {
+        async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+            self.requests.lock().unwrap().push((request, params.clone()));
+            self.mock.send(request, params).await
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            self.mock.get_transport_stats()
+        }
+
+        fn url(&self) -> String {
+            self.mock.url()
+        }
+    }
+
+    /// Process `mode` with a mock client, returning the config it sent `request` with.
+    fn request_config(
+        request: RpcRequest,
+        mode: impl FnOnce(RpcClient) -> Processing<()>,
+    ) -> Value {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let client = RpcClient::new_sender(
+            RecordingSender { mock: MockSender::new("succeeds"), requests: requests.clone() },
+            RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+        );
+        let memo_tx = memo("Foobar");
+        memo_tx.process(mode(client), &mut vec![]).unwrap();
+        let requests = requests.lock().unwrap();
+        let (_, params) = requests.iter().rev().find(|(r, _)| *r == request).unwrap();
+        params[1].clone()
+    }
+
+    #[test]
+    fn send_and_simulate_configs() {
+        // The defaults are what the client uses without a config.
+        let config = request_config(RpcRequest::SendTransaction, |client| {
+            Processing::Execute(client, Box::new(Keypair::new()), None)
+        });
+        assert_eq!(config["skipPreflight"], json!(false));
+        assert_eq!(config["preflightCommitment"], json!("confirmed"));
+        let config = request_config(RpcRequest::SimulateTransaction, |client| {
+            Processing::Simulate(client, Box::new(Keypair::new()), None)
+        });
+        assert_eq!(config["sigVerify"], json!(false));
+        assert_eq!(config["commitment"], json!("confirmed"));
+
+        let config = request_config(RpcRequest::SendTransaction, |client| {
+            Processing::ExecuteWithConfig(client, Box::new(Keypair::new()), None, RpcSendTransactionConfig {
+                skip_preflight: true,
+                max_retries: Some(2),
+                ..Default::default()
+            })
+        });
+        assert_eq!(config["skipPreflight"], json!(true));
+        assert_eq!(config["maxRetries"], json!(2));
+        let config = request_config(RpcRequest::SimulateTransaction, |client| {
+            Processing::SimulateWithConfig(client, Box::new(Keypair::new()), None, RpcSimulateTransactionConfig {
+                sig_verify: true,
+                commitment: Some(CommitmentConfig::processed()),
+                ..Default::default()
+            })
+        });
+        assert_eq!(config["sigVerify"], json!(true));
+        assert_eq!(config["commitment"], json!("processed"));
+    }
+
     #[test]
     fn sign() {
-        let memo_tx = Memo {
-            message: "Foobar".to_string()
-        };
+        let memo_tx = memo("Foobar");
 
         let signer = Keypair::new();
         let client = RpcClient::new_mock("succeeds");
         let response = memo_tx.process(
-            Processing::Sign(client, Box::new(signer)),
+            Processing::Sign(client, Box::new(signer), None),
             &mut vec![],
         ).unwrap();
         if let ProcessedTransaction::SignedSerialized {
@@ -501,14 +609,12 @@ mod tests {
 
     #[test]
     fn serialize() {
-        let memo_tx = Memo {
-            message: "Foobar".to_string()
-        };
+        let memo_tx = memo("Foobar");
 
         let signer = Keypair::new();
         let client = RpcClient::new_mock("succeeds");
         let response = memo_tx.process(
-            Processing::Serialize(client, signer.pubkey()),
+            Processing::Serialize(client, signer.pubkey(), None),
             &mut vec![],
         ).unwrap();
         if let ProcessedTransaction::UnsignedSerialized {
@@ -523,9 +629,7 @@ mod tests {
 
     #[test]
     fn instructions() {
-        let memo_tx = Memo {
-            message: "Foobar".to_string()
-        };
+        let memo_tx = memo("Foobar");
 
         let signer = Keypair::new();
         let client = RpcClient::new_mock("succeeds");
@@ -545,13 +649,11 @@ mod tests {
 
     #[test]
     fn offline_sign() {
-        let memo_tx = Memo {
-            message: "Foobar".to_string()
-        };
+        let memo_tx = memo("Foobar");
 
         let signer = Keypair::new();
         let response = memo_tx.process(
-            Processing::OfflineSign((), Box::new(signer), Hash::new_unique()),
+            Processing::OfflineSign((), Box::new(signer), None, Hash::new_unique()),
             &mut vec![],
         ).unwrap();
         if let ProcessedTransaction::SignedSerialized {
@@ -565,14 +667,264 @@ mod tests {
     }
 
     #[test]
-    fn offline_serialize() {
-        let memo_tx = Memo {
-            message: "Foobar".to_string()
+    fn online_args_fetched_once() {
+        let signer = Keypair::new();
+        let pubkey = signer.pubkey();
+        let keypair = || -> Box<dyn Signer> { Box::new(Keypair::from_bytes(&signer.to_bytes()).unwrap()) };
+        let client = || RpcClient::new_mock("succeeds");
+        // The signer is also the nonce authority.
+        let nonce_client = || {
+            let mut mocks: Mocks = HashMap::new();
+            mocks.insert(
+                RpcRequest::GetAccountInfo,
+                nonce::tests::nonce_account_response(nonce::tests::initialized(pubkey, Hash::new_unique())),
+            );
+            RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+        };
+        // Confirmed on the first poll, so the confirmation doesn't depend on the mock's
+        // block height.
+        let confirm_client = || {
+            let mut mocks: Mocks = HashMap::new();
+            mocks.insert(RpcRequest::GetSignatureStatuses, json!({
+                "context": { "slot": 1 },
+                "value": [{
+                    "slot": 1,
+                    "confirmations": null,
+                    "err": null,
+                    "status": { "Ok": null },
+                    "confirmationStatus": "finalized",
+                }],
+            }));
+            RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+        };
+        let nonce_account = Pubkey::new_unique();
+
+        let modes: Vec<(Processing<()>, usize)> = vec![
+            (Processing::Execute(client(), keypair(), None), 1),
+            (Processing::ExecuteWithConfig(client(), keypair(), None, RpcSendTransactionConfig::default()), 1),
+            (Processing::ExecuteAndConfirm(confirm_client(), keypair(), None, ConfirmOptions::default()), 1),
+            (Processing::Simulate(client(), keypair(), None), 1),
+            (Processing::SimulateWithConfig(client(), keypair(), None, RpcSimulateTransactionConfig::default()), 1),
+            (Processing::Sign(client(), keypair(), None), 1),
+            (Processing::ExecuteWithNonce(nonce_client(), keypair(), None, nonce_account, pubkey), 1),
+            (Processing::SignWithNonce(nonce_client(), keypair(), None, nonce_account, pubkey), 1),
+            (Processing::Serialize(client(), pubkey, None), 1),
+            (Processing::Instructions(client(), pubkey), 1),
+            (Processing::OfflineSign((), keypair(), None, Hash::new_unique()), 0),
+            (Processing::OfflineSignWithNonce((), keypair(), None, nonce_account, pubkey, Hash::new_unique()), 0),
+            (Processing::OfflineSerialize((), pubkey, None), 0),
+            (Processing::OfflineInstructions((), pubkey), 0),
+        ];
+        let processor = memo("counted");
+        for (i, (mode, calls)) in modes.into_iter().enumerate() {
+            processor.reset_online_args_calls();
+            processor.process(mode, &mut vec![]).unwrap();
+            assert_eq!(processor.online_args_calls(), calls, "mode {}", i);
+        }
+    }
+
+    #[test]
+    fn instruction_names() {
+        let signer = Pubkey::new_unique();
+        let misnamed = TestProcessor {
+            extra_names: vec!["missing"],
+            ..memo("memo")
+        };
+        let err = misnamed.process(Processing::OfflineInstructions((), signer), &mut vec![]).unwrap_err();
+        assert!(
+            matches!(err, TransactionProcessorError::InstructionNameMismatch { names: 2, instructions: 1 }),
+            "{}",
+            err,
+        );
+
+        // Processors still implementing create_instructions are paired up by the shim.
+        let memo_tx = memo("Foobar");
+        assert_eq!(
+            memo_tx.named_instructions(&signer, (), ()).unwrap(),
+            vec![("memo".to_string(), spl_memo::build_memo(b"Foobar", &[&signer]))],
+        );
+
+        let named = NamedOnly(memo("named"));
+        let response = named.process(Processing::OfflineInstructions((), signer), &mut vec![]).unwrap();
+        if let ProcessedTransaction::InstructionSet { instruction_names, instructions, .. } = response {
+            assert_eq!(instruction_names, vec!["memo".to_string()]);
+            assert_eq!(instructions.len(), 1);
+        } else {
+            panic!("wrong processing");
+        }
+        named.process(
+            Processing::OfflineSign((), Box::new(Keypair::new()), None, Hash::new_unique()),
+            &mut vec![],
+        ).unwrap();
+        #[allow(deprecated)]
+        let err = named.create_instructions(&signer, (), ()).unwrap_err();
+        assert!(err.to_string().contains("named_instructions"), "{}", err);
+    }
+
+    #[test]
+    fn separate_fee_payer() {
+        let memo_tx = memo("Foobar");
+        let authority = Keypair::new();
+        let fee_payer = Keypair::new();
+
+        let response = memo_tx.process(
+            Processing::OfflineSign(
+                (),
+                Box::new(Keypair::from_bytes(&authority.to_bytes()).unwrap()),
+                Some(Box::new(Keypair::from_bytes(&fee_payer.to_bytes()).unwrap())),
+                Hash::new_unique(),
+            ),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::SignedSerialized { transaction, metadata, .. } = response {
+            let tx: Transaction = bincode::deserialize(&bs58::decode(transaction).into_vec().unwrap()).unwrap();
+            tx.verify().unwrap();
+            assert_eq!(tx.message.header.num_required_signatures, 2);
+            assert_eq!(tx.message.account_keys[..2], [fee_payer.pubkey(), authority.pubkey()]);
+            assert_eq!(metadata["fee_payer"], json!(fee_payer.pubkey().to_string()));
+            assert_eq!(metadata["primary_signer"], json!(authority.pubkey().to_string()));
+            // The instructions are still created for the authority.
+            assert_eq!(metadata["signer"], json!(authority.pubkey().to_string()));
+        } else {
+            panic!("wrong processing");
+        }
+
+        let response = memo_tx.process(
+            Processing::OfflineSerialize((), authority.pubkey(), Some(fee_payer.pubkey())),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::UnsignedSerialized { transaction, .. } = response {
+            let message: VersionedMessage = bincode::deserialize(&bs58::decode(transaction).into_vec().unwrap()).unwrap();
+            assert_eq!(message.header().num_required_signatures, 2);
+            assert_eq!(message.static_account_keys()[..2], [fee_payer.pubkey(), authority.pubkey()]);
+        } else {
+            panic!("wrong processing");
+        }
+
+        // Without one, the primary signer pays, and nothing more is recorded.
+        let response = memo_tx.process(
+            Processing::OfflineSerialize((), authority.pubkey(), None),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::UnsignedSerialized { transaction, metadata, .. } = response {
+            let message: VersionedMessage = bincode::deserialize(&bs58::decode(transaction).into_vec().unwrap()).unwrap();
+            assert_eq!(message.header().num_required_signatures, 1);
+            assert_eq!(message.static_account_keys()[0], authority.pubkey());
+            assert!(!metadata.contains_key("fee_payer"));
+        } else {
+            panic!("wrong processing");
+        }
+    }
+
+    /// The nonce of a transaction signed with a durable nonce, checking it advances the nonce first.
+    fn signed_nonce(response: ProcessedTransaction, nonce_account: &Pubkey, authority: &Pubkey) -> Hash {
+        if let ProcessedTransaction::SignedSerialized { transaction, .. } = response {
+            let tx: Transaction = bincode::deserialize(&bs58::decode(transaction).into_vec().unwrap()).unwrap();
+            tx.verify().unwrap();
+            let advance = solana_sdk::system_instruction::advance_nonce_account(nonce_account, authority);
+            assert_eq!(tx.message.account_keys[tx.message.instructions[0].program_id_index as usize], advance.program_id);
+            assert_eq!(tx.message.instructions[0].data, advance.data);
+            assert_eq!(tx.message.instructions.len(), 2);
+            tx.message.recent_blockhash
+        } else {
+            panic!("wrong processing");
+        }
+    }
+
+    #[test]
+    fn offline_sign_with_nonce() {
+        let memo_tx = memo("Foobar");
+        let signer = Keypair::new();
+        let authority = signer.pubkey();
+        let nonce_account = Pubkey::new_unique();
+        let nonce = Hash::new_unique();
+        let response = memo_tx.process(
+            Processing::OfflineSignWithNonce((), Box::new(signer), None, nonce_account, authority, nonce),
+            &mut vec![],
+        ).unwrap();
+        assert_eq!(signed_nonce(response, &nonce_account, &authority), nonce);
+    }
+
+    #[test]
+    fn sign_with_nonce() {
+        let memo_tx = memo("Foobar");
+        let signer = Keypair::new();
+        let authority = Keypair::new();
+        let nonce_account = Pubkey::new_unique();
+        let blockhash = Hash::new_unique();
+        let client = |state| {
+            let mut mocks: Mocks = HashMap::new();
+            mocks.insert(RpcRequest::GetAccountInfo, nonce::tests::nonce_account_response(state));
+            RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
         };
 
+        // A separate nonce authority signs as an extra signer.
+        let response = memo_tx.process(
+            Processing::SignWithNonce(
+                client(nonce::tests::initialized(authority.pubkey(), blockhash)),
+                Box::new(signer),
+                None,
+                nonce_account,
+                authority.pubkey(),
+            ),
+            &mut vec![Box::new(Keypair::from_bytes(&authority.to_bytes()).unwrap())],
+        ).unwrap();
+        let expected = solana_sdk::nonce::state::DurableNonce::from_blockhash(&blockhash);
+        assert_eq!(signed_nonce(response, &nonce_account, &authority.pubkey()), *expected.as_hash());
+
+        let err = memo_tx.process(
+            Processing::ExecuteWithNonce(
+                client(solana_sdk::nonce::state::State::Uninitialized),
+                Box::new(Keypair::new()),
+                None,
+                nonce_account,
+                authority.pubkey(),
+            ),
+            &mut vec![Box::new(authority)],
+        ).err().unwrap();
+        assert!(matches!(err, TransactionProcessorError::NonceAccountNotInitialized { address } if address == nonce_account));
+    }
+
+    #[test]
+    fn oversize_transactions_fail_to_serialize() {
+        let memo_tx = memo(&"a".repeat(PACKET_DATA_SIZE));
+        let err = memo_tx.process(
+            Processing::OfflineSign((), Box::new(Keypair::new()), None, Hash::new_unique()),
+            &mut vec![],
+        ).unwrap_err();
+        match err {
+            TransactionProcessorError::SerializationError(e) => {
+                assert!(matches!(*e, bincode::ErrorKind::SizeLimit), "{}", e);
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+        let err = memo_tx.process(
+            Processing::Sign(RpcClient::new_mock("succeeds"), Box::new(Keypair::new()), None),
+            &mut vec![],
+        ).unwrap_err();
+        assert!(matches!(err, TransactionProcessorError::SerializationError(_)), "{}", err);
+
+        // Instructions aren't sent on their own, so they can be any size.
+        let response = memo_tx.process(
+            Processing::OfflineInstructions((), Pubkey::new_unique()),
+            &mut vec![],
+        ).unwrap();
+        assert!(matches!(response, ProcessedTransaction::InstructionSet { .. }));
+    }
+
+    #[test]
+    fn errors_are_send_and_sync() {
+        fn send_and_sync<T: Send + Sync + 'static>() {}
+        send_and_sync::<TransactionProcessorError>();
+    }
+
+    #[test]
+    fn offline_serialize() {
+        let memo_tx = memo("Foobar");
+
         let signer = Keypair::new();
         let response = memo_tx.process(
-            Processing::OfflineSerialize((), signer.pubkey()),
+            Processing::OfflineSerialize((), signer.pubkey(), None),
             &mut vec![],
         ).unwrap();
         if let ProcessedTransaction::UnsignedSerialized {
@@ -587,9 +939,7 @@ mod tests {
 
     #[test]
     fn offline_instructions() {
-        let memo_tx = Memo {
-            message: "Foobar".to_string()
-        };
+        let memo_tx = memo("Foobar");
 
         let signer = Keypair::new();
         let response = memo_tx.process(
@@ -605,4 +955,221 @@ mod tests {
             panic!("wrong processing");
         }
     }
+
+    #[test]
+    fn legacy_wire_format() {
+        let memo_tx = memo("Foobar");
+        let signer = Keypair::new();
+        let response = memo_tx.process(
+            Processing::OfflineSign((), Box::new(signer), None, Hash::new_unique()),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::SignedSerialized { transaction, .. } = response {
+            let serialized = bs58::decode(transaction).into_vec().unwrap();
+            let tx: Transaction = bincode::deserialize(&serialized).unwrap();
+            tx.verify().unwrap();
+        } else {
+            panic!("wrong processing");
+        }
+    }
+
+    #[test]
+    fn v0_sign_round_trip() {
+        // Too many accounts for a legacy transaction.
+        let processor = transfers(40);
+        let ixs: Vec<Instruction> = processor.named_instructions(&Pubkey::new_unique(), (), ())
+            .unwrap()
+            .into_iter()
+            .map(|(_, ix)| ix)
+            .collect();
+        let legacy = Transaction::new_with_payer(&ixs, None);
+        assert!(bincode::serialize(&legacy).unwrap().len() > solana_sdk::packet::PACKET_DATA_SIZE);
+
+        let signer = Keypair::new();
+        let payer = signer.pubkey();
+        let response = processor.process(
+            Processing::OfflineSign((), Box::new(signer), None, Hash::new_unique()),
+            &mut vec![],
+        ).unwrap();
+        let transaction = match response {
+            ProcessedTransaction::SignedSerialized { transaction, .. } => transaction,
+            _ => panic!("wrong processing"),
+        };
+        let serialized = bs58::decode(transaction).into_vec().unwrap();
+        assert!(serialized.len() <= solana_sdk::packet::PACKET_DATA_SIZE);
+        let tx: VersionedTransaction = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(bincode::serialize(&tx).unwrap(), serialized);
+        let message = match &tx.message {
+            VersionedMessage::V0(message) => message,
+            VersionedMessage::Legacy(_) => panic!("expected a v0 message"),
+        };
+        assert_eq!(message.account_keys[0], payer);
+        assert_eq!(message.address_table_lookups.len(), 1);
+        assert_eq!(message.address_table_lookups[0].account_key, processor.lookup_table.as_ref().unwrap().key);
+        assert_eq!(message.address_table_lookups[0].writable_indexes.len(), 40);
+        assert!(tx.signatures[0].verify(payer.as_ref(), &tx.message.serialize()));
+    }
+
+    #[test]
+    fn v0_serialize() {
+        let processor = transfers(3);
+        let response = processor.process(
+            Processing::OfflineSerialize((), Pubkey::new_unique(), None),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::UnsignedSerialized { transaction, .. } = response {
+            let serialized = bs58::decode(transaction).into_vec().unwrap();
+            // The version prefix: the high bit, and version 0.
+            assert_eq!(serialized[0], 0x80);
+            let message: VersionedMessage = bincode::deserialize(&serialized).unwrap();
+            assert!(matches!(message, VersionedMessage::V0(_)));
+        } else {
+            panic!("wrong processing");
+        }
+    }
+
+    #[test]
+    fn v0_execute() {
+        let response = transfers(3).process(
+            Processing::Execute(RpcClient::new_mock("succeeds"), Box::new(Keypair::new()), None),
+            &mut vec![],
+        ).unwrap();
+        assert!(matches!(response, ProcessedTransaction::Execution { .. }));
+    }
+
+    #[test]
+    fn compute_budget_in_every_mode() {
+        let processor = TestProcessor {
+            compute_budget: Some(ComputeBudget { unit_limit: Some(50_000), unit_price: Some(UnitPrice::Fixed(10)) }),
+            ..memo("budgeted")
+        };
+        let response = processor.process(
+            Processing::OfflineInstructions((), Pubkey::new_unique()),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::InstructionSet { instructions, instruction_names, .. } = response {
+            assert_eq!(instruction_names, vec!["set_compute_unit_limit", "set_compute_unit_price", "memo"]);
+            let limit: Instruction = bincode::deserialize(&bs58::decode(&instructions[0]).into_vec().unwrap()).unwrap();
+            assert_eq!(limit, solana_sdk::compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(50_000));
+        } else {
+            panic!("wrong processing");
+        }
+
+        let response = processor.process(
+            Processing::OfflineSign((), Box::new(Keypair::new()), None, Hash::new_unique()),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::SignedSerialized { transaction, .. } = response {
+            let tx: Transaction = bincode::deserialize(&bs58::decode(transaction).into_vec().unwrap()).unwrap();
+            assert_eq!(tx.message.instructions.len(), 3);
+            assert_eq!(
+                tx.message.account_keys[tx.message.instructions[0].program_id_index as usize],
+                solana_sdk::compute_budget::id(),
+            );
+        } else {
+            panic!("wrong processing");
+        }
+    }
+
+    #[test]
+    fn automatic_unit_price() {
+        let processor = TestProcessor {
+            compute_budget: Some(ComputeBudget { unit_limit: None, unit_price: Some(UnitPrice::Auto { percentile: 50 }) }),
+            ..memo("budgeted")
+        };
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::Custom { method: "getRecentPrioritizationFees" },
+            serde_json::json!([
+                { "slot": 1, "prioritizationFee": 100 },
+                { "slot": 2, "prioritizationFee": 300 },
+                { "slot": 3, "prioritizationFee": 200 },
+            ]),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let response = processor.process(
+            Processing::Execute(client, Box::new(Keypair::new()), None),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::Execution { metadata, .. } = response {
+            assert_eq!(metadata["compute_unit_price"], serde_json::json!(200));
+        } else {
+            panic!("wrong processing");
+        }
+
+        let err = processor.process(
+            Processing::OfflineSerialize((), Pubkey::new_unique(), None),
+            &mut vec![],
+        ).err().unwrap();
+        assert!(err.to_string().contains("can't be estimated offline"), "{}", err);
+    }
+
+    #[derive(Default)]
+    struct RecordingReporter {
+        events: std::cell::RefCell<Vec<String>>,
+    }
+
+    impl TxReporter for RecordingReporter {
+        fn on_sent(&self, signature: &solana_sdk::signature::Signature) {
+            self.events.borrow_mut().push(format!("sent {}", signature));
+        }
+
+        fn on_failed(&self, signature: &solana_sdk::signature::Signature, _: &ClientError) {
+            self.events.borrow_mut().push(format!("failed {}", signature));
+        }
+
+        fn on_preflight_logs(&self, logs: &[String]) {
+            self.events.borrow_mut().extend(logs.iter().map(|log| format!("log {}", log)));
+        }
+    }
+
+    #[test]
+    fn reports_sent_transactions() {
+        let memo_tx = memo("Foobar");
+        let reporter = RecordingReporter::default();
+        let response = memo_tx.process_with_reporter(
+            Processing::Execute(RpcClient::new_mock("succeeds"), Box::new(Keypair::new()), None),
+            &mut vec![],
+            Some(&reporter),
+        ).unwrap();
+        if let ProcessedTransaction::Execution { signature, .. } = response {
+            assert_eq!(*reporter.events.borrow(), vec![format!("sent {}", signature)]);
+        } else {
+            panic!("wrong processing");
+        }
+    }
+
+    #[test]
+    fn reports_preflight_logs() {
+        let logs = vec![
+            "Program MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr invoke [1]".to_string(),
+            "Program MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr failed: invalid instruction data".to_string(),
+        ];
+        let err = ClientError::from(RpcError::RpcResponseError {
+            code: -32002,
+            message: "Transaction simulation failed".to_string(),
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(
+                RpcSimulateTransactionResult {
+                    err: None,
+                    logs: Some(logs.clone()),
+                    accounts: None,
+                    units_consumed: None,
+                    return_data: None,
+                }
+            ),
+        });
+        let reporter = RecordingReporter::default();
+        let err = report_preflight_simulation_logs(err, &reporter);
+        assert_eq!(
+            *reporter.events.borrow(),
+            logs.iter().map(|log| format!("log {}", log)).collect::<Vec<String>>(),
+        );
+        // The error is handed back untouched.
+        assert!(matches!(err.kind, ClientErrorKind::RpcError(RpcError::RpcResponseError { code: -32002, .. })));
+
+        // Other errors have no logs to report.
+        let reporter = RecordingReporter::default();
+        report_preflight_simulation_logs(ClientError::from(ClientErrorKind::Custom("nope".to_string())), &reporter);
+        assert!(reporter.events.borrow().is_empty());
+    }
 }
@@ -0,0 +1,73 @@
+use anchor_client::solana_client::rpc_client::RpcClient;
+use solana_address_lookup_table_program::state::AddressLookupTable;
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::pubkey::Pubkey;
+use crate::error::TransactionProcessorError;
+
+/// Fetch address lookup tables, e.g. from [crate::TransactionProcessor::get_online_args],
+/// to return them from [crate::TransactionProcessor::lookup_tables].
+pub fn fetch_lookup_tables(
+    client: &RpcClient,
+    addresses: &[Pubkey],
+) -> Result<Vec<AddressLookupTableAccount>, TransactionProcessorError> {
+    let accounts = client.get_multiple_accounts(addresses)
+        .map_err(TransactionProcessorError::ClientError)?;
+    addresses.iter().zip(accounts).map(|(address, account)| {
+        let account = account.ok_or_else(|| TransactionProcessorError::Other(
+            format!("address lookup table {} does not exist", address).into()
+        ))?;
+        let table = AddressLookupTable::deserialize(&account.data)
+            .map_err(|e| TransactionProcessorError::Other(
+                format!("account {} is not an address lookup table: {}", address, e).into()
+            ))?;
+        Ok(AddressLookupTableAccount {
+            key: *address,
+            addresses: table.addresses.to_vec(),
+        })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+    use anchor_client::solana_client::mock_sender::Mocks;
+    use anchor_client::solana_client::rpc_request::RpcRequest;
+    use solana_address_lookup_table_program::state::LookupTableMeta;
+    use super::*;
+
+    #[test]
+    fn fetches_lookup_tables() {
+        let table_address = Pubkey::new_unique();
+        let addresses = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let data = AddressLookupTable {
+            meta: LookupTableMeta::default(),
+            addresses: Cow::Owned(addresses.clone()),
+        }.serialize_for_tests().unwrap();
+        let account = serde_json::json!({
+            "lamports": 1,
+            "data": [base64::encode(data), "base64"],
+            "owner": solana_address_lookup_table_program::ID.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+        });
+
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            serde_json::json!({ "context": { "slot": 1 }, "value": [account] }),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let tables = fetch_lookup_tables(&client, &[table_address]).unwrap();
+        assert_eq!(tables, vec![AddressLookupTableAccount { key: table_address, addresses }]);
+
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            serde_json::json!({ "context": { "slot": 1 }, "value": [null] }),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let err = fetch_lookup_tables(&client, &[table_address]).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+    }
+}
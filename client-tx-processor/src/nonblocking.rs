@@ -0,0 +1,456 @@
+/// [TransactionProcessor] for the nonblocking [RpcClient], e.g. in async servers, without
+/// wrapping every call in `spawn_blocking`.
+///
+/// Only the online modes are here. The offline modes don't make requests, so they're
+/// processed with [TransactionProcessor::process] as usual, without an async runtime.
+use std::time::Instant;
+use anchor_client::solana_client::nonblocking::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig};
+use anchor_client::solana_client::rpc_request::RpcRequest;
+use async_trait::async_trait;
+use serde_json::json;
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::{TransactionError, VersionedTransaction};
+use crate::compute_budget::{fee_accounts, fee_percentile, RecentPrioritizationFee};
+use crate::error::report_failure;
+use crate::nonce::nonce_from_account;
+use crate::prepared::{send_config, simulate_config, Blockhash, Output, PreparedTransaction};
+use crate::{
+    ConfirmOptions, ProcessedTransaction, StdoutReporter, TransactionProcessor,
+    TransactionProcessorError, TxReporter, UnitPrice, CONFIRM_POLL_INTERVAL,
+};
+
+/// The online [crate::Processing] modes, with a nonblocking client. Signers must be
+/// [Send] and [Sync], so the futures of [AsyncTransactionProcessor] can move between threads.
+///
+/// As with [crate::Processing], the signer is the primary signer, and pays the fees unless
+/// there's a separate fee payer.
+pub enum AsyncProcessing {
+    /// Sign, serialize, and send the transaction for execution on the cluster.
+    Execute(RpcClient, Box<dyn Signer + Send + Sync>, Option<Box<dyn Signer + Send + Sync>>), // client, signer, fee payer
+    /// Like [AsyncProcessing::Execute], sending the transaction with the given config.
+    ExecuteWithConfig(RpcClient, Box<dyn Signer + Send + Sync>, Option<Box<dyn Signer + Send + Sync>>, RpcSendTransactionConfig),
+    /// Like [AsyncProcessing::Execute], then wait for the transaction to be confirmed.
+    ExecuteAndConfirm(RpcClient, Box<dyn Signer + Send + Sync>, Option<Box<dyn Signer + Send + Sync>>, ConfirmOptions),
+    /// Sign, serialize, and simulate the transaction.
+    Simulate(RpcClient, Box<dyn Signer + Send + Sync>, Option<Box<dyn Signer + Send + Sync>>),
+    /// Like [AsyncProcessing::Simulate], simulating the transaction with the given config.
+    SimulateWithConfig(RpcClient, Box<dyn Signer + Send + Sync>, Option<Box<dyn Signer + Send + Sync>>, RpcSimulateTransactionConfig),
+    /// Sign and serialize the transaction.
+    Sign(RpcClient, Box<dyn Signer + Send + Sync>, Option<Box<dyn Signer + Send + Sync>>),
+    /// Like [crate::Processing::ExecuteWithNonce].
+    ExecuteWithNonce(RpcClient, Box<dyn Signer + Send + Sync>, Option<Box<dyn Signer + Send + Sync>>, Pubkey, Pubkey), // client, signer, fee payer, nonce account, nonce authority
+    /// Like [crate::Processing::SignWithNonce].
+    SignWithNonce(RpcClient, Box<dyn Signer + Send + Sync>, Option<Box<dyn Signer + Send + Sync>>, Pubkey, Pubkey), // client, signer, fee payer, nonce account, nonce authority
+    /// No signatures applied, simply the Transaction Message serialized.
+    Serialize(RpcClient, Pubkey, Option<Pubkey>), // client, signer, fee payer
+    /// Output the transaction instructions in Base58 encoding.
+    Instructions(RpcClient, Pubkey), // client, multisig_signer
+}
+
+/// An [AsyncProcessing] mode, taken apart like [crate::Processing] modes are.
+struct AsyncMode {
+    client: RpcClient,
+    primary_signer: Pubkey,
+    fee_payer: Option<Pubkey>,
+    signers: Vec<Box<dyn Signer + Send + Sync>>,
+    output: Output,
+}
+
+impl From<AsyncProcessing> for AsyncMode {
+    fn from(mode: AsyncProcessing) -> Self {
+        /// A mode that signs with `signer` and the optional `fee_payer`.
+        fn signing(
+            client: RpcClient,
+            signer: Box<dyn Signer + Send + Sync>,
+            fee_payer: Option<Box<dyn Signer + Send + Sync>>,
+            output: Output,
+        ) -> AsyncMode {
+            AsyncMode {
+                client,
+                primary_signer: signer.pubkey(),
+                fee_payer: fee_payer.as_ref().map(|fee_payer| fee_payer.pubkey()),
+                signers: std::iter::once(signer).chain(fee_payer).collect(),
+                output,
+            }
+        }
+
+        match mode {
+            AsyncProcessing::Execute(client, signer, fee_payer) => {
+                let output = Output::Send(Blockhash::Latest, send_config(client.commitment()));
+                signing(client, signer, fee_payer, output)
+            }
+            AsyncProcessing::ExecuteWithConfig(client, signer, fee_payer, config) => {
+                signing(client, signer, fee_payer, Output::Send(Blockhash::Latest, config))
+            }
+            AsyncProcessing::ExecuteAndConfirm(client, signer, fee_payer, options) => {
+                signing(client, signer, fee_payer, Output::Confirm(options))
+            }
+            AsyncProcessing::Simulate(client, signer, fee_payer) => {
+                let output = Output::Simulate(Blockhash::Latest, simulate_config(client.commitment()));
+                signing(client, signer, fee_payer, output)
+            }
+            AsyncProcessing::SimulateWithConfig(client, signer, fee_payer, config) => {
+                signing(client, signer, fee_payer, Output::Simulate(Blockhash::Latest, config))
+            }
+            AsyncProcessing::Sign(client, signer, fee_payer) => {
+                signing(client, signer, fee_payer, Output::Sign(Blockhash::Latest))
+            }
+            AsyncProcessing::ExecuteWithNonce(client, signer, fee_payer, account, authority) => {
+                let nonce = Blockhash::Nonce { account, authority, nonce: None };
+                let output = Output::Send(nonce, send_config(client.commitment()));
+                signing(client, signer, fee_payer, output)
+            }
+            AsyncProcessing::SignWithNonce(client, signer, fee_payer, account, authority) => {
+                let nonce = Blockhash::Nonce { account, authority, nonce: None };
+                signing(client, signer, fee_payer, Output::Sign(nonce))
+            }
+            AsyncProcessing::Serialize(client, primary_signer, fee_payer) => {
+                AsyncMode { client, primary_signer, fee_payer, signers: vec![], output: Output::Serialize }
+            }
+            AsyncProcessing::Instructions(client, primary_signer) => {
+                AsyncMode { client, primary_signer, fee_payer: None, signers: vec![], output: Output::Instructions }
+            }
+        }
+    }
+}
+
+/// A [TransactionProcessor] that can also fetch its online arguments with a nonblocking client.
+#[async_trait]
+pub trait AsyncTransactionProcessor: TransactionProcessor + Sync {
+    /// [TransactionProcessor::get_online_args], with a nonblocking client.
+    async fn get_online_args_async(&self, client: &RpcClient) -> Result<Self::OnlineArgs, TransactionProcessorError>;
+
+    /// [TransactionProcessor::process], awaiting the cluster instead of blocking on it.
+    async fn process_async(
+        &self,
+        mode: AsyncProcessing,
+        extra_signers: &mut Vec<Box<dyn Signer + Send + Sync>>,
+    ) -> Result<ProcessedTransaction, TransactionProcessorError> {
+        self.process_async_with_reporter(mode, extra_signers, None).await
+    }
+
+    /// [TransactionProcessor::process_with_reporter], awaiting the cluster instead of blocking on it.
+    async fn process_async_with_reporter(
+        &self,
+        mode: AsyncProcessing,
+        extra_signers: &mut Vec<Box<dyn Signer + Send + Sync>>,
+        reporter: Option<&(dyn TxReporter + Sync)>,
+    ) -> Result<ProcessedTransaction, TransactionProcessorError> {
+        let reporter = reporter.unwrap_or(&StdoutReporter);
+        let AsyncMode { client, primary_signer, fee_payer, signers, output } = AsyncMode::from(mode);
+        // The online arguments are consumed within the block, so that they're never held
+        // across an await, and don't need to be [Send].
+        let mut prepared = {
+            let online_args = self.get_online_args_async(&client).await?;
+            PreparedTransaction::new(self, online_args, &primary_signer)?
+        };
+        extra_signers.extend(signers);
+        add_compute_budget(self, &client, &mut prepared).await?;
+        let payer = prepared.payer(&primary_signer, fee_payer);
+        match output {
+            Output::Send(blockhash, config) => {
+                let recent_blockhash = blockhash_async(&mut prepared, blockhash, &client).await?;
+                let tx = prepared.sign(&payer, &signer_refs(extra_signers), recent_blockhash)?;
+                let signature = client.send_transaction_with_config(&tx, config).await
+                    .map_err(|e| report_failure(e, &tx, reporter))?;
+                reporter.on_sent(&signature);
+                Ok(ProcessedTransaction::Execution {
+                    name: prepared.name,
+                    signature: signature.to_string(),
+                    metadata: prepared.metadata,
+                    confirmation_slot: None,
+                    transaction_error: None,
+                })
+            }
+            Output::Confirm(options) => {
+                let (signature, slot, transaction_error) = send_and_confirm_async(
+                    &client,
+                    |recent_blockhash| prepared.sign(&payer, &signer_refs(extra_signers), recent_blockhash),
+                    &options,
+                    reporter,
+                ).await?;
+                Ok(ProcessedTransaction::Execution {
+                    name: prepared.name,
+                    signature: signature.to_string(),
+                    metadata: prepared.metadata,
+                    confirmation_slot: Some(slot),
+                    transaction_error,
+                })
+            }
+            Output::Simulate(blockhash, config) => {
+                let recent_blockhash = blockhash_async(&mut prepared, blockhash, &client).await?;
+                let tx = prepared.sign(&payer, &signer_refs(extra_signers), recent_blockhash)?;
+                let response = client.simulate_transaction_with_config(&tx, config).await
+                    .map_err(|e| report_failure(e, &tx, reporter))?;
+                Ok(prepared.simulation(response))
+            }
+            Output::Sign(blockhash) => {
+                let recent_blockhash = blockhash_async(&mut prepared, blockhash, &client).await?;
+                let tx = prepared.sign(&payer, &signer_refs(extra_signers), recent_blockhash)?;
+                prepared.signed_serialized(&tx)
+            }
+            Output::Serialize => prepared.unsigned_serialized(&payer),
+            Output::Instructions => prepared.instruction_set(),
+        }
+    }
+}
+
+/// Prepend the processor's compute budget instructions, awaiting an automatic unit price.
+async fn add_compute_budget<P: TransactionProcessor + ?Sized>(
+    processor: &P,
+    client: &RpcClient,
+    prepared: &mut PreparedTransaction,
+) -> Result<(), TransactionProcessorError> {
+    let budget = match processor.compute_budget() {
+        Some(budget) => budget,
+        None => return Ok(()),
+    };
+    let unit_price = match budget.unit_price {
+        None => None,
+        Some(UnitPrice::Fixed(micro_lamports)) => Some(micro_lamports),
+        Some(UnitPrice::Auto { percentile }) => {
+            Some(estimate_priority_fee_async(client, &prepared.ixs, percentile).await?)
+        }
+    };
+    prepared.prepend_compute_budget(&budget, unit_price);
+    Ok(())
+}
+
+/// [PreparedTransaction::blockhash], awaiting the latest blockhash or the durable nonce.
+async fn blockhash_async(
+    prepared: &mut PreparedTransaction,
+    blockhash: Blockhash,
+    client: &RpcClient,
+) -> Result<Hash, TransactionProcessorError> {
+    match blockhash {
+        Blockhash::Latest => client.get_latest_blockhash().await
+            .map_err(TransactionProcessorError::ClientError),
+        Blockhash::Given(hash) => Ok(hash),
+        Blockhash::Nonce { account, authority, nonce } => {
+            prepared.advance_nonce(&account, &authority);
+            match nonce {
+                Some(nonce) => Ok(nonce),
+                None => fetch_nonce_async(client, &account, &authority).await,
+            }
+        }
+    }
+}
+
+/// [crate::fetch_nonce], with a nonblocking client.
+pub async fn fetch_nonce_async(
+    client: &RpcClient,
+    nonce_account: &Pubkey,
+    nonce_authority: &Pubkey,
+) -> Result<Hash, TransactionProcessorError> {
+    let account = client.get_account_with_commitment(nonce_account, client.commitment()).await
+        .map_err(TransactionProcessorError::ClientError)?
+        .value;
+    nonce_from_account(nonce_account, nonce_authority, account)
+}
+
+/// The confirmation loop of [crate::Processing::ExecuteAndConfirm], awaiting the cluster
+/// and sleeping without blocking the runtime.
+async fn send_and_confirm_async(
+    client: &RpcClient,
+    sign: impl Fn(Hash) -> Result<VersionedTransaction, TransactionProcessorError>,
+    options: &ConfirmOptions,
+    reporter: &(dyn TxReporter + Sync),
+) -> Result<(Signature, Slot, Option<TransactionError>), TransactionProcessorError> {
+    let start = Instant::now();
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let (recent_blockhash, last_valid_block_height) = client
+            .get_latest_blockhash_with_commitment(client.commitment()).await
+            .map_err(TransactionProcessorError::ClientError)?;
+        let tx = sign(recent_blockhash)?;
+        let signature = client.send_transaction(&tx).await
+            .map_err(|e| report_failure(e, &tx, reporter))?;
+        reporter.on_sent(&signature);
+        loop {
+            if let Some(confirmed) = confirmed_status(client, &signature, options).await? {
+                return Ok(confirmed);
+            }
+            let block_height = client.get_block_height().await
+                .map_err(TransactionProcessorError::ClientError)?;
+            if block_height > last_valid_block_height {
+                // The transaction may have landed since its status was checked. If it
+                // still hasn't, it can't land anymore.
+                match confirmed_status(client, &signature, options).await? {
+                    Some(confirmed) => return Ok(confirmed),
+                    None => break,
+                }
+            }
+            if start.elapsed() >= options.timeout {
+                return Err(TransactionProcessorError::ConfirmationTimeout {
+                    signature,
+                    timeout: options.timeout,
+                });
+            }
+            tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+        }
+        if attempts >= options.max_attempts {
+            return Err(TransactionProcessorError::BlockhashExpired { signature, attempts });
+        }
+    }
+}
+
+/// The slot and result of the transaction, once it satisfies the commitment of `options`.
+async fn confirmed_status(
+    client: &RpcClient,
+    signature: &Signature,
+    options: &ConfirmOptions,
+) -> Result<Option<(Signature, Slot, Option<TransactionError>)>, TransactionProcessorError> {
+    let status = client.get_signature_statuses(&[*signature]).await
+        .map_err(TransactionProcessorError::ClientError)?
+        .value
+        .pop()
+        .flatten();
+    Ok(status
+        .filter(|status| status.satisfies_commitment(options.commitment))
+        .map(|status| (*signature, status.slot, status.err)))
+}
+
+/// [crate::estimate_priority_fee], with a nonblocking client.
+pub async fn estimate_priority_fee_async(
+    client: &RpcClient,
+    ixs: &[Instruction],
+    percentile: u8,
+) -> Result<u64, TransactionProcessorError> {
+    let fees: Vec<RecentPrioritizationFee> = client.send(
+        RpcRequest::Custom { method: "getRecentPrioritizationFees" },
+        json!([fee_accounts(ixs)]),
+    ).await.map_err(TransactionProcessorError::ClientError)?;
+    Ok(fee_percentile(fees, percentile))
+}
+
+fn signer_refs(signers: &[Box<dyn Signer + Send + Sync>]) -> Vec<&dyn Signer> {
+    signers.iter().map(|signer| signer.as_ref() as &dyn Signer).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use anchor_client::solana_client::mock_sender::Mocks;
+    use solana_sdk::bs58;
+    use solana_sdk::nonce::state::DurableNonce;
+    use solana_sdk::signature::Keypair;
+    use crate::test_processor::memo;
+    use super::*;
+
+    // The futures can be spawned, e.g. from an axum handler.
+    fn spawnable<F: std::future::Future + Send>(future: F) -> F {
+        future
+    }
+
+    #[tokio::test]
+    async fn execution() {
+        let memo = memo("Foobar");
+        let mut extra_signers = vec![];
+        let response = spawnable(memo.process_async(
+            AsyncProcessing::Execute(RpcClient::new_mock("succeeds".to_string()), Box::new(Keypair::new()), None),
+            &mut extra_signers,
+        )).await.unwrap();
+        if let ProcessedTransaction::Execution { name, .. } = response {
+            assert_eq!(name, "memo: Foobar".to_string());
+        } else {
+            panic!("wrong processing");
+        }
+        assert_eq!(memo.online_args_calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn simulation() {
+        let response = memo("Foobar").process_async(
+            AsyncProcessing::Simulate(RpcClient::new_mock("succeeds".to_string()), Box::new(Keypair::new()), None),
+            &mut vec![],
+        ).await.unwrap();
+        if let ProcessedTransaction::Simulation { name, simulation_result, .. } = response {
+            assert_eq!(name, "memo: Foobar".to_string());
+            assert!(simulation_result.err.is_none());
+        } else {
+            panic!("wrong processing");
+        }
+    }
+
+    #[tokio::test]
+    async fn confirm_with_fee_payer() {
+        let signer = Keypair::new();
+        let fee_payer = Keypair::new();
+        let (signer_pubkey, fee_payer_pubkey) = (signer.pubkey(), fee_payer.pubkey());
+        let response = spawnable(memo("Foobar").process_async(
+            AsyncProcessing::ExecuteAndConfirm(
+                RpcClient::new_mock("succeeds".to_string()),
+                Box::new(signer),
+                Some(Box::new(fee_payer)),
+                crate::ConfirmOptions::default(),
+            ),
+            &mut vec![],
+        )).await.unwrap();
+        if let ProcessedTransaction::Execution { metadata, confirmation_slot, transaction_error, .. } = response {
+            assert!(confirmation_slot.is_some());
+            assert_eq!(transaction_error, None);
+            assert_eq!(metadata["primary_signer"], json!(signer_pubkey.to_string()));
+            assert_eq!(metadata["fee_payer"], json!(fee_payer_pubkey.to_string()));
+        } else {
+            panic!("wrong processing");
+        }
+    }
+
+    #[tokio::test]
+    async fn sign_with_nonce() {
+        let signer = Keypair::new();
+        let authority = signer.pubkey();
+        let nonce_account = Pubkey::new_unique();
+        let blockhash = Hash::new_unique();
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetAccountInfo,
+            crate::nonce::tests::nonce_account_response(crate::nonce::tests::initialized(authority, blockhash)),
+        );
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        let response = spawnable(memo("Foobar").process_async(
+            AsyncProcessing::SignWithNonce(client, Box::new(signer), None, nonce_account, authority),
+            &mut vec![],
+        )).await.unwrap();
+        let transaction = match response {
+            ProcessedTransaction::SignedSerialized { transaction, .. } => transaction,
+            _ => panic!("wrong processing"),
+        };
+        let tx: VersionedTransaction = bincode::deserialize(&bs58::decode(transaction).into_vec().unwrap()).unwrap();
+        assert_eq!(*tx.message.recent_blockhash(), *DurableNonce::from_blockhash(&blockhash).as_hash());
+        assert_eq!(tx.message.instructions().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn instructions() {
+        let response = memo("Foobar").process_async(
+            AsyncProcessing::Instructions(RpcClient::new_mock("succeeds".to_string()), Pubkey::new_unique()),
+            &mut vec![],
+        ).await.unwrap();
+        if let ProcessedTransaction::InstructionSet { instruction_names, .. } = response {
+            assert_eq!(instruction_names, vec!["memo".to_string()]);
+        } else {
+            panic!("wrong processing");
+        }
+    }
+
+    #[test]
+    fn offline_without_a_runtime() {
+        let response = memo("Foobar").process(
+            crate::Processing::OfflineSign((), Box::new(Keypair::new()), None, solana_sdk::hash::Hash::new_unique()),
+            &mut vec![],
+        ).unwrap();
+        if let ProcessedTransaction::SignedSerialized { name, .. } = response {
+            assert_eq!(name, "memo: Foobar".to_string());
+        } else {
+            panic!("wrong processing");
+        }
+    }
+}
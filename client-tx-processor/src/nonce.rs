@@ -0,0 +1,122 @@
+use anchor_client::solana_client::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::nonce::state::{State, Versions};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction;
+use solana_sdk::system_program;
+use crate::error::TransactionProcessorError;
+
+/// Fetch the durable nonce stored in `nonce_account`, to use as the blockhash of a
+/// transaction that advances it. The account's authority must be `nonce_authority`.
+pub fn fetch_nonce(
+    client: &RpcClient,
+    nonce_account: &Pubkey,
+    nonce_authority: &Pubkey,
+) -> Result<Hash, TransactionProcessorError> {
+    let account = client.get_account_with_commitment(nonce_account, client.commitment())
+        .map_err(TransactionProcessorError::ClientError)?
+        .value;
+    nonce_from_account(nonce_account, nonce_authority, account)
+}
+
+/// The durable nonce in the fetched `account` at `nonce_account`, see [fetch_nonce].
+pub(crate) fn nonce_from_account(
+    nonce_account: &Pubkey,
+    nonce_authority: &Pubkey,
+    account: Option<Account>,
+) -> Result<Hash, TransactionProcessorError> {
+    let invalid = |message: String| TransactionProcessorError::InvalidNonceAccount {
+        address: *nonce_account,
+        message,
+    };
+    let account = account
+        .ok_or(TransactionProcessorError::NonceAccountNotFound { address: *nonce_account })?;
+    if account.owner != system_program::id() {
+        return Err(invalid(format!("owned by {}, not the system program", account.owner)));
+    }
+    let versions: Versions = bincode::deserialize(&account.data)
+        .map_err(|e| invalid(format!("not a nonce account: {}", e)))?;
+    match versions.state() {
+        State::Uninitialized => Err(TransactionProcessorError::NonceAccountNotInitialized {
+            address: *nonce_account,
+        }),
+        State::Initialized(data) if data.authority != *nonce_authority => {
+            Err(invalid(format!("its authority is {}, not {}", data.authority, nonce_authority)))
+        }
+        State::Initialized(data) => Ok(data.blockhash()),
+    }
+}
+
+/// Put the instruction advancing the nonce first, where the runtime expects it.
+pub(crate) fn prepend_advance_nonce(
+    names: Vec<String>,
+    ixs: Vec<Instruction>,
+    nonce_account: &Pubkey,
+    nonce_authority: &Pubkey,
+) -> (Vec<String>, Vec<Instruction>) {
+    let mut nonce_names = vec!["advance_nonce_account".to_string()];
+    let mut nonce_ixs = vec![system_instruction::advance_nonce_account(nonce_account, nonce_authority)];
+    nonce_names.extend(names);
+    nonce_ixs.extend(ixs);
+    (nonce_names, nonce_ixs)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+    use anchor_client::solana_client::mock_sender::Mocks;
+    use anchor_client::solana_client::rpc_request::RpcRequest;
+    use serde_json::{json, Value};
+    use solana_sdk::nonce::state::{Data, DurableNonce};
+    use super::*;
+
+    /// A `getAccountInfo` response for a nonce account in `state`.
+    pub(crate) fn nonce_account_response(state: State) -> Value {
+        let data = bincode::serialize(&Versions::new(state)).unwrap();
+        json!({
+            "context": { "slot": 1 },
+            "value": {
+                "lamports": 1_447_680,
+                "data": [base64::encode(data), "base64"],
+                "owner": system_program::id().to_string(),
+                "executable": false,
+                "rentEpoch": 0,
+            },
+        })
+    }
+
+    pub(crate) fn initialized(authority: Pubkey, nonce: Hash) -> State {
+        State::Initialized(Data::new(authority, DurableNonce::from_blockhash(&nonce), 5_000))
+    }
+
+    fn client(response: Value) -> RpcClient {
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(RpcRequest::GetAccountInfo, response);
+        RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+    }
+
+    #[test]
+    fn fetches_nonce() {
+        let nonce_account = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let blockhash = Hash::new_unique();
+        let nonce = DurableNonce::from_blockhash(&blockhash);
+
+        let client = client(nonce_account_response(initialized(authority, blockhash)));
+        assert_eq!(fetch_nonce(&client, &nonce_account, &authority).unwrap(), *nonce.as_hash());
+
+        let client = client(nonce_account_response(initialized(authority, blockhash)));
+        let err = fetch_nonce(&client, &nonce_account, &Pubkey::new_unique()).unwrap_err();
+        assert!(matches!(err, TransactionProcessorError::InvalidNonceAccount { .. }), "{}", err);
+
+        let client = client(nonce_account_response(State::Uninitialized));
+        let err = fetch_nonce(&client, &nonce_account, &authority).unwrap_err();
+        assert!(matches!(err, TransactionProcessorError::NonceAccountNotInitialized { address } if address == nonce_account));
+
+        let client = client(json!({ "context": { "slot": 1 }, "value": null }));
+        let err = fetch_nonce(&client, &nonce_account, &authority).unwrap_err();
+        assert!(matches!(err, TransactionProcessorError::NonceAccountNotFound { address } if address == nonce_account));
+    }
+}
@@ -0,0 +1,317 @@
+/// The pipeline shared by every [Processing] mode. The online arguments are turned into a
+/// [PreparedTransaction] the same way in every mode, and the modes only differ in what
+/// they do with it afterwards, see [Output]. A new mode only needs a new [Mode] arm.
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_client::rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig};
+use anchor_client::solana_client::rpc_response::{Response, RpcSimulateTransactionResult};
+use serde_json::{json, Map, Value};
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::bs58;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use solana_sdk::signer::signers::Signers;
+use solana_sdk::transaction::VersionedTransaction;
+use crate::nonce::{fetch_nonce, prepend_advance_nonce};
+use crate::{
+    estimate_priority_fee, serialize_ix, serialize_transaction, signed_transaction,
+    unsigned_message, ComputeBudget, ConfirmOptions, ProcessedTransaction, Processing,
+    SimulationReport, TransactionProcessor, TransactionProcessorError, UnitPrice,
+};
+
+/// Everything about the transaction that's derived from the online arguments.
+pub(crate) struct PreparedTransaction {
+    pub name: String,
+    pub metadata: Map<String, Value>,
+    pub lookup_tables: Option<Vec<AddressLookupTableAccount>>,
+    pub names: Vec<String>,
+    pub ixs: Vec<Instruction>,
+}
+
+impl PreparedTransaction {
+    /// Everything but the compute budget, see [PreparedTransaction::add_compute_budget].
+    pub fn new<P: TransactionProcessor + ?Sized>(
+        processor: &P,
+        online_args: P::OnlineArgs,
+        primary_signer: &Pubkey,
+    ) -> Result<Self, TransactionProcessorError> {
+        let remaining_args = processor.calc_remaining_args(&online_args, primary_signer)?;
+        let name = processor.name(primary_signer, &online_args, &remaining_args);
+        let metadata = processor.metadata(primary_signer, &online_args, &remaining_args);
+        let lookup_tables = processor.lookup_tables(&online_args);
+        let (names, ixs) = processor.named_instructions(primary_signer, online_args, remaining_args)?
+            .into_iter()
+            .unzip();
+        Ok(Self { name, metadata, lookup_tables, names, ixs })
+    }
+
+    /// Prepend the processor's [TransactionProcessor::compute_budget] instructions.
+    /// An automatic [UnitPrice] is estimated with `client`, and is an error without one.
+    pub fn add_compute_budget<P: TransactionProcessor + ?Sized>(
+        &mut self,
+        processor: &P,
+        client: Option<&RpcClient>,
+    ) -> Result<(), TransactionProcessorError> {
+        let budget = match processor.compute_budget() {
+            Some(budget) => budget,
+            None => return Ok(()),
+        };
+        let unit_price = match (budget.unit_price, client) {
+            (None, _) => None,
+            (Some(UnitPrice::Fixed(micro_lamports)), _) => Some(micro_lamports),
+            (Some(UnitPrice::Auto { percentile }), Some(client)) => {
+                Some(estimate_priority_fee(client, &self.ixs, percentile)?)
+            }
+            (Some(UnitPrice::Auto { .. }), None) => return Err(TransactionProcessorError::Other(
+                "an automatic compute unit price can't be estimated offline, use a fixed price".into()
+            )),
+        };
+        self.prepend_compute_budget(&budget, unit_price);
+        Ok(())
+    }
+
+    /// Prepend the `budget`'s instructions, with the already resolved `unit_price`.
+    pub fn prepend_compute_budget(&mut self, budget: &ComputeBudget, unit_price: Option<u64>) {
+        let (names, ixs) = budget.prepend(
+            unit_price,
+            std::mem::take(&mut self.names),
+            std::mem::take(&mut self.ixs),
+            &mut self.metadata,
+        );
+        self.names = names;
+        self.ixs = ixs;
+    }
+
+    /// The payer of the transaction: the separate `fee_payer` if there is one, recorded in
+    /// the metadata along with the primary signer, or else the primary signer.
+    pub fn payer(&mut self, primary_signer: &Pubkey, fee_payer: Option<Pubkey>) -> Pubkey {
+        match fee_payer {
+            None => *primary_signer,
+            Some(fee_payer) => {
+                self.metadata.insert("primary_signer".to_string(), Value::String(primary_signer.to_string()));
+                self.metadata.insert("fee_payer".to_string(), Value::String(fee_payer.to_string()));
+                fee_payer
+            }
+        }
+    }
+
+    /// The hash to sign the transaction with. With a durable nonce, the instruction advancing
+    /// it is prepended first.
+    pub fn blockhash(
+        &mut self,
+        blockhash: Blockhash,
+        client: Option<&RpcClient>,
+    ) -> Result<Hash, TransactionProcessorError> {
+        match blockhash {
+            Blockhash::Latest => online(client).get_latest_blockhash()
+                .map_err(TransactionProcessorError::ClientError),
+            Blockhash::Given(hash) => Ok(hash),
+            Blockhash::Nonce { account, authority, nonce } => {
+                self.advance_nonce(&account, &authority);
+                match nonce {
+                    Some(nonce) => Ok(nonce),
+                    None => fetch_nonce(online(client), &account, &authority),
+                }
+            }
+        }
+    }
+
+    /// Prepend the instruction advancing the durable nonce in `account`.
+    pub fn advance_nonce(&mut self, account: &Pubkey, authority: &Pubkey) {
+        let (names, ixs) = prepend_advance_nonce(
+            std::mem::take(&mut self.names),
+            std::mem::take(&mut self.ixs),
+            account,
+            authority,
+        );
+        self.names = names;
+        self.ixs = ixs;
+    }
+
+    /// See [signed_transaction].
+    pub fn sign<T: Signers>(
+        &self,
+        payer: &Pubkey,
+        signers: &T,
+        recent_blockhash: Hash,
+    ) -> Result<VersionedTransaction, TransactionProcessorError> {
+        signed_transaction(&self.ixs, payer, signers, recent_blockhash, &self.lookup_tables)
+    }
+
+    /// The simulation, with its [SimulationReport]. The units consumed are also recorded in
+    /// the metadata as `units_consumed`, to log the same way as the execution that follows.
+    pub fn simulation(mut self, response: Response<RpcSimulateTransactionResult>) -> ProcessedTransaction {
+        let simulation_report = SimulationReport::from(&response.value);
+        if let Some(units) = simulation_report.units_consumed {
+            self.metadata.insert("units_consumed".to_string(), json!(units));
+        }
+        ProcessedTransaction::Simulation {
+            name: self.name,
+            metadata: self.metadata,
+            simulation_result: response.value,
+            simulation_context: response.context,
+            simulation_report,
+        }
+    }
+
+    pub fn signed_serialized(self, tx: &VersionedTransaction) -> Result<ProcessedTransaction, TransactionProcessorError> {
+        Ok(ProcessedTransaction::SignedSerialized {
+            transaction: serialize_transaction(tx)?,
+            name: self.name,
+            metadata: self.metadata,
+        })
+    }
+
+    pub fn unsigned_serialized(self, payer: &Pubkey) -> Result<ProcessedTransaction, TransactionProcessorError> {
+        let message = unsigned_message(&self.ixs, payer, &self.lookup_tables)?;
+        Ok(ProcessedTransaction::UnsignedSerialized {
+            transaction: bs58::encode(message.serialize()).into_string(),
+            name: self.name,
+            metadata: self.metadata,
+        })
+    }
+
+    pub fn instruction_set(self) -> Result<ProcessedTransaction, TransactionProcessorError> {
+        Ok(ProcessedTransaction::InstructionSet {
+            instructions: self.ixs.iter().map(serialize_ix).collect::<Result<_, _>>()?,
+            instruction_names: self.names,
+            name: self.name,
+            metadata: self.metadata,
+        })
+    }
+}
+
+/// A [Processing] mode, taken apart into what the pipeline needs.
+pub(crate) struct Mode<T> {
+    pub args: Args<T>,
+    pub primary_signer: Pubkey,
+    pub fee_payer: Option<Pubkey>,
+    /// The primary signer and fee payer, in the modes that sign.
+    pub signers: Vec<Box<dyn Signer>>,
+    pub output: Output,
+}
+
+/// Where the online arguments come from.
+pub(crate) enum Args<T> {
+    Online(RpcClient),
+    Offline(T),
+}
+
+/// Where the hash a transaction is signed with comes from.
+pub(crate) enum Blockhash {
+    Latest,
+    Given(Hash),
+    /// A durable nonce, fetched from the nonce account without a `nonce`.
+    Nonce { account: Pubkey, authority: Pubkey, nonce: Option<Hash> },
+}
+
+/// What's done with the [PreparedTransaction].
+pub(crate) enum Output {
+    Send(Blockhash, RpcSendTransactionConfig),
+    /// Always with the latest blockhash, which may be fetched again to resend.
+    Confirm(ConfirmOptions),
+    Simulate(Blockhash, RpcSimulateTransactionConfig),
+    Sign(Blockhash),
+    Serialize,
+    Instructions,
+}
+
+/// What `RpcClient::send_transaction` sends with, for a client with `commitment`.
+pub(crate) fn send_config(commitment: CommitmentConfig) -> RpcSendTransactionConfig {
+    RpcSendTransactionConfig {
+        preflight_commitment: Some(commitment.commitment),
+        ..Default::default()
+    }
+}
+
+/// What `RpcClient::simulate_transaction` simulates with, for a client with `commitment`.
+pub(crate) fn simulate_config(commitment: CommitmentConfig) -> RpcSimulateTransactionConfig {
+    RpcSimulateTransactionConfig {
+        commitment: Some(commitment),
+        ..Default::default()
+    }
+}
+
+/// The client of an [Args::Online] mode. Only the online modes have outputs or blockhashes
+/// that need one.
+pub(crate) fn online(client: Option<&RpcClient>) -> &RpcClient {
+    client.expect("only the online modes make requests")
+}
+
+impl<T> From<Processing<T>> for Mode<T> {
+    fn from(mode: Processing<T>) -> Self {
+        /// A mode that signs with `signer` and the optional `fee_payer`.
+        fn signing<T>(
+            args: Args<T>,
+            signer: Box<dyn Signer>,
+            fee_payer: Option<Box<dyn Signer>>,
+            output: Output,
+        ) -> Mode<T> {
+            Mode {
+                args,
+                primary_signer: signer.pubkey(),
+                fee_payer: fee_payer.as_ref().map(|fee_payer| fee_payer.pubkey()),
+                signers: std::iter::once(signer).chain(fee_payer).collect(),
+                output,
+            }
+        }
+        /// A mode that only needs the signers' pubkeys.
+        fn unsigned<T>(args: Args<T>, primary_signer: Pubkey, fee_payer: Option<Pubkey>, output: Output) -> Mode<T> {
+            Mode { args, primary_signer, fee_payer, signers: vec![], output }
+        }
+
+        match mode {
+            Processing::Execute(client, signer, fee_payer) => {
+                let output = Output::Send(Blockhash::Latest, send_config(client.commitment()));
+                signing(Args::Online(client), signer, fee_payer, output)
+            }
+            Processing::ExecuteWithConfig(client, signer, fee_payer, config) => {
+                signing(Args::Online(client), signer, fee_payer, Output::Send(Blockhash::Latest, config))
+            }
+            Processing::ExecuteAndConfirm(client, signer, fee_payer, options) => {
+                signing(Args::Online(client), signer, fee_payer, Output::Confirm(options))
+            }
+            Processing::Simulate(client, signer, fee_payer) => {
+                let output = Output::Simulate(Blockhash::Latest, simulate_config(client.commitment()));
+                signing(Args::Online(client), signer, fee_payer, output)
+            }
+            Processing::SimulateWithConfig(client, signer, fee_payer, config) => {
+                signing(Args::Online(client), signer, fee_payer, Output::Simulate(Blockhash::Latest, config))
+            }
+            Processing::Sign(client, signer, fee_payer) => {
+                signing(Args::Online(client), signer, fee_payer, Output::Sign(Blockhash::Latest))
+            }
+            Processing::ExecuteWithNonce(client, signer, fee_payer, account, authority) => {
+                let nonce = Blockhash::Nonce { account, authority, nonce: None };
+                let output = Output::Send(nonce, send_config(client.commitment()));
+                signing(Args::Online(client), signer, fee_payer, output)
+            }
+            Processing::SignWithNonce(client, signer, fee_payer, account, authority) => {
+                let nonce = Blockhash::Nonce { account, authority, nonce: None };
+                signing(Args::Online(client), signer, fee_payer, Output::Sign(nonce))
+            }
+            Processing::Serialize(client, primary_signer, fee_payer) => {
+                unsigned(Args::Online(client), primary_signer, fee_payer, Output::Serialize)
+            }
+            Processing::Instructions(client, primary_signer) => {
+                unsigned(Args::Online(client), primary_signer, None, Output::Instructions)
+            }
+            Processing::OfflineSign(online_args, signer, fee_payer, recent_blockhash) => {
+                let output = Output::Sign(Blockhash::Given(recent_blockhash));
+                signing(Args::Offline(online_args), signer, fee_payer, output)
+            }
+            Processing::OfflineSignWithNonce(online_args, signer, fee_payer, account, authority, nonce) => {
+                let nonce = Blockhash::Nonce { account, authority, nonce: Some(nonce) };
+                signing(Args::Offline(online_args), signer, fee_payer, Output::Sign(nonce))
+            }
+            Processing::OfflineSerialize(online_args, primary_signer, fee_payer) => {
+                unsigned(Args::Offline(online_args), primary_signer, fee_payer, Output::Serialize)
+            }
+            Processing::OfflineInstructions(online_args, primary_signer) => {
+                unsigned(Args::Offline(online_args), primary_signer, None, Output::Instructions)
+            }
+        }
+    }
+}
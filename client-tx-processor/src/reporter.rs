@@ -0,0 +1,54 @@
+use anchor_client::solana_client::client_error::ClientError;
+use solana_sdk::signature::Signature;
+
+/// Where [crate::TransactionProcessor::process_with_reporter] reports what happened
+/// to sent transactions, so library users and JSON output modes can keep stdout clean.
+/// Every method does nothing by default.
+pub trait TxReporter {
+    /// The transaction was accepted by the RPC node.
+    #[allow(unused)]
+    fn on_sent(&self, signature: &Signature) {}
+
+    /// Sending or simulating the transaction failed.
+    #[allow(unused)]
+    fn on_failed(&self, signature: &Signature, error: &ClientError) {}
+
+    /// The logs of a failed preflight simulation.
+    #[allow(unused)]
+    fn on_preflight_logs(&self, logs: &[String]) {}
+}
+
+/// Prints preflight simulation logs to stdout, one per line.
+/// This is what's used when no reporter is given.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutReporter;
+
+impl TxReporter for StdoutReporter {
+    fn on_preflight_logs(&self, logs: &[String]) {
+        logs.iter().for_each(|log| println!("{}", log))
+    }
+}
+
+/// Reports nothing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SilentReporter;
+
+impl TxReporter for SilentReporter {}
+
+/// Reports through the [log] crate, e.g. to stderr with a CLI's verbosity flags.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogReporter;
+
+impl TxReporter for LogReporter {
+    fn on_sent(&self, signature: &Signature) {
+        log::info!("Sent transaction {}", signature);
+    }
+
+    fn on_failed(&self, signature: &Signature, error: &ClientError) {
+        log::error!("Transaction {} failed: {}", signature, error);
+    }
+
+    fn on_preflight_logs(&self, logs: &[String]) {
+        logs.iter().for_each(|line| log::info!("{}", line))
+    }
+}
@@ -0,0 +1,82 @@
+/// The outcome of a simulation, extracted from the RPC node's [RpcSimulateTransactionResult]
+/// so that callers don't need to unpack it themselves.
+use std::str::FromStr;
+use anchor_client::solana_client::rpc_response::RpcSimulateTransactionResult;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::TransactionError;
+use solana_sdk::transaction_context::TransactionReturnData;
+
+/// What a simulated transaction did, see [crate::ProcessedTransaction::Simulation].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationReport {
+    /// The compute units the transaction consumed, if the RPC node reports them.
+    pub units_consumed: Option<u64>,
+    pub succeeded: bool,
+    /// Why the transaction failed, if it did.
+    pub error: Option<TransactionError>,
+    pub logs: Vec<String>,
+    /// The data set by the last program to call `set_return_data`, decoded.
+    pub return_data: Option<TransactionReturnData>,
+}
+
+impl From<&RpcSimulateTransactionResult> for SimulationReport {
+    fn from(result: &RpcSimulateTransactionResult) -> Self {
+        // The RPC node always encodes return data in base64, so anything
+        // that doesn't decode is left out rather than failing the simulation.
+        let return_data = result.return_data.as_ref().and_then(|return_data| {
+            Some(TransactionReturnData {
+                program_id: Pubkey::from_str(&return_data.program_id).ok()?,
+                data: base64::decode(&return_data.data.0).ok()?,
+            })
+        });
+        Self {
+            units_consumed: result.units_consumed,
+            succeeded: result.err.is_none(),
+            error: result.err.clone(),
+            logs: result.logs.clone().unwrap_or_default(),
+            return_data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn from_simulation_result() {
+        let program_id = Pubkey::new_unique();
+        let result: RpcSimulateTransactionResult = serde_json::from_value(json!({
+            "err": null,
+            "logs": ["Program log: hello"],
+            "accounts": null,
+            "unitsConsumed": 1_200,
+            "returnData": {
+                "programId": program_id.to_string(),
+                "data": [base64::encode([1, 2, 3]), "base64"],
+            },
+        })).unwrap();
+        assert_eq!(SimulationReport::from(&result), SimulationReport {
+            units_consumed: Some(1_200),
+            succeeded: true,
+            error: None,
+            logs: vec!["Program log: hello".to_string()],
+            return_data: Some(TransactionReturnData { program_id, data: vec![1, 2, 3] }),
+        });
+
+        let result = RpcSimulateTransactionResult {
+            err: Some(TransactionError::AccountInUse),
+            logs: None,
+            accounts: None,
+            units_consumed: None,
+            return_data: None,
+        };
+        let report = SimulationReport::from(&result);
+        assert!(!report.succeeded);
+        assert_eq!(report.error, Some(TransactionError::AccountInUse));
+        assert!(report.logs.is_empty());
+        assert_eq!(report.units_consumed, None);
+    }
+}
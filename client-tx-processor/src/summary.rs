@@ -0,0 +1,246 @@
+/// Human readable summaries of a [ProcessedTransaction], so that CLIs and servers don't need
+/// to print every variant by hand.
+use std::fmt;
+use serde_json::{Map, Value};
+use solana_sdk::transaction::TransactionError;
+use crate::ProcessedTransaction;
+
+/// How much of a [ProcessedTransaction] its [ProcessedTransaction::summary] shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SummaryOptions {
+    /// Everything on a single line, without the metadata or logs, e.g. for server logs.
+    pub one_line: bool,
+    /// Encoded transactions and instructions longer than this are truncated.
+    pub payload_width: usize,
+    /// How many lines of simulation logs to show.
+    pub log_lines: usize,
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        Self {
+            one_line: false,
+            payload_width: 64,
+            log_lines: 10,
+        }
+    }
+}
+
+impl SummaryOptions {
+    /// The defaults, on a single line.
+    pub fn one_line() -> Self {
+        Self { one_line: true, ..Default::default() }
+    }
+}
+
+impl ProcessedTransaction {
+    /// The name and outcome of the transaction: its signature, encoded payload, or instruction
+    /// names, along with the pretty-printed metadata. Simulations show whether they failed,
+    /// the compute units they consumed, and the first of their logs.
+    pub fn summary(&self, options: &SummaryOptions) -> String {
+        let mut lines = vec![];
+        match self {
+            ProcessedTransaction::Execution { signature, name, metadata, confirmation_slot, transaction_error } => {
+                lines.push(format!("Executed {}", name));
+                lines.push(format!("Signature: {}", signature));
+                if let Some(slot) = confirmation_slot {
+                    lines.push(format!("Confirmed slot: {}", slot));
+                    lines.push(format!("Result: {}", outcome(transaction_error.as_ref())));
+                }
+                if options.one_line {
+                    return lines.join(", ");
+                }
+                push_metadata(&mut lines, metadata);
+            }
+            ProcessedTransaction::Simulation { name, metadata, simulation_report, .. } => {
+                lines.push(format!("Simulated {}", name));
+                lines.push(format!("Result: {}", outcome(simulation_report.error.as_ref())));
+                if let Some(units) = simulation_report.units_consumed {
+                    lines.push(format!("Units consumed: {}", units));
+                }
+                if options.one_line {
+                    return lines.join(", ");
+                }
+                let logs = &simulation_report.logs;
+                if !logs.is_empty() {
+                    lines.push("Logs:".to_string());
+                    lines.extend(logs.iter().take(options.log_lines).map(|log| format!("  {}", log)));
+                    if logs.len() > options.log_lines {
+                        lines.push(format!("  ... {} more", logs.len() - options.log_lines));
+                    }
+                }
+                push_metadata(&mut lines, metadata);
+            }
+            ProcessedTransaction::SignedSerialized { transaction, name, metadata } => {
+                lines.push(format!("Signed {}", name));
+                lines.push(format!("Transaction: {}", truncate(transaction, options.payload_width)));
+                if options.one_line {
+                    return lines.join(", ");
+                }
+                push_metadata(&mut lines, metadata);
+            }
+            ProcessedTransaction::UnsignedSerialized { transaction, name, metadata } => {
+                lines.push(format!("Serialized {}", name));
+                lines.push(format!("Message: {}", truncate(transaction, options.payload_width)));
+                if options.one_line {
+                    return lines.join(", ");
+                }
+                push_metadata(&mut lines, metadata);
+            }
+            ProcessedTransaction::InstructionSet { instructions, instruction_names, name, metadata } => {
+                lines.push(format!("Instructions for {}", name));
+                if options.one_line {
+                    lines.push(instruction_names.join(", "));
+                    return lines.join(": ");
+                }
+                for (i, (ix_name, ix)) in instruction_names.iter().zip(instructions).enumerate() {
+                    lines.push(format!("  {}. {}: {}", i, ix_name, truncate(ix, options.payload_width)));
+                }
+                push_metadata(&mut lines, metadata);
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// The multi-line [ProcessedTransaction::summary], with the default [SummaryOptions].
+impl fmt::Display for ProcessedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.summary(&SummaryOptions::default()))
+    }
+}
+
+fn outcome(err: Option<&TransactionError>) -> String {
+    match err {
+        None => "success".to_string(),
+        Some(err) => format!("failed: {}", err),
+    }
+}
+
+fn push_metadata(lines: &mut Vec<String>, metadata: &Map<String, Value>) {
+    if metadata.is_empty() {
+        return;
+    }
+    let pretty = serde_json::to_string_pretty(metadata)
+        .expect("metadata failed to serialize");
+    lines.push("Metadata:".to_string());
+    lines.extend(pretty.lines().map(|line| format!("  {}", line)));
+}
+
+fn truncate(payload: &str, width: usize) -> String {
+    if payload.chars().count() <= width {
+        return payload.to_string();
+    }
+    let kept: String = payload.chars().take(width.saturating_sub(3)).collect();
+    format!("{}...", kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_client::solana_client::rpc_response::{RpcResponseContext, RpcSimulateTransactionResult};
+    use serde_json::json;
+    use crate::SimulationReport;
+    use super::*;
+
+    fn metadata() -> Map<String, Value> {
+        let mut metadata = Map::new();
+        metadata.insert("amount".to_string(), json!(5));
+        metadata
+    }
+
+    #[test]
+    fn execution() {
+        let mut processed = ProcessedTransaction::Execution {
+            signature: "5sig".to_string(),
+            name: "memo: Foobar".to_string(),
+            metadata: metadata(),
+            confirmation_slot: None,
+            transaction_error: None,
+        };
+        assert_eq!(
+            processed.to_string(),
+            "Executed memo: Foobar\nSignature: 5sig\nMetadata:\n  {\n    \"amount\": 5\n  }",
+        );
+        assert_eq!(
+            processed.summary(&SummaryOptions::one_line()),
+            "Executed memo: Foobar, Signature: 5sig",
+        );
+
+        if let ProcessedTransaction::Execution { confirmation_slot, transaction_error, .. } = &mut processed {
+            *confirmation_slot = Some(12);
+            *transaction_error = Some(TransactionError::AccountInUse);
+        }
+        assert_eq!(
+            processed.summary(&SummaryOptions::one_line()),
+            format!(
+                "Executed memo: Foobar, Signature: 5sig, Confirmed slot: 12, Result: failed: {}",
+                TransactionError::AccountInUse,
+            ),
+        );
+    }
+
+    #[test]
+    fn simulation() {
+        let logs: Vec<String> = (0..5).map(|i| format!("Program log: {}", i)).collect();
+        let simulation_result = RpcSimulateTransactionResult {
+            err: None,
+            logs: Some(logs),
+            accounts: None,
+            units_consumed: Some(1_234),
+            return_data: None,
+        };
+        let processed = ProcessedTransaction::Simulation {
+            name: "memo".to_string(),
+            metadata: Map::new(),
+            simulation_report: SimulationReport::from(&simulation_result),
+            simulation_result,
+            simulation_context: RpcResponseContext { slot: 7, api_version: None },
+        };
+        let options = SummaryOptions { log_lines: 2, ..Default::default() };
+        assert_eq!(
+            processed.summary(&options),
+            "Simulated memo\nResult: success\nUnits consumed: 1234\nLogs:\n  Program log: 0\n  Program log: 1\n  ... 3 more",
+        );
+        assert_eq!(
+            processed.summary(&SummaryOptions::one_line()),
+            "Simulated memo, Result: success, Units consumed: 1234",
+        );
+    }
+
+    #[test]
+    fn truncates_payloads() {
+        let processed = ProcessedTransaction::SignedSerialized {
+            transaction: "a".repeat(100),
+            name: "memo".to_string(),
+            metadata: Map::new(),
+        };
+        let options = SummaryOptions { payload_width: 10, ..SummaryOptions::one_line() };
+        assert_eq!(processed.summary(&options), "Signed memo, Transaction: aaaaaaa...");
+        assert_eq!(processed.to_string(), format!("Signed memo\nTransaction: {}...", "a".repeat(61)));
+
+        let processed = ProcessedTransaction::UnsignedSerialized {
+            transaction: "short".to_string(),
+            name: "memo".to_string(),
+            metadata: Map::new(),
+        };
+        assert_eq!(processed.summary(&options), "Serialized memo, Message: short");
+    }
+
+    #[test]
+    fn instruction_set() {
+        let processed = ProcessedTransaction::InstructionSet {
+            instructions: vec!["b".repeat(80), "c".to_string()],
+            instruction_names: vec!["set_compute_unit_limit".to_string(), "memo".to_string()],
+            name: "memo".to_string(),
+            metadata: Map::new(),
+        };
+        assert_eq!(
+            processed.to_string(),
+            format!("Instructions for memo\n  0. set_compute_unit_limit: {}...\n  1. memo: c", "b".repeat(61)),
+        );
+        assert_eq!(
+            processed.summary(&SummaryOptions::one_line()),
+            "Instructions for memo: set_compute_unit_limit, memo",
+        );
+    }
+}
@@ -0,0 +1,139 @@
+/// A configurable [TransactionProcessor] shared by the tests, so each test sets up only
+/// what it's about rather than its own processor.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use anchor_client::solana_client::rpc_client::RpcClient;
+use serde_json::{Map, Value};
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use crate::{ComputeBudget, TransactionProcessor, TransactionProcessorError};
+
+/// A memo signed by the primary signer, unless configured otherwise.
+#[derive(Default)]
+pub struct TestProcessor {
+    pub message: String,
+    /// Transfers a lamport to each address in the table instead of the memo,
+    /// and looks the addresses up in it, so the transaction has a v0 message.
+    pub lookup_table: Option<AddressLookupTableAccount>,
+    pub compute_budget: Option<ComputeBudget>,
+    /// Names without an instruction, for a [TransactionProcessorError::InstructionNameMismatch].
+    pub extra_names: Vec<&'static str>,
+    online_args_calls: AtomicUsize,
+}
+
+/// A memo of `message`.
+pub fn memo(message: &str) -> TestProcessor {
+    TestProcessor {
+        message: message.to_string(),
+        ..TestProcessor::default()
+    }
+}
+
+/// Transfers to `recipients` new addresses, all in one lookup table.
+pub fn transfers(recipients: usize) -> TestProcessor {
+    TestProcessor {
+        lookup_table: Some(AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: (0..recipients).map(|_| Pubkey::new_unique()).collect(),
+        }),
+        ..TestProcessor::default()
+    }
+}
+
+impl TestProcessor {
+    /// How many times the online args have been fetched, by either client.
+    pub fn online_args_calls(&self) -> usize {
+        self.online_args_calls.load(Ordering::SeqCst)
+    }
+
+    pub fn reset_online_args_calls(&self) {
+        self.online_args_calls.store(0, Ordering::SeqCst);
+    }
+
+    fn fetch_online_args(&self) -> Result<(), TransactionProcessorError> {
+        self.online_args_calls.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl TransactionProcessor for TestProcessor {
+    type OnlineArgs = ();
+    type RemainingArgs = ();
+
+    fn get_online_args(&self, _: &RpcClient) -> Result<Self::OnlineArgs, TransactionProcessorError> {
+        self.fetch_online_args()
+    }
+
+    fn metadata(&self, primary_signer: &Pubkey, _: &Self::OnlineArgs, _: &Self::RemainingArgs) -> Map<String, Value> {
+        let mut map = Map::new();
+        map.insert("message".to_string(), Value::String(self.message.to_string()));
+        map.insert("signer".to_string(), Value::String(primary_signer.to_string()));
+        map
+    }
+
+    fn name(&self, _: &Pubkey, _: &Self::OnlineArgs, _: &Self::RemainingArgs) -> String {
+        match &self.lookup_table {
+            Some(table) => format!("{} transfers", table.addresses.len()),
+            None => format!("memo: {}", self.message),
+        }
+    }
+
+    fn calc_remaining_args(&self, _: &Self::OnlineArgs, _: &Pubkey) -> Result<Self::RemainingArgs, TransactionProcessorError> {
+        Ok(())
+    }
+
+    fn lookup_tables(&self, _: &Self::OnlineArgs) -> Option<Vec<AddressLookupTableAccount>> {
+        self.lookup_table.clone().map(|table| vec![table])
+    }
+
+    fn compute_budget(&self) -> Option<ComputeBudget> {
+        self.compute_budget
+    }
+
+    fn create_instructions(&self, primary_signer: &Pubkey, _: Self::OnlineArgs, _: Self::RemainingArgs) -> Result<(Vec<&str>, Vec<Instruction>), TransactionProcessorError> {
+        let (mut names, instructions): (Vec<&str>, Vec<Instruction>) = match &self.lookup_table {
+            Some(table) => table.addresses.iter().map(|recipient| {
+                ("transfer", solana_sdk::system_instruction::transfer(primary_signer, recipient, 1))
+            }).unzip(),
+            None => (vec!["memo"], vec![spl_memo::build_memo(self.message.as_bytes(), &[primary_signer])]),
+        };
+        names.extend(&self.extra_names);
+        Ok((names, instructions))
+    }
+}
+
+#[cfg(feature = "nonblocking")]
+#[async_trait::async_trait]
+impl crate::AsyncTransactionProcessor for TestProcessor {
+    async fn get_online_args_async(
+        &self,
+        _: &anchor_client::solana_client::nonblocking::rpc_client::RpcClient,
+    ) -> Result<Self::OnlineArgs, TransactionProcessorError> {
+        self.fetch_online_args()
+    }
+}
+
+/// A [TestProcessor] implementing [TransactionProcessor::named_instructions] instead of
+/// the deprecated [TransactionProcessor::create_instructions].
+pub struct NamedOnly(pub TestProcessor);
+
+impl TransactionProcessor for NamedOnly {
+    type OnlineArgs = ();
+    type RemainingArgs = ();
+
+    fn get_online_args(&self, client: &RpcClient) -> Result<Self::OnlineArgs, TransactionProcessorError> {
+        self.0.get_online_args(client)
+    }
+
+    fn name(&self, primary_signer: &Pubkey, online_args: &Self::OnlineArgs, remaining_args: &Self::RemainingArgs) -> String {
+        self.0.name(primary_signer, online_args, remaining_args)
+    }
+
+    fn calc_remaining_args(&self, online_args: &Self::OnlineArgs, primary_signer: &Pubkey) -> Result<Self::RemainingArgs, TransactionProcessorError> {
+        self.0.calc_remaining_args(online_args, primary_signer)
+    }
+
+    fn named_instructions(&self, primary_signer: &Pubkey, online_args: Self::OnlineArgs, remaining: Self::RemainingArgs) -> Result<Vec<(String, Instruction)>, TransactionProcessorError> {
+        self.0.named_instructions(primary_signer, online_args, remaining)
+    }
+}
@@ -0,0 +1,186 @@
+//! A signer for async code, e.g. request handlers in a tokio server.
+//!
+//! [crate::ThreadsafeSigner] holds a `std::sync::Mutex` while signing, which blocks
+//! whichever executor thread is waiting for it. With interactive signers like a Ledger,
+//! where signing can take seconds, that stalls every other task on the thread.
+//! [AsyncThreadsafeSigner] waits for its lock asynchronously and signs on tokio's
+//! blocking thread pool instead, at the cost of copying the message and a thread
+//! hand-off per signature. For fast signers like keypairs that cost usually outweighs
+//! the benefit, so prefer [crate::ThreadsafeSigner] unless signing can be slow.
+use std::sync::Arc;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer, SignerError};
+use tokio::sync::Mutex;
+
+/// A [Signer] shared between tasks, that signs without blocking the runtime.
+pub struct AsyncThreadsafeSigner<T: Signer + Send + ?Sized> {
+    inner: Arc<Mutex<Box<T>>>,
+    pubkey: Option<Pubkey>,
+}
+
+impl<T: Signer + Send + 'static> AsyncThreadsafeSigner<T> {
+    pub fn new(inner: T) -> Self {
+        Self::from_box(Box::new(inner))
+    }
+}
+
+impl<T: Signer + Send + ?Sized + 'static> AsyncThreadsafeSigner<T> {
+    fn from_box(inner: Box<T>) -> Self {
+        let pubkey = inner.try_pubkey().ok();
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+            pubkey,
+        }
+    }
+
+    /// The pubkey read at construction, or the default pubkey if the signer
+    /// couldn't produce one then.
+    pub fn pubkey(&self) -> Pubkey {
+        self.pubkey.unwrap_or_default()
+    }
+
+    /// The pubkey, asking the signer again if it couldn't produce one at construction.
+    pub async fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        match self.pubkey {
+            Some(pubkey) => Ok(pubkey),
+            None => self.inner.lock().await.try_pubkey(),
+        }
+    }
+
+    /// Sign `message` on the blocking thread pool, once other signatures are done.
+    pub async fn sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let signer = Arc::clone(&self.inner).lock_owned().await;
+        let message = message.to_vec();
+        tokio::task::spawn_blocking(move || signer.try_sign_message(&message))
+            .await
+            .map_err(|e| SignerError::Custom(format!("signing task failed: {}", e)))?
+    }
+
+    /// A synchronous [Signer] sharing this signer, for APIs that need one,
+    /// like building transactions.
+    ///
+    /// Inside a runtime, it signs with [tokio::task::block_in_place], which panics on
+    /// a current-thread runtime, so only use it there from [tokio::task::spawn_blocking].
+    pub fn as_blocking(&self) -> BlockingSigner<T> {
+        BlockingSigner {
+            inner: Arc::clone(&self.inner),
+            pubkey: self.pubkey,
+        }
+    }
+}
+
+impl AsyncThreadsafeSigner<dyn Signer + Send> {
+    pub fn from_boxed(inner: Box<dyn Signer + Send>) -> Self {
+        Self::from_box(inner)
+    }
+}
+
+impl From<Keypair> for AsyncThreadsafeSigner<Keypair> {
+    fn from(value: Keypair) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Signer + Send + ?Sized> Clone for AsyncThreadsafeSigner<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            pubkey: self.pubkey,
+        }
+    }
+}
+
+/// See [AsyncThreadsafeSigner::as_blocking].
+pub struct BlockingSigner<T: Signer + Send + ?Sized> {
+    inner: Arc<Mutex<Box<T>>>,
+    pubkey: Option<Pubkey>,
+}
+
+impl<T: Signer + Send + ?Sized> BlockingSigner<T> {
+    fn with_signer<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            tokio::task::block_in_place(|| f(&self.inner.blocking_lock()))
+        } else {
+            f(&self.inner.blocking_lock())
+        }
+    }
+}
+
+impl<T: Signer + Send + ?Sized> Clone for BlockingSigner<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            pubkey: self.pubkey,
+        }
+    }
+}
+
+impl<T: Signer + Send + ?Sized> Signer for BlockingSigner<T> {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        match self.pubkey {
+            Some(pubkey) => Ok(pubkey),
+            None => self.with_signer(|signer| signer.try_pubkey()),
+        }
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.with_signer(|signer| signer.try_sign_message(message))
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.with_signer(|signer| signer.is_interactive())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signature::keypair_from_seed;
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_tasks() {
+        let keypair = keypair_from_seed(&[3u8; 32]).unwrap();
+        let pubkey = keypair.pubkey();
+        let signer = AsyncThreadsafeSigner::from_boxed(Box::new(keypair));
+        assert_eq!(signer.pubkey(), pubkey);
+        assert_eq!(signer.try_pubkey().await.unwrap(), pubkey);
+        let tasks: Vec<_> = (0..2u8).map(|i| {
+            let signer = signer.clone();
+            tokio::spawn(async move {
+                for j in 0..50u8 {
+                    let message = [i, j];
+                    let signature = signer.sign_message(&message).await.unwrap();
+                    assert!(signature.verify(pubkey.as_ref(), &message));
+                }
+            })
+        }).collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn blocking_bridge() {
+        let keypair = keypair_from_seed(&[4u8; 32]).unwrap();
+        let expected = keypair.sign_message(b"bridge");
+        let signer = AsyncThreadsafeSigner::new(keypair);
+        let blocking = signer.as_blocking();
+        assert_eq!(blocking.pubkey(), signer.pubkey());
+        assert_eq!(blocking.try_sign_message(b"bridge").unwrap(), expected);
+        // While an async signature holds the lock.
+        let (sync, async_) = tokio::join!(
+            tokio::spawn(async move { blocking.try_sign_message(b"bridge") }),
+            signer.sign_message(b"bridge"),
+        );
+        assert_eq!(sync.unwrap().unwrap(), expected);
+        assert_eq!(async_.unwrap(), expected);
+    }
+
+    #[test]
+    fn blocking_outside_a_runtime() {
+        let keypair = keypair_from_seed(&[4u8; 32]).unwrap();
+        let expected = keypair.sign_message(b"bridge");
+        let signer = AsyncThreadsafeSigner::from(keypair);
+        assert_eq!(signer.as_blocking().try_sign_message(b"bridge").unwrap(), expected);
+    }
+}
@@ -1,6 +1,17 @@
 pub mod concrete_signer;
+pub mod offline_signers;
+pub mod signer_set;
 pub mod threadsafe_signer;
+#[cfg(feature = "tokio")]
+pub mod async_signer;
+#[cfg(feature = "remote")]
+pub mod remote_signer;
 
 pub use concrete_signer::ConcreteSigner;
-
-pub use threadsafe_signer::ThreadsafeSigner;
\ No newline at end of file
+pub use offline_signers::{PresignedSigner, ReadOnlySigner};
+pub use signer_set::SignerSet;
+pub use threadsafe_signer::{DynThreadsafeSigner, ThreadsafeSigner};
+#[cfg(feature = "tokio")]
+pub use async_signer::AsyncThreadsafeSigner;
+#[cfg(feature = "remote")]
+pub use remote_signer::{RemoteSigner, RemoteSignerConfig};
\ No newline at end of file
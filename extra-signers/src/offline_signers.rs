@@ -0,0 +1,149 @@
+use solana_program::hash::{hash, Hash};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer, SignerError};
+use crate::ThreadsafeSigner;
+
+/// A signer that only has a pubkey, e.g. a placeholder for a co-signer when
+/// serializing a transaction for someone else to sign. Signing always fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOnlySigner(pub Pubkey);
+
+impl Signer for ReadOnlySigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.0)
+    }
+
+    fn try_sign_message(&self, _: &[u8]) -> Result<Signature, SignerError> {
+        Err(SignerError::Custom("read-only signer".to_string()))
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// A signature made elsewhere, e.g. collected from an offline signer,
+/// that's only handed out for the message it signs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresignedSigner {
+    pub pubkey: Pubkey,
+    /// The hash of the signed message bytes.
+    pub message_hash: Hash,
+    pub signature: Signature,
+}
+
+impl PresignedSigner {
+    pub fn new(pubkey: Pubkey, message: &[u8], signature: Signature) -> Self {
+        Self {
+            pubkey,
+            message_hash: hash(message),
+            signature,
+        }
+    }
+}
+
+impl Signer for PresignedSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        if hash(message) != self.message_hash {
+            return Err(SignerError::Custom(format!(
+                "{} presigned a different message", self.pubkey
+            )));
+        }
+        if !self.signature.verify(self.pubkey.as_ref(), message) {
+            return Err(SignerError::Custom(format!(
+                "the presigned signature doesn't verify for {}", self.pubkey
+            )));
+        }
+        Ok(self.signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+impl From<ReadOnlySigner> for ThreadsafeSigner<ReadOnlySigner> {
+    fn from(value: ReadOnlySigner) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<PresignedSigner> for ThreadsafeSigner<PresignedSigner> {
+    fn from(value: PresignedSigner) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&Keypair> for ReadOnlySigner {
+    fn from(value: &Keypair) -> Self {
+        Self(value.pubkey())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::instruction::{AccountMeta, Instruction};
+    use solana_sdk::message::Message;
+    use solana_sdk::signature::keypair_from_seed;
+    use solana_sdk::transaction::Transaction;
+    use super::*;
+
+    fn two_signer_message(payer: &Pubkey, cosigner: &Pubkey) -> Message {
+        let ix = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![AccountMeta::new(*payer, true), AccountMeta::new_readonly(*cosigner, true)],
+        );
+        Message::new_with_blockhash(&[ix], Some(payer), &Hash::new_unique())
+    }
+
+    #[test]
+    fn read_only() {
+        let pubkey = Pubkey::new_unique();
+        let signer = ThreadsafeSigner::from(ReadOnlySigner(pubkey));
+        assert_eq!(signer.pubkey(), pubkey);
+        let err = signer.try_sign_message(b"message").unwrap_err();
+        assert_eq!(err, SignerError::Custom("read-only signer".to_string()));
+    }
+
+    #[test]
+    fn presigned() {
+        let payer = keypair_from_seed(&[5u8; 32]).unwrap();
+        let cosigner = keypair_from_seed(&[6u8; 32]).unwrap();
+        let message = two_signer_message(&payer.pubkey(), &cosigner.pubkey());
+        let message_data = message.serialize();
+        // Signed offline, and only the signature brought back.
+        let presigned = PresignedSigner::new(
+            cosigner.pubkey(),
+            &message_data,
+            cosigner.sign_message(&message_data),
+        );
+
+        let mut tx = Transaction::new_unsigned(message.clone());
+        tx.try_partial_sign(&[&payer], message.recent_blockhash).unwrap();
+        tx.try_partial_sign(&[&ThreadsafeSigner::from(presigned)], message.recent_blockhash).unwrap();
+        assert!(tx.verify().is_ok());
+
+        let err = presigned.try_sign_message(b"another message").unwrap_err();
+        assert!(err.to_string().contains("presigned a different message"), "{}", err);
+        let forged = PresignedSigner::new(cosigner.pubkey(), &message_data, payer.sign_message(&message_data));
+        assert!(forged.try_sign_message(&message_data).is_err());
+    }
+
+    #[test]
+    fn read_only_placeholder_for_partial_signing() {
+        let payer = keypair_from_seed(&[5u8; 32]).unwrap();
+        let cosigner = ReadOnlySigner(Pubkey::new_unique());
+        let message = two_signer_message(&payer.pubkey(), &cosigner.pubkey());
+        let mut tx = Transaction::new_unsigned(message.clone());
+        // The placeholder can't sign, so the transaction stays partially signed.
+        assert!(tx.try_partial_sign(&[&payer as &dyn Signer, &cosigner], message.recent_blockhash).is_err());
+        tx.try_partial_sign(&[&payer], message.recent_blockhash).unwrap();
+        assert!(!tx.is_signed());
+        assert_eq!(tx.signatures[1], Signature::default());
+    }
+}
@@ -0,0 +1,323 @@
+//! A [Signer] backed by an HTTP signing service.
+//!
+//! The service is expected to serve:
+//! - `GET {base_url}/pubkey`, responding `{"pubkey": "<base-58 pubkey>"}`
+//! - `POST {base_url}/sign` with `{"message": "<base-64 message>"}`, responding
+//!   `{"signature": "<base-64 signature>"}`. Requests carry an `Idempotency-Key`
+//!   header with the message hash, so a retried request can be recognized.
+use std::fmt::{Debug, Formatter};
+use std::sync::Mutex;
+use std::time::Duration;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use solana_program::hash::hash;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Signature, Signer, SignerError};
+
+/// How to reach a [RemoteSigner]'s service.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RemoteSignerConfig {
+    /// e.g. `https://signer.internal/v1`, without a trailing slash.
+    pub base_url: String,
+    /// A header sent with every request, e.g. `("Authorization", "HMAC ...")`.
+    pub auth_header: Option<(String, String)>,
+    pub timeout: Duration,
+    /// How many times to retry a request that failed to get a response or got
+    /// a server error. Client errors like a 401 are never retried.
+    pub retries: u32,
+}
+
+impl RemoteSignerConfig {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            auth_header: None,
+            timeout: Duration::from_secs(30),
+            retries: 0,
+        }
+    }
+}
+
+// Never print the auth header's value.
+impl Debug for RemoteSignerConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteSignerConfig")
+            .field("base_url", &self.base_url)
+            .field("auth_header", &self.auth_header.as_ref().map(|(name, _)| name))
+            .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct PubkeyResponse {
+    pubkey: String,
+}
+
+#[derive(Serialize)]
+struct SignRequest {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+/// A [Signer] that asks an HTTP service to sign, see the [module docs](self).
+/// The pubkey is fetched once, on first use.
+#[derive(Debug)]
+pub struct RemoteSigner {
+    config: RemoteSignerConfig,
+    client: Client,
+    pubkey: Mutex<Option<Pubkey>>,
+}
+
+impl RemoteSigner {
+    pub fn new(config: RemoteSignerConfig) -> Result<Self, SignerError> {
+        let client = Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| SignerError::Custom(format!("could not build the remote signer client: {}", e)))?;
+        Ok(Self {
+            config,
+            client,
+            pubkey: Mutex::new(None),
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    fn send(&self, request: impl Fn() -> RequestBuilder) -> Result<Response, SignerError> {
+        let mut attempt = 0;
+        loop {
+            let mut builder = request();
+            if let Some((name, value)) = &self.config.auth_header {
+                builder = builder.header(name, value);
+            }
+            let retry = match builder.send() {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let error = SignerError::Custom(format!(
+                        "remote signer responded {}: {}",
+                        status,
+                        response.text().unwrap_or_default()
+                    ));
+                    if !status.is_server_error() {
+                        return Err(error);
+                    }
+                    error
+                }
+                Err(e) => SignerError::Custom(format!("remote signer request failed: {}", e)),
+            };
+            if attempt >= self.config.retries {
+                return Err(retry);
+            }
+            attempt += 1;
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        let mut cached = self.pubkey.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(pubkey) = *cached {
+            return Ok(pubkey);
+        }
+        let response: PubkeyResponse = self.send(|| self.client.get(self.url("pubkey")))?
+            .json()
+            .map_err(|e| SignerError::Custom(format!("invalid remote signer pubkey response: {}", e)))?;
+        let pubkey = response.pubkey.parse()
+            .map_err(|e| SignerError::Custom(format!("invalid remote signer pubkey {}: {}", response.pubkey, e)))?;
+        *cached = Some(pubkey);
+        Ok(pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let body = SignRequest { message: base64::encode(message) };
+        let idempotency_key = hash(message).to_string();
+        let response: SignResponse = self.send(|| {
+            self.client.post(self.url("sign"))
+                .header("Idempotency-Key", &idempotency_key)
+                .json(&body)
+        })?
+            .json()
+            .map_err(|e| SignerError::Custom(format!("invalid remote signer sign response: {}", e)))?;
+        let bytes = base64::decode(&response.signature)
+            .map_err(|e| SignerError::Custom(format!("invalid remote signer signature: {}", e)))?;
+        if bytes.len() != 64 {
+            return Err(SignerError::Custom(format!(
+                "invalid remote signer signature: expected 64 bytes, got {}", bytes.len()
+            )));
+        }
+        let signature = Signature::new(&bytes);
+        // A service signing with another key would otherwise only be caught at broadcast.
+        if !signature.verify(self.try_pubkey()?.as_ref(), message) {
+            return Err(SignerError::Custom("remote signer returned an invalid signature".to_string()));
+        }
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use axum::extract::Extension;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use solana_sdk::signature::{keypair_from_seed, Keypair};
+    use crate::ThreadsafeSigner;
+    use super::*;
+
+    const TOKEN: &str = "HMAC secret";
+
+    struct Service {
+        keypair: Keypair,
+        // The key the service actually signs with, normally `keypair`.
+        signing_keypair: Keypair,
+        sign_requests: AtomicUsize,
+        // Respond with a server error this many times first.
+        failures: AtomicUsize,
+        delay: Duration,
+    }
+
+    fn authorized(headers: &HeaderMap) -> bool {
+        headers.get("Authorization").map_or(false, |value| value == TOKEN)
+    }
+
+    async fn pubkey(
+        Extension(service): Extension<Arc<Service>>,
+        headers: HeaderMap,
+    ) -> (StatusCode, Json<Value>) {
+        if !authorized(&headers) {
+            return (StatusCode::UNAUTHORIZED, Json(json!({})));
+        }
+        (StatusCode::OK, Json(json!({ "pubkey": service.keypair.pubkey().to_string() })))
+    }
+
+    async fn sign(
+        Extension(service): Extension<Arc<Service>>,
+        headers: HeaderMap,
+        Json(body): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        service.sign_requests.fetch_add(1, Ordering::SeqCst);
+        if !authorized(&headers) {
+            return (StatusCode::UNAUTHORIZED, Json(json!({})));
+        }
+        tokio::time::sleep(service.delay).await;
+        let failures = service.failures.load(Ordering::SeqCst);
+        if failures > 0 {
+            service.failures.store(failures - 1, Ordering::SeqCst);
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(json!({})));
+        }
+        let message = base64::decode(body["message"].as_str().unwrap()).unwrap();
+        assert_eq!(headers["Idempotency-Key"], hash(&message).to_string());
+        let signature = service.signing_keypair.sign_message(&message);
+        (StatusCode::OK, Json(json!({ "signature": base64::encode(signature) })))
+    }
+
+    fn serve(failures: usize, delay: Duration) -> (String, Arc<Service>) {
+        serve_signing_with(keypair_from_seed(&[7u8; 32]).unwrap(), failures, delay)
+    }
+
+    fn serve_signing_with(signing_keypair: Keypair, failures: usize, delay: Duration) -> (String, Arc<Service>) {
+        let service = Arc::new(Service {
+            keypair: keypair_from_seed(&[7u8; 32]).unwrap(),
+            signing_keypair,
+            sign_requests: AtomicUsize::new(0),
+            failures: AtomicUsize::new(failures),
+            delay,
+        });
+        let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new()
+            .route("/pubkey", get(pubkey))
+            .route("/sign", post(sign))
+            .layer(Extension(Arc::clone(&service)));
+        std::thread::spawn(move || {
+            tokio::runtime::Runtime::new().unwrap().block_on(async move {
+                axum::Server::from_tcp(listener).unwrap()
+                    .serve(app.into_make_service())
+                    .await
+                    .unwrap();
+            })
+        });
+        (url, service)
+    }
+
+    fn config(url: &str) -> RemoteSignerConfig {
+        RemoteSignerConfig {
+            auth_header: Some(("Authorization".to_string(), TOKEN.to_string())),
+            timeout: Duration::from_secs(5),
+            ..RemoteSignerConfig::new(url)
+        }
+    }
+
+    #[test]
+    fn signs() {
+        let (url, service) = serve(1, Duration::ZERO);
+        let signer = ThreadsafeSigner::new(RemoteSigner::new(RemoteSignerConfig {
+            retries: 1,
+            ..config(&url)
+        }).unwrap());
+        let expected = service.keypair.sign_message(b"message");
+        assert_eq!(signer.try_pubkey().unwrap(), service.keypair.pubkey());
+        assert_eq!(signer.try_sign_message(b"message").unwrap(), expected);
+        // One failure, then the retry
+        assert_eq!(service.sign_requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn unauthorized() {
+        let (url, service) = serve(0, Duration::ZERO);
+        let signer = RemoteSigner::new(RemoteSignerConfig {
+            auth_header: None,
+            retries: 3,
+            ..config(&url)
+        }).unwrap();
+        let err = signer.try_pubkey().unwrap_err().to_string();
+        assert!(err.contains("401"), "{}", err);
+        let err = signer.try_sign_message(b"message").unwrap_err().to_string();
+        assert!(err.contains("401"), "{}", err);
+        // Client errors aren't retried.
+        assert_eq!(service.sign_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn wrong_key() {
+        let (url, _service) = serve_signing_with(Keypair::new(), 0, Duration::ZERO);
+        let signer = RemoteSigner::new(config(&url)).unwrap();
+        let err = signer.try_sign_message(b"message").unwrap_err().to_string();
+        assert!(err.contains("remote signer returned an invalid signature"), "{}", err);
+    }
+
+    #[test]
+    fn timeout() {
+        let (url, _service) = serve(0, Duration::from_secs(2));
+        let signer = RemoteSigner::new(RemoteSignerConfig {
+            timeout: Duration::from_millis(200),
+            ..config(&url)
+        }).unwrap();
+        let err = signer.try_sign_message(b"message").unwrap_err().to_string();
+        assert!(err.contains("remote signer request failed"), "{}", err);
+    }
+
+    #[test]
+    fn debug_hides_the_auth_header() {
+        let debug = format!("{:?}", config("http://localhost"));
+        assert!(debug.contains("Authorization"), "{}", debug);
+        assert!(!debug.contains(TOKEN), "{}", debug);
+    }
+}
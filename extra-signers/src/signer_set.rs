@@ -0,0 +1,175 @@
+use std::sync::Arc;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Signature, Signer, SignerError};
+use solana_sdk::signer::signers::Signers;
+
+/// An ordered set of signers, unique by pubkey, that can be passed anywhere the SDK
+/// takes `&T: Signers`, e.g. `Transaction::new_signed_with_payer`.
+///
+/// Signing fails when the same pubkey is passed twice, so [SignerSet::push] skips
+/// signers that are already in the set.
+#[derive(Default)]
+pub struct SignerSet {
+    signers: Vec<Box<dyn Signer>>,
+}
+
+impl SignerSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `signer` unless one with the same pubkey is already in the set.
+    /// Returns whether it was added.
+    pub fn push(&mut self, signer: Box<dyn Signer>) -> bool {
+        if self.contains(&signer.pubkey()) {
+            return false;
+        }
+        self.signers.push(signer);
+        true
+    }
+
+    /// [SignerSet::push] for a signer shared with other owners.
+    pub fn push_shared(&mut self, signer: Arc<dyn Signer>) -> bool {
+        self.push(Box::new(SharedSigner(signer)))
+    }
+
+    /// Move the signer for `payer` to the front, if it's in the set.
+    pub fn with_payer_first(mut self, payer: &Pubkey) -> Self {
+        if let Some(index) = self.signers.iter().position(|signer| signer.pubkey() == *payer) {
+            let payer = self.signers.remove(index);
+            self.signers.insert(0, payer);
+        }
+        self
+    }
+
+    pub fn pubkeys(&self) -> Vec<Pubkey> {
+        self.signers.iter().map(|signer| signer.pubkey()).collect()
+    }
+
+    pub fn contains(&self, pubkey: &Pubkey) -> bool {
+        self.signers.iter().any(|signer| signer.pubkey() == *pubkey)
+    }
+
+    pub fn len(&self) -> usize {
+        self.signers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signers.is_empty()
+    }
+
+    /// The signers, in order, e.g. for `TransactionProcessor::process`.
+    pub fn into_vec(self) -> Vec<Box<dyn Signer>> {
+        self.signers
+    }
+}
+
+impl FromIterator<Box<dyn Signer>> for SignerSet {
+    fn from_iter<I: IntoIterator<Item = Box<dyn Signer>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        iter.into_iter().for_each(|signer| {
+            set.push(signer);
+        });
+        set
+    }
+}
+
+impl Extend<Box<dyn Signer>> for SignerSet {
+    fn extend<I: IntoIterator<Item = Box<dyn Signer>>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|signer| {
+            self.push(signer);
+        });
+    }
+}
+
+impl Signers for SignerSet {
+    fn pubkeys(&self) -> Vec<Pubkey> {
+        self.signers.pubkeys()
+    }
+
+    fn try_pubkeys(&self) -> Result<Vec<Pubkey>, SignerError> {
+        self.signers.try_pubkeys()
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Vec<Signature> {
+        self.signers.sign_message(message)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Vec<Signature>, SignerError> {
+        self.signers.try_sign_message(message)
+    }
+
+    fn is_interactive(&self) -> bool {
+        Signers::is_interactive(&self.signers)
+    }
+}
+
+struct SharedSigner(Arc<dyn Signer>);
+
+impl Signer for SharedSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        self.0.try_pubkey()
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.0.try_sign_message(message)
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.0.is_interactive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::hash::Hash;
+    use solana_program::instruction::{AccountMeta, Instruction};
+    use solana_sdk::signature::keypair_from_seed;
+    use solana_sdk::transaction::Transaction;
+    use super::*;
+
+    fn keypair(seed: u8) -> Box<dyn Signer> {
+        Box::new(keypair_from_seed(&[seed; 32]).unwrap())
+    }
+
+    #[test]
+    fn dedupes() {
+        let mut set = SignerSet::new();
+        assert!(set.push(keypair(1)));
+        assert!(!set.push(keypair(1)));
+        assert!(set.push_shared(Arc::new(keypair_from_seed(&[2; 32]).unwrap())));
+        assert!(!set.push_shared(Arc::new(keypair_from_seed(&[2; 32]).unwrap())));
+        set.extend([keypair(1), keypair(3)]);
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&keypair(3).pubkey()));
+        assert!(!set.contains(&keypair(4).pubkey()));
+    }
+
+    #[test]
+    fn payer_first() {
+        let set: SignerSet = [keypair(1), keypair(2), keypair(3)].into_iter().collect();
+        let payer = keypair(3).pubkey();
+        let set = set.with_payer_first(&payer);
+        assert_eq!(set.pubkeys(), vec![payer, keypair(1).pubkey(), keypair(2).pubkey()]);
+        // Unknown payers leave the order alone.
+        let set = set.with_payer_first(&Pubkey::new_unique());
+        assert_eq!(set.pubkeys()[0], payer);
+    }
+
+    #[test]
+    fn signs_transactions() {
+        let payer = keypair(1).pubkey();
+        let cosigner = keypair(2).pubkey();
+        let ix = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            vec![AccountMeta::new(payer, true), AccountMeta::new_readonly(cosigner, true)],
+        );
+        // Duplicates would otherwise make signing fail.
+        let signers: SignerSet = [keypair(2), keypair(1), keypair(2)].into_iter().collect();
+        let signers = signers.with_payer_first(&payer);
+        let tx = Transaction::new_signed_with_payer(&[ix], Some(&payer), &signers, Hash::new_unique());
+        assert!(tx.verify().is_ok());
+        assert_eq!(signers.into_vec().len(), 2);
+    }
+}
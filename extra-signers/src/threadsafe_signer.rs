@@ -1,41 +1,141 @@
-use solana_sdk::signature::{Signature, Signer, SignerError};
-use std::sync::{Arc, Mutex};
+use solana_sdk::signature::{Keypair, Signature, Signer, SignerError};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::time::{Duration, Instant};
 use solana_program::pubkey::Pubkey;
 
 /// Basic struct that imbues a [T: Signer] with [Clone + Send + Sync].
-#[derive(Debug)]
-pub struct ThreadsafeSigner<T: Signer> {
-    pub inner: Arc<Mutex<T>>,
+///
+/// `T` can be a trait object, see [DynThreadsafeSigner] for one that holds any signer.
+/// The pubkey and whether it's interactive are read once at construction, so only
+/// signing waits on the lock.
+///
+/// A panic while signing doesn't poison the signer, later calls still go through.
+/// This is a [Mutex] rather than a read-write lock, since that would require
+/// `T: Sync`, which many signers, and `dyn Signer + Send`, aren't.
+pub struct ThreadsafeSigner<T: Signer + ?Sized> {
+    inner: Arc<Mutex<Box<T>>>,
+    pubkey: Option<Pubkey>,
+    is_interactive: bool,
 }
 
+/// A [ThreadsafeSigner] that holds any signer, e.g. one parsed from a CLI argument.
+pub type DynThreadsafeSigner = ThreadsafeSigner<dyn Signer + Send>;
+
 impl<T: Signer> ThreadsafeSigner<T> {
-    #[allow(dead_code)]
-    fn new(inner: T) -> Self {
+    pub fn new(inner: T) -> Self {
+        Self::from_box(Box::new(inner))
+    }
+
+    /// The wrapped signer, or `self` back if it's still shared with clones.
+    pub fn into_inner(self) -> Result<T, Self> {
+        self.into_boxed().map(|inner| *inner)
+    }
+}
+
+impl<T: Signer + ?Sized> ThreadsafeSigner<T> {
+    fn from_box(inner: Box<T>) -> Self {
+        // Signers that can't produce a pubkey yet, e.g. a disconnected hardware
+        // wallet, are asked again on every call.
+        let pubkey = inner.try_pubkey().ok();
+        let is_interactive = inner.is_interactive();
         Self {
-            inner: Arc::new(Mutex::new(inner))
+            inner: Arc::new(Mutex::new(inner)),
+            pubkey,
+            is_interactive,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Box<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// [Signer::try_sign_message], failing with "signer busy" instead of waiting more
+    /// than `timeout` for another signature to finish, e.g. a hardware wallet waiting
+    /// for someone to approve.
+    pub fn try_sign_message_timeout(
+        &self,
+        message: &[u8],
+        timeout: Duration,
+    ) -> Result<Signature, SignerError> {
+        let start = Instant::now();
+        loop {
+            match self.inner.try_lock() {
+                Ok(signer) => return signer.try_sign_message(message),
+                Err(TryLockError::Poisoned(poisoned)) => {
+                    return poisoned.into_inner().try_sign_message(message);
+                }
+                Err(TryLockError::WouldBlock) if start.elapsed() >= timeout => {
+                    return Err(SignerError::Custom("signer busy".to_string()));
+                }
+                Err(TryLockError::WouldBlock) => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+    }
+
+    /// Whether `other` has the same pubkey, i.e. signs for the same account.
+    pub fn eq_pubkey<S: Signer + ?Sized>(&self, other: &S) -> bool {
+        match (self.try_pubkey(), other.try_pubkey()) {
+            (Ok(pubkey), Ok(other)) => pubkey == other,
+            _ => false,
+        }
+    }
+
+    /// [ThreadsafeSigner::into_inner], for signers that can't be unboxed.
+    pub fn into_boxed(self) -> Result<Box<T>, Self> {
+        let (pubkey, is_interactive) = (self.pubkey, self.is_interactive);
+        Arc::try_unwrap(self.inner)
+            .map(|inner| inner.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .map_err(|inner| Self { inner, pubkey, is_interactive })
+    }
+}
+
+impl From<Keypair> for ThreadsafeSigner<Keypair> {
+    fn from(value: Keypair) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<Box<dyn Signer + Send>> for ThreadsafeSigner<dyn Signer + Send> {
+    fn from(value: Box<dyn Signer + Send>) -> Self {
+        Self::from_box(value)
+    }
+}
+
+// Only the pubkey, so key material never ends up in logs.
+impl<T: Signer + ?Sized> Debug for ThreadsafeSigner<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.pubkey {
+            Some(pubkey) => write!(f, "ThreadsafeSigner({})", pubkey),
+            None => write!(f, "ThreadsafeSigner(<unknown pubkey>)"),
         }
     }
 }
 
-impl<T: Signer> Clone for ThreadsafeSigner<T> {
+impl<T: Signer + ?Sized> Clone for ThreadsafeSigner<T> {
     fn clone(&self) -> Self {
         Self {
             inner: Arc::clone(&self.inner),
+            pubkey: self.pubkey,
+            is_interactive: self.is_interactive,
         }
     }
 }
 
-impl<T: Signer> Signer for ThreadsafeSigner<T> {
+impl<T: Signer + ?Sized> Signer for ThreadsafeSigner<T> {
     fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
-        Ok(self.inner.lock().unwrap().pubkey())
+        match self.pubkey {
+            Some(pubkey) => Ok(pubkey),
+            None => self.lock().try_pubkey(),
+        }
     }
 
     fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
-        self.inner.lock().unwrap().try_sign_message(message)
+        self.lock().try_sign_message(message)
     }
 
     fn is_interactive(&self) -> bool {
-        self.inner.lock().unwrap().is_interactive()
+        self.is_interactive
     }
 }
 
@@ -44,7 +144,11 @@ mod tests {
     use std::thread;
     use solana_sdk::signature::keypair_from_seed;
     use solana_sdk::signature::Signer;
-    use crate::threadsafe_signer::ThreadsafeSigner;
+    use std::time::Duration;
+    use solana_program::pubkey::Pubkey;
+    use solana_sdk::signature::{Signature, SignerError};
+    use crate::offline_signers::ReadOnlySigner;
+    use crate::threadsafe_signer::{DynThreadsafeSigner, ThreadsafeSigner};
 
 
     #[test]
@@ -80,4 +184,129 @@ mod tests {
         assert_eq!(keypair.sign_message(&data), sig);
         let _ = takes_trait_object(Box::new(keypair));
     }
+
+    #[test]
+    fn constructors() {
+        let keypair = keypair_from_seed(&[1u8; 32]).unwrap();
+        let pubkey = keypair.pubkey();
+        let signer = ThreadsafeSigner::from(keypair);
+        assert_eq!(signer.pubkey(), pubkey);
+
+        // Shared signers can't be taken back out.
+        let clone = signer.clone();
+        let signer = signer.into_inner().unwrap_err();
+        drop(clone);
+        assert_eq!(signer.into_inner().unwrap().pubkey(), pubkey);
+
+        let boxed: Box<dyn Signer + Send> = Box::new(keypair_from_seed(&[1u8; 32]).unwrap());
+        let signer: DynThreadsafeSigner = ThreadsafeSigner::from(boxed);
+        assert_eq!(signer.pubkey(), pubkey);
+        assert_eq!(signer.into_boxed().unwrap().pubkey(), pubkey);
+    }
+
+    #[test]
+    fn concurrent_use() {
+        let keypair = keypair_from_seed(&[2u8; 32]).unwrap();
+        let pubkey = keypair.pubkey();
+        let boxed: Box<dyn Signer + Send> = Box::new(keypair);
+        let signer = ThreadsafeSigner::from(boxed);
+        let threads: Vec<_> = (0..8u8).map(|i| {
+            let signer = signer.clone();
+            thread::spawn(move || {
+                for j in 0..200u8 {
+                    assert_eq!(signer.pubkey(), pubkey);
+                    let message = [i, j];
+                    let signature = signer.try_sign_message(&message).unwrap();
+                    assert!(signature.verify(pubkey.as_ref(), &message));
+                }
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn debug_and_eq_pubkey() {
+        let keypair = keypair_from_seed(&[8u8; 32]).unwrap();
+        let secret = format!("{:?}", keypair.secret().as_bytes());
+        let pubkey = keypair.pubkey();
+        let signer = ThreadsafeSigner::new(keypair);
+        let debug = format!("{:?}", signer);
+        assert_eq!(debug, format!("ThreadsafeSigner({})", pubkey));
+        assert!(!debug.contains(&secret));
+
+        assert!(signer.eq_pubkey(&ReadOnlySigner(pubkey)));
+        assert!(signer.eq_pubkey(&signer.clone()));
+        assert!(!signer.eq_pubkey(&ReadOnlySigner(Pubkey::new_unique())));
+    }
+
+    /// Panics on its first signature, and takes `delay` to sign.
+    struct FlakySigner {
+        keypair: solana_sdk::signature::Keypair,
+        panicked: std::sync::atomic::AtomicBool,
+        delay: Duration,
+    }
+
+    impl Signer for FlakySigner {
+        fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+            self.keypair.try_pubkey()
+        }
+
+        fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+            if !self.panicked.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                panic!("signer bug");
+            }
+            thread::sleep(self.delay);
+            self.keypair.try_sign_message(message)
+        }
+
+        fn is_interactive(&self) -> bool {
+            false
+        }
+    }
+
+    fn flaky(delay: Duration) -> ThreadsafeSigner<FlakySigner> {
+        ThreadsafeSigner::new(FlakySigner {
+            keypair: keypair_from_seed(&[9u8; 32]).unwrap(),
+            panicked: Default::default(),
+            delay,
+        })
+    }
+
+    #[test]
+    fn survives_panics() {
+        let signer = flaky(Duration::ZERO);
+        let panicking = signer.clone();
+        assert!(thread::spawn(move || panicking.sign_message(b"message")).join().is_err());
+        assert!(signer.inner.is_poisoned());
+
+        let pubkey = signer.pubkey();
+        assert!(!signer.is_interactive());
+        let signature = signer.try_sign_message(b"message").unwrap();
+        assert!(signature.verify(pubkey.as_ref(), b"message"));
+        assert!(signer.try_sign_message_timeout(b"message", Duration::from_secs(1)).is_ok());
+        assert!(signer.into_inner().is_ok());
+    }
+
+    #[test]
+    fn busy_timeout() {
+        let signer = flaky(Duration::from_millis(500));
+        // Get the panic out of the way.
+        let panicking = signer.clone();
+        let _ = thread::spawn(move || panicking.sign_message(b"message")).join();
+
+        let slow = signer.clone();
+        let (started, wait) = std::sync::mpsc::channel();
+        let slow = thread::spawn(move || {
+            let guard = slow.lock();
+            started.send(()).unwrap();
+            guard.try_sign_message(b"message")
+        });
+        wait.recv().unwrap();
+        let err = signer.try_sign_message_timeout(b"message", Duration::from_millis(20)).unwrap_err();
+        assert_eq!(err, SignerError::Custom("signer busy".to_string()));
+        assert!(slow.join().unwrap().is_ok());
+        assert!(signer.try_sign_message_timeout(b"message", Duration::from_millis(20)).is_ok());
+    }
 }
@@ -0,0 +1,37 @@
+/// The jungle-fi crates from one dependency, at versions built against the same
+/// Solana and Anchor releases, so their types line up with each other.
+///
+/// Each crate is behind a feature, all on by default, and re-exported under a short name.
+/// The SDKs they're built against are re-exported too, so downstream code can use the
+/// same versions instead of pinning its own.
+pub use anchor_client;
+pub use solana_sdk;
+
+#[cfg(feature = "tx")]
+pub use solana_client_tx_processor as tx;
+#[cfg(feature = "rpc")]
+pub use solana_rpc_client_headers as rpc;
+#[cfg(feature = "cli")]
+pub use jungle_fi_cli_utils as cli;
+#[cfg(feature = "localnet")]
+pub use jungle_fi_localnet_tools as localnet;
+#[cfg(feature = "signers")]
+pub use solana_extra_signers as signers;
+
+/// The most used items, for `use jungle_fi::prelude::*;`.
+pub mod prelude {
+    #[cfg(feature = "tx")]
+    pub use solana_client_tx_processor::{
+        ProcessedTransaction, Processing, TransactionProcessor, TransactionProcessorError,
+    };
+    #[cfg(feature = "rpc")]
+    pub use solana_rpc_client_headers::HttpSenderWithHeaders;
+    #[cfg(feature = "cli")]
+    pub use jungle_fi_cli_utils::clap::{CliContext, SolanaCliArgs};
+    #[cfg(feature = "cli")]
+    pub use jungle_fi_cli_utils::cli::{keypair_from_path, resolve_url};
+    #[cfg(feature = "localnet")]
+    pub use jungle_fi_localnet_tools::{LocalnetAccount, TestTomlGenerator};
+    #[cfg(feature = "signers")]
+    pub use solana_extra_signers::{SignerSet, ThreadsafeSigner};
+}
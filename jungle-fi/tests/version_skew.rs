@@ -0,0 +1,82 @@
+//! Passes types between the re-exported crates and the re-exported SDK, so a crate
+//! that drifts onto another Solana or Anchor version fails to compile here.
+use jungle_fi::anchor_client::solana_client::rpc_client::RpcClient;
+use jungle_fi::prelude::*;
+use jungle_fi::solana_sdk::instruction::Instruction;
+use jungle_fi::solana_sdk::pubkey::Pubkey;
+use jungle_fi::solana_sdk::signature::{Keypair, Signer};
+
+struct Noop;
+
+impl TransactionProcessor for Noop {
+    type OnlineArgs = ();
+    type RemainingArgs = ();
+
+    fn get_online_args(&self, _: &RpcClient) -> Result<(), TransactionProcessorError> {
+        Ok(())
+    }
+
+    fn name(&self, _: &Pubkey, _: &(), _: &()) -> String {
+        "noop".to_string()
+    }
+
+    fn calc_remaining_args(&self, _: &(), _: &Pubkey) -> Result<(), TransactionProcessorError> {
+        Ok(())
+    }
+
+    fn named_instructions(
+        &self,
+        primary_signer: &Pubkey,
+        _: (),
+        _: (),
+    ) -> Result<Vec<(String, Instruction)>, TransactionProcessorError> {
+        Ok(vec![("noop".to_string(), Instruction::new_with_bytes(*primary_signer, &[], vec![]))])
+    }
+}
+
+#[test]
+fn tx() {
+    let processed = Noop.process(Processing::OfflineInstructions((), Pubkey::new_unique()), &mut vec![]).unwrap();
+    assert!(matches!(processed, ProcessedTransaction::InstructionSet { .. }));
+}
+
+#[test]
+fn rpc() {
+    let sender = HttpSenderWithHeaders::new("http://localhost:8899", None);
+    let _client = RpcClient::new_sender(sender, Default::default());
+}
+
+#[test]
+fn cli() {
+    let url = resolve_url(&Some("devnet".to_string()), None).unwrap();
+    assert_eq!(url, "https://api.devnet.solana.com");
+    let path = std::env::temp_dir().join(format!("jungle-fi-{}.json", std::process::id()));
+    let keypair = Keypair::new();
+    jungle_fi::solana_sdk::signature::write_keypair_file(&keypair, &path).unwrap();
+    let read = keypair_from_path(&path.display().to_string());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(read.unwrap().pubkey(), keypair.pubkey());
+}
+
+#[test]
+fn localnet() {
+    let account = LocalnetAccount {
+        address: Pubkey::new_unique(),
+        name: "noop".to_string(),
+        ..LocalnetAccount::default()
+    };
+    let generator = TestTomlGenerator {
+        accounts: vec![account.clone()],
+        ..TestTomlGenerator::default()
+    };
+    assert_eq!(generator.accounts[0].address, account.address);
+}
+
+#[test]
+fn signers() {
+    let keypair = Keypair::new();
+    let pubkey = keypair.pubkey();
+    let mut set = SignerSet::new();
+    set.push(Box::new(ThreadsafeSigner::new(keypair)));
+    assert_eq!(set.pubkeys(), vec![pubkey]);
+}
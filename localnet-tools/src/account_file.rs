@@ -0,0 +1,201 @@
+/// Reads the `{"pubkey", "account"}` JSON files consumed by `solana-test-validator`
+/// back into SDK types, and compares accounts, e.g. an old and a new clone of a fixture.
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use serde_json::Value;
+use solana_account_decoder::UiAccount;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::Account;
+use crate::error::LocalnetError;
+
+/// Parse an account file written by [crate::LocalnetAccount::write_to_validator_json_file]
+/// or `solana account --output json`, with its data in base58, base64 or base64+zstd.
+/// Use [solana_sdk::account::AccountSharedData::from] for an [solana_sdk::account::AccountSharedData].
+pub fn parse_account_file(path: impl AsRef<Path>) -> Result<(Pubkey, Account), LocalnetError> {
+    let path = path.as_ref();
+    let file = File::open(path)
+        .map_err(|error| LocalnetError::Read { path: path.to_path_buf(), error })?;
+    let invalid = |message: String| LocalnetError::InvalidAccountFile {
+        path: path.to_path_buf(),
+        message,
+    };
+    let value: Value = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| invalid(e.to_string()))?;
+    parse_account_json(&value).map_err(|e| match e {
+        LocalnetError::InvalidAccountJson { message } => invalid(message),
+        e => e,
+    })
+}
+
+/// Like [parse_account_file], for JSON that's already been read.
+pub fn parse_account_json(value: &Value) -> Result<(Pubkey, Account), LocalnetError> {
+    let invalid = |message: String| LocalnetError::InvalidAccountJson { message };
+    let pubkey = value["pubkey"].as_str()
+        .ok_or_else(|| invalid("missing \"pubkey\"".to_string()))?;
+    let address = Pubkey::from_str(pubkey)
+        .map_err(|e| invalid(format!("invalid pubkey {}: {}", pubkey, e)))?;
+    let ui_account: UiAccount = serde_json::from_value(value["account"].clone())
+        .map_err(|e| invalid(format!("invalid \"account\": {}", e)))?;
+    let account = ui_account.decode()
+        .ok_or_else(|| invalid("account data could not be decoded".to_string()))?;
+    Ok((address, account))
+}
+
+/// The differences between two accounts, see [diff_accounts].
+/// Each changed field holds its (before, after) values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub lamports: Option<(u64, u64)>,
+    pub owner: Option<(Pubkey, Pubkey)>,
+    pub executable: Option<(bool, bool)>,
+    pub rent_epoch: Option<(u64, u64)>,
+    pub data_len: Option<(usize, usize)>,
+    /// Byte ranges where the data differs. When the lengths differ, the extra
+    /// bytes of the longer account are one range.
+    pub data: Vec<Range<usize>>,
+}
+
+impl AccountDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for AccountDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences");
+        }
+        let mut lines = vec![];
+        if let Some((a, b)) = self.lamports {
+            lines.push(format!("lamports: {} -> {}", a, b));
+        }
+        if let Some((a, b)) = self.owner {
+            lines.push(format!("owner: {} -> {}", a, b));
+        }
+        if let Some((a, b)) = self.executable {
+            lines.push(format!("executable: {} -> {}", a, b));
+        }
+        if let Some((a, b)) = self.rent_epoch {
+            lines.push(format!("rent_epoch: {} -> {}", a, b));
+        }
+        if let Some((a, b)) = self.data_len {
+            lines.push(format!("data length: {} -> {}", a, b));
+        }
+        for range in &self.data {
+            lines.push(format!("data bytes {}..{} differ", range.start, range.end));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Compare two accounts field by field, and their data byte by byte.
+pub fn diff_accounts(a: &Account, b: &Account) -> AccountDiff {
+    fn changed<T: PartialEq + Copy>(a: T, b: T) -> Option<(T, T)> {
+        (a != b).then_some((a, b))
+    }
+    let mut data = vec![];
+    let mut start = None;
+    let common = a.data.len().min(b.data.len());
+    for i in 0..common {
+        match (a.data[i] != b.data[i], start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                data.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    let end = a.data.len().max(b.data.len());
+    if let Some(s) = start {
+        data.push(s..end);
+    } else if common < end {
+        data.push(common..end);
+    }
+    AccountDiff {
+        lamports: changed(a.lamports, b.lamports),
+        owner: changed(a.owner, b.owner),
+        executable: changed(a.executable, b.executable),
+        rent_epoch: changed(a.rent_epoch, b.rent_epoch),
+        data_len: changed(a.data.len(), b.data.len()),
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_account_decoder::UiAccountEncoding;
+    use solana_sdk::account::AccountSharedData;
+    use tempfile::TempDir;
+    use crate::LocalnetAccount;
+    use super::*;
+
+    #[test]
+    fn parses_every_encoding() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let account = LocalnetAccount {
+            address: Pubkey::new_unique(),
+            lamports: 1_000,
+            account_data: (0..=255).collect(),
+            owner: Pubkey::new_unique(),
+            executable: true,
+            rent_epoch: 3,
+            name: String::new(),
+        };
+        for (encoding, name) in [
+            (UiAccountEncoding::Base58, "base58.json"),
+            (UiAccountEncoding::Base64, "base64.json"),
+            (UiAccountEncoding::Base64Zstd, "base64zstd.json"),
+        ] {
+            let account = LocalnetAccount { name: name.to_string(), ..account.clone() };
+            account.write_to_validator_json_file_with_encoding(dir.to_str().unwrap(), encoding).unwrap();
+            let (address, parsed) = parse_account_file(dir.join(name)).unwrap();
+            assert_eq!(address, account.address);
+            assert_eq!(parsed, account.to_account());
+            assert_eq!(AccountSharedData::from(parsed), AccountSharedData::from(account.to_account()));
+        }
+    }
+
+    #[test]
+    fn invalid_json_is_an_error() {
+        let value = serde_json::json!({ "pubkey": "not a pubkey", "account": {} });
+        assert!(matches!(
+            parse_account_json(&value),
+            Err(LocalnetError::InvalidAccountJson { message }) if message.starts_with("invalid pubkey")
+        ));
+    }
+
+    #[test]
+    fn diff_highlights_changed_bytes() {
+        let a = Account {
+            lamports: 10,
+            data: vec![0; 64],
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        assert!(diff_accounts(&a, &a).is_empty());
+
+        let mut b = a.clone();
+        b.lamports = 20;
+        b.data[8..12].copy_from_slice(&[1, 2, 3, 4]);
+        let diff = diff_accounts(&a, &b);
+        assert_eq!(diff, AccountDiff {
+            lamports: Some((10, 20)),
+            data: vec![8..12],
+            ..Default::default()
+        });
+        assert_eq!(diff.to_string(), "lamports: 10 -> 20\ndata bytes 8..12 differ");
+
+        b.data.extend([5; 4]);
+        let diff = diff_accounts(&a, &b);
+        assert_eq!(diff.data_len, Some((64, 68)));
+        assert_eq!(diff.data, vec![8..12, 64..68]);
+    }
+}
@@ -0,0 +1,220 @@
+/// Funding accounts from the test validator's faucet, with the retries and
+/// confirmation polling that every test suite otherwise re-implements.
+use std::time::{Duration, Instant};
+use anchor_client::solana_client::client_error::ClientError;
+use anchor_client::solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::TransactionError;
+use thiserror::Error;
+
+/// How many times an airdrop request is sent before the faucet is considered unavailable.
+pub const AIRDROP_ATTEMPTS: usize = 5;
+/// How long to wait for an airdrop to reach processed commitment.
+pub const AIRDROP_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(30);
+const RETRY_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Error)]
+pub enum AirdropError {
+    #[error("faucet unavailable after {attempts} attempts: {source}")]
+    FaucetUnavailable {
+        attempts: usize,
+        source: ClientError,
+    },
+    #[error("airdrop {signature} was not confirmed within {timeout:?}")]
+    ConfirmationTimeout {
+        signature: Signature,
+        timeout: Duration,
+    },
+    #[error("airdrop {signature} failed: {error}")]
+    TransactionFailed {
+        signature: Signature,
+        error: TransactionError,
+    },
+    #[error("rpc client error: {0}")]
+    ClientError(#[from] ClientError),
+}
+
+/// Request an airdrop and wait until it is processed.
+pub fn airdrop(client: &RpcClient, to: &Pubkey, lamports: u64) -> Result<Signature, AirdropError> {
+    let signature = request_airdrop(client, to, lamports)?;
+    confirm_airdrops(client, &[signature])?;
+    Ok(signature)
+}
+
+/// Request all of the airdrops up front, then wait until every one of them is processed.
+pub fn airdrop_many(
+    client: &RpcClient,
+    recipients: &[(Pubkey, u64)],
+) -> Result<Vec<Signature>, AirdropError> {
+    let signatures = recipients
+        .iter()
+        .map(|(to, lamports)| request_airdrop(client, to, *lamports))
+        .collect::<Result<Vec<_>, _>>()?;
+    confirm_airdrops(client, &signatures)?;
+    Ok(signatures)
+}
+
+fn request_airdrop(client: &RpcClient, to: &Pubkey, lamports: u64) -> Result<Signature, AirdropError> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match client.request_airdrop(to, lamports) {
+            Ok(signature) => return Ok(signature),
+            Err(source) if attempts >= AIRDROP_ATTEMPTS => {
+                return Err(AirdropError::FaucetUnavailable { attempts, source });
+            }
+            Err(_) => std::thread::sleep(RETRY_INTERVAL),
+        }
+    }
+}
+
+fn confirm_airdrops(client: &RpcClient, signatures: &[Signature]) -> Result<(), AirdropError> {
+    let started = Instant::now();
+    for signature in signatures {
+        loop {
+            let status = client.get_signature_status_with_commitment(
+                signature,
+                CommitmentConfig::processed(),
+            )?;
+            match status {
+                Some(Ok(())) => break,
+                Some(Err(error)) => {
+                    return Err(AirdropError::TransactionFailed { signature: *signature, error });
+                }
+                None if started.elapsed() >= AIRDROP_CONFIRMATION_TIMEOUT => {
+                    return Err(AirdropError::ConfirmationTimeout {
+                        signature: *signature,
+                        timeout: AIRDROP_CONFIRMATION_TIMEOUT,
+                    });
+                }
+                None => std::thread::sleep(RETRY_INTERVAL),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Async versions of the airdrop helpers, for the nonblocking RPC client.
+#[cfg(feature = "tokio")]
+pub mod nonblocking {
+    use anchor_client::solana_client::nonblocking::rpc_client::RpcClient;
+    use super::*;
+
+    /// Request an airdrop and wait until it is processed.
+    pub async fn airdrop(
+        client: &RpcClient,
+        to: &Pubkey,
+        lamports: u64,
+    ) -> Result<Signature, AirdropError> {
+        let signature = request_airdrop(client, to, lamports).await?;
+        confirm_airdrops(client, &[signature]).await?;
+        Ok(signature)
+    }
+
+    /// Request all of the airdrops up front, then wait until every one of them is processed.
+    pub async fn airdrop_many(
+        client: &RpcClient,
+        recipients: &[(Pubkey, u64)],
+    ) -> Result<Vec<Signature>, AirdropError> {
+        let mut signatures = vec![];
+        for (to, lamports) in recipients {
+            signatures.push(request_airdrop(client, to, *lamports).await?);
+        }
+        confirm_airdrops(client, &signatures).await?;
+        Ok(signatures)
+    }
+
+    async fn request_airdrop(
+        client: &RpcClient,
+        to: &Pubkey,
+        lamports: u64,
+    ) -> Result<Signature, AirdropError> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match client.request_airdrop(to, lamports).await {
+                Ok(signature) => return Ok(signature),
+                Err(source) if attempts >= AIRDROP_ATTEMPTS => {
+                    return Err(AirdropError::FaucetUnavailable { attempts, source });
+                }
+                Err(_) => tokio::time::sleep(RETRY_INTERVAL).await,
+            }
+        }
+    }
+
+    async fn confirm_airdrops(
+        client: &RpcClient,
+        signatures: &[Signature],
+    ) -> Result<(), AirdropError> {
+        let started = Instant::now();
+        for signature in signatures {
+            loop {
+                let status = client.get_signature_status_with_commitment(
+                    signature,
+                    CommitmentConfig::processed(),
+                ).await?;
+                match status {
+                    Some(Ok(())) => break,
+                    Some(Err(error)) => {
+                        return Err(AirdropError::TransactionFailed { signature: *signature, error });
+                    }
+                    None if started.elapsed() >= AIRDROP_CONFIRMATION_TIMEOUT => {
+                        return Err(AirdropError::ConfirmationTimeout {
+                            signature: *signature,
+                            timeout: AIRDROP_CONFIRMATION_TIMEOUT,
+                        });
+                    }
+                    None => tokio::time::sleep(RETRY_INTERVAL).await,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_cli::config::{Config, TestValidator, Validator};
+    use solana_sdk::signature::{Keypair, Signer};
+    use crate::test_validator::{start_test_validator, TestValidatorOptions};
+    use super::*;
+
+    #[test]
+    fn unreachable_faucet_is_reported() {
+        // The mock client returns a null response for every request when its url is "fails".
+        let client = RpcClient::new_mock("fails".to_string());
+        let err = airdrop(&client, &Pubkey::new_unique(), 1).unwrap_err();
+        assert!(matches!(err, AirdropError::FaucetUnavailable { attempts: AIRDROP_ATTEMPTS, .. }));
+    }
+
+    #[test]
+    #[ignore = "requires solana-test-validator on the PATH"]
+    fn airdrop_funds_a_fresh_keypair() {
+        let test_validator = Some(TestValidator {
+            validator: Some(Validator {
+                ledger: ".anchor/airdrop-test-ledger".to_string(),
+                bind_address: "0.0.0.0".to_string(),
+                rpc_port: 8899,
+                ..Default::default()
+            }),
+            startup_wait: 30_000,
+            ..Default::default()
+        });
+        let mut config = Config::default();
+        config.test_validator = test_validator.clone();
+        let options = TestValidatorOptions {
+            auto_pick_ports: true,
+            mint_pubkey: Some(Pubkey::new_unique()),
+            ..Default::default()
+        };
+        let handle = start_test_validator(&config, &test_validator, None, true, &options).unwrap();
+        let recipients = [Keypair::new().pubkey(), Keypair::new().pubkey()];
+        handle.airdrop(&recipients[0], 1_000_000_000).unwrap();
+        handle.airdrop_many(&[(recipients[1], 2_000_000_000)]).unwrap();
+        let client = RpcClient::new(handle.rpc_url.clone());
+        assert_eq!(client.get_balance(&recipients[0]).unwrap(), 1_000_000_000);
+        assert_eq!(client.get_balance(&recipients[1]).unwrap(), 2_000_000_000);
+    }
+}
@@ -0,0 +1,260 @@
+/// An async front-end for running the test validator under tokio, for harnesses that
+/// need to await readiness and shutdown instead of blocking on stdin.
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use anchor_cli::config::{Config, TestValidator, WithPath};
+use anchor_client::solana_client::nonblocking::rpc_client::RpcClient;
+use anyhow::{anyhow, Result};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use tokio::task::JoinHandle;
+use crate::airdrop::{nonblocking, AirdropError};
+use crate::log_rotation::{LogTarget, LogTargets};
+use crate::program_logs::create_run_dir;
+use crate::test_validator::{
+    log_stream_targets, prepare_test_validator, PreparedTestValidator, TestValidatorOptions,
+};
+
+/// A test validator spawned by [spawn_localnet]. The validator and any log streams
+/// are killed when this is dropped, but prefer [LocalnetHandle::shutdown].
+pub struct LocalnetHandle {
+    child: Child,
+    config: WithPath<Config>,
+    log_streams: Vec<(Child, JoinHandle<()>)>,
+    pub rpc_url: String,
+    pub rpc_port: u16,
+    pub faucet_port: u16,
+    /// The file receiving the validator's stdout.
+    pub stdout_log: PathBuf,
+    /// The file receiving the validator's stderr, which is the same as
+    /// [LocalnetHandle::stdout_log] unless the options separate them.
+    pub stderr_log: PathBuf,
+    /// Where program logs are streamed once the validator is ready.
+    pub program_logs_dir: PathBuf,
+    startup_wait: Duration,
+}
+
+/// Spawn `solana-test-validator` without waiting for it to start.
+/// Call [LocalnetHandle::await_ready] before using it.
+/// Output from the validator is written to [LocalnetHandle::stdout_log] and
+/// [LocalnetHandle::stderr_log].
+pub async fn spawn_localnet(
+    config: WithPath<Config>,
+    test_validator: &Option<TestValidator>,
+    flags: impl Into<Vec<String>>,
+    options: &TestValidatorOptions,
+) -> Result<LocalnetHandle> {
+    let PreparedTestValidator {
+        command,
+        rpc_url,
+        rpc_port,
+        faucet_port,
+        log_filename,
+        startup_wait,
+    } = prepare_test_validator(&config, test_validator, Some(flags.into()), options)?;
+    let log_targets = LogTargets::open(&log_filename, &options.log_options)?;
+    let program_logs_dir = create_run_dir(
+        "localnet",
+        options.program_logs_dir.as_deref(),
+        options.program_logs_retention,
+    )?;
+    let mut child = Command::from(command)
+        .stdout(log_targets.stdout.stdio()?)
+        .stderr(log_targets.stderr.stdio()?)
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("Failed to spawn solana-test-validator: {}", e))?;
+    pump_log(&log_targets.stdout, child.stdout.take());
+    pump_log(&log_targets.stderr, child.stderr.take());
+    Ok(LocalnetHandle {
+        child,
+        config,
+        log_streams: vec![],
+        rpc_url,
+        rpc_port,
+        faucet_port,
+        stdout_log: log_targets.stdout_path,
+        stderr_log: log_targets.stderr_path,
+        program_logs_dir,
+        startup_wait: Duration::from_millis(startup_wait.max(0) as u64),
+    })
+}
+
+// The async equivalent of [LogTarget::pump], for rotated log files.
+fn pump_log<R: AsyncRead + Unpin + Send + 'static>(target: &LogTarget, pipe: Option<R>) {
+    if let (LogTarget::Rotating(file), Some(mut pipe)) = (target, pipe) {
+        let file = file.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 8192];
+            while let Ok(n) = pipe.read(&mut buf).await {
+                if n == 0 {
+                    break;
+                }
+                let mut file = match file.lock() {
+                    Ok(file) => file,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+                if file.write_all(&buf[..n]).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+impl LocalnetHandle {
+    /// A nonblocking client connected to the validator.
+    pub fn rpc_client(&self) -> RpcClient {
+        RpcClient::new(self.rpc_url.clone())
+    }
+
+    /// The port of the validator's faucet, for tools that request airdrops directly.
+    pub fn faucet_port(&self) -> u16 {
+        self.faucet_port
+    }
+
+    /// Fund `to` from the faucet, retrying if the faucet isn't ready yet,
+    /// and wait until the airdrop is processed.
+    pub async fn airdrop(&self, to: &Pubkey, lamports: u64) -> Result<Signature, AirdropError> {
+        nonblocking::airdrop(&self.rpc_client(), to, lamports).await
+    }
+
+    /// Fund several accounts, waiting until every airdrop is processed.
+    pub async fn airdrop_many(
+        &self,
+        recipients: &[(Pubkey, u64)],
+    ) -> Result<Vec<Signature>, AirdropError> {
+        nonblocking::airdrop_many(&self.rpc_client(), recipients).await
+    }
+
+    /// Wait until the validator responds to RPC requests, then start streaming program logs.
+    /// Errors if the validator exits or doesn't respond within its configured `startup_wait`.
+    pub async fn await_ready(&mut self) -> Result<()> {
+        let client = self.rpc_client();
+        let started = tokio::time::Instant::now();
+        loop {
+            if client.get_latest_blockhash().await.is_ok() {
+                break;
+            }
+            if let Some(status) = self.child.try_wait()? {
+                return Err(anyhow!(
+                    "Test validator exited with {} during startup. Check {} for errors.",
+                    status, self.stderr_log.display()
+                ));
+            }
+            if started.elapsed() >= self.startup_wait {
+                return Err(anyhow!(
+                    "Unable to get latest blockhash. Test validator does not look started. Check {} for errors. Consider increasing [test.startup_wait] in Anchor.toml.",
+                    self.stderr_log.display()
+                ));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        // Like the blocking front-end, a missing IDL shouldn't prevent the validator from being used.
+        if self.log_streams.is_empty() {
+            if let Err(err) = self.stream_logs() {
+                log::warn!("Failed to stream program logs: {}", err);
+            }
+        }
+        Ok(())
+    }
+
+    // Same as the blocking log streaming, except the output is copied into
+    // each log file by a tokio task.
+    fn stream_logs(&mut self) -> Result<()> {
+        for (address, log_file) in log_stream_targets(&self.config, &self.program_logs_dir)? {
+            let mut child = Command::new("solana")
+                .arg("logs")
+                .arg(address)
+                .arg("--url")
+                .arg(&self.rpc_url)
+                .stdout(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()?;
+            let mut stdout = child.stdout.take()
+                .ok_or_else(|| anyhow!("Failed to capture the output of solana logs"))?;
+            let task = tokio::spawn(async move {
+                if let Ok(mut file) = tokio::fs::File::create(log_file).await {
+                    let _ = tokio::io::copy(&mut stdout, &mut file).await;
+                }
+            });
+            self.log_streams.push((child, task));
+        }
+        Ok(())
+    }
+
+    /// Resolves when the validator exits. Since it should run until [LocalnetHandle::shutdown],
+    /// any exit is unexpected.
+    pub async fn wait_crashed(&mut self) -> Result<ExitStatus> {
+        Ok(self.child.wait().await?)
+    }
+
+    /// Kill the validator and any log streams, and wait for them to exit.
+    pub async fn shutdown(mut self) -> Result<()> {
+        for (mut child, task) in self.log_streams.drain(..) {
+            let _ = child.kill().await;
+            let _ = task.await;
+        }
+        if self.child.try_wait()?.is_none() {
+            self.child.kill().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use anchor_cli::config::Validator;
+    use anchor_client::solana_sdk::signature::{Keypair, Signer};
+    use anchor_client::solana_sdk::transaction::Transaction;
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires solana-test-validator on the PATH"]
+    async fn memo_on_async_localnet() {
+        let payer = Keypair::new();
+        let test_validator = Some(TestValidator {
+            validator: Some(Validator {
+                ledger: ".anchor/async-test-ledger".to_string(),
+                bind_address: "0.0.0.0".to_string(),
+                rpc_port: 8899,
+                ..Default::default()
+            }),
+            startup_wait: 30_000,
+            ..Default::default()
+        });
+        let mut config = Config::default();
+        config.test_validator = test_validator.clone();
+        let options = TestValidatorOptions {
+            auto_pick_ports: true,
+            mint_pubkey: Some(payer.pubkey()),
+            ..Default::default()
+        };
+        let mut localnet = spawn_localnet(
+            WithPath::new(config, PathBuf::from("Anchor.toml")),
+            &test_validator,
+            vec![],
+            &options,
+        ).await.unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(60), async {
+            localnet.await_ready().await.unwrap();
+            let client = localnet.rpc_client();
+            let blockhash = client.get_latest_blockhash().await.unwrap();
+            let tx = Transaction::new_signed_with_payer(
+                &[spl_memo::build_memo(b"hello", &[&payer.pubkey()])],
+                Some(&payer.pubkey()),
+                &[&payer],
+                blockhash,
+            );
+            client.send_and_confirm_transaction(&tx).await.unwrap();
+        }).await;
+        localnet.shutdown().await.unwrap();
+        result.unwrap();
+    }
+}
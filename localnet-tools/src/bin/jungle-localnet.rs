@@ -0,0 +1,12 @@
+use clap::Parser;
+use jungle_fi_localnet_tools::localnet_cli::JungleLocalnet;
+
+fn main() {
+    match JungleLocalnet::parse().process() {
+        Ok(code) => std::process::exit(code),
+        Err(err) => {
+            eprintln!("Error: {:#}", err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,7 +1,12 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use anchor_cli::config::TestConfig;
 use anyhow::anyhow;
 use clap::Parser;
-use crate::test_validator::localnet_from_test_config;
+use solana_program::pubkey::Pubkey;
+use crate::idl::{parse_idl_from_source, write_idl_address};
+use crate::test_validator::{LedgerPolicy, localnet_from_test_config, LocalnetOverrides, TestValidatorOptions};
 use crate::TestTomlGenerator;
 
 #[derive(Debug, Parser)]
@@ -10,6 +15,36 @@ pub enum Subcommand {
     FromTestConfig {
         cfg: String,
         flags: Vec<String>,
+        /// Pick free ports if the configured RPC or faucet port is in use.
+        #[clap(long)]
+        auto_pick_ports: bool,
+        /// Whether to recreate the ledger from genesis, or keep the previous run's state.
+        #[clap(long, value_enum, default_value_t = LedgerPolicy::Reset)]
+        ledger_policy: LedgerPolicy,
+        /// Address to receive the faucet supply, instead of the Anchor wallet.
+        #[clap(long)]
+        mint: Option<Pubkey>,
+        /// Path to the Anchor.toml, if it isn't in the working directory or one of its parents.
+        #[clap(long)]
+        anchor_toml: Option<PathBuf>,
+        /// Overrides the provider wallet in the Anchor.toml.
+        #[clap(long)]
+        wallet: Option<PathBuf>,
+    },
+    /// Write program addresses into the IDL metadata in `target/idl`, e.g. after
+    /// deploying to a validator that `anchor test --skip-local-validator` will use.
+    FixIdlMetadata {
+        /// `<NAME_OR_PATH>=<ADDRESS>`, where the name is a workspace program,
+        /// and a path is an IDL JSON file. Can be repeated.
+        #[clap(long = "program", value_parser = parse_program_address)]
+        programs: Vec<(String, Pubkey)>,
+        /// Also fix every program in the `[programs.localnet]` section of this Anchor.toml.
+        #[clap(long, num_args = 0..=1, default_missing_value = "Anchor.toml")]
+        from_anchor_toml: Option<PathBuf>,
+        /// The workspace directory. Defaults to the Anchor.toml's directory,
+        /// or the working directory.
+        #[clap(long)]
+        workspace: Option<PathBuf>,
     },
 }
 
@@ -23,15 +58,46 @@ impl SolanaLocalnetCli {
     pub fn process(self, test_toml_generators: Vec<TestTomlGenerator>) -> anyhow::Result<()> {
         if let Some(subcommand) = self.command {
             match subcommand {
-                Subcommand::FromTestConfig { cfg, flags } => {
+                Subcommand::FromTestConfig {
+                    cfg,
+                    flags,
+                    auto_pick_ports,
+                    ledger_policy,
+                    mint,
+                    anchor_toml,
+                    wallet,
+                } => {
                     let test_config = TestConfig::discover(&cfg, vec![])?;
                     if let Some(test_config) = test_config {
-                        localnet_from_test_config(test_config, flags)?;
+                        let options = TestValidatorOptions {
+                            auto_pick_ports,
+                            ledger_policy,
+                            mint_pubkey: mint,
+                            ..Default::default()
+                        };
+                        let overrides = LocalnetOverrides { anchor_toml, wallet };
+                        localnet_from_test_config(test_config, flags, &options, &overrides)?;
                         return Ok(())
                     }
                     return Err(anyhow!(
                         "Could not find {}, you might need to build the localnet first.", &cfg));
                 },
+                Subcommand::FixIdlMetadata { mut programs, from_anchor_toml, workspace } => {
+                    if let Some(anchor_toml) = &from_anchor_toml {
+                        programs.extend(localnet_programs(anchor_toml)?);
+                    }
+                    if programs.is_empty() {
+                        return Err(anyhow!("No programs given, use --program or --from-anchor-toml"));
+                    }
+                    let workspace = workspace
+                        .or_else(|| from_anchor_toml.as_ref()
+                            .and_then(|path| path.parent())
+                            .map(|dir| dir.to_path_buf()))
+                        .unwrap_or_default();
+                    for (path, address) in fix_idl_metadata(&workspace, &programs)? {
+                        println!("Updated {} with address {}", path.display(), address);
+                    }
+                }
                 Subcommand::Build => {
                     build_test_toml_files(test_toml_generators)?;
                 }
@@ -56,3 +122,168 @@ pub fn build_test_toml_files(test_toml_generators: Vec<TestTomlGenerator>) -> an
     Ok(())
 }
 
+
+/// Set the address in the IDL metadata of each `(name or path, address)` pair,
+/// like `anchor test` does when it starts a validator. Names are looked up in
+/// `target/idl` under `workspace`. If the IDL hasn't been built yet, it's parsed
+/// from `programs/<name>/src/lib.rs`.
+///
+/// Nothing is written unless every IDL is found. Returns the updated files.
+pub fn fix_idl_metadata(
+    workspace: &Path,
+    programs: &[(String, Pubkey)],
+) -> anyhow::Result<Vec<(PathBuf, Pubkey)>> {
+    let mut found = vec![];
+    let mut missing = vec![];
+    for (program, address) in programs {
+        match find_idl(workspace, program) {
+            Some(idl) => found.push((idl, *address)),
+            None => missing.push(program.as_str()),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "No IDL JSON or program source found for: {}",
+            missing.join(", ")
+        ));
+    }
+    let mut updated = vec![];
+    for (idl, address) in found {
+        let path = match idl {
+            IdlLocation::Json(path) => path,
+            IdlLocation::Source { lib_rs, json } => {
+                let idl = parse_idl_from_source(&lib_rs.display().to_string())?;
+                fs::create_dir_all(json.parent().unwrap())?;
+                fs::write(&json, serde_json::to_string_pretty(&idl)?)?;
+                json
+            }
+        };
+        write_idl_address(&path, &address.to_string())?;
+        updated.push((path, address));
+    }
+    Ok(updated)
+}
+
+enum IdlLocation {
+    Json(PathBuf),
+    /// Not built yet, so written to `json` from the program source.
+    Source { lib_rs: PathBuf, json: PathBuf },
+}
+
+fn find_idl(workspace: &Path, program: &str) -> Option<IdlLocation> {
+    if program.ends_with(".json") || program.contains('/') {
+        let path = PathBuf::from(program);
+        return path.is_file().then_some(IdlLocation::Json(path));
+    }
+    // Anchor names IDLs after the program's lib name, which uses underscores.
+    let json = workspace.join("target/idl").join(program.replace('-', "_")).with_extension("json");
+    if json.is_file() {
+        return Some(IdlLocation::Json(json));
+    }
+    [program.to_string(), program.replace('_', "-")]
+        .into_iter()
+        .map(|dir| workspace.join("programs").join(dir).join("src/lib.rs"))
+        .find(|lib_rs| lib_rs.is_file())
+        .map(|lib_rs| IdlLocation::Source { lib_rs, json })
+}
+
+// The `[programs.localnet]` section of an Anchor.toml, as (name, address) pairs.
+fn localnet_programs(anchor_toml: &Path) -> anyhow::Result<Vec<(String, Pubkey)>> {
+    let contents = fs::read_to_string(anchor_toml)
+        .map_err(|e| anyhow!("Failed to read {}: {}", anchor_toml.display(), e))?;
+    let config: toml::Value = toml::from_str(&contents)?;
+    let programs = match config.get("programs").and_then(|programs| programs.get("localnet")) {
+        Some(toml::Value::Table(programs)) => programs,
+        _ => return Err(anyhow!("{} has no [programs.localnet] section", anchor_toml.display())),
+    };
+    programs
+        .iter()
+        .map(|(name, address)| {
+            // Either `name = "address"`, or `name = { address = "address", idl = ... }`.
+            let address = address.as_str()
+                .or_else(|| address.get("address").and_then(|address| address.as_str()))
+                .ok_or_else(|| anyhow!("No address for program {} in {}", name, anchor_toml.display()))?;
+            Ok((name.clone(), Pubkey::from_str(address)?))
+        })
+        .collect()
+}
+
+fn parse_program_address(arg: &str) -> Result<(String, Pubkey), String> {
+    let (program, address) = arg
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected <NAME_OR_PATH>=<ADDRESS>, got {}", arg))?;
+    let address = Pubkey::from_str(address).map_err(|e| format!("invalid address {}: {}", address, e))?;
+    Ok((program.to_string(), address))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+    use tempfile::TempDir;
+    use super::*;
+
+    fn read_json(path: &Path) -> Value {
+        serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn fix_idl_metadata_in_workspace() {
+        let temp = TempDir::new().unwrap();
+        let workspace = temp.path();
+        fs::create_dir_all(workspace.join("target/idl")).unwrap();
+        let foo = Pubkey::new_unique();
+        let bar = Pubkey::new_unique();
+        fs::write(workspace.join("Anchor.toml"), format!(
+            "[programs.localnet]\nfoo = \"{}\"\nbar_baz = {{ address = \"{}\" }}\n", foo, bar,
+        )).unwrap();
+        fs::write(
+            workspace.join("target/idl/foo.json"),
+            r#"{ "name": "foo", "metadata": { "origin": "anchor" } }"#,
+        ).unwrap();
+        fs::write(workspace.join("target/idl/bar_baz.json"), r#"{ "name": "bar_baz" }"#).unwrap();
+
+        let anchor_toml = workspace.join("Anchor.toml");
+        let cli = SolanaLocalnetCli::try_parse_from([
+            "localnet",
+            "fix-idl-metadata",
+            "--from-anchor-toml",
+            anchor_toml.to_str().unwrap(),
+        ]).unwrap();
+        cli.process(vec![]).unwrap();
+        assert_eq!(
+            read_json(&workspace.join("target/idl/foo.json"))["metadata"],
+            serde_json::json!({ "origin": "anchor", "address": foo.to_string() }),
+        );
+        assert_eq!(
+            read_json(&workspace.join("target/idl/bar_baz.json"))["metadata"]["address"],
+            bar.to_string(),
+        );
+
+        // Nothing is written if any program is missing.
+        let other = Pubkey::new_unique();
+        let err = fix_idl_metadata(workspace, &[
+            ("foo".to_string(), other),
+            ("missing".to_string(), other),
+            ("missing/idl.json".to_string(), other),
+        ]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No IDL JSON or program source found for: missing, missing/idl.json",
+        );
+        assert_eq!(
+            read_json(&workspace.join("target/idl/foo.json"))["metadata"]["address"],
+            foo.to_string(),
+        );
+    }
+
+    #[test]
+    fn program_address_pairs() {
+        let address = Pubkey::new_unique();
+        assert_eq!(
+            parse_program_address(&format!("target/idl/foo.json={}", address)).unwrap(),
+            ("target/idl/foo.json".to_string(), address),
+        );
+        assert!(parse_program_address("foo").is_err());
+        assert!(parse_program_address("foo=bar").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+/// Compares account fixtures, e.g. clones of mainnet accounts, against the accounts
+/// currently on a cluster, so stale fixtures are noticed before tests fail in odd ways.
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anchor_client::solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::Account;
+use crate::account_file::{diff_accounts, parse_account_file, AccountDiff};
+use crate::error::LocalnetError;
+
+/// `getMultipleAccounts` accepts at most this many addresses.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Which differences count as drift, and what drift means.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DriftPolicy {
+    /// Balances of e.g. fee payers change all the time.
+    pub ignore_lamports: bool,
+    /// Only compare owners and lamports, e.g. for accounts with timestamps in their data.
+    pub ignore_data: bool,
+    /// Return [LocalnetError::FixtureDrift] rather than a report with drift in it.
+    pub fail_on_drift: bool,
+}
+
+/// How one fixture compares to the cluster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriftStatus {
+    Unchanged,
+    /// The differences from the fixture to the cluster's account.
+    Drifted(AccountDiff),
+    /// The account doesn't exist on the cluster anymore.
+    Missing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixtureDrift {
+    pub path: PathBuf,
+    pub address: Pubkey,
+    pub status: DriftStatus,
+}
+
+/// The result of [check_fixture_drift], one entry per fixture, sorted by path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DriftReport {
+    pub fixtures: Vec<FixtureDrift>,
+    /// JSON files in the directory that aren't account files, such as `tsconfig.json`.
+    pub skipped: Vec<PathBuf>,
+}
+
+impl DriftReport {
+    pub fn drifted(&self) -> Vec<&FixtureDrift> {
+        self.fixtures
+            .iter()
+            .filter(|f| matches!(f.status, DriftStatus::Drifted(_)))
+            .collect()
+    }
+
+    pub fn missing(&self) -> Vec<&FixtureDrift> {
+        self.fixtures
+            .iter()
+            .filter(|f| f.status == DriftStatus::Missing)
+            .collect()
+    }
+
+    /// Whether every fixture matches the cluster.
+    pub fn is_clean(&self) -> bool {
+        self.fixtures.iter().all(|f| f.status == DriftStatus::Unchanged)
+    }
+}
+
+impl fmt::Display for DriftReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for fixture in &self.fixtures {
+            match &fixture.status {
+                DriftStatus::Unchanged => {
+                    writeln!(f, "OK      {} ({})", fixture.path.display(), fixture.address)?;
+                }
+                DriftStatus::Missing => {
+                    writeln!(f, "MISSING {} ({}) no longer exists", fixture.path.display(), fixture.address)?;
+                }
+                DriftStatus::Drifted(diff) => {
+                    writeln!(f, "DRIFTED {} ({})", fixture.path.display(), fixture.address)?;
+                    for line in diff.to_string().lines() {
+                        writeln!(f, "        {}", line)?;
+                    }
+                }
+            }
+        }
+        write!(
+            f,
+            "{} unchanged, {} drifted, {} missing",
+            self.fixtures.len() - self.drifted().len() - self.missing().len(),
+            self.drifted().len(),
+            self.missing().len(),
+        )
+    }
+}
+
+/// Compare every account file in `dir` (not its subdirectories) against the account
+/// at the same address on `client`'s cluster. JSON files that aren't account files
+/// are skipped. Rent epochs are never compared, they change as the cluster runs.
+pub fn check_fixture_drift(
+    client: &RpcClient,
+    dir: &Path,
+    policy: DriftPolicy,
+) -> Result<DriftReport, LocalnetError> {
+    let read_error = |error| LocalnetError::Read { path: dir.to_path_buf(), error };
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir).map_err(read_error)? {
+        let path = entry.map_err(read_error)?.path();
+        if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut report = DriftReport::default();
+    let mut fixtures: Vec<(PathBuf, Pubkey, Account)> = vec![];
+    for path in paths {
+        match parse_account_file(&path) {
+            Ok((address, account)) => fixtures.push((path, address, account)),
+            Err(LocalnetError::InvalidAccountFile { .. }) => report.skipped.push(path),
+            Err(e) => return Err(e),
+        }
+    }
+
+    for chunk in fixtures.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let addresses: Vec<Pubkey> = chunk.iter().map(|(_, address, _)| *address).collect();
+        let on_chain = client.get_multiple_accounts(&addresses)
+            .map_err(|error| LocalnetError::Fetch { url: client.url(), error })?;
+        for ((path, address, fixture), on_chain) in chunk.iter().zip(on_chain) {
+            let status = match on_chain {
+                None => DriftStatus::Missing,
+                Some(on_chain) => {
+                    let diff = drift(fixture, &on_chain, &policy);
+                    if diff.is_empty() {
+                        DriftStatus::Unchanged
+                    } else {
+                        DriftStatus::Drifted(diff)
+                    }
+                }
+            };
+            report.fixtures.push(FixtureDrift { path: path.clone(), address: *address, status });
+        }
+    }
+
+    if policy.fail_on_drift && !report.is_clean() {
+        return Err(LocalnetError::FixtureDrift(Box::new(report)));
+    }
+    Ok(report)
+}
+
+// The differences that matter under `policy`.
+fn drift(fixture: &Account, on_chain: &Account, policy: &DriftPolicy) -> AccountDiff {
+    let mut diff = diff_accounts(fixture, on_chain);
+    diff.rent_epoch = None;
+    if policy.ignore_lamports {
+        diff.lamports = None;
+    }
+    if policy.ignore_data {
+        diff.data_len = None;
+        diff.data = vec![];
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+    use anchor_client::solana_client::mock_sender::Mocks;
+    use anchor_client::solana_client::rpc_request::RpcRequest;
+    use solana_account_decoder::{UiAccount, UiAccountEncoding};
+    use crate::LocalnetAccount;
+    use super::*;
+
+    fn fixture(name: &str, data: Vec<u8>) -> LocalnetAccount {
+        LocalnetAccount {
+            address: Pubkey::new_unique(),
+            lamports: 1_000,
+            account_data: data,
+            owner: Pubkey::new_unique(),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    // A client whose `getMultipleAccounts` returns `accounts`, in order.
+    fn mock_client(accounts: &[Option<&LocalnetAccount>]) -> RpcClient {
+        let value: Vec<Option<UiAccount>> = accounts
+            .iter()
+            .map(|account| account.map(|a| a.ui_account(UiAccountEncoding::Base64)))
+            .collect();
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(
+            RpcRequest::GetMultipleAccounts,
+            serde_json::json!({ "context": { "slot": 1 }, "value": value }),
+        );
+        RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+    }
+
+    #[test]
+    fn reports_drifted_and_missing_fixtures() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let unchanged = fixture("a_unchanged.json", vec![1; 32]);
+        let drifted = fixture("b_drifted.json", vec![1; 32]);
+        let missing = fixture("c_missing.json", vec![]);
+        for account in [&unchanged, &drifted, &missing] {
+            account.write_to_validator_json_file(dir.to_str().unwrap()).unwrap();
+        }
+        fs::write(dir.join("tsconfig.json"), "{}").unwrap();
+
+        let mut on_chain = drifted.clone();
+        on_chain.lamports = 2_000;
+        on_chain.account_data[4..8].copy_from_slice(&[0; 4]);
+        let client = mock_client(&[Some(&unchanged), Some(&on_chain), None]);
+
+        let report = check_fixture_drift(&client, dir, DriftPolicy::default()).unwrap();
+        assert_eq!(report.skipped, vec![dir.join("tsconfig.json")]);
+        assert_eq!(report.fixtures[0].status, DriftStatus::Unchanged);
+        assert_eq!(report.fixtures[1].status, DriftStatus::Drifted(AccountDiff {
+            lamports: Some((1_000, 2_000)),
+            data: vec![4..8],
+            ..Default::default()
+        }));
+        assert_eq!(report.fixtures[2].address, missing.address);
+        assert_eq!(report.fixtures[2].status, DriftStatus::Missing);
+        assert!(report.to_string().ends_with("1 unchanged, 1 drifted, 1 missing"), "{}", report);
+
+        let policy = DriftPolicy { ignore_lamports: true, ignore_data: true, ..Default::default() };
+        let client = mock_client(&[Some(&unchanged), Some(&on_chain), Some(&missing)]);
+        assert!(check_fixture_drift(&client, dir, policy).unwrap().is_clean());
+
+        let policy = DriftPolicy { fail_on_drift: true, ..Default::default() };
+        let client = mock_client(&[Some(&unchanged), Some(&on_chain), None]);
+        match check_fixture_drift(&client, dir, policy) {
+            Err(LocalnetError::FixtureDrift(report)) => assert_eq!(report.drifted().len(), 1),
+            other => panic!("expected drift, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+/// Writes the localnet connection info and fixture addresses as a dotenv file,
+/// for test harnesses that read their configuration from the environment.
+use std::fs;
+use std::path::Path;
+use crate::error::LocalnetError;
+use inflector::Inflector;
+use solana_program::pubkey::Pubkey;
+
+/// Default file name for [LocalnetEnv::write].
+pub const LOCALNET_ENV_FILE: &str = ".env.localnet";
+
+/// The values written to a `.env.localnet` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalnetEnv {
+    pub rpc_url: String,
+    pub ws_url: String,
+    pub faucet_port: Option<u16>,
+    pub wallet_path: Option<String>,
+    /// Written as `ACCOUNT_<NAME>=<pubkey>` lines.
+    pub accounts: Vec<(String, Pubkey)>,
+}
+
+impl LocalnetEnv {
+    /// Uses the conventional pubsub port, one above the RPC port.
+    pub fn new(rpc_url: &str, rpc_port: u16) -> Self {
+        Self {
+            rpc_url: rpc_url.to_string(),
+            ws_url: ws_url(rpc_url, rpc_port),
+            ..Default::default()
+        }
+    }
+
+    /// The `KEY=value` pairs, in the order they are written.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = vec![
+            ("RPC_URL".to_string(), self.rpc_url.clone()),
+            ("WS_URL".to_string(), self.ws_url.clone()),
+        ];
+        if let Some(faucet_port) = self.faucet_port {
+            entries.push(("FAUCET_PORT".to_string(), faucet_port.to_string()));
+        }
+        if let Some(wallet_path) = &self.wallet_path {
+            entries.push(("WALLET_PATH".to_string(), wallet_path.clone()));
+        }
+        for (name, address) in &self.accounts {
+            entries.push((format!("ACCOUNT_{}", env_key(name)), address.to_string()));
+        }
+        entries
+    }
+
+    /// Write the entries to `path`. If the file exists, lines with the same keys
+    /// are replaced in place, and any other lines are kept.
+    pub fn write(&self, path: &Path) -> Result<(), LocalnetError> {
+        let existing = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(LocalnetError::Read { path: path.to_path_buf(), error }),
+        };
+        fs::write(path, merge_env(&existing, &self.entries()))
+            .map_err(|error| LocalnetError::Write { path: path.to_path_buf(), error })
+    }
+}
+
+/// The WebSocket URL of the validator's pubsub service, which listens on `rpc_port + 1`.
+pub fn ws_url(rpc_url: &str, rpc_port: u16) -> String {
+    let ws_url = if let Some(rest) = rpc_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = rpc_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        rpc_url.to_string()
+    };
+    let rpc_port_suffix = format!(":{}", rpc_port);
+    match ws_url.strip_suffix(&rpc_port_suffix) {
+        Some(host) => format!("{}:{}", host, rpc_port.saturating_add(1)),
+        None => ws_url,
+    }
+}
+
+/// An upper snake case environment variable name, e.g. `usdc-mint.json` becomes `USDC_MINT`.
+pub fn env_key(name: &str) -> String {
+    let name = name.strip_suffix(".json").unwrap_or(name);
+    let key: String = name
+        .to_screaming_snake_case()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    if key.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", key)
+    } else {
+        key
+    }
+}
+
+/// Quote a value if it would otherwise be misread by dotenv parsers.
+pub fn escape_value(value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.chars().any(|c| c.is_whitespace() || "\"'#$\\`=".contains(c));
+    if !needs_quotes {
+        return value.to_string();
+    }
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' | '\\' | '$' | '`' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// Replaces existing assignments of the given keys, and appends the rest.
+fn merge_env(existing: &str, entries: &[(String, String)]) -> String {
+    let mut written = vec![false; entries.len()];
+    let mut lines = vec![];
+    for line in existing.lines() {
+        let key = line
+            .trim_start()
+            .trim_start_matches("export ")
+            .split('=')
+            .next()
+            .unwrap_or_default()
+            .trim();
+        match entries.iter().position(|(k, _)| k == key) {
+            Some(i) => {
+                lines.push(format!("{}={}", key, escape_value(&entries[i].1)));
+                written[i] = true;
+            }
+            None => lines.push(line.to_string()),
+        }
+    }
+    for (i, (key, value)) in entries.iter().enumerate() {
+        if !written[i] {
+            lines.push(format!("{}={}", key, escape_value(value)));
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn account_names_are_sanitized() {
+        assert_eq!(env_key("usdc-mint.json"), "USDC_MINT");
+        assert_eq!(env_key("userTokenAccount"), "USER_TOKEN_ACCOUNT");
+        assert_eq!(env_key("pool (main).json"), "POOL_MAIN");
+        assert_eq!(env_key("2nd_wallet"), "_2ND_WALLET");
+    }
+
+    #[test]
+    fn values_are_escaped() {
+        assert_eq!(escape_value("http://localhost:8899"), "http://localhost:8899");
+        assert_eq!(escape_value("~/my wallet.json"), "\"~/my wallet.json\"");
+        assert_eq!(escape_value("a\"b$c"), "\"a\\\"b\\$c\"");
+        assert_eq!(escape_value(""), "\"\"");
+    }
+
+    #[test]
+    fn ws_url_uses_the_pubsub_port() {
+        assert_eq!(ws_url("http://localhost:8899", 8899), "ws://localhost:8900");
+        assert_eq!(ws_url("https://0.0.0.0:18899", 18899), "wss://0.0.0.0:18900");
+    }
+
+    #[test]
+    fn merging_preserves_unrelated_lines() {
+        let existing = "# my settings\nDEBUG=1\nRPC_URL=http://localhost:8899\nexport WS_URL=old\n";
+        let mut env = LocalnetEnv::new("http://localhost:18899", 18899);
+        env.accounts.push(("usdc-mint.json".to_string(), Pubkey::default()));
+        let merged = merge_env(existing, &env.entries());
+        assert_eq!(merged, format!(
+            "# my settings\nDEBUG=1\nRPC_URL=http://localhost:18899\nWS_URL=ws://localhost:18900\nACCOUNT_USDC_MINT={}\n",
+            Pubkey::default(),
+        ));
+    }
+}
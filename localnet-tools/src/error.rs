@@ -0,0 +1,86 @@
+/// Typed errors for the public entry points, so callers can match on the kind of
+/// failure. Both convert into [anyhow::Error] with `?`.
+use std::path::PathBuf;
+use thiserror::Error;
+use crate::validator_version::ValidatorStartError;
+
+/// Failures writing localnet fixtures, or starting a test validator.
+#[derive(Debug, Error)]
+pub enum LocalnetError {
+    #[error("Error reading {}: {error}", .path.display())]
+    Read { path: PathBuf, error: std::io::Error },
+    #[error("Error writing to {}: {error}", .path.display())]
+    Write { path: PathBuf, error: std::io::Error },
+    /// The file isn't a `solana-test-validator` account file, or its data can't be decoded.
+    #[error("Invalid account file {}: {message}", .path.display())]
+    InvalidAccountFile { path: PathBuf, message: String },
+    #[error("Invalid IDL {}: {message}", .path.display())]
+    InvalidIdl { path: PathBuf, message: String },
+    #[error("Invalid account JSON: {message}")]
+    InvalidAccountJson { message: String },
+    /// `solana-test-validator` can only load account data in binary encodings.
+    #[cfg(feature = "accounts")]
+    #[error("Account files can't be written with the {encoding:?} encoding")]
+    UnsupportedEncoding { encoding: solana_account_decoder::UiAccountEncoding },
+    #[error("slots_per_epoch must be set to warp to an epoch")]
+    SlotsPerEpochRequired,
+    #[error("Epoch {epoch} is out of range")]
+    EpochOutOfRange { epoch: u64 },
+    #[error("slots_per_epoch must be at least {minimum}, got {slots_per_epoch}")]
+    SlotsPerEpochTooLow { slots_per_epoch: u64, minimum: u64 },
+    /// Ledger directories are removed on reset, so absolute paths like `/` are refused.
+    #[error("Ledger directory {} must be relative", .path.display())]
+    AbsoluteLedgerPath { path: PathBuf },
+    #[error("Your configured {name} port: {port} is already in use")]
+    PortInUse { name: String, port: u16 },
+    #[error("Your configured {name} port: {port} is already in use, and no free port could be found")]
+    NoFreePort { name: String, port: u16 },
+    #[error(transparent)]
+    ValidatorVersion(#[from] ValidatorStartError),
+    #[error("Failed to spawn solana-test-validator: {0}")]
+    Spawn(std::io::Error),
+    #[error(
+        "Unable to get latest blockhash from {rpc_url} after {waited_ms}ms. Test validator does not look started. \
+        Check {log} for errors. Consider increasing [test.startup_wait] in Anchor.toml."
+    )]
+    StartupTimeout {
+        rpc_url: String,
+        /// The log file with the validator's output.
+        log: String,
+        waited_ms: i32,
+    },
+    #[cfg(feature = "accounts")]
+    #[error("Failed to fetch accounts from {url}: {error}")]
+    Fetch {
+        url: String,
+        error: anchor_client::solana_client::client_error::ClientError,
+    },
+    /// Only with [crate::drift::DriftPolicy::fail_on_drift].
+    #[cfg(feature = "accounts")]
+    #[error("Fixtures differ from the cluster:\n{0}")]
+    FixtureDrift(Box<crate::drift::DriftReport>),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Failures cloning an on-chain account with [crate::LocalnetAccount::new_from_clone].
+#[cfg(feature = "accounts")]
+#[derive(Debug, Error)]
+pub enum CloneError {
+    #[error("Failed to fetch account {address} from {url}: {error}")]
+    Fetch {
+        address: solana_program::pubkey::Pubkey,
+        url: String,
+        error: anchor_client::solana_client::client_error::ClientError,
+    },
+    #[error("Account {address} could not be deserialized as the expected type: {error}")]
+    Deserialize {
+        address: solana_program::pubkey::Pubkey,
+        error: anchor_lang::error::Error,
+    },
+    #[error("Account {address} could not be serialized after modification: {error}")]
+    Serialize {
+        address: solana_program::pubkey::Pubkey,
+        error: anchor_lang::error::Error,
+    },
+}
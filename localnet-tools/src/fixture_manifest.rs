@@ -0,0 +1,95 @@
+/// Records a hash of every fixture file (account JSON files and program binaries)
+/// a ledger was created from, so that a ledger can be reused for as long
+/// as its fixtures haven't changed.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::hash::hash;
+
+/// Saved in the ledger directory.
+pub const FIXTURE_MANIFEST_FILE: &str = "fixture-manifest.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixtureManifest {
+    /// File path to the SHA-256 of its contents, in Base58.
+    pub files: BTreeMap<String, String>,
+}
+
+impl FixtureManifest {
+    /// Hash the contents of every file. Order and duplicates don't matter.
+    pub fn from_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for path in paths {
+            let path = path.as_ref();
+            let contents = fs::read(path)
+                .map_err(|e| anyhow!("Error reading fixture {}: {}", path.display(), e))?;
+            files.insert(path.display().to_string(), hash(&contents).to_string());
+        }
+        Ok(Self { files })
+    }
+
+    /// Load the manifest saved in `ledger_directory`, if there is one.
+    pub fn load(ledger_directory: &str) -> Result<Option<Self>> {
+        let path = Path::new(ledger_directory).join(FIXTURE_MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read(&path)?;
+        Ok(Some(serde_json::from_slice(&contents)?))
+    }
+
+    pub fn save(&self, ledger_directory: &str) -> Result<()> {
+        let path = Path::new(ledger_directory).join(FIXTURE_MANIFEST_FILE);
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns true if the fixtures differ from the manifest saved in `ledger_directory`,
+    /// or if there is no saved manifest.
+    pub fn differs_from_saved(&self, ledger_directory: &str) -> Result<bool> {
+        Ok(Self::load(ledger_directory)?.as_ref() != Some(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use super::*;
+
+    #[test]
+    fn detects_changed_fixtures() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let ledger = dir.to_str().unwrap();
+        let account = dir.join("account.json");
+        let program = dir.join("program.so");
+        fs::write(&account, "{}").unwrap();
+        fs::write(&program, [1u8, 2, 3]).unwrap();
+
+        let manifest = FixtureManifest::from_files(&[&account, &program]).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        // No previous manifest
+        assert!(manifest.differs_from_saved(ledger).unwrap());
+        manifest.save(ledger).unwrap();
+        assert_eq!(FixtureManifest::load(ledger).unwrap(), Some(manifest.clone()));
+
+        // Same contents, in a different order
+        let same = FixtureManifest::from_files(&[&program, &account]).unwrap();
+        assert!(!same.differs_from_saved(ledger).unwrap());
+
+        // Modified program binary
+        fs::write(&program, [1u8, 2, 4]).unwrap();
+        let modified = FixtureManifest::from_files(&[&account, &program]).unwrap();
+        assert!(modified.differs_from_saved(ledger).unwrap());
+    }
+
+    #[test]
+    fn missing_fixture_errors() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        assert!(FixtureManifest::from_files(&[dir.join("nope.json")]).is_err());
+        assert_eq!(FixtureManifest::load(dir.to_str().unwrap()).unwrap(), None);
+    }
+}
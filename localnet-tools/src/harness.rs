@@ -0,0 +1,226 @@
+/// An isolated test validator per test, with a funded payer and a client,
+/// torn down when the [TestContext] is dropped, including when a test panics.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use anchor_cli::config::{Config, TestValidator, Validator};
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anyhow::{anyhow, Result};
+use solana_client_tx_processor::{
+    ProcessedTransaction, Processing, TransactionProcessor, TransactionProcessorError,
+};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{Keypair, Signer};
+use crate::localnet_account::LocalnetAccount;
+use crate::test_validator::{start_test_validator, TestValidatorHandle, TestValidatorOptions};
+
+/// Every context lives under here, relative to the working directory,
+/// since [start_test_validator] only accepts relative ledger paths.
+pub const TEST_CONTEXT_DIR: &str = ".anchor/test-context";
+
+/// How long to wait for a validator to start, in milliseconds.
+const STARTUP_WAIT: i32 = 60_000;
+
+// Contexts in one process are numbered, so their directories don't collide.
+static NEXT_CONTEXT: AtomicUsize = AtomicUsize::new(0);
+// Ports are checked for being free before the validator binds them, so two contexts
+// starting at once could pick the same ones. Starting one at a time avoids that.
+static STARTUP: Mutex<()> = Mutex::new(());
+
+/// Configures a [TestContext], see [TestContext::builder].
+#[derive(Debug, Clone, Default)]
+pub struct TestContextBuilder {
+    accounts: Vec<LocalnetAccount>,
+    programs: Vec<(Pubkey, PathBuf)>,
+}
+
+impl TestContextBuilder {
+    /// Accounts to load at genesis. Their names must be unique, they're used as file names.
+    pub fn accounts(mut self, accounts: Vec<LocalnetAccount>) -> Self {
+        self.accounts.extend(accounts);
+        self
+    }
+
+    /// Programs to load at genesis, as pairs of program id and `.so` file.
+    pub fn programs(mut self, programs: Vec<(Pubkey, PathBuf)>) -> Self {
+        self.programs.extend(programs);
+        self
+    }
+
+    /// Start the validator, and wait until it's ready.
+    pub fn build(self) -> Result<TestContext> {
+        let dir = PathBuf::from(TEST_CONTEXT_DIR).join(format!(
+            "{}-{}",
+            std::process::id(),
+            NEXT_CONTEXT.fetch_add(1, Ordering::SeqCst),
+        ));
+        // Removes the directory if anything below fails.
+        let mut guard = DirGuard(Some(dir.clone()));
+        let accounts_dir = dir.join("accounts");
+        fs::create_dir_all(&accounts_dir)?;
+        let accounts_dir_str = path_str(&accounts_dir)?;
+
+        let mut flags = vec![];
+        for account in &self.accounts {
+            account.write_to_validator_json_file(accounts_dir_str)?;
+            flags.push("--account".to_string());
+            flags.push(account.address.to_string());
+            flags.push(path_str(&accounts_dir.join(&account.name))?.to_string());
+        }
+        for (program_id, path) in &self.programs {
+            if !path.is_file() {
+                return Err(anyhow!("Program {} not found at {}", program_id, path.display()));
+            }
+            flags.push("--bpf-program".to_string());
+            flags.push(program_id.to_string());
+            flags.push(path_str(path)?.to_string());
+        }
+
+        // The payer is the mint, so it holds the faucet supply from genesis.
+        let payer = Keypair::new();
+        let test_validator = Some(TestValidator {
+            validator: Some(Validator {
+                ledger: path_str(&dir.join("ledger"))?.to_string(),
+                bind_address: "127.0.0.1".to_string(),
+                rpc_port: solana_sdk::rpc_port::DEFAULT_RPC_PORT,
+                ..Default::default()
+            }),
+            startup_wait: STARTUP_WAIT,
+            ..Default::default()
+        });
+        let mut config = Config::default();
+        config.test_validator = test_validator.clone();
+        let options = TestValidatorOptions {
+            auto_pick_ports: true,
+            mint_pubkey: Some(payer.pubkey()),
+            ..Default::default()
+        };
+        let validator = {
+            let _startup = STARTUP.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            start_test_validator(&config, &test_validator, Some(flags), true, &options)?
+        };
+        Ok(TestContext {
+            validator: Some(validator),
+            dir: guard.0.take().unwrap(),
+            payer,
+        })
+    }
+}
+
+/// A running test validator, its funded payer, and the files backing them.
+/// The validator is stopped and its files removed on drop.
+///
+/// ```ignore
+/// let ctx = TestContext::builder()
+///     .accounts(vec![mint_account])
+///     .programs(vec![(program_id, PathBuf::from("target/deploy/my_program.so"))])
+///     .build()?;
+/// let balance = ctx.rpc_client().get_balance(&ctx.payer().pubkey())?;
+/// ```
+pub struct TestContext {
+    validator: Option<TestValidatorHandle>,
+    dir: PathBuf,
+    payer: Keypair,
+}
+
+/// What [TestContext::process] should do with a transaction, signed by the payer.
+/// The online [Processing] variants, without their client and signer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessMode {
+    Execute,
+    Simulate,
+    Sign,
+    Serialize,
+    Instructions,
+}
+
+impl TestContext {
+    pub fn builder() -> TestContextBuilder {
+        TestContextBuilder::default()
+    }
+
+    pub fn rpc_url(&self) -> &str {
+        &self.validator().rpc_url
+    }
+
+    /// A client for the validator, with confirmed commitment.
+    pub fn rpc_client(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.rpc_url().to_string(), CommitmentConfig::confirmed())
+    }
+
+    /// Holds the faucet supply, so it can pay for anything.
+    pub fn payer(&self) -> &Keypair {
+        &self.payer
+    }
+
+    pub fn validator(&self) -> &TestValidatorHandle {
+        self.validator.as_ref().expect("validator is only taken on drop")
+    }
+
+    /// Where the validator's ledger, logs and account files live.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Process `processor`'s transaction against this validator, with the payer
+    /// as the primary signer.
+    pub fn process<P: TransactionProcessor>(
+        &self,
+        processor: &P,
+        mode: ProcessMode,
+    ) -> Result<ProcessedTransaction, TransactionProcessorError> {
+        let client = self.rpc_client();
+        let signer: Box<dyn Signer> = Box::new(
+            Keypair::from_bytes(&self.payer.to_bytes()).expect("keypair bytes round trip"),
+        );
+        let mode = match mode {
+            ProcessMode::Execute => Processing::Execute(client, signer, None),
+            ProcessMode::Simulate => Processing::Simulate(client, signer, None),
+            ProcessMode::Sign => Processing::Sign(client, signer, None),
+            ProcessMode::Serialize => Processing::Serialize(client, signer.pubkey(), None),
+            ProcessMode::Instructions => Processing::Instructions(client, signer.pubkey()),
+        };
+        processor.process(mode, &mut vec![])
+    }
+}
+
+impl Drop for TestContext {
+    fn drop(&mut self) {
+        // Stop the validator before removing its ledger.
+        drop(self.validator.take());
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// Removes the directory on drop, unless it has been taken.
+struct DirGuard(Option<PathBuf>);
+
+impl Drop for DirGuard {
+    fn drop(&mut self) {
+        if let Some(dir) = self.0.take() {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str().ok_or_else(|| anyhow!("{} is not valid UTF-8", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_program_is_an_error() {
+        let err = TestContext::builder()
+            .programs(vec![(Pubkey::new_unique(), PathBuf::from("does/not/exist.so"))])
+            .build()
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("does/not/exist.so"), "{}", err);
+    }
+}
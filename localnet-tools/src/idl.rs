@@ -1,28 +1,58 @@
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
 use anchor_cli::config::{Manifest, Program};
 use anchor_syn::idl::Idl;
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
 /// Serialize and compress the idl.
 pub fn on_chain_idl_account_data(idl_file: &str) -> Result<Vec<u8>> {
-    let file = shellexpand::tilde(idl_file);
-    let manifest_from_path = std::env::current_dir()?.join(PathBuf::from(&*file).parent().unwrap());
-    let cargo = Manifest::discover_from_path(manifest_from_path)?
-        .ok_or_else(|| anyhow!("Cargo.toml not found"))?;
-    let idl = anchor_syn::idl::file::parse(&*file, cargo.version(), false, false, false)?
-        .ok_or(anyhow!("Failed to parse idl: {}", file))?;
+    let idl = parse_idl_from_source(idl_file)?;
     let json_bytes = serde_json::to_vec(&idl)?;
     let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
     e.write_all(&json_bytes)?;
     e.finish().map_err(Into::into)
 }
 
+/// Parse the IDL of a program from its `src/lib.rs`, like `anchor build` does.
+pub fn parse_idl_from_source(lib_rs: &str) -> Result<Idl> {
+    let file = shellexpand::tilde(lib_rs);
+    let manifest_from_path = std::env::current_dir()?.join(PathBuf::from(&*file).parent().unwrap());
+    let cargo = Manifest::discover_from_path(manifest_from_path)?
+        .ok_or_else(|| anyhow!("Cargo.toml not found"))?;
+    anchor_syn::idl::file::parse(&*file, cargo.version(), false, false, false)?
+        .ok_or(anyhow!("Failed to parse idl: {}", file))
+}
+
+/// Set the `"address"` in the metadata of an IDL JSON file. Other metadata, and any
+/// fields unknown to [Idl], are kept.
+pub fn write_idl_address(idl_path: &Path, address: &str) -> Result<()> {
+    let contents = fs::read(idl_path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", idl_path.display(), e))?;
+    let mut idl: Value = serde_json::from_slice(&contents)
+        .map_err(|e| anyhow!("Invalid IDL {}: {}", idl_path.display(), e))?;
+    let idl_object = idl.as_object_mut()
+        .ok_or_else(|| anyhow!("Invalid IDL {}: not a JSON object", idl_path.display()))?;
+    set_address(idl_object.entry("metadata").or_insert(Value::Null), address);
+    fs::write(idl_path, serde_json::to_string_pretty(&idl)?)?;
+    Ok(())
+}
+
+// Sets the address in an object, or replaces anything else with `{"address": address}`.
+fn set_address(metadata: &mut Value, address: &str) {
+    match metadata {
+        Value::Object(metadata) => {
+            metadata.insert("address".to_string(), Value::String(address.to_string()));
+        }
+        _ => *metadata = json!({ "address": address }),
+    }
+}
+
 /// Used to write an "address" field to the IDL file.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IdlTestMetadata {
@@ -45,8 +75,8 @@ impl IdlTestMetadata {
     }
 
     pub fn write_to_file(&self, idl: &mut Idl) -> Result<()> {
-        // Add program address to the IDL.
-        idl.metadata = Some(serde_json::to_value(self)?);
+        // Add program address to the IDL, keeping any other metadata.
+        set_address(idl.metadata.get_or_insert(Value::Null), &self.address);
 
         // Persist it.
         let idl_out = PathBuf::from("target/idl")
@@ -57,3 +87,28 @@ impl IdlTestMetadata {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use super::*;
+
+    #[test]
+    fn address_is_merged_into_metadata() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("idl.json");
+        fs::write(&path, r#"{ "name": "foo", "custom": 1, "metadata": { "origin": "shank" } }"#).unwrap();
+        write_idl_address(&path, "Foo1111111111111111111111111111111111111111").unwrap();
+        let idl: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(idl["custom"], 1);
+        assert_eq!(idl["metadata"], json!({
+            "origin": "shank",
+            "address": "Foo1111111111111111111111111111111111111111",
+        }));
+
+        fs::write(&path, r#"{ "name": "foo" }"#).unwrap();
+        write_idl_address(&path, "Bar").unwrap();
+        let idl: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(idl["metadata"], json!({ "address": "Bar" }));
+    }
+}
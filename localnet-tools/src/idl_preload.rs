@@ -0,0 +1,99 @@
+/// On-chain IDL accounts loaded at genesis, for programs outside the workspace
+/// (e.g. cloned ones), or with an authority other than the workspace wallet.
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anchor_lang::idl::IdlAccount;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use solana_program::pubkey::Pubkey;
+use crate::error::LocalnetError;
+use crate::LocalnetAccount;
+
+/// An IDL account to preload, at the canonical IDL address of `program_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdlPreload {
+    pub program_id: Pubkey,
+    /// A JSON IDL, as `anchor build` writes to `target/idl`.
+    pub idl_path: PathBuf,
+    pub authority: Pubkey,
+}
+
+impl IdlPreload {
+    /// Named `<program_id>-idl-account.json`.
+    pub fn to_localnet_account(&self) -> Result<LocalnetAccount, LocalnetError> {
+        let idl = fs::read(&self.idl_path)
+            .map_err(|error| LocalnetError::Read { path: self.idl_path.clone(), error })?;
+        let data = compress_idl_json(&self.idl_path, &idl)?;
+        Ok(idl_account(
+            &self.program_id,
+            &self.authority,
+            data,
+            format!("{}-idl-account.json", self.program_id),
+        ))
+    }
+}
+
+/// The IDL account of `program_id`, holding `data`, the zlib compressed IDL.
+pub fn idl_account(program_id: &Pubkey, authority: &Pubkey, data: Vec<u8>, name: String) -> LocalnetAccount {
+    LocalnetAccount::new(
+        IdlAccount::address(program_id),
+        name,
+        IdlAccount {
+            authority: *authority,
+            data,
+        },
+    )
+        .set_owner(*program_id)
+}
+
+// Minified and zlib compressed, like `anchor idl init` stores it.
+fn compress_idl_json(path: &Path, idl: &[u8]) -> Result<Vec<u8>, LocalnetError> {
+    let invalid = |message: String| LocalnetError::InvalidIdl { path: path.to_path_buf(), message };
+    let idl: serde_json::Value = serde_json::from_slice(idl).map_err(|e| invalid(e.to_string()))?;
+    let json_bytes = serde_json::to_vec(&idl).map_err(|e| invalid(e.to_string()))?;
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json_bytes)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| invalid(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use anchor_lang::AccountDeserialize;
+    use flate2::read::ZlibDecoder;
+    use tempfile::TempDir;
+    use super::*;
+
+    #[test]
+    fn preload_account_has_authority_and_idl() {
+        let dir = TempDir::new().unwrap();
+        let idl_path = dir.path().join("third_party.json");
+        fs::write(&idl_path, r#"{ "version": "0.1.0", "name": "third_party", "instructions": [] }"#).unwrap();
+        let preload = IdlPreload {
+            program_id: Pubkey::new_unique(),
+            idl_path,
+            authority: Pubkey::new_unique(),
+        };
+        let account = preload.to_localnet_account().unwrap();
+        assert_eq!(account.address, IdlAccount::address(&preload.program_id));
+        assert_eq!(account.owner, preload.program_id);
+        assert_eq!(account.name, format!("{}-idl-account.json", preload.program_id));
+        // The authority follows the 8 byte discriminator.
+        assert_eq!(&account.account_data[8..40], preload.authority.as_ref());
+
+        let idl_account = IdlAccount::try_deserialize(&mut account.account_data.as_slice()).unwrap();
+        assert_eq!(idl_account.authority, preload.authority);
+        let mut json = vec![];
+        ZlibDecoder::new(idl_account.data.as_slice()).read_to_end(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["name"], "third_party");
+
+        fs::write(&preload.idl_path, "not json").unwrap();
+        assert!(matches!(
+            preload.to_localnet_account(),
+            Err(LocalnetError::InvalidIdl { path, .. }) if path == preload.idl_path
+        ));
+    }
+}
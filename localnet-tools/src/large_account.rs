@@ -0,0 +1,156 @@
+/// Writes account files for accounts of several megabytes, such as merkle trees,
+/// without holding the account data, or its encoding, in memory more than once.
+/// The data is compressed and base64 encoded on its way to the file,
+/// in the `base64+zstd` encoding `solana-test-validator` understands.
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use solana_program::clock::Epoch;
+use solana_program::pubkey::Pubkey;
+use crate::error::LocalnetError;
+
+/// Write an account file for `len` bytes of account data read from `data_reader`.
+/// It's an error if the reader doesn't hold exactly `len` bytes.
+pub fn write_large_account(
+    address: &Pubkey,
+    data_reader: impl Read,
+    len: usize,
+    owner: &Pubkey,
+    lamports: u64,
+    path: impl AsRef<Path>,
+) -> Result<(), LocalnetError> {
+    let header = AccountHeader {
+        address,
+        lamports,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    };
+    header.write(path.as_ref(), |data| {
+        // Read one byte more than expected, to notice readers that hold too much.
+        let copied = io::copy(&mut data_reader.take(len as u64 + 1), data)?;
+        if copied != len as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected {} bytes of account data, read {}", len, copied),
+            ));
+        }
+        Ok(())
+    })
+}
+
+/// Everything in an account file but the data.
+pub(crate) struct AccountHeader<'a> {
+    pub address: &'a Pubkey,
+    pub lamports: u64,
+    pub owner: &'a Pubkey,
+    pub executable: bool,
+    pub rent_epoch: Epoch,
+}
+
+impl AccountHeader<'_> {
+    /// Write the account file at `path`, with `write_data` writing the data into a
+    /// compressing, base64 encoding writer.
+    pub fn write(
+        &self,
+        path: &Path,
+        write_data: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+    ) -> Result<(), LocalnetError> {
+        let write_error = |error| LocalnetError::Write { path: path.to_path_buf(), error };
+        let mut out = BufWriter::new(File::create(path).map_err(write_error)?);
+        let write = || -> io::Result<()> {
+            write!(out, r#"{{"pubkey":"{}","account":{{"lamports":{},"data":[""#, self.address, self.lamports)?;
+            {
+                let base64 = base64::write::EncoderWriter::new(&mut out, base64::STANDARD);
+                let mut zstd = zstd::stream::write::Encoder::new(base64, 0)?;
+                write_data(&mut zstd)?;
+                zstd.finish()?.finish()?;
+            }
+            write!(
+                out,
+                r#"","base64+zstd"],"owner":"{}","executable":{},"rentEpoch":{}}}}}"#,
+                self.owner, self.executable, self.rent_epoch,
+            )?;
+            out.flush()
+        };
+        write().map_err(write_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+    use tempfile::TempDir;
+    use solana_account_decoder::UiAccountEncoding;
+    use crate::account_file::parse_account_file;
+    use crate::LocalnetAccount;
+    use super::*;
+
+    // Like a merkle tree account: mostly zeroes, with some noise at the start.
+    fn synthetic_data(len: usize) -> Vec<u8> {
+        let mut data: Vec<u8> = (0..len as u32 / 4)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect();
+        data.resize(len, 0);
+        data
+    }
+
+    #[test]
+    fn streamed_file_parses() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let address = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let data = synthetic_data(100_000);
+        let path = dir.join("large.json");
+        write_large_account(&address, data.as_slice(), data.len(), &owner, 5, &path).unwrap();
+        let (parsed_address, account) = parse_account_file(&path).unwrap();
+        assert_eq!(parsed_address, address);
+        assert_eq!(account.owner, owner);
+        assert_eq!(account.lamports, 5);
+        assert_eq!(account.data, data);
+
+        let err = write_large_account(&address, data.as_slice(), data.len() - 1, &owner, 5, &path)
+            .unwrap_err();
+        assert!(err.to_string().contains("expected 99999 bytes of account data, read 100000"), "{}", err);
+    }
+
+    #[test]
+    #[ignore = "timing benchmark, run with --ignored --nocapture"]
+    fn streamed_is_faster_for_5mb() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let account = LocalnetAccount {
+            address: Pubkey::new_unique(),
+            lamports: 1,
+            account_data: synthetic_data(5 * 1024 * 1024),
+            owner: Pubkey::new_unique(),
+            name: "buffered.json".to_string(),
+            ..Default::default()
+        };
+        // Base58 is left out, it takes minutes for 5MB.
+        let start = Instant::now();
+        account.write_to_validator_json_file_with_encoding(dir.to_str().unwrap(), UiAccountEncoding::Base64)
+            .unwrap();
+        let buffered = start.elapsed();
+
+        let path = dir.join("streamed.json");
+        let start = Instant::now();
+        write_large_account(
+            &account.address,
+            account.account_data.as_slice(),
+            account.account_data.len(),
+            &account.owner,
+            account.lamports,
+            &path,
+        ).unwrap();
+        let streamed = start.elapsed();
+
+        let buffered_size = std::fs::metadata(dir.join("buffered.json")).unwrap().len();
+        let streamed_size = std::fs::metadata(&path).unwrap().len();
+        println!("buffered base64: {:?}, {} bytes", buffered, buffered_size);
+        println!("streamed base64+zstd: {:?}, {} bytes", streamed, streamed_size);
+        assert!(streamed < buffered);
+        assert_eq!(parse_account_file(&path).unwrap().1, account.to_account());
+    }
+}
@@ -1,43 +1,111 @@
 /// A variety of QoL functions and tooling to do extensive
 /// localnet setup and testing.
+///
+/// Split into features, all on by default: `accounts` for defining account data,
+/// `toml` for writing Anchor's `Test.toml`, and `runner` for starting a validator.
+#[cfg(feature = "accounts")]
 use std::io::Write;
-use anchor_lang;
+#[cfg(feature = "accounts")]
 use solana_program::pubkey::Pubkey;
+#[cfg(feature = "accounts")]
 use anchor_lang::prelude::System;
+#[cfg(feature = "accounts")]
 use anchor_lang::Id;
 
+pub mod error;
+#[cfg(feature = "accounts")]
 mod wrapped_spl_types;
-pub mod test_toml_generator;
+#[cfg(feature = "accounts")]
 pub mod localnet_account;
+#[cfg(feature = "accounts")]
+pub mod account_file;
+#[cfg(feature = "accounts")]
+pub mod large_account;
+#[cfg(feature = "accounts")]
+pub mod drift;
+#[cfg(feature = "accounts")]
 pub mod trait_based;
+#[cfg(feature = "toml")]
+pub mod test_toml_generator;
+#[cfg(feature = "toml")]
+pub mod env_file;
+#[cfg(feature = "toml")]
+pub mod idl_preload;
+#[cfg(feature = "runner")]
 pub mod idl;
+#[cfg(feature = "runner")]
 pub mod test_validator;
+#[cfg(feature = "runner")]
+pub mod fixture_manifest;
+#[cfg(feature = "runner")]
+pub mod validator_flags;
+#[cfg(feature = "runner")]
+pub mod log_rotation;
+#[cfg(feature = "runner")]
+pub mod airdrop;
+#[cfg(feature = "runner")]
+pub mod suite_runner;
+#[cfg(feature = "runner")]
+pub mod watchdog;
+#[cfg(feature = "runner")]
+pub mod program_logs;
+pub mod validator_version;
+#[cfg(feature = "runner")]
 pub mod cli;
+#[cfg(feature = "tokio")]
+pub mod async_localnet;
+#[cfg(feature = "harness")]
+pub mod harness;
+#[cfg(feature = "bin")]
+pub mod localnet_cli;
 
+pub use error::LocalnetError;
+#[cfg(feature = "accounts")]
+pub use error::CloneError;
+#[cfg(feature = "accounts")]
 pub use localnet_account::LocalnetAccount;
-pub use test_toml_generator::TestTomlGenerator;
+#[cfg(feature = "accounts")]
+pub use account_file::{diff_accounts, parse_account_file, parse_account_json, AccountDiff};
+#[cfg(feature = "accounts")]
+pub use large_account::write_large_account;
+#[cfg(feature = "accounts")]
+pub use drift::{check_fixture_drift, DriftPolicy, DriftReport, DriftStatus};
+#[cfg(feature = "accounts")]
 pub use wrapped_spl_types::{spl_mint_account, SplMintAccount, spl_token_account, SplTokenAccount};
+#[cfg(feature = "toml")]
+pub use test_toml_generator::TestTomlGenerator;
+#[cfg(feature = "toml")]
+pub use idl_preload::IdlPreload;
+#[cfg(feature = "runner")]
+pub use validator_flags::{IdlOptions, ValidatorFlags};
+#[cfg(feature = "harness")]
+pub use harness::{ProcessMode, TestContext, TestContextBuilder};
 
 /// Use this struct as type T for any [GeneratedAccount] or [ClonedAccount]
 /// owned by `SystemProgram` (e.g. typical user accounts).
+#[cfg(feature = "accounts")]
 pub struct SystemAccount;
 
+#[cfg(feature = "accounts")]
 impl SystemAccount {
     pub const LEN: usize = 0;
 }
 
+#[cfg(feature = "accounts")]
 impl anchor_lang::Owner for SystemAccount {
     fn owner() -> Pubkey {
         System::id()
     }
 }
 
+#[cfg(feature = "accounts")]
 impl anchor_lang::AccountDeserialize for SystemAccount {
     fn try_deserialize_unchecked(_buf: &mut &[u8]) -> anchor_lang::Result<Self> {
         Ok(SystemAccount)
     }
 }
 
+#[cfg(feature = "accounts")]
 impl anchor_lang::AccountSerialize for SystemAccount {
     fn try_serialize<W: Write>(&self, _writer: &mut W) -> anchor_lang::Result<()> {
         Ok(())
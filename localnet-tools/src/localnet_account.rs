@@ -2,12 +2,19 @@ use anchor_client::anchor_lang::{AccountDeserialize, AccountSerialize, system_pr
 use solana_program::pubkey::Pubkey;
 use anchor_client::solana_client::rpc_client::RpcClient;
 use solana_program::clock::Epoch;
+#[cfg(feature = "toml")]
 use anchor_cli::config::AccountEntry;
 use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use serde_json::json;
 use solana_account_decoder::{UiAccount, UiAccountData, UiAccountEncoding};
+use solana_sdk::account::Account;
 use solana_sdk::bs58;
 use inflector::Inflector;
+use crate::account_file::parse_account_file;
+use crate::error::{CloneError, LocalnetError};
+use crate::large_account::AccountHeader;
 
 pub const THOUSAND_SOL: u64 = 1_000_000_000_000;
 
@@ -49,16 +56,22 @@ impl LocalnetAccount {
         client: &RpcClient,
         name: String,
         modify: Option<F>,
-    ) -> anyhow::Result<Self> {
-        let info = client.get_account(address)?;
+    ) -> Result<Self, CloneError> {
+        let info = client.get_account(address).map_err(|error| CloneError::Fetch {
+            address: *address,
+            url: client.url(),
+            error,
+        })?;
         // Even if there is no modify function, deserialization verifies the expected account type
-        let mut deserialized = T::try_deserialize(&mut info.data.as_slice())?;
+        let mut deserialized = T::try_deserialize(&mut info.data.as_slice())
+            .map_err(|error| CloneError::Deserialize { address: *address, error })?;
         // Maybe modify the account data.
         if let Some(func) = modify {
             deserialized = func(deserialized);
         }
         let mut serialized = Vec::new();
-        deserialized.try_serialize(&mut serialized)?;
+        deserialized.try_serialize(&mut serialized)
+            .map_err(|error| CloneError::Serialize { address: *address, error })?;
         Ok(Self {
             address: address.clone(),
             lamports: info.lamports,
@@ -96,6 +109,7 @@ impl LocalnetAccount {
     }
 
     /// For inclusion in autogenerated `Test.toml` files.
+    #[cfg(feature = "toml")]
     pub fn to_account_entry(&self) -> AccountEntry {
         AccountEntry {
             address: self.address.to_string(),
@@ -109,30 +123,95 @@ impl LocalnetAccount {
         js_test_import(&self.name)
     }
 
-    /// Write to a JSON file that can be consumed by `--account` flags in
-    /// `solana-test-validator`.
-    pub fn write_to_validator_json_file(&self, path_prefix: &str) -> anyhow::Result<()> {
-        let ui_act = UiAccount {
+    /// The account as `solana-test-validator` and RPC clients represent it, with its
+    /// data in the given encoding. Base58 is the most widely readable, but Base64 is
+    /// much smaller for large accounts, and Base64Zstd smaller still if the data compresses.
+    pub fn ui_account(&self, encoding: UiAccountEncoding) -> UiAccount {
+        match encoding {
+            // `UiAccount::encode` refuses to base58 encode more than 128 bytes.
+            UiAccountEncoding::Base58 | UiAccountEncoding::Binary => UiAccount {
+                lamports: self.lamports,
+                data: UiAccountData::Binary(
+                    bs58::encode(&self.account_data).into_string(),
+                    UiAccountEncoding::Base58
+                ),
+                owner: self.owner.to_string(),
+                executable: self.executable,
+                rent_epoch: self.rent_epoch,
+            },
+            _ => UiAccount::encode(&self.address, &self.to_account(), encoding, None, None),
+        }
+    }
+
+    pub fn to_account(&self) -> Account {
+        Account {
             lamports: self.lamports,
-            data: UiAccountData::Binary(
-                bs58::encode(&self.account_data).into_string(),
-                UiAccountEncoding::Base58
-            ),
-            owner: self.owner.to_string(),
+            data: self.account_data.clone(),
+            owner: self.owner,
             executable: self.executable,
             rent_epoch: self.rent_epoch,
-        };
+        }
+    }
+
+    /// Write to a JSON file that can be consumed by `--account` flags in
+    /// `solana-test-validator`, with the account data base58 encoded.
+    pub fn write_to_validator_json_file(&self, path_prefix: &str) -> Result<(), LocalnetError> {
+        self.write_to_validator_json_file_with_encoding(path_prefix, UiAccountEncoding::Base58)
+    }
+
+    /// Like [LocalnetAccount::write_to_validator_json_file], with the account data in the
+    /// given encoding. `JsonParsed` isn't loadable by `solana-test-validator`, so it's an error.
+    /// Base64Zstd is written compactly and streamed, see [crate::large_account].
+    pub fn write_to_validator_json_file_with_encoding(
+        &self,
+        path_prefix: &str,
+        encoding: UiAccountEncoding,
+    ) -> Result<(), LocalnetError> {
+        if encoding == UiAccountEncoding::JsonParsed {
+            return Err(LocalnetError::UnsupportedEncoding { encoding });
+        }
+        let path = PathBuf::from(format!("{}/{}", path_prefix, &self.name));
+        if encoding == UiAccountEncoding::Base64Zstd {
+            // Streamed, so large accounts aren't copied into an encoded string first.
+            return AccountHeader {
+                address: &self.address,
+                lamports: self.lamports,
+                owner: &self.owner,
+                executable: self.executable,
+                rent_epoch: self.rent_epoch,
+            }.write(&path, |data| data.write_all(&self.account_data));
+        }
+        let ui_act = self.ui_account(encoding);
         let pubkey = self.address.to_string();
-        let file = File::create(format!("{}/{}", path_prefix, &self.name))?;
+        let write_error = |error| LocalnetError::Write { path: path.clone(), error };
+        let file = File::create(&path).map_err(write_error)?;
         serde_json::to_writer_pretty(
             file,
             &json!({
                     "pubkey": pubkey,
                     "account": &ui_act,
                 }),
-        )?;
+        ).map_err(|e| write_error(e.into()))?;
         Ok(())
     }
+
+    /// Read an account file, see [parse_account_file]. The account is named after the file.
+    pub fn read_from_file(path: impl AsRef<Path>) -> Result<Self, LocalnetError> {
+        let path = path.as_ref();
+        let (address, account) = parse_account_file(path)?;
+        let name = path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self {
+            address,
+            lamports: account.lamports,
+            account_data: account.data,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            name,
+        })
+    }
 }
 
 /// Takes a filepath to a JSON file, and produces a source code string
@@ -161,3 +240,100 @@ pub fn js_test_import(location: &str) -> String {
     // and its subsequent extraction of the Typescript `PublicKey` object.
     format!("import * as {}Json from \"./{}\";\nexport const {} = new anchor.web3.PublicKey({}Json.pubkey);", &name, &location, &name, &name)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use anchor_client::solana_client::mock_sender::Mocks;
+    use anchor_client::solana_client::rpc_request::RpcRequest;
+    use tempfile::TempDir;
+    use crate::SplMintAccount;
+    use super::*;
+
+    fn clone_mint(client: &RpcClient, address: &Pubkey) -> Result<LocalnetAccount, CloneError> {
+        LocalnetAccount::new_from_clone::<SplMintAccount, fn(SplMintAccount) -> SplMintAccount>(
+            address, client, "mint.json".to_string(), None,
+        )
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let dir_str = dir.to_str().unwrap();
+        // 10KB, half of it noise and half zeroes, like a large config account.
+        let mut data: Vec<u8> = (0..5_120u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8).collect();
+        data.resize(10_240, 0);
+        let account = LocalnetAccount {
+            address: Pubkey::new_unique(),
+            lamports: 42,
+            account_data: data,
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 7,
+            name: String::new(),
+        };
+
+        let mut sizes = vec![];
+        for (encoding, name) in [
+            (UiAccountEncoding::Base58, "base58.json"),
+            (UiAccountEncoding::Base64, "base64.json"),
+            (UiAccountEncoding::Base64Zstd, "base64zstd.json"),
+        ] {
+            let account = LocalnetAccount { name: name.to_string(), ..account.clone() };
+            account.write_to_validator_json_file_with_encoding(dir_str, encoding).unwrap();
+            let path = dir.join(name);
+            sizes.push(std::fs::metadata(&path).unwrap().len());
+
+            let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            let ui_account: UiAccount = serde_json::from_value(json["account"].clone()).unwrap();
+            assert_eq!(ui_account.decode::<Account>().unwrap(), account.to_account());
+
+            let read = LocalnetAccount::read_from_file(&path).unwrap();
+            assert_eq!(read.address, account.address);
+            assert_eq!(read.name, name);
+            assert_eq!(read.to_account(), account.to_account());
+        }
+        // Roughly 14.0KB of base58, 13.7KB of base64, and under 7KB compressed.
+        // Base64 is only a little smaller, but unlike base58 it encodes and decodes in linear time.
+        let [base58, base64, zstd] = [sizes[0], sizes[1], sizes[2]];
+        assert!(base58 > 13_900, "{:?}", sizes);
+        assert!(base64 < base58 && base64 > 13_600, "{:?}", sizes);
+        assert!(zstd < 7_000, "{:?}", sizes);
+
+        assert!(matches!(
+            account.write_to_validator_json_file_with_encoding(dir_str, UiAccountEncoding::JsonParsed),
+            Err(LocalnetError::UnsupportedEncoding { .. })
+        ));
+    }
+
+    #[test]
+    fn clone_errors() {
+        let address = Pubkey::new_unique();
+        // The mock client returns a null response for every request when its url is "fails".
+        let client = RpcClient::new_mock("fails".to_string());
+        let err = clone_mint(&client, &address).unwrap_err();
+        assert!(matches!(
+            &err,
+            CloneError::Fetch { address: a, url, .. } if *a == address && *url == client.url()
+        ));
+        assert!(err.to_string().contains(&address.to_string()), "{}", err);
+
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(RpcRequest::GetAccountInfo, json!({
+            "context": { "slot": 1 },
+            "value": {
+                "lamports": 1,
+                "data": ["", "base64"],
+                "owner": system_program::ID.to_string(),
+                "executable": false,
+                "rentEpoch": 0,
+            },
+        }));
+        let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+        assert!(matches!(
+            clone_mint(&client, &address).unwrap_err(),
+            CloneError::Deserialize { address: a, .. } if a == address
+        ));
+    }
+}
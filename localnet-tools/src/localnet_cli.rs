@@ -0,0 +1,426 @@
+/// The `jungle-localnet` command: generate Test.toml suites, start a localnet for one,
+/// run a suite's tests, and clean up ledgers and generated files.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anyhow::{anyhow, Result};
+use clap::{ArgAction, Args, Parser, Subcommand};
+use jungle_fi_cli_utils::cli::{get_solana_cli_config, resolve_keypair, resolve_url};
+use jungle_fi_cli_utils::logging::init_logging;
+use jungle_fi_cli_utils::prompts::{prompt_confirm, PromptOptions};
+use serde::Deserialize;
+use solana_program::pubkey::Pubkey;
+use crate::drift::{check_fixture_drift, DriftPolicy};
+use crate::error::LocalnetError;
+use crate::suite_runner::run_test_suite;
+use crate::test_validator::{
+    start_localnet_from_test_toml, start_suite_validator, LedgerPolicy, LocalnetOverrides,
+    Shutdown, TestValidatorOptions,
+};
+use crate::{LocalnetAccount, TestTomlGenerator};
+
+/// Where `anchor test` puts the ledger when a Test.toml doesn't set one.
+const DEFAULT_LEDGER: &str = ".anchor/test-ledger";
+/// Where [crate::validator_flags::ValidatorFlags] writes on-chain IDL accounts.
+const IDL_ACCOUNT_DIR: &str = "target/idl-account";
+
+#[derive(Debug, Parser)]
+#[clap(name = "jungle-localnet", version, about = "Generate, start and test Anchor localnets")]
+pub struct JungleLocalnet {
+    #[clap(flatten)]
+    pub global: GlobalArgs,
+    #[clap(subcommand)]
+    pub command: LocalnetCommand,
+}
+
+/// The usual Solana CLI flags, resolved like the rest of the Solana tooling.
+#[derive(Debug, Clone, Default, Args)]
+pub struct GlobalArgs {
+    /// The cluster to clone accounts from, or check fixtures against: a URL or moniker,
+    /// e.g. `mainnet-beta`.
+    /// Defaults to the Solana CLI config.
+    #[clap(short = 'u', long = "url", global = true)]
+    pub url: Option<String>,
+    /// The wallet for the localnet, instead of the Anchor.toml's provider wallet.
+    #[clap(short = 'k', long = "keypair", global = true)]
+    pub keypair: Option<String>,
+    /// Log more, repeat for even more (-v, -vv, -vvv). Ignored if RUST_LOG is set.
+    #[clap(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Only log errors. Ignored if RUST_LOG is set.
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum LocalnetCommand {
+    /// Build the Test.toml suites, with a generator binary or from a manifest.
+    Generate {
+        /// A binary that builds the suites, such as one using SolanaLocalnetCli.
+        #[clap(long, conflicts_with = "manifest", required_unless_present = "manifest")]
+        generator: Option<PathBuf>,
+        /// A TOML file listing each suite's directory, accounts and programs.
+        #[clap(long)]
+        manifest: Option<PathBuf>,
+        /// Arguments for the generator.
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+    /// Start a localnet for a suite, until Ctrl-C.
+    Start {
+        /// The suite's Test.toml.
+        #[clap(long)]
+        suite: String,
+        /// Leave the validator running in the background, and print its process id.
+        #[clap(long)]
+        detach: bool,
+        #[clap(flatten)]
+        validator: ValidatorArgs,
+        /// Extra solana-test-validator flags.
+        #[clap(last = true)]
+        flags: Vec<String>,
+    },
+    /// Start a localnet for a suite, run its `test` script, and exit with the script's exit code.
+    Run {
+        /// The suite's Test.toml.
+        #[clap(long)]
+        suite: String,
+        #[clap(flatten)]
+        validator: ValidatorArgs,
+        /// Extra solana-test-validator flags.
+        #[clap(last = true)]
+        flags: Vec<String>,
+    },
+    /// Remove ledgers, on-chain IDL account files and generated account files.
+    Clean {
+        /// Also remove the ledger and account files of this suite's Test.toml. Can be repeated.
+        #[clap(long = "suite")]
+        suites: Vec<PathBuf>,
+        /// Don't ask for confirmation.
+        #[clap(short, long)]
+        yes: bool,
+    },
+    /// Compare a directory of account files against the `-u` cluster.
+    CheckDrift {
+        /// The directory of account files, e.g. a suite's `accounts` directory.
+        dir: PathBuf,
+        /// Exit with 1 if any account differs or no longer exists, e.g. in CI.
+        #[clap(long)]
+        fail_on_drift: bool,
+        /// Don't compare lamports.
+        #[clap(long)]
+        ignore_lamports: bool,
+        /// Don't compare account data, only owners and lamports.
+        #[clap(long)]
+        ignore_data: bool,
+    },
+}
+
+/// Validator options shared by `start` and `run`.
+#[derive(Debug, Clone, Args)]
+pub struct ValidatorArgs {
+    /// Pick free ports if the configured RPC or faucet port is in use.
+    #[clap(long)]
+    pub auto_pick_ports: bool,
+    /// Whether to recreate the ledger from genesis, or keep the previous run's state.
+    #[clap(long, value_enum, default_value_t = LedgerPolicy::Reset)]
+    pub ledger_policy: LedgerPolicy,
+    /// Address to receive the faucet supply, instead of the wallet.
+    #[clap(long)]
+    pub mint: Option<Pubkey>,
+    /// Path to the Anchor.toml, if it isn't in the working directory or one of its parents.
+    #[clap(long)]
+    pub anchor_toml: Option<PathBuf>,
+}
+
+impl ValidatorArgs {
+    fn options(&self) -> TestValidatorOptions {
+        TestValidatorOptions {
+            auto_pick_ports: self.auto_pick_ports,
+            ledger_policy: self.ledger_policy,
+            mint_pubkey: self.mint,
+            shutdown: Shutdown::CtrlC,
+            ..Default::default()
+        }
+    }
+}
+
+/// Test.toml suites to generate with `jungle-localnet generate --manifest`.
+/// Paths are relative to the working directory.
+///
+/// ```toml
+/// [[suite]]
+/// save_directory = "tests/suite-1"
+/// test_file_glob = "tests/suite-1/*.ts"
+///
+/// [[suite.accounts]]
+/// name = "usdc_mint.json"
+/// clone = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+///
+/// [[suite.accounts]]
+/// name = "user.json"
+/// file = "fixtures/user.json"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GenerateManifest {
+    #[serde(default)]
+    pub suite: Vec<SuiteManifest>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SuiteManifest {
+    pub save_directory: String,
+    pub test_file_glob: Option<String>,
+    #[serde(default)]
+    pub accounts: Vec<AccountManifest>,
+    /// Programs to load at genesis.
+    #[serde(default)]
+    pub programs: Vec<ProgramManifest>,
+    #[serde(default)]
+    pub extends: Vec<String>,
+    pub startup_wait: Option<i32>,
+    pub shutdown_wait: Option<i32>,
+}
+
+/// An account, either read from an account file, or cloned from the `-u` cluster as is.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AccountManifest {
+    /// The account file name in the suite.
+    pub name: String,
+    /// An account file, as written by [LocalnetAccount::write_to_validator_json_file].
+    pub file: Option<PathBuf>,
+    /// The address of an account to clone.
+    pub clone: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProgramManifest {
+    pub address: String,
+    pub path: String,
+}
+
+impl JungleLocalnet {
+    /// Run the command, and return the exit code for the process.
+    pub fn process(self) -> Result<i32> {
+        init_logging(self.global.verbose, self.global.quiet);
+        let overrides = |anchor_toml: &Option<PathBuf>| -> Result<LocalnetOverrides> {
+            Ok(LocalnetOverrides {
+                anchor_toml: anchor_toml.clone(),
+                wallet: self.global.wallet()?,
+            })
+        };
+        match &self.command {
+            LocalnetCommand::Generate { generator: Some(generator), args, .. } => {
+                run_generator(generator, args)?;
+            }
+            LocalnetCommand::Generate { manifest: Some(manifest), .. } => {
+                let client = RpcClient::new(self.global.url()?);
+                for generator in generators_from_manifest(manifest, &client)? {
+                    println!("Building: {}/Test.toml", generator.save_directory);
+                    generator.build()?;
+                }
+            }
+            LocalnetCommand::Generate { .. } => {
+                return Err(anyhow!("Either --generator or --manifest is required"));
+            }
+            LocalnetCommand::Start { suite, detach: true, validator, flags } => {
+                let handle = start_suite_validator(
+                    suite,
+                    flags.clone(),
+                    &validator.options(),
+                    &overrides(&validator.anchor_toml)?,
+                )?;
+                let rpc_url = handle.rpc_url.clone();
+                let pid = handle.detach();
+                println!("Validator started at {} with process id {}", rpc_url, pid);
+            }
+            LocalnetCommand::Start { suite, detach: false, validator, flags } => {
+                println!("Starting {}, press Ctrl-C to shut down", suite);
+                start_localnet_from_test_toml(
+                    suite,
+                    flags.clone(),
+                    &validator.options(),
+                    &overrides(&validator.anchor_toml)?,
+                )?;
+            }
+            LocalnetCommand::Run { suite, validator, flags } => {
+                let result = run_test_suite(
+                    suite,
+                    flags.clone(),
+                    &validator.options(),
+                    &overrides(&validator.anchor_toml)?,
+                )?;
+                println!("{} finished with {}", suite, result.status);
+                return Ok(result.status.code().unwrap_or(1));
+            }
+            LocalnetCommand::Clean { suites, yes } => {
+                let paths = clean_paths(suites)?;
+                if paths.is_empty() {
+                    println!("Nothing to clean");
+                    return Ok(0);
+                }
+                println!("Removing:");
+                for path in &paths {
+                    println!("  {}", path.display());
+                }
+                if !yes && !prompt_confirm("Confirmation (--yes)", &PromptOptions::default())? {
+                    println!("Nothing removed");
+                    return Ok(0);
+                }
+                for path in &paths {
+                    remove(path)?;
+                }
+            }
+            LocalnetCommand::CheckDrift { dir, fail_on_drift, ignore_lamports, ignore_data } => {
+                let client = RpcClient::new(self.global.url()?);
+                let policy = DriftPolicy {
+                    ignore_lamports: *ignore_lamports,
+                    ignore_data: *ignore_data,
+                    fail_on_drift: *fail_on_drift,
+                };
+                let report = match check_fixture_drift(&client, dir, policy) {
+                    Ok(report) => report,
+                    Err(LocalnetError::FixtureDrift(report)) => {
+                        println!("{}", report);
+                        return Ok(1);
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                for path in &report.skipped {
+                    println!("Skipped {}, not an account file", path.display());
+                }
+                println!("{}", report);
+            }
+        }
+        Ok(0)
+    }
+}
+
+impl GlobalArgs {
+    fn url(&self) -> Result<String> {
+        Ok(resolve_url(&self.url, get_solana_cli_config().ok().as_ref())?)
+    }
+
+    // The `-k` keypair, checked to be readable, as the Anchor wallet override.
+    fn wallet(&self) -> Result<Option<PathBuf>> {
+        match &self.keypair {
+            Some(keypair) => {
+                resolve_keypair(&self.keypair, None)?;
+                Ok(Some(PathBuf::from(keypair)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+fn run_generator(generator: &Path, args: &[String]) -> Result<()> {
+    let status = Command::new(generator)
+        .args(args)
+        .status()
+        .map_err(|e| anyhow!("Failed to run {}: {}", generator.display(), e))?;
+    if !status.success() {
+        return Err(anyhow!("{} failed with {}", generator.display(), status));
+    }
+    Ok(())
+}
+
+/// A [TestTomlGenerator] for each suite in the manifest. Accounts to clone are fetched with `client`.
+pub fn generators_from_manifest(manifest: &Path, client: &RpcClient) -> Result<Vec<TestTomlGenerator>> {
+    let contents = fs::read_to_string(manifest)
+        .map_err(|e| anyhow!("Failed to read {}: {}", manifest.display(), e))?;
+    let manifest: GenerateManifest = toml::from_str(&contents)
+        .map_err(|e| anyhow!("Invalid manifest {}: {}", manifest.display(), e))?;
+    manifest.suite
+        .into_iter()
+        .map(|suite| {
+            let accounts = suite.accounts
+                .iter()
+                .map(|account| manifest_account(account, client))
+                .collect::<Result<Vec<_>>>()?;
+            fs::create_dir_all(&suite.save_directory)?;
+            Ok(TestTomlGenerator {
+                save_directory: suite.save_directory,
+                test_file_glob: suite.test_file_glob,
+                accounts,
+                programs: suite.programs
+                    .into_iter()
+                    .map(|program| (program.address, program.path))
+                    .collect(),
+                extends: suite.extends,
+                startup_wait: suite.startup_wait,
+                shutdown_wait: suite.shutdown_wait,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+fn manifest_account(account: &AccountManifest, client: &RpcClient) -> Result<LocalnetAccount> {
+    let localnet_account = match (&account.file, &account.clone) {
+        (Some(file), None) => LocalnetAccount::read_from_file(file)?,
+        (None, Some(address)) => {
+            let address = Pubkey::from_str(address)
+                .map_err(|e| anyhow!("Invalid address {} for {}: {}", address, account.name, e))?;
+            let info = client.get_account(&address)
+                .map_err(|e| anyhow!("Failed to clone {} from {}: {}", address, client.url(), e))?;
+            LocalnetAccount {
+                address,
+                lamports: info.lamports,
+                account_data: info.data,
+                owner: info.owner,
+                executable: info.executable,
+                rent_epoch: info.rent_epoch,
+                name: String::new(),
+            }
+        }
+        _ => return Err(anyhow!("Account {} needs exactly one of `file` or `clone`", account.name)),
+    };
+    Ok(LocalnetAccount { name: account.name.clone(), ..localnet_account })
+}
+
+/// Everything `clean` removes that exists: the default ledger, the on-chain IDL account
+/// files, and for each suite its ledger, account files and `accounts.ts`.
+/// Suites that a Test.toml `extends` aren't followed.
+pub fn clean_paths(suites: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![PathBuf::from(DEFAULT_LEDGER), PathBuf::from(IDL_ACCOUNT_DIR)];
+    for suite in suites {
+        let contents = fs::read_to_string(suite)
+            .map_err(|e| anyhow!("Failed to read {}: {}", suite.display(), e))?;
+        let test_toml: toml::Value = toml::from_str(&contents)
+            .map_err(|e| anyhow!("Invalid {}: {}", suite.display(), e))?;
+        let suite_dir = suite.parent().unwrap_or_else(|| Path::new("."));
+        let validator = test_toml.get("test").and_then(|test| test.get("validator"));
+        if let Some(ledger) = validator.and_then(|v| v.get("ledger")).and_then(|l| l.as_str()) {
+            paths.push(PathBuf::from(ledger));
+        }
+        let accounts = validator
+            .and_then(|v| v.get("account"))
+            .and_then(|accounts| accounts.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for account in accounts {
+            if let Some(filename) = account.get("filename").and_then(|f| f.as_str()) {
+                paths.push(suite_dir.join(filename));
+            }
+        }
+        paths.push(suite_dir.join("accounts.ts"));
+    }
+    let mut existing: Vec<PathBuf> = vec![];
+    for path in paths {
+        if path.exists() && !existing.contains(&path) {
+            existing.push(path);
+        }
+    }
+    Ok(existing)
+}
+
+fn remove(path: &Path) -> Result<()> {
+    let removed = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    removed.map_err(|e| anyhow!("Failed to remove {}: {}", path.display(), e))
+}
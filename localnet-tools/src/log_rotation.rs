@@ -0,0 +1,281 @@
+/// Size-capped log files for the test validator's output, so that long localnet
+/// sessions don't produce unbounded log files.
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{ChildStderr, ChildStdout, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// How the test validator's stdout and stderr are written to the ledger directory.
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    /// Truncate existing log files on startup, rather than appending to them.
+    pub truncate: bool,
+    /// Rotate a log file once it reaches this many bytes.
+    pub max_bytes: Option<u64>,
+    /// How many rotated files to keep, e.g. `test-ledger-log.1.txt` is the most recent.
+    pub keep: usize,
+    /// Write stderr to its own file, so panics aren't interleaved with gossip noise.
+    pub separate_stderr: bool,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self {
+            truncate: true,
+            max_bytes: None,
+            keep: 3,
+            separate_stderr: true,
+        }
+    }
+}
+
+/// `dir/name.txt` becomes `dir/name.<generation>.txt`.
+pub fn rotated_path(path: &Path, generation: usize) -> PathBuf {
+    insert_before_extension(path, &generation.to_string())
+}
+
+/// `dir/name.txt` becomes `dir/name.stderr.txt`.
+pub fn stderr_path(path: &Path) -> PathBuf {
+    insert_before_extension(path, "stderr")
+}
+
+fn insert_before_extension(path: &Path, infix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, infix, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, infix),
+    };
+    path.with_file_name(name)
+}
+
+/// A log file that is rotated once it exceeds [LogOptions::max_bytes].
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: Option<u64>,
+    keep: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, options: &LogOptions) -> io::Result<Self> {
+        let file = open_log_file(path, options.truncate)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes: options.max_bytes,
+            keep: options.keep,
+            file,
+            written,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Shift `name.N.txt` to `name.N+1.txt`, dropping anything beyond [LogOptions::keep],
+    /// then start a fresh `name.txt`.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+            self.written = 0;
+            return Ok(());
+        }
+        let oldest = rotated_path(&self.path, self.keep);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for generation in (1..self.keep).rev() {
+            let from = rotated_path(&self.path, generation);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, generation + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(max_bytes) = self.max_bytes {
+            if self.written > 0 && self.written + buf.len() as u64 > max_bytes {
+                self.rotate()?;
+            }
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_log_file(path: &Path, truncate: bool) -> io::Result<File> {
+    if truncate {
+        File::create(path)
+    } else {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+}
+
+/// Where one of the validator's output streams goes.
+#[derive(Debug, Clone)]
+pub(crate) enum LogTarget {
+    /// Written directly by the validator process.
+    File(Arc<File>),
+    /// Piped through this process, so that it can be rotated.
+    Rotating(Arc<Mutex<RotatingFile>>),
+}
+
+impl LogTarget {
+    pub fn stdio(&self) -> io::Result<Stdio> {
+        match self {
+            LogTarget::File(file) => Ok(Stdio::from(file.try_clone()?)),
+            LogTarget::Rotating(_) => Ok(Stdio::piped()),
+        }
+    }
+
+    /// Copies a piped output stream into the rotating file on a background thread.
+    pub fn pump<R: Read + Send + 'static>(&self, pipe: Option<R>) {
+        if let (LogTarget::Rotating(file), Some(mut pipe)) = (self, pipe) {
+            let file = Arc::clone(file);
+            std::thread::spawn(move || {
+                let mut buf = [0u8; 8192];
+                while let Ok(n) = pipe.read(&mut buf) {
+                    if n == 0 {
+                        break;
+                    }
+                    let mut file = match file.lock() {
+                        Ok(file) => file,
+                        Err(poisoned) => poisoned.into_inner(),
+                    };
+                    if file.write_all(&buf[..n]).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// The validator's stdout and stderr targets, and the paths they are written to.
+#[derive(Debug, Clone)]
+pub(crate) struct LogTargets {
+    pub stdout: LogTarget,
+    pub stderr: LogTarget,
+    pub stdout_path: PathBuf,
+    pub stderr_path: PathBuf,
+}
+
+impl LogTargets {
+    pub fn open(log_filename: &str, options: &LogOptions) -> io::Result<Self> {
+        let stdout_path = PathBuf::from(log_filename);
+        let stderr_path = if options.separate_stderr {
+            stderr_path(&stdout_path)
+        } else {
+            stdout_path.clone()
+        };
+        let open = |path: &Path| -> io::Result<LogTarget> {
+            Ok(match options.max_bytes {
+                Some(_) => LogTarget::Rotating(Arc::new(Mutex::new(RotatingFile::open(path, options)?))),
+                None => LogTarget::File(Arc::new(open_log_file(path, options.truncate)?)),
+            })
+        };
+        let stdout = open(&stdout_path)?;
+        let stderr = if options.separate_stderr {
+            open(&stderr_path)?
+        } else {
+            stdout.clone()
+        };
+        Ok(Self {
+            stdout,
+            stderr,
+            stdout_path,
+            stderr_path,
+        })
+    }
+
+    /// Starts copying the piped output of a spawned validator, if the logs are rotated.
+    pub fn pump(&self, stdout: Option<ChildStdout>, stderr: Option<ChildStderr>) {
+        self.stdout.pump(stdout);
+        self.stderr.pump(stderr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use super::*;
+
+    #[test]
+    fn rotated_file_names() {
+        let path = Path::new(".anchor/test-ledger/test-ledger-log.txt");
+        assert_eq!(rotated_path(path, 1), PathBuf::from(".anchor/test-ledger/test-ledger-log.1.txt"));
+        assert_eq!(stderr_path(path), PathBuf::from(".anchor/test-ledger/test-ledger-log.stderr.txt"));
+        assert_eq!(rotated_path(Path::new("log"), 2), PathBuf::from("log.2"));
+    }
+
+    #[test]
+    fn rotates_and_keeps_n_files() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let path = dir.join("test-ledger-log.txt");
+        let options = LogOptions {
+            max_bytes: Some(10),
+            keep: 2,
+            ..Default::default()
+        };
+        let mut file = RotatingFile::open(&path, &options).unwrap();
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n", "dddddddd\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "dddddddd\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "cccccccc\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 2)).unwrap(), "bbbbbbbb\n");
+        assert!(!rotated_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn truncates_or_appends_on_open() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let path = dir.join("test-ledger-log.txt");
+        fs::write(&path, "previous session\n").unwrap();
+        let mut options = LogOptions {
+            truncate: false,
+            max_bytes: Some(1024),
+            ..Default::default()
+        };
+        RotatingFile::open(&path, &options).unwrap().write_all(b"appended\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "previous session\nappended\n");
+        options.truncate = true;
+        RotatingFile::open(&path, &options).unwrap().write_all(b"fresh\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fresh\n");
+    }
+
+    #[test]
+    fn separate_stderr_file() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let path = dir.join("test-ledger-log.txt");
+        let targets = LogTargets::open(path.to_str().unwrap(), &LogOptions::default()).unwrap();
+        assert_eq!(targets.stdout_path, path);
+        assert_eq!(targets.stderr_path, stderr_path(&path));
+        assert!(targets.stderr_path.exists());
+        let combined = LogTargets::open(path.to_str().unwrap(), &LogOptions {
+            separate_stderr: false,
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(combined.stderr_path, path);
+    }
+}
@@ -0,0 +1,132 @@
+/// Per-run directories for the program logs streamed from the test validator,
+/// so that earlier runs are kept around for post-mortem debugging.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+
+/// Every run gets its own subdirectory of this, unless a directory is configured.
+pub const PROGRAM_LOGS_DIR: &str = ".anchor/program-logs";
+
+/// The name of a test suite, taken from the directory containing its `Test.toml`.
+pub fn suite_name(test_toml_path: &Path) -> String {
+    let dir = if test_toml_path.file_name().map_or(false, |name| name == "Test.toml") {
+        test_toml_path.parent()
+    } else {
+        Some(test_toml_path)
+    };
+    dir.and_then(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "localnet".to_string())
+}
+
+/// `.anchor/program-logs/<suite-name>-<timestamp>`
+pub fn default_run_dir(suite_name: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    Path::new(PROGRAM_LOGS_DIR).join(format!("{}-{}", suite_name, timestamp))
+}
+
+/// Creates the log directory for this run. Unless `configured` is given, a new
+/// directory is created under [PROGRAM_LOGS_DIR], and the older runs beyond
+/// `retain_runs` are deleted.
+pub fn create_run_dir(
+    suite_name: &str,
+    configured: Option<&Path>,
+    retain_runs: Option<usize>,
+) -> Result<PathBuf> {
+    let dir = match configured {
+        Some(dir) => dir.to_path_buf(),
+        None => default_run_dir(suite_name),
+    };
+    fs::create_dir_all(&dir)?;
+    if let (None, Some(retain_runs)) = (configured, retain_runs) {
+        prune_runs(Path::new(PROGRAM_LOGS_DIR), retain_runs)?;
+    }
+    Ok(dir)
+}
+
+/// Deletes all but the `keep` most recently modified run directories in `root`.
+pub fn prune_runs(root: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    let mut runs = vec![];
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            runs.push((entry.metadata()?.modified()?, entry.path()));
+        }
+    }
+    // Newest first, with the name as a tie-breaker since the timestamp is part of it.
+    runs.sort_by(|a, b| b.cmp(a));
+    let mut pruned = vec![];
+    for (_, dir) in runs.into_iter().skip(keep) {
+        fs::remove_dir_all(&dir)?;
+        pruned.push(dir);
+    }
+    Ok(pruned)
+}
+
+/// The program log files in a run directory, as (program, path) pairs sorted by program.
+/// Programs from the workspace are named by their library name, and genesis
+/// programs by their address.
+pub fn collect_logs(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut logs: Vec<(String, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?.strip_suffix(".log")?.to_string();
+            let program = match name.split_once('.') {
+                Some((_address, lib_name)) => lib_name.to_string(),
+                None => name,
+            };
+            Some((program, path))
+        })
+        .collect();
+    logs.sort();
+    logs
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use super::*;
+
+    #[test]
+    fn suite_names() {
+        assert_eq!(suite_name(Path::new("tests/suite-1/Test.toml")), "suite-1");
+        assert_eq!(suite_name(Path::new("tests/suite-2")), "suite-2");
+        assert_eq!(suite_name(Path::new("Test.toml")), "localnet");
+    }
+
+    #[test]
+    fn logs_are_collected_by_program() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let address = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS";
+        fs::write(dir.join(format!("{}.my_program.log", address)), "").unwrap();
+        fs::write(dir.join(format!("{}.log", address)), "").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        assert_eq!(collect_logs(dir), vec![
+            (address.to_string(), dir.join(format!("{}.log", address))),
+            ("my_program".to_string(), dir.join(format!("{}.my_program.log", address))),
+        ]);
+    }
+
+    #[test]
+    fn oldest_runs_are_pruned() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        for run in ["suite-1-1", "suite-2-2", "suite-1-3"] {
+            fs::create_dir_all(root.join(run)).unwrap();
+            // Distinct modification times.
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        let pruned = prune_runs(root, 2).unwrap();
+        assert_eq!(pruned, vec![root.join("suite-1-1")]);
+        assert!(root.join("suite-2-2").exists());
+        assert!(root.join("suite-1-3").exists());
+    }
+}
@@ -0,0 +1,205 @@
+/// Runs the `test` script of generated Test.toml suites against a freshly started
+/// validator, without going through `anchor test`.
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, Instant};
+use anchor_cli::config::TestConfig;
+use anyhow::{anyhow, Result};
+use crate::program_logs::{create_run_dir, suite_name};
+use crate::test_validator::{
+    start_test_validator, stream_logs, suite_anchor_config, validator_flags, LocalnetOverrides,
+    TestValidatorOptions,
+};
+
+/// The outcome of running one Test.toml suite.
+#[derive(Debug, Clone)]
+pub struct SuiteResult {
+    pub test_toml_path: PathBuf,
+    pub status: ExitStatus,
+    /// How long the test script ran for, excluding validator startup.
+    pub duration: Duration,
+    /// Where the program logs of this run were streamed to.
+    pub program_logs_dir: PathBuf,
+}
+
+impl SuiteResult {
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
+}
+
+/// The results of [run_test_suites], in the order the suites were run.
+#[derive(Debug, Clone, Default)]
+pub struct SuiteReport {
+    pub results: Vec<SuiteResult>,
+}
+
+impl SuiteReport {
+    pub fn failed(&self) -> Vec<&SuiteResult> {
+        self.results.iter().filter(|r| !r.success()).collect()
+    }
+}
+
+impl fmt::Display for SuiteReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            writeln!(
+                f,
+                "{} {} ({:.1}s, {}), program logs: {}",
+                if result.success() { "PASS" } else { "FAIL" },
+                result.test_toml_path.display(),
+                result.duration.as_secs_f64(),
+                result.status,
+                result.program_logs_dir.display(),
+            )?;
+        }
+        write!(
+            f,
+            "{} passed, {} failed",
+            self.results.len() - self.failed().len(),
+            self.failed().len(),
+        )
+    }
+}
+
+/// Start the validator for the suite at `test_toml_path`, as [crate::test_validator::start_localnet_from_test_toml]
+/// would, then run its `test` script with `sh -c` and tear the validator down once it exits.
+/// The script's output is streamed to this process's stdout and stderr.
+pub fn run_test_suite(
+    test_toml_path: &str,
+    flags: impl Into<Vec<String>>,
+    options: &TestValidatorOptions,
+    overrides: &LocalnetOverrides,
+) -> Result<SuiteResult> {
+    let path = PathBuf::from(test_toml_path);
+    if !path.is_file() {
+        return Err(anyhow!("{} is not a file.", &test_toml_path));
+    }
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let test_config = TestConfig::discover(parent, vec![])?
+        .ok_or_else(|| anyhow!("Failed to create a test configuration from {}", &test_toml_path))?;
+    let test_toml = test_config
+        .values()
+        .next()
+        .ok_or_else(|| anyhow!("No test suite found in {}", &test_toml_path))?;
+    let script = test_toml
+        .scripts
+        .get("test")
+        .ok_or_else(|| anyhow!("{} has no \"test\" script", &test_toml_path))?;
+
+    let with_path = suite_anchor_config(test_toml, overrides)?;
+    let mut cfg_flags = validator_flags(&with_path, &test_toml.test, options.ledger_policy)?;
+    cfg_flags.extend(flags.into());
+    let mut validator_handle = start_test_validator(
+        &with_path,
+        &test_toml.test,
+        Some(cfg_flags),
+        true,
+        options,
+    )?;
+    let program_logs_dir = create_run_dir(
+        &suite_name(&path),
+        options.program_logs_dir.as_deref(),
+        options.program_logs_retention,
+    )?;
+    let log_streams = stream_logs(&with_path, &validator_handle.rpc_url, &program_logs_dir)?;
+
+    let started = Instant::now();
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .env("ANCHOR_PROVIDER_URL", &validator_handle.rpc_url)
+        .env("ANCHOR_WALLET", with_path.provider.wallet.to_string())
+        .status();
+    let duration = started.elapsed();
+
+    for mut child in log_streams {
+        if let Err(err) = child.kill() {
+            println!("Failed to kill subprocess {}: {}", child.id(), err);
+        }
+    }
+    if let Err(err) = validator_handle.child.kill() {
+        println!(
+            "Failed to kill subprocess {}: {}",
+            validator_handle.child.id(),
+            err
+        );
+    }
+    let _ = validator_handle.child.wait();
+
+    // Give the validator time to release its ports before the next suite starts.
+    let shutdown_wait = test_toml
+        .test
+        .as_ref()
+        .map(|test| test.shutdown_wait)
+        .unwrap_or(0);
+    if shutdown_wait > 0 {
+        std::thread::sleep(Duration::from_millis(shutdown_wait as u64));
+    }
+
+    Ok(SuiteResult {
+        test_toml_path: path,
+        status: status.map_err(|e| anyhow!("Failed to run {}: {}", script, e))?,
+        duration,
+        program_logs_dir,
+    })
+}
+
+/// Run each suite in turn with [run_test_suite], and print a combined report.
+/// Errors if any suite fails, after all of them have run.
+pub fn run_test_suites(
+    test_toml_paths: &[String],
+    flags: impl Into<Vec<String>>,
+    options: &TestValidatorOptions,
+    overrides: &LocalnetOverrides,
+) -> Result<SuiteReport> {
+    let flags: Vec<String> = flags.into();
+    let mut report = SuiteReport::default();
+    for test_toml_path in test_toml_paths {
+        println!("Running test suite: {}", test_toml_path);
+        report.results.push(run_test_suite(test_toml_path, flags.clone(), options, overrides)?);
+    }
+    println!("{}", report);
+    let failed = report.failed().len();
+    if failed > 0 {
+        return Err(anyhow!(
+            "{} of {} test suites failed",
+            failed,
+            report.results.len()
+        ));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+    use super::*;
+
+    #[test]
+    fn report_counts_failures() {
+        let report = SuiteReport {
+            results: vec![
+                SuiteResult {
+                    test_toml_path: PathBuf::from("tests/suite-1/Test.toml"),
+                    status: ExitStatus::from_raw(0),
+                    duration: Duration::from_millis(1500),
+                    program_logs_dir: PathBuf::from(".anchor/program-logs/suite-1-1"),
+                },
+                SuiteResult {
+                    test_toml_path: PathBuf::from("tests/suite-2/Test.toml"),
+                    status: ExitStatus::from_raw(1 << 8),
+                    duration: Duration::from_millis(300),
+                    program_logs_dir: PathBuf::from(".anchor/program-logs/suite-2-2"),
+                },
+            ],
+        };
+        assert_eq!(report.failed().len(), 1);
+        let printed = report.to_string();
+        assert!(printed.starts_with("PASS tests/suite-1/Test.toml (1.5s"));
+        assert!(printed.contains("FAIL tests/suite-2/Test.toml (0.3s"));
+        assert!(printed.contains("program logs: .anchor/program-logs/suite-2-2"));
+        assert!(printed.ends_with("1 passed, 1 failed"));
+    }
+}
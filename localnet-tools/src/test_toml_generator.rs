@@ -1,10 +1,22 @@
 use std::fs;
-use anyhow::anyhow;
+use std::path::PathBuf;
 use anchor_cli::config::{_TestToml, _TestValidator, _Validator,
-                         AccountEntry, GenesisEntry, ScriptsConfig, TestConfig};
+                         AccountEntry, GenesisEntry, ScriptsConfig};
+#[cfg(feature = "runner")]
+use anchor_cli::config::TestConfig;
 use serde_json::json;
+use solana_account_decoder::UiAccountEncoding;
+use solana_program::clock::Clock;
+use solana_program::epoch_schedule::MINIMUM_SLOTS_PER_EPOCH;
+use solana_program::pubkey::Pubkey;
+use solana_program::sysvar;
+use solana_sdk::account::Account;
+use crate::env_file::LocalnetEnv;
+use crate::error::LocalnetError;
+use crate::idl_preload::IdlPreload;
 use crate::localnet_account::LocalnetAccount;
-use crate::test_validator::localnet_from_test_config;
+#[cfg(feature = "runner")]
+use crate::test_validator::{localnet_from_test_config, LocalnetOverrides, TestValidatorOptions};
 
 
 /// Standard Anchor test command. The [TestTomlGenerator.test_file_glob] is appended
@@ -36,29 +48,146 @@ pub struct TestTomlGenerator {
     /// To ensure that the test validator has enough time to start up before tests begin.
     pub startup_wait: Option<i32>,
     pub shutdown_wait: Option<i32>,
+    /// Overrides the `--mint` address used by [TestTomlGenerator::start_localnet],
+    /// which otherwise falls back to the Anchor wallet.
+    pub mint_pubkey: Option<Pubkey>,
+    /// Start the validator at this slot, via `--warp-slot`.
+    pub warp_slot: Option<u64>,
+    /// Passed as `--slots-per-epoch`, which also disables the warmup epochs.
+    pub slots_per_epoch: Option<u64>,
+    /// Write a dotenv file with the connection info and account addresses here.
+    /// [TestTomlGenerator::build] writes the configured ports, and
+    /// [TestTomlGenerator::start_localnet] rewrites it with the ports actually used.
+    pub env_output: Option<PathBuf>,
+    /// Encoding of the account data in the account files, base58 if unset.
+    /// Base64 or Base64Zstd keep files small when accounts are large.
+    pub account_encoding: Option<UiAccountEncoding>,
+    /// IDL accounts to pre-load, e.g. for cloned programs. They're written and
+    /// loaded like the other accounts.
+    pub idl_accounts: Vec<IdlPreload>,
 }
 
 impl TestTomlGenerator {
-    pub fn build(&self) -> anyhow::Result<()> {
+    pub fn build(&self) -> Result<(), LocalnetError> {
         self.write_accounts()?;
         self.write_js_import_file()?;
         self.write_toml()?;
+        self.write_env_file()?;
         Ok(())
     }
 
-    pub fn write_accounts(&self) -> anyhow::Result<()> {
-        for act in &self.accounts {
-            act.write_to_validator_json_file(&self.save_directory)?;
+    /// Write [TestTomlGenerator::env_output], if set, using the configured ports.
+    pub fn write_env_file(&self) -> Result<(), LocalnetError> {
+        let env_output = match &self.env_output {
+            Some(env_output) => env_output,
+            None => return Ok(()),
+        };
+        let settings = self.validator_settings.clone().unwrap_or_default();
+        let rpc_port = settings.rpc_port.unwrap_or(solana_sdk::rpc_port::DEFAULT_RPC_PORT);
+        let host = settings.bind_address.unwrap_or_else(|| "localhost".to_string());
+        let mut env = LocalnetEnv::new(&format!("http://{}:{}", host, rpc_port), rpc_port);
+        env.faucet_port = Some(settings.faucet_port.unwrap_or(solana_faucet::faucet::FAUCET_PORT));
+        env.accounts = self.all_accounts()?
+            .iter()
+            .map(|act| (act.name.clone(), act.address))
+            .collect();
+        env.write(env_output)
+    }
+
+    /// Set [TestTomlGenerator::warp_slot] to the first slot of `epoch`.
+    /// Requires [TestTomlGenerator::slots_per_epoch] to be set.
+    pub fn warp_to_epoch(&mut self, epoch: u64) -> Result<(), LocalnetError> {
+        let slots_per_epoch = self.slots_per_epoch
+            .ok_or(LocalnetError::SlotsPerEpochRequired)?;
+        let warp_slot = epoch.checked_mul(slots_per_epoch)
+            .ok_or(LocalnetError::EpochOutOfRange { epoch })?;
+        self.warp_slot = Some(warp_slot);
+        Ok(())
+    }
+
+    /// The [TestTomlGenerator::validator_settings], with the warp slot and slots per epoch applied.
+    pub fn effective_validator_settings(&self) -> Result<Option<_Validator>, LocalnetError> {
+        if self.warp_slot.is_none() && self.slots_per_epoch.is_none() {
+            return Ok(self.validator_settings.clone());
+        }
+        let mut settings = self.validator_settings.clone().unwrap_or_default();
+        if let Some(slots_per_epoch) = self.slots_per_epoch {
+            if slots_per_epoch < MINIMUM_SLOTS_PER_EPOCH {
+                return Err(LocalnetError::SlotsPerEpochTooLow {
+                    slots_per_epoch,
+                    minimum: MINIMUM_SLOTS_PER_EPOCH,
+                });
+            }
+            settings.slots_per_epoch = Some(slots_per_epoch.to_string());
+        }
+        if let Some(warp_slot) = self.warp_slot {
+            settings.warp_slot = Some(warp_slot.to_string());
+        }
+        self.warn_on_clock_divergence();
+        Ok(Some(settings))
+    }
+
+    // A preloaded Clock sysvar account should agree with the slot being warped to,
+    // otherwise programs see a different slot or epoch than the validator.
+    fn warn_on_clock_divergence(&self) {
+        let warp_slot = match self.warp_slot {
+            Some(warp_slot) => warp_slot,
+            None => return,
+        };
+        for act in self.accounts.iter().filter(|act| act.address == sysvar::clock::id()) {
+            let account = Account {
+                lamports: act.lamports,
+                data: act.account_data.clone(),
+                owner: act.owner,
+                executable: act.executable,
+                rent_epoch: act.rent_epoch,
+            };
+            let clock: Clock = match account.deserialize_data() {
+                Ok(clock) => clock,
+                Err(_) => {
+                    log::warn!("Account {} is not a valid Clock sysvar", act.name);
+                    continue;
+                }
+            };
+            let epoch = self.slots_per_epoch.map(|slots| warp_slot / slots);
+            if clock.slot != warp_slot || epoch.map_or(false, |epoch| clock.epoch != epoch) {
+                log::warn!(
+                    "Clock sysvar account {} is at slot {} epoch {}, but the validator warps to slot {}{}",
+                    act.name,
+                    clock.slot,
+                    clock.epoch,
+                    warp_slot,
+                    epoch.map(|epoch| format!(" epoch {}", epoch)).unwrap_or_default(),
+                );
+            }
+        }
+    }
+
+    /// [TestTomlGenerator::accounts], followed by the [TestTomlGenerator::idl_accounts].
+    pub fn all_accounts(&self) -> Result<Vec<LocalnetAccount>, LocalnetError> {
+        let mut accounts = self.accounts.clone();
+        for preload in &self.idl_accounts {
+            accounts.push(preload.to_localnet_account()?);
+        }
+        Ok(accounts)
+    }
+
+    pub fn write_accounts(&self) -> Result<(), LocalnetError> {
+        for act in &self.all_accounts()? {
+            act.write_to_validator_json_file_with_encoding(
+                &self.save_directory,
+                self.account_encoding.unwrap_or(UiAccountEncoding::Base58),
+            )?;
         }
         Ok(())
     }
 
     /// Create a file that allows for easy import of the files in this test suite.
-    pub fn write_js_import_file(&self) -> anyhow::Result<()> {
+    pub fn write_js_import_file(&self) -> Result<(), LocalnetError> {
         let mut script = vec![JS_ANCHOR_IMPORT.to_string()];
         script
             .extend(
-                self.accounts
+                self.all_accounts()?
                     .iter()
                     .map(|act| act.js_import())
                     .collect::<Vec<String>>()
@@ -66,15 +195,15 @@ impl TestTomlGenerator {
         let script: String = script.join("\n");
         let save_to = self.save_directory.as_str().to_owned() + "/" + JS_IMPORT_FILE;
         fs::write(&save_to, script)
-            .map_err(|e| anyhow!("Error writing to {}: {:?}", save_to, e))?;
+            .map_err(|error| LocalnetError::Write { path: save_to.into(), error })?;
         Ok(())
     }
 
-    pub fn write_toml(&self) -> anyhow::Result<()> {
+    pub fn write_toml(&self) -> Result<(), LocalnetError> {
         // This is where we inject our accounts and programs.
         let mut test_validator = _TestValidator::default();
         // [[test.validator.account]] blocks
-        let account_entries: Vec<AccountEntry> = self.accounts
+        let account_entries: Vec<AccountEntry> = self.all_accounts()?
             .iter()
             .map(|act| act.to_account_entry())
             .collect();
@@ -127,7 +256,7 @@ impl TestTomlGenerator {
         let mut toml_str_output = toml::to_string(&test_toml).unwrap();
         // Possible [test.validator] settings need to be added this way
         // due to a quirk in [toml] crate's serialization and the [_TestToml] object.
-        if let Some(settings) = self.validator_settings.clone() {
+        if let Some(settings) = self.effective_validator_settings()? {
             let val_settings = json!({
                 "test": {
                     "validator": serde_json::to_value(&settings).unwrap(),
@@ -138,17 +267,156 @@ impl TestTomlGenerator {
         }
         let save_to = self.save_directory.as_str().to_owned() + "/Test.toml";
         fs::write(&save_to, toml_str_output)
-            .map_err(|e| anyhow!("Error writing to {}: {:?}", save_to, e))?;
+            .map_err(|error| LocalnetError::Write { path: save_to.into(), error })?;
         Ok(())
     }
 
-    pub fn start_localnet(&self, flags: Vec<String>) -> anyhow::Result<()> {
+    #[cfg(feature = "runner")]
+    pub fn start_localnet(
+        &self,
+        flags: Vec<String>,
+        options: &TestValidatorOptions,
+    ) -> anyhow::Result<()> {
         let test_config = TestConfig::discover(&self.save_directory, vec![])?;
         if let Some(test_config) = test_config {
-            localnet_from_test_config(test_config, flags)?;
+            let mut options = options.clone();
+            if options.mint_pubkey.is_none() {
+                options.mint_pubkey = self.mint_pubkey;
+            }
+            if options.env_output.is_none() {
+                options.env_output = self.env_output.clone();
+            }
+            localnet_from_test_config(test_config, flags, &options, &LocalnetOverrides::default())?;
             return Ok(())
         }
-        Err(anyhow!("Failed to create a test configuration from {}", &self.save_directory))
+        Err(anyhow::anyhow!("Failed to create a test configuration from {}", &self.save_directory))
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    #[cfg(feature = "runner")]
+    use anchor_cli::config::{Config, TestValidator, Validator};
+    #[cfg(feature = "runner")]
+    use crate::test_validator::start_test_validator;
+    use super::*;
+
+    #[test]
+    fn warp_to_epoch_requires_slots_per_epoch() {
+        let mut generator = TestTomlGenerator::default();
+        assert!(generator.warp_to_epoch(300).is_err());
+        generator.slots_per_epoch = Some(16);
+        generator.warp_to_epoch(300).unwrap();
+        assert_eq!(generator.warp_slot, Some(4800));
+        // Below the minimum allowed by the validator.
+        assert!(generator.effective_validator_settings().is_err());
+    }
+
+    #[test]
+    fn typed_errors() {
+        let mut generator = TestTomlGenerator::default();
+        assert!(matches!(generator.warp_to_epoch(1), Err(LocalnetError::SlotsPerEpochRequired)));
+        generator.slots_per_epoch = Some(u64::MAX);
+        assert!(matches!(
+            generator.warp_to_epoch(2),
+            Err(LocalnetError::EpochOutOfRange { epoch: 2 })
+        ));
+        generator.slots_per_epoch = Some(16);
+        assert!(matches!(
+            generator.effective_validator_settings(),
+            Err(LocalnetError::SlotsPerEpochTooLow { slots_per_epoch: 16, minimum: MINIMUM_SLOTS_PER_EPOCH })
+        ));
+
+        let temp = TempDir::new().unwrap();
+        let generator = TestTomlGenerator {
+            save_directory: temp.path().join("missing").display().to_string(),
+            ..Default::default()
+        };
+        let err = generator.build().unwrap_err();
+        assert!(matches!(
+            &err,
+            LocalnetError::Write { path, .. } if path.ends_with("missing/accounts.ts")
+        ), "{}", err);
+        assert!(err.to_string().starts_with("Error writing to "), "{}", err);
+    }
+
+    #[test]
+    fn idl_accounts_are_written() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let idl_path = dir.join("third_party.json");
+        fs::write(&idl_path, r#"{ "version": "0.1.0", "name": "third_party", "instructions": [] }"#).unwrap();
+        let program_id = Pubkey::new_unique();
+        let generator = TestTomlGenerator {
+            save_directory: dir.display().to_string(),
+            idl_accounts: vec![IdlPreload { program_id, idl_path, authority: Pubkey::new_unique() }],
+            ..Default::default()
+        };
+        generator.build().unwrap();
+        let name = format!("{}-idl-account.json", program_id);
+        let account = LocalnetAccount::read_from_file(dir.join(&name)).unwrap();
+        assert_eq!(account.address, anchor_lang::idl::IdlAccount::address(&program_id));
+        let toml = fs::read_to_string(dir.join("Test.toml")).unwrap();
+        assert!(toml.contains(&account.address.to_string()), "{}", toml);
+        assert!(toml.contains(&name), "{}", toml);
+    }
+
+    #[test]
+    fn warp_settings_are_written() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let mut generator = TestTomlGenerator {
+            save_directory: dir.display().to_string(),
+            slots_per_epoch: Some(32),
+            ..Default::default()
+        };
+        generator.warp_to_epoch(300).unwrap();
+        generator.write_toml().unwrap();
+        let toml = fs::read_to_string(dir.join("Test.toml")).unwrap();
+        assert!(toml.contains("slots_per_epoch = \"32\""));
+        assert!(toml.contains("warp_slot = \"9600\""));
+    }
+
+    #[test]
+    #[cfg(feature = "runner")]
+    #[ignore = "requires solana-test-validator on the PATH"]
+    fn validator_starts_at_warped_epoch() {
+        let mut generator = TestTomlGenerator {
+            slots_per_epoch: Some(32),
+            ..Default::default()
+        };
+        generator.warp_to_epoch(300).unwrap();
+        let settings = generator.effective_validator_settings().unwrap().unwrap();
+        let test_validator = Some(TestValidator {
+            validator: Some(Validator {
+                ledger: ".anchor/warp-test-ledger".to_string(),
+                bind_address: "0.0.0.0".to_string(),
+                rpc_port: 8899,
+                ..Default::default()
+            }),
+            startup_wait: 30_000,
+            ..Default::default()
+        });
+        let mut config = Config::default();
+        config.test_validator = test_validator.clone();
+        let flags = vec![
+            "--slots-per-epoch".to_string(),
+            settings.slots_per_epoch.unwrap(),
+            "--warp-slot".to_string(),
+            settings.warp_slot.unwrap(),
+        ];
+        let options = TestValidatorOptions {
+            auto_pick_ports: true,
+            mint_pubkey: Some(Pubkey::new_unique()),
+            ..Default::default()
+        };
+        let handle = start_test_validator(&config, &test_validator, Some(flags), true, &options)
+            .unwrap();
+        let client = anchor_client::solana_client::rpc_client::RpcClient::new(handle.rpc_url.clone());
+        let epoch_info = client.get_epoch_info().unwrap();
+        assert_eq!(epoch_info.epoch, 300);
+        assert!(epoch_info.absolute_slot >= 9600);
+    }
+}
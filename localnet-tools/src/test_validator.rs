@@ -1,41 +1,263 @@
 /// Copied from Anchor `anchor-cli` crate.
-use std::collections::HashSet;
-
 use std::fs;
 use std::fs::File;
 use std::io::{BufRead, Read};
 use std::path::{Path, PathBuf};
-use std::process::{Child, Stdio};
-use std::str::FromStr;
-use anchor_cli::config::{Config, ConfigOverride, STARTUP_WAIT, TestConfig, TestValidator, WithPath};
-use anchor_client::anchor_lang::idl::IdlAccount;
-use anchor_client::Cluster;
+use std::process::{Child, Command, Stdio};
+use anchor_cli::config::{Config, STARTUP_WAIT, TestConfig, TestToml, TestValidator, WithPath};
 use anchor_client::solana_client::rpc_client::RpcClient;
 use anchor_syn::idl::Idl;
 use anyhow::{anyhow, Result};
-use solana_program::bpf_loader_upgradeable;
-use solana_program::bpf_loader_upgradeable::UpgradeableLoaderState;
 use solana_program::pubkey::Pubkey;
-use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::signature::Signer;
-use crate::idl::{IdlTestMetadata, on_chain_idl_account_data};
-use crate::LocalnetAccount;
+use solana_sdk::signature::Signature;
+use crate::airdrop::{airdrop, airdrop_many, AirdropError};
+use crate::env_file::LocalnetEnv;
+use crate::error::LocalnetError;
+use crate::fixture_manifest::FixtureManifest;
+use crate::idl::IdlTestMetadata;
+use crate::log_rotation::{LogOptions, LogTargets};
+use crate::program_logs::{create_run_dir, suite_name};
+use crate::validator_flags::ValidatorFlags;
+use crate::validator_version::check_validator_version;
+
+/// Written to the ledger directory once the ports are resolved, so that test scripts
+/// can discover where the validator is actually listening.
+pub const PORTS_ENV_FILE: &str = "validator-ports.env";
+
+/// Options for [start_test_validator] that aren't part of the Anchor configuration.
+#[derive(Debug, Clone, Default)]
+pub struct TestValidatorOptions {
+    /// When the configured RPC or faucet port is already in use, pick a free one
+    /// instead of erroring out. Useful when parallel CI jobs share a host.
+    pub auto_pick_ports: bool,
+    /// Whether to start from genesis, or to keep the state of a previous run.
+    pub ledger_policy: LedgerPolicy,
+    /// The `--mint` address that receives the faucet supply. Defaults to the Anchor wallet,
+    /// but a throwaway key keeps tests from depending on the developer's wallet.
+    pub mint_pubkey: Option<Pubkey>,
+    /// Truncation, rotation and stdout/stderr separation of the validator's log files.
+    pub log_options: LogOptions,
+    /// Write the resolved connection info and `--account` addresses to this dotenv file.
+    pub env_output: Option<PathBuf>,
+    /// Stream program logs into this directory, instead of a new
+    /// `.anchor/program-logs/<suite-name>-<timestamp>` directory per run.
+    pub program_logs_dir: Option<PathBuf>,
+    /// Keep only this many of the per-run program log directories.
+    pub program_logs_retention: Option<usize>,
+    /// What [localnet_from_test_config] waits for before shutting the validator down.
+    pub shutdown: Shutdown,
+}
+
+/// How a localnet started for interactive use is told to shut down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shutdown {
+    /// Wait for a line on stdin, e.g. the user pressing Enter.
+    #[default]
+    Enter,
+    /// Wait for Ctrl-C. Only one handler can be set per process.
+    CtrlC,
+}
+
+impl Shutdown {
+    /// Block until it's time to shut down.
+    pub fn wait(self) -> Result<()> {
+        match self {
+            Shutdown::Enter => {
+                std::io::stdin().lock().lines().next();
+            }
+            Shutdown::CtrlC => {
+                let (tx, rx) = std::sync::mpsc::channel();
+                ctrlc::set_handler(move || {
+                    let _ = tx.send(());
+                }).map_err(|e| anyhow!("Failed to set a Ctrl-C handler: {}", e))?;
+                let _ = rx.recv();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What to do with an existing ledger directory when starting the validator.
+///
+/// Note that `--account` and `--bpf-program` flags are only honored when the ledger
+/// is created at genesis. Reusing a ledger intentionally keeps its old state,
+/// including any changes made by previous test runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LedgerPolicy {
+    /// Always recreate the ledger from genesis.
+    #[default]
+    Reset,
+    /// Never reset, and don't re-write account files that already exist.
+    Reuse,
+    /// Reset only when the account JSON files or program binaries differ from
+    /// those recorded in the ledger's [FixtureManifest].
+    ReuseIfFixturesUnchanged,
+}
+
+/// Remove everything in the ledger directory, so the validator starts from genesis.
+fn reset_ledger_directory(ledger_directory: &str) -> Result<()> {
+    fs::remove_dir_all(ledger_directory)?;
+    fs::create_dir_all(ledger_directory)?;
+    Ok(())
+}
+
+/// The address of every `--account` flag, named after its JSON file.
+fn account_names_from_flags(flags: &[String]) -> Vec<(String, Pubkey)> {
+    let mut accounts = vec![];
+    for (i, flag) in flags.iter().enumerate() {
+        if flag == "--account" {
+            if let (Some(address), Some(path)) = (flags.get(i + 1), flags.get(i + 2)) {
+                if let (Ok(address), Some(name)) = (
+                    address.parse::<Pubkey>(),
+                    Path::new(path).file_name(),
+                ) {
+                    accounts.push((name.to_string_lossy().to_string(), address));
+                }
+            }
+        }
+    }
+    accounts
+}
+
+/// Collects the file arguments of every `--account` and `--bpf-program` flag.
+fn fixture_paths_from_flags(flags: &[String]) -> Vec<PathBuf> {
+    let mut paths = vec![];
+    for (i, flag) in flags.iter().enumerate() {
+        if flag == "--account" || flag == "--bpf-program" {
+            if let Some(path) = flags.get(i + 2) {
+                paths.push(PathBuf::from(path));
+            }
+        }
+    }
+    paths
+}
+
+/// A running `solana-test-validator` process, and where to reach it.
+/// The process is killed when this is dropped.
+#[derive(Debug)]
+pub struct TestValidatorHandle {
+    pub child: Child,
+    /// The RPC URL of the validator, reflecting any automatically chosen port.
+    pub rpc_url: String,
+    pub rpc_port: u16,
+    pub faucet_port: u16,
+    /// The file receiving the validator's stdout, unless it was inherited.
+    pub stdout_log: Option<PathBuf>,
+    /// The file receiving the validator's stderr, unless it was inherited.
+    /// This is the same as [TestValidatorHandle::stdout_log] unless
+    /// [LogOptions::separate_stderr] is set.
+    pub stderr_log: Option<PathBuf>,
+}
+
+impl TestValidatorHandle {
+    /// Leave the validator running after this handle, and this process, are gone.
+    /// Returns its process id, to stop it with later.
+    ///
+    /// Logs that are rotated are piped through this process, so they stop when it exits.
+    pub fn detach(self) -> u32 {
+        let pid = self.child.id();
+        // Dropping the handle would kill the validator.
+        std::mem::forget(self);
+        pid
+    }
+
+    /// The port of the validator's faucet, for tools that request airdrops directly.
+    pub fn faucet_port(&self) -> u16 {
+        self.faucet_port
+    }
+
+    /// Fund `to` from the faucet, retrying if the faucet isn't ready yet,
+    /// and wait until the airdrop is processed.
+    pub fn airdrop(&self, to: &Pubkey, lamports: u64) -> Result<Signature, AirdropError> {
+        airdrop(&RpcClient::new(self.rpc_url.clone()), to, lamports)
+    }
+
+    /// Fund several accounts, waiting until every airdrop is processed.
+    pub fn airdrop_many(&self, recipients: &[(Pubkey, u64)]) -> Result<Vec<Signature>, AirdropError> {
+        airdrop_many(&RpcClient::new(self.rpc_url.clone()), recipients)
+    }
+}
+
+impl Drop for TestValidatorHandle {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
 
 // Return the URL that solana-test-validator should be running on given the
-// configuration
-fn test_validator_rpc_url(test_validator: &Option<TestValidator>) -> String {
+// configuration and the resolved RPC port.
+fn test_validator_rpc_url(test_validator: &Option<TestValidator>, rpc_port: u16) -> String {
     match test_validator {
         Some(TestValidator {
                  validator: Some(validator),
                  ..
-             }) => format!("http://{}:{}", validator.bind_address, validator.rpc_port),
-        _ => "http://localhost:8899".to_string(),
+             }) => format!("http://{}:{}", validator.bind_address, rpc_port),
+        _ => format!("http://localhost:{}", rpc_port),
+    }
+}
+
+/// Returns the configured port if it is free. Otherwise, picks an unused port
+/// if `auto_pick` is set, or errors out.
+/// `neighbours` is the number of consecutive ports after the returned one that must
+/// also be free, e.g. the RPC pubsub port listens on `rpc_port + 1`.
+pub fn resolve_port(name: &str, configured: u16, neighbours: u16, auto_pick: bool) -> Result<u16, LocalnetError> {
+    let all_free = |port: u16| {
+        (0..=neighbours).all(|offset| {
+            port.checked_add(offset)
+                .map(portpicker::is_free)
+                .unwrap_or(false)
+        })
+    };
+    if all_free(configured) {
+        return Ok(configured);
+    }
+    if !auto_pick {
+        return Err(LocalnetError::PortInUse { name: name.to_string(), port: configured });
     }
+    for _ in 0..100 {
+        if let Some(port) = portpicker::pick_unused_port() {
+            if all_free(port) {
+                return Ok(port);
+            }
+        }
+    }
+    Err(LocalnetError::NoFreePort { name: name.to_string(), port: configured })
+}
+
+/// Replace the value of `flag` if it is already present, otherwise append it.
+/// `solana-test-validator` rejects flags that are passed more than once.
+fn set_flag(flags: &mut Vec<String>, flag: &str, value: String) {
+    match flags.iter().position(|f| f == flag) {
+        Some(i) if i + 1 < flags.len() => flags[i + 1] = value,
+        _ => {
+            flags.push(flag.to_string());
+            flags.push(value);
+        }
+    }
+}
+
+/// Write the resolved ports as `KEY=value` lines into the ledger directory.
+fn write_ports_env_file(
+    ledger_directory: &str,
+    rpc_url: &str,
+    rpc_port: u16,
+    faucet_port: u16,
+) -> Result<()> {
+    let contents = format!(
+        "RPC_URL={}\nRPC_PORT={}\nFAUCET_PORT={}\n",
+        rpc_url, rpc_port, faucet_port,
+    );
+    fs::write(Path::new(ledger_directory).join(PORTS_ENV_FILE), contents)?;
+    Ok(())
 }
 
 // Setup and return paths to the solana-test-validator ledger directory and log
 // files given the configuration
-fn test_validator_file_paths(test_validator: &Option<TestValidator>) -> (String, String) {
+fn test_validator_file_paths(
+    test_validator: &Option<TestValidator>,
+) -> Result<(String, String), LocalnetError> {
     let ledger_directory = match test_validator {
         Some(TestValidator {
                  validator: Some(validator),
@@ -47,179 +269,39 @@ fn test_validator_file_paths(test_validator: &Option<TestValidator>) -> (String,
     if !Path::new(&ledger_directory).is_relative() {
         // Prevent absolute paths to avoid someone using / or similar, as the
         // directory gets removed
-        eprintln!("Ledger directory {} must be relative", ledger_directory);
-        std::process::exit(1);
+        return Err(LocalnetError::AbsoluteLedgerPath { path: ledger_directory.into() });
     }
     if !Path::new(&ledger_directory).exists() {
-        fs::create_dir_all(&ledger_directory).unwrap();
+        fs::create_dir_all(&ledger_directory).map_err(|error| LocalnetError::Write {
+            path: ledger_directory.into(),
+            error,
+        })?;
     }
-    (
+    Ok((
         ledger_directory.to_string(),
         format!("{}/test-ledger-log.txt", ledger_directory),
-    )
+    ))
 }
 
-// Returns the solana-test-validator flags. This will embed the workspace
-// programs in the genesis block so we don't have to deploy every time. It also
-// allows control of other solana-test-validator features.
-fn validator_flags(
+// Returns the solana-test-validator flags. See [ValidatorFlags] for a typed version.
+pub(crate) fn validator_flags(
     cfg: &WithPath<Config>,
     test_validator: &Option<TestValidator>,
+    ledger_policy: LedgerPolicy,
 ) -> Result<Vec<String>> {
-    let programs = cfg.programs.get(&Cluster::Localnet);
-
-    // On-chain IDL accounts are written here.
-    if !PathBuf::from("target/idl-account").exists() {
-        fs::create_dir("target/idl-account")?;
-    }
-
-    let mut flags = Vec::new();
-    for mut program in cfg.read_all_programs()? {
-        let binary_path = program.binary_path().display().to_string();
-
-        // Use the [programs.cluster] override and fallback to the keypair
-        // files if no override is given.
-        let address: Pubkey = programs
-            .and_then(|m| m.get(&program.lib_name))
-            .map(|deployment| Ok(deployment.address))
-            .unwrap_or_else(|| program.pubkey())?;
-
-        flags.push("--bpf-program".to_string());
-        flags.push(address.clone().to_string());
-        flags.push(binary_path);
-
-        if let Some(idl) = program.idl.as_mut() {
-            // Write the on-chain IDL account to a file and add it as an `--account` flag.
-            let idl_account_data = on_chain_idl_account_data(
-                &program.path.join("src/lib.rs").as_os_str().to_str().unwrap())?;
-            let localnet_idl_act = LocalnetAccount::new(
-                IdlAccount::address(&address),
-                program.lib_name + "-account.json",
-                IdlAccount {
-                    authority: cfg.wallet_kp()?.pubkey(),
-                    data: idl_account_data,
-                },
-            )
-                .set_owner(address.clone());
-            localnet_idl_act.write_to_validator_json_file("target/idl-account")?;
-            flags.push("--account".to_string());
-            flags.push(localnet_idl_act.address.to_string());
-            flags.push(("target/idl-account/".to_string() + &localnet_idl_act.name)
-                .as_str().to_string()
-            );
-            // Add program address to the IDL JSON file.
-            // This is used during shutdown to log transactions.
-            IdlTestMetadata { address: address.to_string() }.write_to_file(idl)?;
-        }
-    }
-
-    if let Some(test) = test_validator.as_ref() {
-        if let Some(genesis) = &test.genesis {
-            for entry in genesis {
-                let program_path = Path::new(&entry.program);
-                if !program_path.exists() {
-                    return Err(anyhow!(
-                        "Program in genesis configuration does not exist at path: {}",
-                        program_path.display()
-                    ));
-                }
-                flags.push("--bpf-program".to_string());
-                flags.push(entry.address.clone());
-                flags.push(entry.program.clone());
-            }
-        }
-        if let Some(validator) = &test.validator {
-            let entries = serde_json::to_value(validator)?;
-            for (key, value) in entries.as_object().unwrap() {
-                if key == "ledger" {
-                    // Ledger flag is a special case as it is passed separately to the rest of
-                    // these validator flags.
-                    continue;
-                };
-                if key == "account" {
-                    for entry in value.as_array().unwrap() {
-                        // Push the account flag for each array entry
-                        flags.push("--account".to_string());
-                        flags.push(entry["address"].as_str().unwrap().to_string());
-                        flags.push(entry["filename"].as_str().unwrap().to_string());
-                    }
-                } else if key == "clone" {
-                    // Client for fetching accounts data
-                    let client = if let Some(url) = entries["url"].as_str() {
-                        RpcClient::new(url.to_string())
-                    } else {
-                        return Err(anyhow!(
-                    "Validator url for Solana's JSON RPC should be provided in order to clone accounts from   it"
-                ));
-                    };
-
-                    let mut pubkeys = value
-                        .as_array()
-                        .unwrap()
-                        .iter()
-                        .map(|entry| {
-                            let address = entry["address"].as_str().unwrap();
-                            Pubkey::from_str(address)
-                                .map_err(|_| anyhow!("Invalid pubkey {}", address))
-                        })
-                        .collect::<anyhow::Result<HashSet<Pubkey>>>()?;
-
-                    let accounts_keys = pubkeys.iter().cloned().collect::<Vec<_>>();
-                    let accounts = client
-                        .get_multiple_accounts_with_commitment(
-                            &accounts_keys,
-                            CommitmentConfig::default(),
-                        )?
-                        .value;
-
-                    // Check if there are program accounts
-                    for (account, acc_key) in accounts.iter().zip(accounts_keys) {
-                        if let Some(account) = account {
-                            if account.owner == bpf_loader_upgradeable::id() {
-                                let upgradable: UpgradeableLoaderState = account
-                                    .deserialize_data()
-                                    .map_err(|_| anyhow!("Invalid program account {}", acc_key))?;
-
-                                if let UpgradeableLoaderState::Program {
-                                    programdata_address,
-                                } = upgradable
-                                {
-                                    pubkeys.insert(programdata_address);
-                                }
-                            }
-                        } else {
-                            return Err(anyhow!("Account {} not found", acc_key));
-                        }
-                    }
-
-                    for pubkey in &pubkeys {
-                        // Push the clone flag for each array entry
-                        flags.push("--clone".to_string());
-                        flags.push(pubkey.to_string());
-                    }
-                } else {
-                    // Remaining validator flags are non-array types
-                    flags.push(format!("--{}", key.replace('_', "-")));
-                    if let serde_json::Value::String(v) = value {
-                        flags.push(v.to_string());
-                    } else {
-                        flags.push(value.to_string());
-                    }
-                }
-            }
-        }
-    }
-    Ok(flags)
+    Ok(ValidatorFlags::from_config_with_ledger_policy(cfg, test_validator, ledger_policy)?.to_args())
 }
 
 
-fn stream_logs(config: &WithPath<Config>, rpc_url: &str) -> Result<Vec<Child>> {
-    let program_logs_dir = ".anchor/program-logs";
-    if Path::new(program_logs_dir).exists() {
-        fs::remove_dir_all(program_logs_dir)?;
-    }
+// Creates the program log directory, and returns the address of every program
+// whose logs should be streamed, along with the file to stream them to.
+pub(crate) fn log_stream_targets(
+    config: &WithPath<Config>,
+    program_logs_dir: &Path,
+) -> Result<Vec<(String, String)>> {
     fs::create_dir_all(program_logs_dir)?;
-    let mut handles = vec![];
+    let program_logs_dir = program_logs_dir.display();
+    let mut targets = vec![];
     for program in config.read_all_programs()? {
         let mut file = File::open(&format!("target/idl/{}.json", program.lib_name))?;
         let mut contents = vec![];
@@ -232,106 +314,186 @@ fn stream_logs(config: &WithPath<Config>, rpc_url: &str) -> Result<Vec<Child>> {
             )
         })?;
         let metadata: IdlTestMetadata = serde_json::from_value(metadata)?;
-
-        let log_file = File::create(format!(
+        let log_file = format!(
             "{}/{}.{}.log",
             program_logs_dir, metadata.address, program.lib_name,
-        ))?;
-        let stdio = std::process::Stdio::from(log_file);
+        );
+        targets.push((metadata.address, log_file));
+    }
+    if let Some(test) = config.test_validator.as_ref() {
+        if let Some(genesis) = &test.genesis {
+            for entry in genesis {
+                let log_file = format!("{}/{}.log", program_logs_dir, entry.address);
+                targets.push((entry.address.clone(), log_file));
+            }
+        }
+    }
+    Ok(targets)
+}
+
+pub(crate) fn stream_logs(
+    config: &WithPath<Config>,
+    rpc_url: &str,
+    program_logs_dir: &Path,
+) -> Result<Vec<Child>> {
+    let mut handles = vec![];
+    for (address, log_file) in log_stream_targets(config, program_logs_dir)? {
+        let stdio = std::process::Stdio::from(File::create(log_file)?);
         let child = std::process::Command::new("solana")
             .arg("logs")
-            .arg(metadata.address)
+            .arg(address)
             .arg("--url")
             .arg(rpc_url)
             .stdout(stdio)
             .spawn()?;
         handles.push(child);
     }
-    if let Some(test) = config.test_validator.as_ref() {
-        if let Some(genesis) = &test.genesis {
-            for entry in genesis {
-                let log_file = File::create(format!("{}/{}.log", program_logs_dir, entry.address))?;
-                let stdio = std::process::Stdio::from(log_file);
-                let child = std::process::Command::new("solana")
-                    .arg("logs")
-                    .arg(entry.address.clone())
-                    .arg("--url")
-                    .arg(rpc_url)
-                    .stdout(stdio)
-                    .spawn()?;
-                handles.push(child);
-            }
-        }
-    }
     Ok(handles)
 }
 
-/// Run a `solana-test-validator` command according to a configuration specified
-/// in an Anchor workspace or Test.toml file.
-pub fn start_test_validator(
+// The `solana-test-validator` command, without any stdio configuration.
+fn test_validator_command(ledger_directory: &str, mint: &Pubkey, flags: &[String]) -> Command {
+    let mut command = Command::new("solana-test-validator");
+    command
+        .arg("--ledger")
+        .arg(ledger_directory)
+        .arg("--mint")
+        .arg(mint.to_string())
+        .args(flags);
+    command
+}
+
+/// A `solana-test-validator` command that is ready to be spawned, with its ports
+/// resolved and its ledger directory prepared.
+pub(crate) struct PreparedTestValidator {
+    pub command: Command,
+    pub rpc_url: String,
+    pub rpc_port: u16,
+    pub faucet_port: u16,
+    pub log_filename: String,
+    /// Milliseconds to wait for the validator to respond after spawning it.
+    pub startup_wait: i32,
+}
+
+// Resolves ports, applies the ledger policy, and builds the command line shared
+// by the blocking and async front-ends.
+pub(crate) fn prepare_test_validator(
     cfg: &Config,
     test_validator: &Option<TestValidator>,
     flags: Option<Vec<String>>,
-    test_log_stdout: bool,
-) -> Result<Child> {
-    //
+    options: &TestValidatorOptions,
+) -> Result<PreparedTestValidator, LocalnetError> {
     let (test_ledger_directory, test_ledger_log_filename) =
-        test_validator_file_paths(test_validator);
-
-    // Start a validator for testing.
-    let (test_validator_stdout, test_validator_stderr) = match test_log_stdout {
-        true => {
-            let test_validator_stdout_file = File::create(&test_ledger_log_filename)?;
-            let test_validator_sterr_file = test_validator_stdout_file.try_clone()?;
-            (
-                Stdio::from(test_validator_stdout_file),
-                Stdio::from(test_validator_sterr_file),
-            )
-        }
-        false => (Stdio::inherit(), Stdio::inherit()),
-    };
-
-    let rpc_url = test_validator_rpc_url(test_validator);
+        test_validator_file_paths(test_validator)?;
 
     let rpc_port = cfg
         .test_validator
         .as_ref()
         .and_then(|test| test.validator.as_ref().map(|v| v.rpc_port))
         .unwrap_or(solana_sdk::rpc_port::DEFAULT_RPC_PORT);
-    if !portpicker::is_free(rpc_port) {
-        return Err(anyhow!(
-            "Your configured rpc port: {rpc_port} is already in use"
-        ));
-    }
+    // The pubsub service listens on the port after the RPC port.
+    let rpc_port = resolve_port("rpc", rpc_port, 1, options.auto_pick_ports)?;
     let faucet_port = cfg
         .test_validator
         .as_ref()
         .and_then(|test| test.validator.as_ref().and_then(|v| v.faucet_port))
         .unwrap_or(solana_faucet::faucet::FAUCET_PORT);
-    if !portpicker::is_free(faucet_port) {
-        return Err(anyhow!(
-            "Your configured faucet port: {faucet_port} is already in use"
-        ));
+    let faucet_port = resolve_port("faucet", faucet_port, 0, options.auto_pick_ports)?;
+
+    let mut flags = flags.unwrap_or_default();
+    set_flag(&mut flags, "--rpc-port", rpc_port.to_string());
+    set_flag(&mut flags, "--faucet-port", faucet_port.to_string());
+    // Fail with a clear error rather than a clap error in the ledger log.
+    check_validator_version(&flags)?;
+
+    // The ledger is reset here rather than with `--reset`, since the validator would
+    // also delete the log file and fixture manifest that live in the ledger directory.
+    match options.ledger_policy {
+        LedgerPolicy::Reset => reset_ledger_directory(&test_ledger_directory)?,
+        LedgerPolicy::Reuse => {}
+        LedgerPolicy::ReuseIfFixturesUnchanged => {
+            let manifest = FixtureManifest::from_files(&fixture_paths_from_flags(&flags))?;
+            if manifest.differs_from_saved(&test_ledger_directory)? {
+                reset_ledger_directory(&test_ledger_directory)?;
+                manifest.save(&test_ledger_directory)?;
+            }
+        }
     }
 
-    let mut validator_handle = std::process::Command::new("solana-test-validator")
-        .arg("--ledger")
-        .arg(test_ledger_directory)
-        .arg("--mint")
-        .arg(cfg.wallet_kp()?.pubkey().to_string())
-        .args(flags.unwrap_or_default())
+    let rpc_url = test_validator_rpc_url(test_validator, rpc_port);
+    write_ports_env_file(&test_ledger_directory, &rpc_url, rpc_port, faucet_port)?;
+    if let Some(env_output) = &options.env_output {
+        let mut env = LocalnetEnv::new(&rpc_url, rpc_port);
+        env.faucet_port = Some(faucet_port);
+        env.wallet_path = Some(cfg.provider.wallet.to_string());
+        env.accounts = account_names_from_flags(&flags);
+        env.write(env_output)?;
+    }
+
+    let mint = match options.mint_pubkey {
+        Some(mint) => mint,
+        None => cfg.wallet_kp()?.pubkey(),
+    };
+    let startup_wait = test_validator
+        .as_ref()
+        .map(|test| test.startup_wait)
+        .unwrap_or(STARTUP_WAIT);
+    Ok(PreparedTestValidator {
+        command: test_validator_command(&test_ledger_directory, &mint, &flags),
+        rpc_url,
+        rpc_port,
+        faucet_port,
+        log_filename: test_ledger_log_filename,
+        startup_wait,
+    })
+}
+
+/// Run a `solana-test-validator` command according to a configuration specified
+/// in an Anchor workspace or Test.toml file.
+pub fn start_test_validator(
+    cfg: &Config,
+    test_validator: &Option<TestValidator>,
+    flags: Option<Vec<String>>,
+    test_log_stdout: bool,
+    options: &TestValidatorOptions,
+) -> Result<TestValidatorHandle, LocalnetError> {
+    let PreparedTestValidator {
+        mut command,
+        rpc_url,
+        rpc_port,
+        faucet_port,
+        log_filename: test_ledger_log_filename,
+        startup_wait: ms_wait,
+    } = prepare_test_validator(cfg, test_validator, flags, options)?;
+
+    // Start a validator for testing.
+    let log_error = |error| LocalnetError::Write { path: test_ledger_log_filename.clone().into(), error };
+    let log_targets = match test_log_stdout {
+        true => Some(LogTargets::open(&test_ledger_log_filename, &options.log_options).map_err(log_error)?),
+        false => None,
+    };
+    let (test_validator_stdout, test_validator_stderr) = match &log_targets {
+        Some(targets) => (
+            targets.stdout.stdio().map_err(log_error)?,
+            targets.stderr.stdio().map_err(log_error)?,
+        ),
+        None => (Stdio::inherit(), Stdio::inherit()),
+    };
+
+    let mut validator_handle = command
         .stdout(test_validator_stdout)
         .stderr(test_validator_stderr)
         .spawn()
-        .map_err(|e| anyhow::format_err!("{}", e.to_string()))?;
+        .map_err(LocalnetError::Spawn)?;
+    if let Some(targets) = &log_targets {
+        targets.pump(validator_handle.stdout.take(), validator_handle.stderr.take());
+    }
+    let stdout_log = log_targets.as_ref().map(|t| t.stdout_path.clone());
+    let stderr_log = log_targets.as_ref().map(|t| t.stderr_path.clone());
 
     // Wait for the validator to be ready.
-    let client = RpcClient::new(rpc_url);
+    let client = RpcClient::new(rpc_url.clone());
     let mut count = 0;
-    let ms_wait = test_validator
-        .as_ref()
-        .map(|test| test.startup_wait)
-        .unwrap_or(STARTUP_WAIT);
     while count < ms_wait {
         let r = client.get_latest_blockhash();
         if r.is_ok() {
@@ -341,39 +503,113 @@ pub fn start_test_validator(
         count += 1;
     }
     if count == ms_wait {
-        eprintln!(
-            "Unable to get latest blockhash. Test validator does not look started. Check {} for errors.       Consider increasing [test.startup_wait] in Anchor.toml.",
-            test_ledger_log_filename
-        );
-        validator_handle.kill()?;
-        std::process::exit(1);
+        let log = stderr_log
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or(test_ledger_log_filename);
+        let _ = validator_handle.kill();
+        let _ = validator_handle.wait();
+        return Err(LocalnetError::StartupTimeout { rpc_url, log, waited_ms: ms_wait });
+    }
+    Ok(TestValidatorHandle {
+        child: validator_handle,
+        rpc_url,
+        rpc_port,
+        faucet_port,
+        stdout_log,
+        stderr_log,
+    })
+}
+
+/// Where to find the Anchor workspace configuration, instead of searching upwards
+/// from the working directory.
+#[derive(Debug, Clone, Default)]
+pub struct LocalnetOverrides {
+    /// Path to the `Anchor.toml` to use.
+    pub anchor_toml: Option<PathBuf>,
+    /// Replaces the `[provider] wallet` in the `Anchor.toml`.
+    pub wallet: Option<PathBuf>,
+}
+
+// Search `start` and its ancestors for an `Anchor.toml`, like `anchor` does.
+fn find_anchor_toml(start: &Path) -> Result<PathBuf> {
+    let mut searched = vec![];
+    for dir in start.ancestors() {
+        let candidate = dir.join("Anchor.toml");
+        if candidate.is_file() {
+            return Ok(candidate);
+        }
+        searched.push(dir.display().to_string());
     }
-    Ok(validator_handle)
+    Err(anyhow!(
+        "Anchor.toml not found. Searched:\n  {}\nUse an Anchor.toml override to point to it explicitly.",
+        searched.join("\n  ")
+    ))
 }
 
-pub fn localnet_from_test_config(test_config: TestConfig, flags: Vec<String>) -> Result<()> {
-    for (_, test_toml) in &*test_config {
-        // Copy the test suite into the Anchor [Config].
-        // Set the startup_wait to zero, since it's irrelevant when we aren't running tests.
-        let mut anchor_cfg = Config::discover(
-            &ConfigOverride::default(),
-        )?.unwrap();
-        let mut test_validator = test_toml.test.clone();
-        if let Some(inner) = test_validator {
-            let mut with_no_wait = inner.clone();
-            with_no_wait.startup_wait = 0;
-            test_validator = Some(with_no_wait);
-        } else {
-            let mut with_no_wait = TestValidator::default();
-            with_no_wait.startup_wait = 0;
-            test_validator = Some(with_no_wait);
+/// Load the Anchor workspace configuration, applying any overrides.
+/// The returned path is that of the `Anchor.toml` actually used.
+pub fn discover_anchor_config(overrides: &LocalnetOverrides) -> Result<WithPath<Config>> {
+    let anchor_toml = match &overrides.anchor_toml {
+        Some(anchor_toml) if anchor_toml.is_file() => anchor_toml.clone(),
+        Some(anchor_toml) => {
+            return Err(anyhow!("{} is not a file.", anchor_toml.display()));
         }
-        anchor_cfg.test_validator = test_validator;
-        let with_path = &WithPath::new(
-            anchor_cfg, PathBuf::from("./Anchor.toml"));
+        None => find_anchor_toml(&std::env::current_dir()?)?,
+    };
+    let mut cfg: Config = fs::read_to_string(&anchor_toml)
+        .map_err(|e| anyhow!("Error reading {}: {}", anchor_toml.display(), e))?
+        .parse()?;
+    if let Some(wallet) = &overrides.wallet {
+        cfg.provider.wallet = wallet.display().to_string().parse()?;
+    }
+    Ok(WithPath::new(cfg, anchor_toml))
+}
+
+// Copy the test suite into the discovered Anchor [Config].
+pub(crate) fn suite_anchor_config(
+    test_toml: &TestToml,
+    overrides: &LocalnetOverrides,
+) -> Result<WithPath<Config>> {
+    // Set the startup_wait to zero, since it's irrelevant when we aren't running tests.
+    let with_path = discover_anchor_config(overrides)?;
+    let anchor_toml = with_path.path().to_path_buf();
+    let mut anchor_cfg = with_path.into_inner();
+    let mut test_validator = test_toml.test.clone();
+    if let Some(inner) = test_validator {
+        let mut with_no_wait = inner.clone();
+        with_no_wait.startup_wait = 0;
+        test_validator = Some(with_no_wait);
+    } else {
+        let mut with_no_wait = TestValidator::default();
+        with_no_wait.startup_wait = 0;
+        test_validator = Some(with_no_wait);
+    }
+    anchor_cfg.test_validator = test_validator;
+    Ok(WithPath::new(anchor_cfg, anchor_toml))
+}
+
+/// Start a localnet for the first test suite in `test_config`, and wait for a line on stdin
+/// before shutting it down. Extra `flags` can be given either as a [ValidatorFlags] or
+/// as raw strings.
+pub fn localnet_from_test_config(
+    test_config: TestConfig,
+    flags: impl Into<Vec<String>>,
+    options: &TestValidatorOptions,
+    overrides: &LocalnetOverrides,
+) -> Result<()> {
+    let flags: Vec<String> = flags.into();
+    for (suite_path, test_toml) in &*test_config {
+        let with_path = &suite_anchor_config(test_toml, overrides)?;
+        let program_logs_dir = create_run_dir(
+            &suite_name(Path::new(suite_path)),
+            options.program_logs_dir.as_deref(),
+            options.program_logs_retention,
+        )?;
+        println!("Program logs: {}", program_logs_dir.display());
         // Gather the CLI flags
         let mut cfg_flags = validator_flags(
-            &with_path, &test_toml.test)?;
+            &with_path, &test_toml.test, options.ledger_policy)?;
         cfg_flags.extend(flags);
         // Start the validator
         let mut validator_handle = start_test_validator(
@@ -381,21 +617,22 @@ pub fn localnet_from_test_config(test_config: TestConfig, flags: Vec<String>) ->
             &test_toml.test,
             Some(cfg_flags),
             false,
+            options,
         )?;
 
-        let url = test_validator_rpc_url(&test_toml.test);
         let log_streams = stream_logs(
             &with_path,
-            &url,
+            &validator_handle.rpc_url,
+            &program_logs_dir,
         );
 
-        std::io::stdin().lock().lines().next().unwrap().unwrap();
+        options.shutdown.wait()?;
 
         // Check all errors and shut down.
-        if let Err(err) = validator_handle.kill() {
+        if let Err(err) = validator_handle.child.kill() {
             println!(
                 "Failed to kill subprocess {}: {}",
-                validator_handle.id(),
+                validator_handle.child.id(),
                 err
             );
         }
@@ -410,7 +647,12 @@ pub fn localnet_from_test_config(test_config: TestConfig, flags: Vec<String>) ->
     Ok(())
 }
 
-pub fn start_localnet_from_test_toml(test_toml_path: &str, flags: Vec<String>) -> Result<()> {
+pub fn start_localnet_from_test_toml(
+    test_toml_path: &str,
+    flags: impl Into<Vec<String>>,
+    options: &TestValidatorOptions,
+    overrides: &LocalnetOverrides,
+) -> Result<()> {
     let path = PathBuf::from(test_toml_path);
     if !path.exists() {
         return Err(anyhow!("{} does not exist.", &test_toml_path));
@@ -420,8 +662,184 @@ pub fn start_localnet_from_test_toml(test_toml_path: &str, flags: Vec<String>) -
     }
     let test_config = TestConfig::discover(&path.parent().unwrap(), vec![])?;
     if let Some(test_config) = test_config {
-        localnet_from_test_config(test_config, flags)?;
+        localnet_from_test_config(test_config, flags, options, overrides)?;
         return Ok(());
     }
     Err(anyhow!("Failed to create a test configuration from {}", &test_toml_path))
 }
+
+/// Start the validator for the suite at `test_toml_path`, and return once it's ready.
+/// Unlike [start_localnet_from_test_toml], this doesn't wait for a shutdown, or
+/// stream program logs. The validator's output goes to the ledger directory.
+pub fn start_suite_validator(
+    test_toml_path: &str,
+    flags: impl Into<Vec<String>>,
+    options: &TestValidatorOptions,
+    overrides: &LocalnetOverrides,
+) -> Result<TestValidatorHandle> {
+    let path = PathBuf::from(test_toml_path);
+    if !path.is_file() {
+        return Err(anyhow!("{} is not a file.", &test_toml_path));
+    }
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    let test_config = TestConfig::discover(parent, vec![])?
+        .ok_or_else(|| anyhow!("Failed to create a test configuration from {}", &test_toml_path))?;
+    let test_toml = test_config
+        .values()
+        .next()
+        .ok_or_else(|| anyhow!("No test suite found in {}", &test_toml_path))?;
+    let with_path = suite_anchor_config(test_toml, overrides)?;
+    let mut cfg_flags = validator_flags(&with_path, &test_toml.test, options.ledger_policy)?;
+    cfg_flags.extend(flags.into());
+    Ok(start_test_validator(&with_path, &test_toml.test, Some(cfg_flags), true, options)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use tempfile::TempDir;
+    use anchor_cli::config::Validator;
+    use super::*;
+
+    #[test]
+    fn busy_port_is_replaced_when_auto_picking() {
+        let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+        let busy = listener.local_addr().unwrap().port();
+        assert!(resolve_port("rpc", busy, 1, false).is_err());
+        let picked = resolve_port("rpc", busy, 1, true).unwrap();
+        assert_ne!(picked, busy);
+        assert!(portpicker::is_free(picked));
+        assert!(portpicker::is_free(picked + 1));
+    }
+
+    #[test]
+    fn typed_errors() {
+        let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+        let busy = listener.local_addr().unwrap().port();
+        assert!(matches!(
+            resolve_port("rpc", busy, 1, false),
+            Err(LocalnetError::PortInUse { name, port }) if name == "rpc" && port == busy
+        ));
+
+        let test_validator = Some(TestValidator {
+            validator: Some(Validator {
+                ledger: "/".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let err = start_test_validator(
+            &Config::default(), &test_validator, None, true, &TestValidatorOptions::default(),
+        ).unwrap_err();
+        assert!(matches!(&err, LocalnetError::AbsoluteLedgerPath { path } if path == Path::new("/")));
+        assert_eq!(err.to_string(), "Ledger directory / must be relative");
+    }
+
+    #[test]
+    fn free_port_is_kept() {
+        let free = portpicker::pick_unused_port().unwrap();
+        assert_eq!(resolve_port("faucet", free, 0, true).unwrap(), free);
+    }
+
+    #[test]
+    fn mint_is_passed_to_the_validator() {
+        let mint = Pubkey::new_unique();
+        let flags = vec!["--rpc-port".to_string(), "8899".to_string()];
+        let command = test_validator_command(".anchor/test-ledger", &mint, &flags);
+        let args: Vec<String> = command.get_args()
+            .map(|a| a.to_str().unwrap().to_string())
+            .collect();
+        assert_eq!(args, vec![
+            "--ledger".to_string(),
+            ".anchor/test-ledger".to_string(),
+            "--mint".to_string(),
+            mint.to_string(),
+            "--rpc-port".to_string(),
+            "8899".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn fixture_paths_are_collected_from_flags() {
+        let flags: Vec<String> = [
+            "--bpf-program", "addr1", "target/deploy/program.so",
+            "--account", "addr2", "tests/suite-1/mint.json",
+            "--clone", "addr3",
+        ].iter().map(|s| s.to_string()).collect();
+        assert_eq!(fixture_paths_from_flags(&flags), vec![
+            PathBuf::from("target/deploy/program.so"),
+            PathBuf::from("tests/suite-1/mint.json"),
+        ]);
+    }
+
+    #[test]
+    fn account_names_are_collected_from_flags() {
+        let address = Pubkey::new_unique();
+        let flags: Vec<String> = [
+            "--account", &address.to_string(), "tests/suite-1/usdc-mint.json",
+            "--clone", &Pubkey::new_unique().to_string(),
+        ].iter().map(|s| s.to_string()).collect();
+        assert_eq!(account_names_from_flags(&flags), vec![
+            ("usdc-mint.json".to_string(), address),
+        ]);
+    }
+
+    const ANCHOR_TOML: &str = "[provider]\ncluster = \"localnet\"\nwallet = \"~/.config/solana/id.json\"\n";
+
+    #[test]
+    fn anchor_toml_is_found_in_an_ancestor() {
+        let temp = TempDir::new().unwrap();
+        let workspace = temp.path();
+        fs::write(workspace.join("Anchor.toml"), ANCHOR_TOML).unwrap();
+        let nested = workspace.join("tests/suite-1");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_anchor_toml(&nested).unwrap(), workspace.join("Anchor.toml"));
+    }
+
+    #[test]
+    fn missing_anchor_toml_lists_searched_directories() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let err = find_anchor_toml(dir).unwrap_err().to_string();
+        assert!(err.contains(&dir.display().to_string()));
+    }
+
+    #[test]
+    fn anchor_toml_outside_the_working_directory() {
+        let temp = TempDir::new().unwrap();
+        let workspace = temp.path().join("programs-workspace");
+        fs::create_dir_all(&workspace).unwrap();
+        fs::write(workspace.join("Anchor.toml"), ANCHOR_TOML).unwrap();
+        let overrides = LocalnetOverrides {
+            anchor_toml: Some(workspace.join("Anchor.toml")),
+            wallet: Some(PathBuf::from("/tmp/ci-wallet.json")),
+        };
+        let cfg = discover_anchor_config(&overrides).unwrap();
+        assert_eq!(cfg.path(), &workspace.join("Anchor.toml"));
+        assert_eq!(cfg.provider.wallet.to_string(), "/tmp/ci-wallet.json");
+        let missing = LocalnetOverrides {
+            anchor_toml: Some(workspace.join("Missing.toml")),
+            ..Default::default()
+        };
+        assert!(discover_anchor_config(&missing).is_err());
+    }
+
+    #[test]
+    fn picked_ports_replace_configured_flags() {
+        let mut flags = vec![
+            "--rpc-port".to_string(),
+            "8899".to_string(),
+            "--bind-address".to_string(),
+            "0.0.0.0".to_string(),
+        ];
+        set_flag(&mut flags, "--rpc-port", "18899".to_string());
+        set_flag(&mut flags, "--faucet-port", "19900".to_string());
+        assert_eq!(flags, vec![
+            "--rpc-port", "18899",
+            "--bind-address", "0.0.0.0",
+            "--faucet-port", "19900",
+        ]);
+        let url = test_validator_rpc_url(&None, 18899);
+        assert_eq!(url, "http://localhost:18899");
+    }
+}
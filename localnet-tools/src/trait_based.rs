@@ -1,3 +1,5 @@
+use std::io;
+use std::path::PathBuf;
 use anchor_client::anchor_lang::{AccountDeserialize, AccountSerialize};
 use anchor_client::solana_client::rpc_client::RpcClient;
 use anchor_client::solana_sdk::account::Account;
@@ -5,6 +7,8 @@ use solana_program::clock::Epoch;
 use anyhow::Result;
 use solana_program::pubkey::Pubkey;
 use solana_program::system_program;
+use crate::error::LocalnetError;
+use crate::large_account::AccountHeader;
 use crate::localnet_account::THOUSAND_SOL;
 use crate::LocalnetAccount;
 
@@ -37,6 +41,24 @@ pub trait GeneratedAccount {
         format!("{}.json", self.address().to_string())
     }
 
+    /// Write the account file in the `base64+zstd` encoding, serializing the account
+    /// straight into the compressor rather than into a buffer first.
+    /// Meant for accounts of several megabytes.
+    fn write_large_account_file(&self, path_prefix: &str) -> Result<(), LocalnetError> {
+        let data = self.generate();
+        let path = PathBuf::from(format!("{}/{}", path_prefix, self.name()));
+        AccountHeader {
+            address: &self.address(),
+            lamports: self.lamports(),
+            owner: &self.owner(),
+            executable: self.executable(),
+            rent_epoch: self.rent_epoch(),
+        }.write(&path, |mut writer| {
+            data.try_serialize(&mut writer)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+        })
+    }
+
     fn to_localnet_account(&self) -> LocalnetAccount {
         let data = self.generate();
         let mut buf = vec![];
@@ -0,0 +1,502 @@
+/// A structured form of the `solana-test-validator` flags that are derived from
+/// an Anchor workspace and `[test.validator]` configuration.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anchor_cli::config::{Config, TestValidator, WithPath};
+use anchor_client::Cluster;
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anyhow::{anyhow, Result};
+use solana_program::bpf_loader_upgradeable;
+use solana_program::bpf_loader_upgradeable::UpgradeableLoaderState;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signer;
+use crate::idl::{IdlTestMetadata, on_chain_idl_account_data};
+use crate::idl_preload::{idl_account, IdlPreload};
+use crate::LocalnetAccount;
+use crate::test_validator::LedgerPolicy;
+
+/// IDL accounts to load beyond the defaults of [ValidatorFlags::from_config].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdlOptions {
+    /// IDL authorities of workspace programs, by program name.
+    /// Programs not listed use the workspace wallet.
+    pub authorities: HashMap<String, Pubkey>,
+    /// IDL accounts of programs outside the workspace, e.g. cloned ones.
+    pub preloads: Vec<IdlPreload>,
+}
+
+/// Typed `solana-test-validator` flags, so that callers can inspect or adjust
+/// specific flags before the validator is started.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidatorFlags {
+    /// `--bpf-program <ADDRESS> <PATH>`
+    pub bpf_programs: Vec<(Pubkey, PathBuf)>,
+    /// `--account <ADDRESS> <PATH>`
+    pub accounts: Vec<(Pubkey, PathBuf)>,
+    /// `--clone <ADDRESS>`
+    pub clones: Vec<Pubkey>,
+    /// Every other `[test.validator]` setting, keyed by its `snake_case` name,
+    /// e.g. `"slots_per_epoch"` becomes `--slots-per-epoch`.
+    pub other: BTreeMap<String, String>,
+}
+
+impl ValidatorFlags {
+    /// Builds the flags for a workspace and test validator configuration. This embeds the
+    /// workspace programs in the genesis block so we don't have to deploy every time.
+    /// It also allows control of other solana-test-validator features.
+    pub fn from_config(
+        cfg: &WithPath<Config>,
+        test_validator: &Option<TestValidator>,
+    ) -> Result<Self> {
+        Self::from_config_with_ledger_policy(cfg, test_validator, LedgerPolicy::Reset)
+    }
+
+    /// Same as [ValidatorFlags::from_config], except IDL account files that already
+    /// exist are left alone when the ledger is going to be reused.
+    pub fn from_config_with_ledger_policy(
+        cfg: &WithPath<Config>,
+        test_validator: &Option<TestValidator>,
+        ledger_policy: LedgerPolicy,
+    ) -> Result<Self> {
+        Self::from_config_with_idl_options(cfg, test_validator, ledger_policy, &IdlOptions::default())
+    }
+
+    /// Same as [ValidatorFlags::from_config_with_ledger_policy], with control over
+    /// the IDL accounts' authorities, and extra IDL accounts to load.
+    pub fn from_config_with_idl_options(
+        cfg: &WithPath<Config>,
+        test_validator: &Option<TestValidator>,
+        ledger_policy: LedgerPolicy,
+        idl_options: &IdlOptions,
+    ) -> Result<Self> {
+        let programs = cfg.programs.get(&Cluster::Localnet);
+
+        // On-chain IDL accounts are written here.
+        if !PathBuf::from("target/idl-account").exists() {
+            fs::create_dir("target/idl-account")?;
+        }
+
+        let mut flags = Self::default();
+        for mut program in cfg.read_all_programs()? {
+            let binary_path = program.binary_path();
+
+            // Use the [programs.cluster] override and fallback to the keypair
+            // files if no override is given.
+            let address: Pubkey = programs
+                .and_then(|m| m.get(&program.lib_name))
+                .map(|deployment| Ok(deployment.address))
+                .unwrap_or_else(|| program.pubkey())?;
+
+            flags.bpf_programs.push((address, binary_path));
+
+            if let Some(idl) = program.idl.as_mut() {
+                // Write the on-chain IDL account to a file and add it as an `--account` flag.
+                let idl_account_data = on_chain_idl_account_data(
+                    &program.path.join("src/lib.rs").as_os_str().to_str().unwrap())?;
+                let authority = match idl_options.authorities.get(&program.lib_name) {
+                    Some(authority) => *authority,
+                    None => cfg.wallet_kp()?.pubkey(),
+                };
+                let localnet_idl_act = idl_account(
+                    &address,
+                    &authority,
+                    idl_account_data,
+                    program.lib_name + "-account.json",
+                );
+                flags.push_idl_account(&localnet_idl_act, ledger_policy)?;
+                // Add program address to the IDL JSON file.
+                // This is used during shutdown to log transactions.
+                IdlTestMetadata { address: address.to_string() }.write_to_file(idl)?;
+            }
+        }
+
+        for preload in &idl_options.preloads {
+            flags.push_idl_account(&preload.to_localnet_account()?, ledger_policy)?;
+        }
+
+        if let Some(test) = test_validator.as_ref() {
+            if let Some(genesis) = &test.genesis {
+                for entry in genesis {
+                    let program_path = Path::new(&entry.program);
+                    if !program_path.exists() {
+                        return Err(anyhow!(
+                            "Program in genesis configuration does not exist at path: {}",
+                            program_path.display()
+                        ));
+                    }
+                    flags.bpf_programs.push((
+                        parse_address(&entry.address)?,
+                        program_path.to_path_buf(),
+                    ));
+                }
+            }
+            if let Some(validator) = &test.validator {
+                let entries = serde_json::to_value(validator)?;
+                for (key, value) in entries.as_object().unwrap() {
+                    if key == "ledger" {
+                        // Ledger flag is a special case as it is passed separately to the rest of
+                        // these validator flags.
+                        continue;
+                    };
+                    if key == "account" {
+                        for entry in value.as_array().unwrap() {
+                            flags.accounts.push((
+                                parse_address(entry["address"].as_str().unwrap())?,
+                                PathBuf::from(entry["filename"].as_str().unwrap()),
+                            ));
+                        }
+                    } else if key == "clone" {
+                        // Client for fetching accounts data
+                        let client = if let Some(url) = entries["url"].as_str() {
+                            RpcClient::new(url.to_string())
+                        } else {
+                            return Err(anyhow!(
+                                "Validator url for Solana's JSON RPC should be provided in order to clone accounts from it"
+                            ));
+                        };
+
+                        let pubkeys = value
+                            .as_array()
+                            .unwrap()
+                            .iter()
+                            .map(|entry| parse_address(entry["address"].as_str().unwrap()))
+                            .collect::<Result<HashSet<Pubkey>>>()?;
+                        flags.clones.extend(clone_addresses(&client, pubkeys)?);
+                    } else {
+                        // Remaining validator flags are non-array types
+                        let value = if let serde_json::Value::String(v) = value {
+                            v.to_string()
+                        } else {
+                            value.to_string()
+                        };
+                        flags.other.insert(key.to_string(), value);
+                    }
+                }
+            }
+        }
+        Ok(flags)
+    }
+
+    // Write an IDL account to `target/idl-account` and add it as an `--account` flag.
+    // Existing files are left alone when the ledger is going to be reused.
+    fn push_idl_account(&mut self, account: &LocalnetAccount, ledger_policy: LedgerPolicy) -> Result<()> {
+        let path = PathBuf::from("target/idl-account").join(&account.name);
+        if ledger_policy != LedgerPolicy::Reuse || !path.exists() {
+            account.write_to_validator_json_file("target/idl-account")?;
+        }
+        self.accounts.push((account.address, path));
+        Ok(())
+    }
+
+    /// Parses flags in the format output by [ValidatorFlags::to_args].
+    /// Every flag other than `--bpf-program`, `--account` and `--clone` must take exactly one value.
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        let mut flags = Self::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let mut value = || args.next()
+                .ok_or_else(|| anyhow!("Missing value for {}", flag));
+            match flag.as_str() {
+                "--bpf-program" => {
+                    let address = parse_address(value()?)?;
+                    flags.bpf_programs.push((address, PathBuf::from(value()?)));
+                }
+                "--account" => {
+                    let address = parse_address(value()?)?;
+                    flags.accounts.push((address, PathBuf::from(value()?)));
+                }
+                "--clone" => flags.clones.push(parse_address(value()?)?),
+                _ => {
+                    let key = flag.strip_prefix("--")
+                        .ok_or_else(|| anyhow!("Expected a flag, found {}", flag))?
+                        .replace('-', "_");
+                    flags.other.insert(key, value()?.to_string());
+                }
+            }
+        }
+        Ok(flags)
+    }
+
+    /// The flags as passed to `solana-test-validator`. Programs and accounts come first,
+    /// followed by the remaining settings in alphabetical order.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        for (address, path) in &self.bpf_programs {
+            args.push("--bpf-program".to_string());
+            args.push(address.to_string());
+            args.push(path.display().to_string());
+        }
+        for (address, path) in &self.accounts {
+            args.push("--account".to_string());
+            args.push(address.to_string());
+            args.push(path.display().to_string());
+        }
+        let mut clones_pushed = false;
+        let push_clones = |args: &mut Vec<String>| {
+            for address in &self.clones {
+                args.push("--clone".to_string());
+                args.push(address.to_string());
+            }
+        };
+        for (key, value) in &self.other {
+            if !clones_pushed && key.as_str() > "clone" {
+                push_clones(&mut args);
+                clones_pushed = true;
+            }
+            args.push(format!("--{}", key.replace('_', "-")));
+            args.push(value.to_string());
+        }
+        if !clones_pushed {
+            push_clones(&mut args);
+        }
+        args
+    }
+}
+
+impl From<ValidatorFlags> for Vec<String> {
+    fn from(flags: ValidatorFlags) -> Self {
+        flags.to_args()
+    }
+}
+
+fn parse_address(address: &str) -> Result<Pubkey> {
+    Pubkey::from_str(address).map_err(|_| anyhow!("Invalid pubkey {}", address))
+}
+
+/// Checks that every account exists, and adds the programdata address of any
+/// upgradeable programs so that they are cloned as well.
+fn clone_addresses(client: &RpcClient, pubkeys: HashSet<Pubkey>) -> Result<Vec<Pubkey>> {
+    let (clones, warnings) = check_cloned_accounts(client, pubkeys)?;
+    for warning in warnings {
+        log::warn!("{}", warning);
+    }
+    Ok(clones)
+}
+
+// Fetches every cloned account up front, so that all of the missing or invalid
+// addresses are reported in one error. Returns the addresses to clone, including
+// the programdata of upgradeable programs, along with any warnings.
+fn check_cloned_accounts(
+    client: &RpcClient,
+    pubkeys: HashSet<Pubkey>,
+) -> Result<(Vec<Pubkey>, Vec<String>)> {
+    let url = client.url();
+    let mut accounts_keys = pubkeys.into_iter().collect::<Vec<_>>();
+    accounts_keys.sort();
+    let accounts = client
+        .get_multiple_accounts_with_commitment(
+            &accounts_keys,
+            CommitmentConfig::default(),
+        )
+        .map_err(|e| anyhow!("Failed to fetch cloned accounts from {}: {}", url, e))?
+        .value;
+
+    // Check if there are program accounts
+    let mut failures = vec![];
+    let mut programdata_addresses = vec![];
+    for (account, acc_key) in accounts.iter().zip(&accounts_keys) {
+        match account {
+            None => failures.push(format!("{}: account not found", acc_key)),
+            Some(account) if account.owner == bpf_loader_upgradeable::id() => {
+                match account.deserialize_data::<UpgradeableLoaderState>() {
+                    Ok(UpgradeableLoaderState::Program { programdata_address }) => {
+                        programdata_addresses.push((*acc_key, programdata_address));
+                    }
+                    Ok(_) => {}
+                    Err(e) => failures.push(format!(
+                        "{}: invalid upgradeable loader account ({})", acc_key, e
+                    )),
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "{} of {} cloned accounts could not be cloned from {}:\n  {}",
+            failures.len(),
+            accounts_keys.len(),
+            url,
+            failures.join("\n  "),
+        ));
+    }
+
+    // A program whose programdata is gone has usually been closed. The validator
+    // would fail to clone it, so it is skipped rather than treated as an error.
+    let mut warnings = vec![];
+    for (program, programdata_address) in programdata_addresses {
+        let programdata = client
+            .get_account_with_commitment(&programdata_address, CommitmentConfig::default())
+            .map_err(|e| anyhow!(
+                "Failed to fetch programdata {} from {}: {}", programdata_address, url, e
+            ))?
+            .value;
+        if programdata.is_some() {
+            accounts_keys.push(programdata_address);
+        } else {
+            warnings.push(format!(
+                "Programdata {} of cloned program {} was not found on {}. The program may have been closed.",
+                programdata_address, program, url,
+            ));
+        }
+    }
+    Ok((accounts_keys, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use anchor_client::solana_client::mock_sender::Mocks;
+    use anchor_client::solana_client::rpc_request::RpcRequest;
+    use solana_account_decoder::{UiAccount, UiAccountEncoding};
+    use solana_sdk::account::Account;
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn to_args_reproduces_flag_list() {
+        let program = Pubkey::new_unique().to_string();
+        let account = Pubkey::new_unique().to_string();
+        let clone = Pubkey::new_unique().to_string();
+        // The order the flags were emitted in when they were assembled as strings.
+        let expected = args(&[
+            "--bpf-program", &program, "target/deploy/test_program.so",
+            "--account", &account, "tests/suite-1/mint.json",
+            "--bind-address", "0.0.0.0",
+            "--clone", &clone,
+            "--faucet-port", "9900",
+            "--rpc-port", "8899",
+            "--url", "https://api.mainnet-beta.solana.com",
+        ]);
+        let flags = ValidatorFlags::from_args(&expected).unwrap();
+        assert_eq!(flags.bpf_programs, vec![(
+            Pubkey::from_str(&program).unwrap(),
+            PathBuf::from("target/deploy/test_program.so"),
+        )]);
+        assert_eq!(flags.clones, vec![Pubkey::from_str(&clone).unwrap()]);
+        assert_eq!(flags.other["rpc_port"], "8899");
+        assert_eq!(flags.to_args(), expected);
+        let as_vec: Vec<String> = flags.into();
+        assert_eq!(as_vec, expected);
+    }
+
+    #[test]
+    fn clones_without_settings_come_last() {
+        let clone = Pubkey::new_unique();
+        let flags = ValidatorFlags {
+            clones: vec![clone],
+            ..Default::default()
+        };
+        assert_eq!(flags.to_args(), args(&["--clone", &clone.to_string()]));
+    }
+
+    #[test]
+    fn adjusting_flags() {
+        let keep = Pubkey::new_unique().to_string();
+        let drop = Pubkey::new_unique().to_string();
+        let mut flags = ValidatorFlags::from_args(&args(&[
+            "--clone", &keep,
+            "--clone", &drop,
+            "--slots-per-epoch", "32",
+        ])).unwrap();
+        flags.clones.retain(|c| c.to_string() != drop);
+        flags.other.insert("slots_per_epoch".to_string(), "64".to_string());
+        assert_eq!(flags.to_args(), args(&["--clone", &keep, "--slots-per-epoch", "64"]));
+    }
+
+    #[test]
+    fn missing_values_error() {
+        assert!(ValidatorFlags::from_args(&args(&["--account", "foo"])).is_err());
+        assert!(ValidatorFlags::from_args(&args(&["--rpc-port"])).is_err());
+        assert!(ValidatorFlags::from_args(&args(&["8899"])).is_err());
+    }
+
+    fn mock_client(accounts: &[(Pubkey, Option<Account>)]) -> RpcClient {
+        let value: Vec<serde_json::Value> = accounts.iter().map(|(pubkey, account)| {
+            match account {
+                Some(account) => serde_json::to_value(UiAccount::encode(
+                    pubkey, account, UiAccountEncoding::Base64, None, None,
+                )).unwrap(),
+                None => serde_json::Value::Null,
+            }
+        }).collect();
+        let mut mocks: Mocks = HashMap::new();
+        mocks.insert(RpcRequest::GetMultipleAccounts, serde_json::json!({
+            "context": { "slot": 1 },
+            "value": value,
+        }));
+        RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+    }
+
+    // The mocked response is positional, so the keys are sorted like the request.
+    fn sorted_keys(n: usize) -> Vec<Pubkey> {
+        let mut keys: Vec<Pubkey> = (0..n).map(|_| Pubkey::new_unique()).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn all_missing_clones_are_reported() {
+        let keys = sorted_keys(3);
+        let client = mock_client(&[
+            (keys[0], None),
+            (keys[1], Some(Account::new(1, 0, &Pubkey::default()))),
+            (keys[2], None),
+        ]);
+        let err = check_cloned_accounts(&client, keys.iter().cloned().collect())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(&format!("2 of 3 cloned accounts could not be cloned from {}", client.url())), "{}", err);
+        assert!(err.contains(&format!("{}: account not found", keys[0])));
+        assert!(err.contains(&format!("{}: account not found", keys[2])));
+        assert!(!err.contains(&keys[1].to_string()));
+    }
+
+    #[test]
+    fn invalid_program_accounts_are_reported() {
+        let keys = sorted_keys(2);
+        let client = mock_client(&[
+            (keys[0], Some(Account {
+                lamports: 1,
+                data: vec![9, 9],
+                owner: bpf_loader_upgradeable::id(),
+                executable: true,
+                rent_epoch: 0,
+            })),
+            (keys[1], None),
+        ]);
+        let err = check_cloned_accounts(&client, keys.iter().cloned().collect())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(&format!("{}: invalid upgradeable loader account", keys[0])));
+        assert!(err.contains(&format!("{}: account not found", keys[1])));
+    }
+
+    #[test]
+    fn missing_programdata_is_a_warning() {
+        let keys = sorted_keys(2);
+        let programdata_address = Pubkey::new_unique();
+        let program = Account::new_data(
+            1,
+            &UpgradeableLoaderState::Program { programdata_address },
+            &bpf_loader_upgradeable::id(),
+        ).unwrap();
+        let client = mock_client(&[
+            (keys[0], Some(program)),
+            (keys[1], Some(Account::new(1, 0, &Pubkey::default()))),
+        ]);
+        // The mock client reports every `getAccountInfo` as not found.
+        let (clones, warnings) = check_cloned_accounts(&client, keys.iter().cloned().collect())
+            .unwrap();
+        assert_eq!(clones, keys);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains(&programdata_address.to_string()));
+        assert!(warnings[0].contains(&keys[0].to_string()));
+    }
+}
@@ -0,0 +1,171 @@
+/// Checks that the installed `solana-test-validator` understands the flags being passed,
+/// since an old binary otherwise fails with a clap error buried in the ledger log.
+use std::fmt;
+use std::io::ErrorKind;
+use std::process::Command;
+use thiserror::Error;
+
+/// A `major.minor.patch` validator version. Pre-release and build suffixes are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ValidatorVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl ValidatorVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// Parses the output of `solana-test-validator --version`, e.g.
+    /// `solana-test-validator 1.14.11 (src:devbuild; feat:1879391783)`.
+    pub fn parse(version_output: &str) -> Option<Self> {
+        version_output
+            .split_whitespace()
+            .find_map(|word| Self::parse_semver(word.trim_start_matches('v')))
+    }
+
+    fn parse_semver(version: &str) -> Option<Self> {
+        let core = version.split(|c| c == '-' || c == '+').next()?;
+        let mut parts = core.split('.');
+        let version = Self::new(
+            parts.next()?.parse().ok()?,
+            parts.next()?.parse().ok()?,
+            parts.next()?.parse().ok()?,
+        );
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(version)
+    }
+}
+
+impl fmt::Display for ValidatorVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Flags that older `solana-test-validator` binaries reject, with the first version accepting them.
+pub const FLAG_MINIMUM_VERSIONS: &[(&str, ValidatorVersion)] = &[
+    ("--deactivate-feature", ValidatorVersion::new(1, 11, 0)),
+    ("--account-dir", ValidatorVersion::new(1, 14, 0)),
+    ("--upgradeable-program", ValidatorVersion::new(1, 14, 0)),
+    ("--clone-upgradeable-program", ValidatorVersion::new(1, 16, 0)),
+];
+
+#[derive(Debug, Error)]
+pub enum ValidatorStartError {
+    #[error("solana-test-validator was not found on the PATH. Install the Solana CLI tools, or add them to the PATH.")]
+    BinaryNotFound,
+    #[error("could not determine the solana-test-validator version from {0:?}")]
+    UnknownVersion(String),
+    #[error(
+        "solana-test-validator {found} is too old, {required} or newer is required for {}",
+        offending_flags.join(", ")
+    )]
+    IncompatibleBinary {
+        found: ValidatorVersion,
+        required: ValidatorVersion,
+        offending_flags: Vec<String>,
+    },
+    #[error("failed to run solana-test-validator --version: {0}")]
+    Io(std::io::Error),
+}
+
+/// The minimum version needed for `flags`, along with the flags that require it.
+/// Returns `None` if none of the flags have a known minimum version.
+pub fn required_version(flags: &[String]) -> Option<(ValidatorVersion, Vec<String>)> {
+    let required = flags
+        .iter()
+        .filter_map(|flag| minimum_version(flag))
+        .max()?;
+    let mut offending_flags: Vec<String> = flags
+        .iter()
+        .filter(|flag| minimum_version(flag) == Some(required))
+        .cloned()
+        .collect();
+    offending_flags.dedup();
+    Some((required, offending_flags))
+}
+
+fn minimum_version(flag: &str) -> Option<ValidatorVersion> {
+    FLAG_MINIMUM_VERSIONS
+        .iter()
+        .find(|(f, _)| *f == flag)
+        .map(|(_, version)| *version)
+}
+
+/// Run `solana-test-validator --version`.
+pub fn installed_version() -> Result<ValidatorVersion, ValidatorStartError> {
+    let output = Command::new("solana-test-validator")
+        .arg("--version")
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => ValidatorStartError::BinaryNotFound,
+            _ => ValidatorStartError::Io(e),
+        })?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    ValidatorVersion::parse(&stdout).ok_or(ValidatorStartError::UnknownVersion(stdout))
+}
+
+/// Checks that the binary exists, and is new enough for the given flags.
+pub fn check_validator_version(flags: &[String]) -> Result<(), ValidatorStartError> {
+    let found = installed_version()?;
+    check_version(found, flags)
+}
+
+fn check_version(found: ValidatorVersion, flags: &[String]) -> Result<(), ValidatorStartError> {
+    match required_version(flags) {
+        Some((required, offending_flags)) if found < required => {
+            Err(ValidatorStartError::IncompatibleBinary { found, required, offending_flags })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_version_output() {
+        let cases = [
+            ("solana-test-validator 1.14.11 (src:devbuild; feat:1879391783)", (1, 14, 11)),
+            ("solana-test-validator 1.16.27 (src:d1b2b0d5; feat:4033350765, client:SolanaLabs)", (1, 16, 27)),
+            ("solana-test-validator 1.18.0 (src:00000000; feat:4215500110, client:SolanaLabs)", (1, 18, 0)),
+            ("solana-test-validator 2.0.14 (src:a6ba57e4; feat:607245837, client:Agave)\n", (2, 0, 14)),
+            ("solana-test-validator 1.17.0-rc.1 (src:devbuild; feat:3746964731)", (1, 17, 0)),
+        ];
+        for (output, (major, minor, patch)) in cases {
+            assert_eq!(
+                ValidatorVersion::parse(output),
+                Some(ValidatorVersion::new(major, minor, patch)),
+                "{}", output,
+            );
+        }
+        assert_eq!(ValidatorVersion::parse("solana-test-validator (src:devbuild)"), None);
+        assert_eq!(ValidatorVersion::parse(""), None);
+    }
+
+    #[test]
+    fn offending_flags_are_reported() {
+        let flags: Vec<String> = [
+            "--clone-upgradeable-program", "addr1",
+            "--deactivate-feature", "addr2",
+            "--rpc-port", "8899",
+        ].iter().map(|s| s.to_string()).collect();
+        let err = check_version(ValidatorVersion::new(1, 14, 11), &flags).unwrap_err();
+        match err {
+            ValidatorStartError::IncompatibleBinary { found, required, offending_flags } => {
+                assert_eq!(found, ValidatorVersion::new(1, 14, 11));
+                assert_eq!(required, ValidatorVersion::new(1, 16, 0));
+                assert_eq!(offending_flags, vec!["--clone-upgradeable-program".to_string()]);
+            }
+            err => panic!("unexpected error {}", err),
+        }
+        assert!(check_version(ValidatorVersion::new(1, 16, 27), &flags).is_ok());
+        assert!(check_version(ValidatorVersion::new(1, 8, 0), &flags[4..]).is_ok());
+    }
+}
@@ -0,0 +1,255 @@
+/// Monitors a running test validator, and restarts it or notifies the caller if it
+/// exits unexpectedly during a long localnet session.
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use anchor_cli::config::{Config, TestValidator, WithPath};
+use anyhow::Result;
+use crate::program_logs::create_run_dir;
+use crate::test_validator::{start_test_validator, stream_logs, TestValidatorHandle, TestValidatorOptions};
+
+/// How often the watchdog checks whether the validator is still running.
+pub const WATCHDOG_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What the watchdog does when the validator exits unexpectedly.
+pub enum OnCrash {
+    /// Call this with the exit status, then stop watching.
+    Callback(Box<dyn FnMut(ExitStatus) + Send>),
+    /// Restart the validator with the same flags and options, at most this many times.
+    Restart { max_restarts: usize },
+}
+
+/// A test validator that is watched by a background thread.
+/// The validator is killed when this is dropped.
+pub struct Watchdog {
+    handle: Arc<Mutex<TestValidatorHandle>>,
+    crashes: Arc<AtomicUsize>,
+    restarts: Arc<AtomicUsize>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Start a test validator like [start_test_validator], streaming program logs, and watch it.
+/// Restarts re-run the readiness probe and re-attach the log streams.
+pub fn start_watched_test_validator(
+    cfg: WithPath<Config>,
+    test_validator: Option<TestValidator>,
+    flags: Vec<String>,
+    test_log_stdout: bool,
+    options: TestValidatorOptions,
+    on_crash: OnCrash,
+) -> Result<Watchdog> {
+    let program_logs_dir = create_run_dir(
+        "localnet",
+        options.program_logs_dir.as_deref(),
+        options.program_logs_retention,
+    )?;
+    let log_streams: Arc<Mutex<Vec<Child>>> = Arc::new(Mutex::new(vec![]));
+    let mut start = move || -> Result<TestValidatorHandle> {
+        let handle = start_test_validator(
+            &cfg,
+            &test_validator,
+            Some(flags.clone()),
+            test_log_stdout,
+            &options,
+        )?;
+        let mut streams = lock(&log_streams);
+        for mut child in streams.drain(..) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        streams.extend(stream_logs(&cfg, &handle.rpc_url, &program_logs_dir)?);
+        Ok(handle)
+    };
+    let handle = start()?;
+    Ok(Watchdog::spawn(handle, start, on_crash, WATCHDOG_POLL_INTERVAL))
+}
+
+// A panicking callback shouldn't prevent the validator from being shut down.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+impl Watchdog {
+    /// Watch an already started validator. `restart` is used to start a replacement
+    /// when `on_crash` is [OnCrash::Restart].
+    pub fn spawn<F>(
+        handle: TestValidatorHandle,
+        mut restart: F,
+        mut on_crash: OnCrash,
+        poll_interval: Duration,
+    ) -> Self
+    where
+        F: FnMut() -> Result<TestValidatorHandle> + Send + 'static,
+    {
+        let handle = Arc::new(Mutex::new(handle));
+        let crashes = Arc::new(AtomicUsize::new(0));
+        let restarts = Arc::new(AtomicUsize::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let handle = Arc::clone(&handle);
+            let crashes = Arc::clone(&crashes);
+            let restarts = Arc::clone(&restarts);
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    std::thread::sleep(poll_interval);
+                    let mut handle = lock(&handle);
+                    if stop.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let status = match handle.child.try_wait() {
+                        Ok(Some(status)) => status,
+                        Ok(None) => continue,
+                        Err(err) => {
+                            eprintln!("Watchdog failed to check the test validator: {}", err);
+                            return;
+                        }
+                    };
+                    crashes.fetch_add(1, Ordering::SeqCst);
+                    match &mut on_crash {
+                        OnCrash::Callback(callback) => {
+                            callback(status);
+                            return;
+                        }
+                        OnCrash::Restart { max_restarts } => {
+                            let restarted = restarts.load(Ordering::SeqCst);
+                            if restarted >= *max_restarts {
+                                eprintln!(
+                                    "Test validator exited with {}, and has already been restarted {} times. Giving up.",
+                                    status, restarted
+                                );
+                                return;
+                            }
+                            eprintln!(
+                                "Test validator exited with {}. Restarting ({}/{}).",
+                                status, restarted + 1, max_restarts
+                            );
+                            match restart() {
+                                Ok(new_handle) => {
+                                    *handle = new_handle;
+                                    restarts.fetch_add(1, Ordering::SeqCst);
+                                }
+                                Err(err) => {
+                                    eprintln!("Failed to restart the test validator: {}", err);
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                }
+            })
+        };
+        Self {
+            handle,
+            crashes,
+            restarts,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// How many times the validator has exited unexpectedly.
+    pub fn crash_count(&self) -> usize {
+        self.crashes.load(Ordering::SeqCst)
+    }
+
+    /// How many times the validator has been restarted.
+    pub fn restart_count(&self) -> usize {
+        self.restarts.load(Ordering::SeqCst)
+    }
+
+    /// The RPC URL of the current validator, which may change on restart if
+    /// ports are picked automatically.
+    pub fn rpc_url(&self) -> String {
+        lock(&self.handle).rpc_url.clone()
+    }
+
+    /// Access the current validator handle. The watchdog waits while this is held.
+    pub fn handle(&self) -> MutexGuard<'_, TestValidatorHandle> {
+        lock(&self.handle)
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::time::Instant;
+    use super::*;
+
+    // Stands in for the validator, so the watchdog can be tested without one.
+    fn sleeping_handle() -> TestValidatorHandle {
+        TestValidatorHandle {
+            child: Command::new("sleep").arg("60").spawn().unwrap(),
+            rpc_url: "http://localhost:8899".to_string(),
+            rpc_port: 8899,
+            faucet_port: 9900,
+            stdout_log: None,
+            stderr_log: None,
+        }
+    }
+
+    fn kill_externally(pid: u32) {
+        Command::new("kill").arg(pid.to_string()).status().unwrap();
+    }
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        let started = Instant::now();
+        while !condition() {
+            assert!(started.elapsed() < Duration::from_secs(10), "timed out");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn crashed_validator_is_restarted() {
+        let watchdog = Watchdog::spawn(
+            sleeping_handle(),
+            || Ok(sleeping_handle()),
+            OnCrash::Restart { max_restarts: 1 },
+            Duration::from_millis(10),
+        );
+        let first = watchdog.handle().child.id();
+        kill_externally(first);
+        wait_until(|| watchdog.restart_count() == 1);
+        assert_eq!(watchdog.crash_count(), 1);
+        let second = watchdog.handle().child.id();
+        assert_ne!(first, second);
+
+        // No more restarts are allowed.
+        kill_externally(second);
+        wait_until(|| watchdog.crash_count() == 2);
+        assert_eq!(watchdog.restart_count(), 1);
+    }
+
+    #[test]
+    fn callback_is_invoked_on_crash() {
+        let crashed = Arc::new(AtomicBool::new(false));
+        let watchdog = {
+            let crashed = Arc::clone(&crashed);
+            Watchdog::spawn(
+                sleeping_handle(),
+                || panic!("should not restart"),
+                OnCrash::Callback(Box::new(move |_| crashed.store(true, Ordering::SeqCst))),
+                Duration::from_millis(10),
+            )
+        };
+        kill_externally(watchdog.handle().child.id());
+        wait_until(|| crashed.load(Ordering::SeqCst));
+        assert_eq!(watchdog.restart_count(), 0);
+    }
+}
@@ -0,0 +1,120 @@
+#![cfg(feature = "harness")]
+//! Proves out [TestContext] against real validators. The tests run in parallel,
+//! each with a validator of its own.
+use anchor_client::solana_client::rpc_client::RpcClient;
+use jungle_fi_localnet_tools::{
+    check_fixture_drift, spl_mint_account, AccountDiff, DriftPolicy, DriftStatus, LocalnetAccount, ProcessMode,
+    TestContext,
+};
+use serde_json::{Map, Value};
+use solana_client_tx_processor::{ProcessedTransaction, TransactionProcessor, TransactionProcessorError};
+use solana_program::program_pack::Pack;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Signature, Signer};
+
+struct Memo {
+    message: String,
+}
+
+impl TransactionProcessor for Memo {
+    type OnlineArgs = ();
+    type RemainingArgs = ();
+
+    fn get_online_args(&self, _: &RpcClient) -> Result<Self::OnlineArgs, TransactionProcessorError> {
+        Ok(())
+    }
+
+    fn metadata(&self, _: &Pubkey, _: &Self::OnlineArgs, _: &Self::RemainingArgs) -> Map<String, Value> {
+        Map::new()
+    }
+
+    fn name(&self, _: &Pubkey, _: &Self::OnlineArgs, _: &Self::RemainingArgs) -> String {
+        format!("memo: {}", self.message)
+    }
+
+    fn calc_remaining_args(&self, _: &Self::OnlineArgs, _: &Pubkey) -> Result<Self::RemainingArgs, TransactionProcessorError> {
+        Ok(())
+    }
+
+    fn named_instructions(&self, primary_signer: &Pubkey, _: Self::OnlineArgs, _: Self::RemainingArgs) -> Result<Vec<(String, Instruction)>, TransactionProcessorError> {
+        Ok(vec![("memo".to_string(), spl_memo::build_memo(self.message.as_bytes(), &[primary_signer]))])
+    }
+}
+
+#[test]
+#[ignore = "requires solana-test-validator on the PATH"]
+fn memo_execute() {
+    let ctx = TestContext::builder().build().unwrap();
+    assert!(ctx.rpc_client().get_balance(&ctx.payer().pubkey()).unwrap() > 0);
+
+    let memo = Memo { message: "harness".to_string() };
+    let processed = ctx.process(&memo, ProcessMode::Execute).unwrap();
+    let signature = match processed {
+        ProcessedTransaction::Execution { signature, .. } => signature.parse::<Signature>().unwrap(),
+        _ => panic!("wrong processing"),
+    };
+    assert!(ctx.rpc_client().confirm_transaction(&signature).unwrap());
+}
+
+#[test]
+#[ignore = "requires solana-test-validator on the PATH"]
+fn spl_mint_fixture_readback() {
+    let authority = Pubkey::new_unique();
+    let mint = LocalnetAccount::new(
+        Pubkey::new_unique(),
+        "mint.json".to_string(),
+        spl_mint_account(&authority, 1_000, 6),
+    ).set_owner(spl_token::ID);
+    let ctx = TestContext::builder().accounts(vec![mint.clone()]).build().unwrap();
+
+    let account = ctx.rpc_client().get_account(&mint.address).unwrap();
+    assert_eq!(account.owner, spl_token::ID);
+    let state = spl_token::state::Mint::unpack(&account.data).unwrap();
+    assert_eq!(state.supply, 1_000);
+    assert_eq!(state.decimals, 6);
+    assert_eq!(state.mint_authority, Some(authority).into());
+
+    let dir = ctx.dir().to_path_buf();
+    assert!(dir.join("accounts/mint.json").is_file());
+    drop(ctx);
+    assert!(!dir.exists());
+}
+
+#[test]
+#[ignore = "requires solana-test-validator on the PATH"]
+fn fixture_drift() {
+    let fixture = |name: &str| LocalnetAccount {
+        address: Pubkey::new_unique(),
+        lamports: 1_000_000,
+        account_data: vec![7; 64],
+        owner: Pubkey::new_unique(),
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let kept = fixture("kept.json");
+    let modified = fixture("modified.json");
+    let ctx = TestContext::builder().accounts(vec![kept.clone(), modified.clone()]).build().unwrap();
+
+    // Change one fixture after the validator loaded it, and add one it never had.
+    let dir = ctx.dir().join("accounts");
+    let mut changed = modified.clone();
+    changed.account_data[10] = 8;
+    changed.write_to_validator_json_file(dir.to_str().unwrap()).unwrap();
+    let unknown = fixture("unknown.json");
+    unknown.write_to_validator_json_file(dir.to_str().unwrap()).unwrap();
+
+    let report = check_fixture_drift(&ctx.rpc_client(), &dir, DriftPolicy::default()).unwrap();
+    let status = |address: Pubkey| {
+        report.fixtures.iter().find(|f| f.address == address).unwrap().status.clone()
+    };
+    assert_eq!(status(kept.address), DriftStatus::Unchanged);
+    assert_eq!(status(modified.address), DriftStatus::Drifted(AccountDiff {
+        data: vec![10..11],
+        ..Default::default()
+    }));
+    assert_eq!(status(unknown.address), DriftStatus::Missing);
+
+    let policy = DriftPolicy { fail_on_drift: true, ..Default::default() };
+    assert!(check_fixture_drift(&ctx.rpc_client(), &dir, policy).is_err());
+}
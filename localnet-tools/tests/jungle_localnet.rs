@@ -0,0 +1,216 @@
+#![cfg(feature = "bin")]
+//! Runs the `jungle-localnet` binary against fixture workspaces in temporary directories.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use anchor_client::solana_client::rpc_client::RpcClient;
+use jungle_fi_localnet_tools::{spl_mint_account, LocalnetAccount};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{write_keypair_file, Keypair};
+use tempfile::TempDir;
+
+// An Anchor workspace without programs, and a wallet.
+fn workspace() -> TempDir {
+    let temp = TempDir::new().unwrap();
+    let dir = temp.path();
+    fs::create_dir_all(dir.join("programs")).unwrap();
+    fs::create_dir_all(dir.join("target")).unwrap();
+    write_keypair_file(&Keypair::new(), dir.join("wallet.json")).unwrap();
+    fs::write(dir.join("Anchor.toml"), "\
+[provider]
+cluster = \"localnet\"
+wallet = \"wallet.json\"
+
+[scripts]
+test = \"true\"
+").unwrap();
+    temp
+}
+
+fn jungle_localnet(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_jungle-localnet"))
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+// A suite with a `test` script, and no accounts or programs.
+fn write_suite(dir: &Path, script: &str) -> PathBuf {
+    let suite = dir.join("tests/suite");
+    fs::create_dir_all(&suite).unwrap();
+    fs::write(suite.join("Test.toml"), format!("\
+[scripts]
+test = \"{}\"
+
+[test]
+startup_wait = 30000
+
+[test.validator]
+ledger = \".anchor/suite-ledger\"
+", script)).unwrap();
+    suite.join("Test.toml")
+}
+
+#[test]
+fn generate_from_manifest() {
+    let temp = workspace();
+    let dir = temp.path();
+    let mint = LocalnetAccount::new(
+        Pubkey::new_unique(),
+        "mint.json".to_string(),
+        spl_mint_account(&Pubkey::new_unique(), 1_000, 6),
+    ).set_owner(spl_token::ID);
+    fs::create_dir_all(dir.join("fixtures")).unwrap();
+    mint.write_to_validator_json_file(dir.join("fixtures").to_str().unwrap()).unwrap();
+    fs::write(dir.join("localnet.toml"), "\
+[[suite]]
+save_directory = \"tests/suite-1\"
+test_file_glob = \"tests/suite-1/*.ts\"
+
+[[suite.accounts]]
+name = \"usdc_mint.json\"
+file = \"fixtures/mint.json\"
+").unwrap();
+
+    let output = jungle_localnet(dir, &["generate", "--manifest", "localnet.toml"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let suite = dir.join("tests/suite-1");
+    let test_toml = fs::read_to_string(suite.join("Test.toml")).unwrap();
+    assert!(test_toml.contains(&mint.address.to_string()), "{}", test_toml);
+    assert!(test_toml.contains("usdc_mint.json"), "{}", test_toml);
+    let copied = LocalnetAccount::read_from_file(suite.join("usdc_mint.json")).unwrap();
+    assert_eq!(copied.to_account(), mint.to_account());
+    assert!(suite.join("accounts.ts").is_file());
+}
+
+#[test]
+fn generate_with_generator() {
+    let temp = workspace();
+    let dir = temp.path();
+    let generator = dir.join("generator.sh");
+    fs::write(&generator, "#!/bin/sh\necho \"$@\" > generated.txt\n").unwrap();
+    let chmod = Command::new("chmod").arg("+x").arg(&generator).status().unwrap();
+    assert!(chmod.success());
+
+    let output = jungle_localnet(dir, &["generate", "--generator", "./generator.sh", "--", "build"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fs::read_to_string(dir.join("generated.txt")).unwrap(), "build\n");
+
+    fs::write(&generator, "#!/bin/sh\nexit 2\n").unwrap();
+    let output = jungle_localnet(dir, &["generate", "--generator", "./generator.sh"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("./generator.sh failed"), "{}", stderr(&output));
+}
+
+#[test]
+#[ignore = "requires solana-test-validator on the PATH"]
+fn start_detached() {
+    let temp = workspace();
+    let dir = temp.path();
+    write_suite(dir, "true");
+    let output = jungle_localnet(dir, &[
+        "start", "--suite", "tests/suite/Test.toml", "--detach", "--auto-pick-ports",
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    // "Validator started at <url> with process id <pid>"
+    let printed = stdout(&output);
+    let line = printed.lines().find(|line| line.starts_with("Validator started at")).unwrap();
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (rpc_url, pid) = (words[3], words[7]);
+    let health = RpcClient::new(rpc_url.to_string()).get_health();
+    Command::new("kill").arg(pid).status().unwrap();
+    health.unwrap();
+}
+
+#[test]
+#[ignore = "requires solana-test-validator on the PATH"]
+fn run_propagates_exit_code() {
+    let temp = workspace();
+    let dir = temp.path();
+    write_suite(dir, "exit 3");
+    let output = jungle_localnet(dir, &[
+        "run", "--suite", "tests/suite/Test.toml", "--auto-pick-ports",
+    ]);
+    assert_eq!(output.status.code(), Some(3), "{}", stderr(&output));
+}
+
+#[test]
+#[ignore = "requires solana-test-validator on the PATH"]
+fn check_drift_fails_on_missing_accounts() {
+    let temp = workspace();
+    let dir = temp.path();
+    write_suite(dir, "true");
+    let output = jungle_localnet(dir, &[
+        "start", "--suite", "tests/suite/Test.toml", "--detach", "--auto-pick-ports",
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let printed = stdout(&output);
+    let line = printed.lines().find(|line| line.starts_with("Validator started at")).unwrap();
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (rpc_url, pid) = (words[3], words[7]);
+
+    let fixture = LocalnetAccount {
+        address: Pubkey::new_unique(),
+        name: "fixture.json".to_string(),
+        ..Default::default()
+    };
+    fs::create_dir_all(dir.join("fixtures")).unwrap();
+    fixture.write_to_validator_json_file(dir.join("fixtures").to_str().unwrap()).unwrap();
+    let warn = jungle_localnet(dir, &["check-drift", "fixtures", "-u", rpc_url]);
+    let fail = jungle_localnet(dir, &["check-drift", "fixtures", "-u", rpc_url, "--fail-on-drift"]);
+    Command::new("kill").arg(pid).status().unwrap();
+
+    assert!(warn.status.success(), "{}", stderr(&warn));
+    assert!(stdout(&warn).contains("MISSING"), "{}", stdout(&warn));
+    assert_eq!(fail.status.code(), Some(1), "{}", stderr(&fail));
+    assert!(stdout(&fail).contains("0 unchanged, 0 drifted, 1 missing"), "{}", stdout(&fail));
+}
+
+#[test]
+fn clean_requires_confirmation() {
+    let temp = workspace();
+    let dir = temp.path();
+    let suite = dir.join("tests/suite");
+    fs::create_dir_all(&suite).unwrap();
+    fs::write(suite.join("Test.toml"), "\
+[test.validator]
+ledger = \".anchor/suite-ledger\"
+
+[[test.validator.account]]
+address = \"11111111111111111111111111111111\"
+filename = \"system.json\"
+").unwrap();
+    fs::write(suite.join("system.json"), "{}").unwrap();
+    fs::write(suite.join("accounts.ts"), "").unwrap();
+    fs::create_dir_all(dir.join(".anchor/suite-ledger")).unwrap();
+    fs::create_dir_all(dir.join(".anchor/test-ledger")).unwrap();
+    fs::create_dir_all(dir.join("target/idl-account")).unwrap();
+    let removed = [
+        dir.join(".anchor/suite-ledger"),
+        dir.join(".anchor/test-ledger"),
+        dir.join("target/idl-account"),
+        suite.join("system.json"),
+        suite.join("accounts.ts"),
+    ];
+
+    // Without a terminal to confirm on, nothing is removed.
+    let output = jungle_localnet(dir, &["clean", "--suite", "tests/suite/Test.toml"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("--yes"), "{}", stderr(&output));
+    assert!(removed.iter().all(|path| path.exists()));
+
+    let output = jungle_localnet(dir, &["clean", "--suite", "tests/suite/Test.toml", "--yes"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(removed.iter().all(|path| !path.exists()), "{}", stdout(&output));
+    assert!(suite.join("Test.toml").is_file());
+}
@@ -0,0 +1,167 @@
+#![cfg(feature = "runner")]
+//! Checks [ValidatorFlags::from_config] against the flags that were built as strings
+//! before it, for a fixture workspace. `from_config` writes to `target/` under the
+//! current directory, so this runs in its own test binary.
+use std::fs;
+use std::path::Path;
+use anchor_cli::config::{Config, TestValidator, WithPath};
+use anchor_client::anchor_lang::idl::IdlAccount;
+use anyhow::{anyhow, Result};
+use jungle_fi_localnet_tools::ValidatorFlags;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{write_keypair_file, Keypair};
+use tempfile::TempDir;
+
+const PROGRAM_CARGO_TOML: &str = "\
+[package]
+name = \"test-program\"
+version = \"0.1.0\"
+
+[lib]
+crate-type = [\"cdylib\", \"lib\"]
+name = \"test_program\"
+";
+
+const PROGRAM_LIB_RS: &str = "\
+use anchor_lang::prelude::*;
+
+declare_id!(\"Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS\");
+
+#[program]
+pub mod test_program {
+    use super::*;
+
+    pub fn initialize(_ctx: Context<Initialize>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize {}
+";
+
+// A workspace with one Anchor program, a genesis program and a `[test.validator]`.
+fn workspace(program: &Pubkey, genesis: &Pubkey, account: &Pubkey) -> TempDir {
+    let temp = TempDir::new().unwrap();
+    let dir = temp.path();
+    fs::create_dir_all(dir.join("programs/test-program/src")).unwrap();
+    fs::write(dir.join("programs/test-program/Cargo.toml"), PROGRAM_CARGO_TOML).unwrap();
+    fs::write(dir.join("programs/test-program/src/lib.rs"), PROGRAM_LIB_RS).unwrap();
+    fs::create_dir_all(dir.join("target/idl")).unwrap();
+    fs::write(dir.join("genesis.so"), b"").unwrap();
+    write_keypair_file(&Keypair::new(), dir.join("wallet.json")).unwrap();
+    fs::write(dir.join("Anchor.toml"), format!("\
+[programs.localnet]
+test_program = \"{program}\"
+
+[provider]
+cluster = \"localnet\"
+wallet = \"wallet.json\"
+
+[scripts]
+test = \"true\"
+
+[test]
+startup_wait = 5000
+
+[[test.genesis]]
+address = \"{genesis}\"
+program = \"genesis.so\"
+
+[test.validator]
+url = \"https://api.mainnet-beta.solana.com\"
+bind_address = \"0.0.0.0\"
+ledger = \".anchor/test-ledger\"
+rpc_port = 8899
+
+[[test.validator.account]]
+address = \"{account}\"
+filename = \"tests/mint.json\"
+")).unwrap();
+    temp
+}
+
+// The string builder `ValidatorFlags` replaced, for the parts of a workspace that
+// don't need an RPC connection. `address` is the one program's `[programs.localnet]` address.
+fn string_built_flags(
+    cfg: &WithPath<Config>,
+    address: &Pubkey,
+    test_validator: &Option<TestValidator>,
+) -> Result<Vec<String>> {
+    let mut flags = Vec::new();
+    for program in cfg.read_all_programs()? {
+        flags.push("--bpf-program".to_string());
+        flags.push(address.to_string());
+        flags.push(program.binary_path().display().to_string());
+        flags.push("--account".to_string());
+        flags.push(IdlAccount::address(address).to_string());
+        flags.push(format!("target/idl-account/{}-account.json", program.lib_name));
+    }
+
+    if let Some(test) = test_validator.as_ref() {
+        if let Some(genesis) = &test.genesis {
+            for entry in genesis {
+                flags.push("--bpf-program".to_string());
+                flags.push(entry.address.clone());
+                flags.push(entry.program.clone());
+            }
+        }
+        if let Some(validator) = &test.validator {
+            let entries = serde_json::to_value(validator)?;
+            for (key, value) in entries.as_object().unwrap() {
+                if key == "ledger" {
+                    continue;
+                };
+                if key == "account" {
+                    for entry in value.as_array().unwrap() {
+                        flags.push("--account".to_string());
+                        flags.push(entry["address"].as_str().unwrap().to_string());
+                        flags.push(entry["filename"].as_str().unwrap().to_string());
+                    }
+                } else if key == "clone" {
+                    return Err(anyhow!("clones need an RPC connection"));
+                } else {
+                    flags.push(format!("--{}", key.replace('_', "-")));
+                    if let serde_json::Value::String(v) = value {
+                        flags.push(v.to_string());
+                    } else {
+                        flags.push(value.to_string());
+                    }
+                }
+            }
+        }
+    }
+    Ok(flags)
+}
+
+// Each flag with its values, sorted, so that only the order of the flags is ignored.
+fn flag_set(args: &[String]) -> Vec<Vec<String>> {
+    let mut flags: Vec<Vec<String>> = vec![];
+    for arg in args {
+        match flags.last_mut() {
+            Some(flag) if !arg.starts_with("--") => flag.push(arg.clone()),
+            _ => flags.push(vec![arg.clone()]),
+        }
+    }
+    flags.sort();
+    flags
+}
+
+#[test]
+fn from_config_matches_the_string_built_flags() {
+    let program = Pubkey::new_unique();
+    let genesis = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    let temp = workspace(&program, &genesis, &account);
+    std::env::set_current_dir(temp.path()).unwrap();
+
+    let cfg: Config = fs::read_to_string("Anchor.toml").unwrap().parse().unwrap();
+    let test_validator = cfg.test_validator.clone();
+    assert!(test_validator.is_some());
+    let cfg = WithPath::new(cfg, temp.path().join("Anchor.toml"));
+
+    let flags = ValidatorFlags::from_config(&cfg, &test_validator).unwrap().to_args();
+    let expected = string_built_flags(&cfg, &program, &test_validator).unwrap();
+    assert_eq!(flag_set(&flags), flag_set(&expected));
+    assert!(Path::new("target/idl-account/test_program-account.json").exists());
+}